@@ -0,0 +1,126 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+use std::fs;
+
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rtimelog::activity::Activities;
+use rtimelog::config::Sentinels;
+use rtimelog::index;
+use rtimelog::journal;
+use rtimelog::storage::{FileStorage, MemoryStorage, Storage};
+use rtimelog::store::Timelog;
+
+const TASKS: [&str; 8] = [
+    "customer joe: support",
+    "customer joe: call",
+    "rtimelog: code",
+    "rtimelog: review",
+    "email",
+    "** tea",
+    "meeting",
+    "** lunch",
+];
+
+// synthetic log with `days` working days of 20 entries each
+fn generate(days: u32) -> String {
+    let mut log = String::new();
+    let mut day = NaiveDate::from_ymd_opt(2000, 1, 3).unwrap();
+    for d in 0..days {
+        let mut time = day.and_hms_opt(8, 0, 0).unwrap();
+        writeln!(log, "{}: arrived", time.format("%Y-%m-%d %H:%M")).unwrap();
+        for i in 0..19 {
+            time += Duration::minutes(13 + (i * 7 + d as i64) % 20);
+            let task = TASKS[(i as usize + d as usize) % TASKS.len()];
+            writeln!(log, "{}: {task}", time.format("%Y-%m-%d %H:%M")).unwrap();
+        }
+        log.push('\n');
+        day += Duration::days(1);
+    }
+    log
+}
+
+fn bench(c: &mut Criterion) {
+    // one year, ten years, and about 50 MB
+    for (name, days) in [("1y", 250), ("10y", 2_500), ("50MB", 75_000)] {
+        let log = generate(days);
+        let tl = Timelog::new_from_string(&log);
+        let last_day =
+            NaiveDate::from_ymd_opt(2000, 1, 3).unwrap() + Duration::days(days as i64 - 1);
+
+        let sentinels = Sentinels::default();
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        group.throughput(Throughput::Bytes(log.len() as u64));
+        group.bench_function(BenchmarkId::new("parse", days), |b| {
+            b.iter(|| Timelog::new_from_string(&log))
+        });
+        group.bench_function(BenchmarkId::new("get_n_days", days), |b| {
+            b.iter(|| tl.get_n_days(&last_day, 1, NaiveTime::MIN).len())
+        });
+        group.bench_function(BenchmarkId::new("report_week", days), |b| {
+            b.iter(|| {
+                Activities::new_from_entries(
+                    tl.get_n_weeks(&last_day, 1, NaiveTime::MIN, Weekday::Mon),
+                    &sentinels,
+                )
+                .total_work()
+            })
+        });
+        group.bench_function(BenchmarkId::new("report_all", days), |b| {
+            b.iter(|| {
+                Activities::new_from_entries(
+                    tl.get_n_days(&last_day, days, NaiveTime::MIN),
+                    &sentinels,
+                )
+                .total_work()
+            })
+        });
+        // like `rtimelog add` and `status`: this week only, found by seeking in the file
+        let path = std::env::temp_dir().join(format!("rtimelog-bench-{}", std::process::id()));
+        fs::write(&path, &log).unwrap();
+        let monday = (last_day - Duration::days(6)).and_hms_opt(0, 0, 0).unwrap();
+        group.bench_function(BenchmarkId::new("load_recent", days), |b| {
+            b.iter(|| {
+                Timelog::new_from_storage_recent(Box::new(FileStorage::new(&path)), monday)
+                    .unwrap()
+                    .len()
+            })
+        });
+        // same with the index that saving writes
+        FileStorage::new(&path).save(&log).unwrap();
+        group.bench_function(BenchmarkId::new("load_recent_indexed", days), |b| {
+            b.iter(|| {
+                Timelog::new_from_storage_recent(Box::new(FileStorage::new(&path)), monday)
+                    .unwrap()
+                    .len()
+            })
+        });
+        fs::remove_file(journal::journal_path(&path)).unwrap();
+        fs::remove_file(index::index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+        group.bench_function(BenchmarkId::new("save", days), |b| {
+            let mut tl = Timelog::new_from_storage(Box::new(MemoryStorage::new(&log))).unwrap();
+            b.iter(|| tl.save().unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
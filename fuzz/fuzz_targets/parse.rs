@@ -0,0 +1,49 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![no_main]
+
+use chrono::NaiveDateTime;
+use libfuzzer_sys::fuzz_target;
+
+use rtimelog::activity::Activities;
+use rtimelog::config::Sentinels;
+use rtimelog::storage::MemoryStorage;
+use rtimelog::store::Timelog;
+
+fuzz_target!(|data: &[u8]| {
+    // parsing and reporting must not panic on anything
+    let tl = Timelog::new_from_bytes(data);
+    let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+    let _ = Activities::new_from_entries(entries, &Sentinels::default()).to_string();
+    let _ = Timelog::get_history(entries);
+
+    // saving keeps all parsed entries
+    let storage = MemoryStorage::new(&String::from_utf8_lossy(data));
+    let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+    // write everything, instead of only appending
+    tl.recover(&[]).unwrap();
+    tl.save().unwrap();
+    let saved = Timelog::new_from_string(&storage.contents());
+    assert_eq!(entries_with_notes(&saved), entries_with_notes(&tl));
+});
+
+// note IDs are specific to their Timelog, so compare the notes themselves
+fn entries_with_notes(tl: &Timelog) -> Vec<(String, Option<&str>)> {
+    tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX)
+        .iter()
+        .map(|e| (e.to_string(), tl.note(e)))
+        .collect()
+}
@@ -15,10 +15,12 @@
 
 extern crate chrono;
 
+use std::collections::HashMap;
 use std::fmt;
 
-use chrono::{Datelike, Duration, NaiveDateTime};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 
+use crate::config::{in_project, Absence, Config, RoundingPolicy, Sentinels, TaskFilter};
 use crate::store::Entry;
 
 /**
@@ -51,47 +53,39 @@ pub struct Activities {
 }
 
 impl Activities {
-    pub fn new_from_entries(entries: &[Entry]) -> Activities {
-        // don't use a hashmap here, we do want to keep this sorted by "first occurrence of task"
-        let mut activities = Vec::new();
+    pub fn total_work(&self) -> Duration {
+        self.total_work
+    }
+
+    pub fn total_slack(&self) -> Duration {
+        self.total_slack
+    }
+
+    pub fn new_from_entries(entries: &[Entry], sentinels: &Sentinels) -> Activities {
+        // keep this sorted by "first occurrence of task", the map only finds the index
+        let mut activities: Vec<Activity> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
         let mut total_work = Duration::minutes(0);
         let mut total_slack = Duration::minutes(0);
-        let mut prev_stop: Option<NaiveDateTime> = None;
-
-        for entry in entries {
-            match prev_stop {
-                Some(prev_stop_time) => {
-                    // continue if not the same day
-                    // first entry of every day gets ignored
-                    if prev_stop_time.day() != entry.stop.day() {
-                        prev_stop = Some(entry.stop);
-                        continue;
-                    }
 
-                    let duration = entry.stop.signed_duration_since(prev_stop_time);
-                    if entry.task.contains("**") {
-                        total_slack += duration;
-                    } else {
-                        total_work += duration;
-                    }
-
-                    // meh quadratic loop, but not important
-                    match activities
-                        .iter_mut()
-                        .find(|a: &&mut Activity| a.name == entry.task)
-                    {
-                        Some(a) => a.duration += duration,
-                        None => activities.push(Activity {
-                            name: entry.task.to_string(),
-                            duration,
-                        }),
-                    }
+        for (i, entry) in entries.iter().enumerate() {
+            let Some(duration) = interval(entries[..i].last(), entry, sentinels) else {
+                continue;
+            };
+            if entry.is_slack() {
+                total_slack += duration;
+            } else {
+                total_work += duration;
+            }
 
-                    prev_stop = Some(entry.stop);
-                }
+            match index.get(&*entry.task) {
+                Some(&i) => activities[i].duration += duration,
                 None => {
-                    // first entry's task is ignored, it just provides the start time
-                    prev_stop = Some(entry.stop);
+                    index.insert(&entry.task, activities.len());
+                    activities.push(Activity {
+                        name: entry.task.to_string(),
+                        duration,
+                    });
                 }
             }
         }
@@ -102,6 +96,214 @@ impl Activities {
             total_slack,
         }
     }
+
+    // like new_from_entries(), with the time of each task rounded per day, like on a timesheet;
+    // the totals are the sums of the rounded times
+    pub fn new_rounded(
+        entries: &[Entry],
+        sentinels: &Sentinels,
+        rounding: &RoundingPolicy,
+    ) -> Activities {
+        if rounding.every.is_none() {
+            return Activities::new_from_entries(entries, sentinels);
+        }
+        let mut rounded = Activities {
+            activities: Vec::new(),
+            total_work: Duration::minutes(0),
+            total_slack: Duration::minutes(0),
+        };
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let day_of = |e: &Entry| sentinels.day_of(e.stop);
+        for day in entries.chunk_by(|a, b| day_of(a) == day_of(b)) {
+            for a in Activities::new_from_entries(day, sentinels).activities {
+                let duration = rounding.apply(a.duration);
+                if is_slack(&a.name) {
+                    rounded.total_slack += duration;
+                } else {
+                    rounded.total_work += duration;
+                }
+                match index.get(&a.name) {
+                    Some(&i) => rounded.activities[i].duration += duration,
+                    None => {
+                        index.insert(a.name.clone(), rounded.activities.len());
+                        rounded.activities.push(Activity { duration, ..a });
+                    }
+                }
+            }
+        }
+        rounded
+    }
+
+    // what reports count: new_rounded() with the rounding of `config`, and only the tasks of
+    // its filter
+    pub fn new_for_report(entries: &[Entry], config: &Config) -> Activities {
+        Activities::new_rounded(entries, &config.sentinels, &config.rounding)
+            .filtered(&config.filter)
+    }
+
+    // only the tasks which match `filter`, with their totals
+    pub fn filtered(mut self, filter: &TaskFilter) -> Activities {
+        if filter.is_empty() {
+            return self;
+        }
+        self.activities.retain(|a| filter.matches(&a.name));
+        let (slack, work): (Vec<&Activity>, Vec<&Activity>) =
+            self.activities.iter().partition(|a| is_slack(&a.name));
+        self.total_work = work.iter().map(|a| a.duration).sum();
+        self.total_slack = slack.iter().map(|a| a.duration).sum();
+        self
+    }
+}
+
+// Time spent on `entry` since `prev`, or None if it merely provides a start time: the first
+// entry of a day, an arrival, or the first entry after a departure
+pub(crate) fn interval(
+    prev: Option<&Entry>,
+    entry: &Entry,
+    sentinels: &Sentinels,
+) -> Option<Duration> {
+    let prev = prev?;
+    if sentinels.day_of(prev.stop) != sentinels.day_of(entry.stop)
+        || sentinels.is_departure(&prev.task)
+        || sentinels.is_arrival(&entry.task)
+    {
+        return None;
+    }
+    Some(entry.stop.signed_duration_since(prev.stop))
+}
+
+// each entry with the time spent on it; zero for the ones which just start the clock (see
+// interval()), like the "arrived" at the beginning of the day
+pub fn durations<'a>(
+    entries: &'a [Entry],
+    sentinels: &'a Sentinels,
+) -> impl Iterator<Item = (&'a Entry, Duration)> + 'a {
+    entries.iter().enumerate().map(|(i, entry)| {
+        let duration = interval(entries[..i].last(), entry, sentinels);
+        (entry, duration.unwrap_or_else(|| Duration::minutes(0)))
+    })
+}
+
+// (day, absence) of the days with a vacation or sick entry (see Config::absence()), in the order
+// of the entries
+pub fn absences(entries: &[Entry], config: &Config) -> Vec<(NaiveDate, Absence)> {
+    let mut days: Vec<(NaiveDate, Absence)> = Vec::new();
+    for e in entries {
+        let Some(absence) = config.absence(&e.task) else {
+            continue;
+        };
+        let day = config.sentinels.day_of(e.stop);
+        if !days.iter().any(|(d, _)| *d == day) {
+            days.push((day, absence));
+        }
+    }
+    days
+}
+
+/**
+ * Time worked without a break: since the last slack entry, arrival, or the start of the day,
+ * including the still running interval until `now`
+ */
+pub fn continuous_work(entries: &[Entry], now: NaiveDateTime, sentinels: &Sentinels) -> Duration {
+    let last = match entries.last() {
+        Some(e) if !sentinels.is_departure(&e.task) => e,
+        _ => return Duration::minutes(0),
+    };
+    let mut stretch = now.signed_duration_since(last.stop);
+    for pair in entries.windows(2).rev() {
+        let (prev, e) = (&pair[0], &pair[1]);
+        if e.is_slack()
+            || sentinels.day_of(prev.stop) != sentinels.day_of(e.stop)
+            || sentinels.is_arrival(&e.task)
+            || sentinels.is_departure(&prev.task)
+        {
+            break;
+        }
+        stretch += e.stop.signed_duration_since(prev.stop);
+    }
+    stretch
+}
+
+// the part of a task before the first ": ", like the customer or project
+pub fn project(task: &str) -> &str {
+    match task.split_once(": ") {
+        Some((prefix, _)) => prefix,
+        None => task,
+    }
+}
+
+// the part between the project and the text, like "emergency" in
+// "customer joe: emergency: fix login"
+pub fn subproject(task: &str) -> Option<&str> {
+    let (_, rest) = task.split_once(": ")?;
+    rest.rsplit_once(": ").map(|(sub, _)| sub)
+}
+
+// what got done: the part after the last ": ", without the slack marker
+pub fn text(task: &str) -> &str {
+    let text = task.rsplit_once(": ").map_or(task, |(_, text)| text);
+    text.trim().trim_start_matches("**").trim_start()
+}
+
+// gtimelog counts a task with "**" anywhere in it as slacking
+pub fn is_slack(task: &str) -> bool {
+    task.contains("**")
+}
+
+// a "$" word marks a task billable, also outside of the billable projects
+pub fn has_billable_marker(task: &str) -> bool {
+    task.split_whitespace().any(|word| word == "$")
+}
+
+// words like "#review" anywhere in the task, without the "#"
+pub fn tags(task: &str) -> Vec<&str> {
+    task.split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(|tag| tag.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '-' && c != '_'))
+        .filter(|tag| !tag.is_empty() && !tag.starts_with('#'))
+        .collect()
+}
+
+// like "1 hour 5 minutes", for plain output which screen readers read well
+pub fn duration_in_words(d: Duration) -> String {
+    let unit = |n: i64, name: &str| format!("{n} {name}{}", if n == 1 { "" } else { "s" });
+    let (hours, minutes) = (d.num_hours(), d.num_minutes() % 60);
+    match (hours, minutes) {
+        (0, m) => unit(m, "minute"),
+        (h, 0) => unit(h, "hour"),
+        (h, m) => format!("{} {}", unit(h, "hour"), unit(m, "minute")),
+    }
+}
+
+impl Activities {
+    // task names and their durations, in the order of their first entry
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.activities
+            .iter()
+            .map(|a| (a.name.as_str(), a.duration))
+    }
+
+    // time spent on the tasks in `project`, see config::in_project()
+    pub fn duration_matching(&self, project: &str) -> Duration {
+        self.activities
+            .iter()
+            .filter(|a| in_project(&a.name, project))
+            .fold(Duration::minutes(0), |sum, a| sum + a.duration)
+    }
+
+    // without aligned columns and separator lines
+    pub fn to_plain_string(&self) -> String {
+        let mut output = String::new();
+        for a in &self.activities {
+            output.push_str(&format!("{}: {}\n", a.name, duration_in_words(a.duration)));
+        }
+        output.push_str(&format!(
+            "\nTotal work done: {}\nTotal slacking: {}\n",
+            duration_in_words(self.total_work),
+            duration_in_words(self.total_slack)
+        ));
+        output
+    }
 }
 
 impl fmt::Display for Activities {
@@ -128,8 +330,9 @@ impl fmt::Display for Activities {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::RoundingMode;
     use crate::store::Timelog;
-    use chrono::NaiveDate;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -176,9 +379,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_task_parts() {
+        let task = "customer joe: emergency: fix login #urgent, #ops";
+        assert_eq!(project(task), "customer joe");
+        assert_eq!(subproject(task), Some("emergency"));
+        assert_eq!(text(task), "fix login #urgent, #ops");
+        assert_eq!(tags(task), vec!["urgent", "ops"]);
+        assert!(!is_slack(task));
+
+        assert_eq!(subproject("rtimelog: code"), None);
+        assert_eq!(text("rtimelog: code"), "code");
+        assert_eq!(text("** lunch"), "lunch");
+        assert_eq!(text("arrived"), "arrived");
+        assert!(is_slack("** lunch"));
+        assert!(has_billable_marker("acme: call $"));
+        assert!(!has_billable_marker("acme: order $5 of coffee"));
+        assert_eq!(tags("issue #42 and ## heading"), vec!["42"]);
+
+        let tl = Timelog::new_from_string("2023-06-09 09:00: rtimelog: code: parser #review\n");
+        let e = tl.get_all().next().unwrap();
+        assert_eq!(e.project(), "rtimelog");
+        assert_eq!(e.subproject(), Some("code"));
+        assert_eq!(e.text(), "parser #review");
+        assert_eq!(e.tags(), vec!["review"]);
+        assert!(!e.is_slack());
+    }
+
     #[test]
     fn test_activities_empty() {
-        let a = Activities::new_from_entries(&[]);
+        let a = Activities::new_from_entries(&[], &Sentinels::default());
         assert_eq!(a.activities.len(), 0);
         assert_eq!(a.total_work, Duration::minutes(0));
         assert_eq!(a.total_slack, Duration::minutes(0));
@@ -202,7 +432,12 @@ mod tests {
         );
 
         let a = Activities::new_from_entries(
-            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+            tl.get_n_days(
+                &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+                1,
+                NaiveTime::MIN,
+            ),
+            &Sentinels::default(),
         );
         assert_eq!(a.total_work, Duration::minutes(475));
         assert_eq!(a.total_slack, Duration::minutes(65));
@@ -229,6 +464,44 @@ Total slacking: 1 h 5 min\n"
         )
     }
 
+    #[test]
+    fn test_continuous_work() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 16:00: work
+
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+2022-06-10 12:05: gtimelog: code
+2022-06-10 12:35: customer joe: inquiry
+",
+        );
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let at = |h, m| day.and_hms_opt(h, m, 0).unwrap();
+        let entries = tl.get_n_days(&day, 1, NaiveTime::MIN);
+        let s = Sentinels::default();
+
+        assert_eq!(continuous_work(&[], at(13, 0), &s), Duration::minutes(0));
+        // since tea, plus the running interval
+        assert_eq!(continuous_work(entries, at(13, 0), &s), Duration::hours(4));
+        // until tea, since the start of the day
+        assert_eq!(
+            continuous_work(&entries[..2], at(9, 0), &s),
+            Duration::hours(2)
+        );
+        // right after a break
+        assert_eq!(
+            continuous_work(&entries[..3], at(9, 10), &s),
+            Duration::minutes(10)
+        );
+        // previous day does not count
+        assert_eq!(
+            continuous_work(tl.get_n_days(&day, 2, NaiveTime::MIN), at(13, 0), &s),
+            Duration::hours(4)
+        );
+    }
+
     #[test]
     fn test_activities_weekly() {
         let tl = Timelog::new_from_string(
@@ -255,7 +528,13 @@ Total slacking: 1 h 5 min\n"
         );
 
         let a = Activities::new_from_entries(
-            tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(), 1),
+            tl.get_n_weeks(
+                &NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(),
+                1,
+                NaiveTime::MIN,
+                Weekday::Mon,
+            ),
+            &Sentinels::default(),
         );
         assert_eq!(a.total_work, Duration::hours(3));
         assert_eq!(a.total_slack, Duration::minutes(20));
@@ -272,6 +551,167 @@ Total slacking: 1 h 5 min\n"
 -------
 Total work done: 3 h 0 min
 Total slacking: 0 h 20 min
+"
+        );
+    }
+
+    #[test]
+    fn test_activities_rounded() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 09:00: arrived
+2022-06-09 09:05: customer joe: support
+2022-06-09 09:12: ** tea
+2022-06-09 09:15: customer joe: support
+
+2022-06-10 09:00: arrived
+2022-06-10 09:05: customer joe: support
+2022-06-10 10:00: acme
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let rounding = RoundingPolicy {
+            every: Some(Duration::minutes(15)),
+            mode: RoundingMode::Up,
+        };
+        let a = Activities::new_rounded(entries, &Sentinels::default(), &rounding);
+        // 8 minutes on one day and 5 on the next are a quarter each
+        assert_eq!(a.activities[0].name, "customer joe: support");
+        assert_eq!(a.activities[0].duration, Duration::minutes(30));
+        assert_eq!(a.activities[1].duration, Duration::minutes(15));
+        assert_eq!(a.activities[2].name, "acme");
+        assert_eq!(a.activities[2].duration, Duration::minutes(60));
+        assert_eq!(a.total_work, Duration::minutes(90));
+        assert_eq!(a.total_slack, Duration::minutes(15));
+
+        let a = Activities::new_rounded(entries, &Sentinels::default(), &Default::default());
+        assert_eq!(a.total_work, Duration::minutes(68));
+    }
+
+    #[test]
+    fn test_activities_filtered() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 09:00: arrived
+2022-06-09 09:05: customer joe: support
+2022-06-09 09:12: ** customer joe: lunch
+2022-06-09 10:15: acme: support
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let mut config = Config::default();
+        config.filter.project = Some("customer joe".to_string());
+        let a = Activities::new_for_report(entries, &config);
+        assert_eq!(
+            a.iter().collect::<Vec<_>>(),
+            vec![("customer joe: support", Duration::minutes(5))]
+        );
+        assert_eq!(a.total_work, Duration::minutes(5));
+        // "** customer joe: lunch" is not in the project, but slacking counts if it matches
+        assert_eq!(a.total_slack, Duration::zero());
+        config.filter.project = None;
+        config.filter.pattern = Some(regex::Regex::new("joe").unwrap());
+        let a = Activities::new_for_report(entries, &config);
+        assert_eq!(a.total_work, Duration::minutes(5));
+        assert_eq!(a.total_slack, Duration::minutes(7));
+    }
+
+    #[test]
+    fn test_activities_sentinels() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: start
+2022-06-10 08:00: code
+2022-06-10 09:00: left
+2022-06-10 10:00: doctor
+2022-06-10 13:00: start
+2022-06-10 14:00: review
+",
+        );
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let entries = tl.get_n_days(&day, 1, NaiveTime::MIN);
+        let at = |h, m| day.and_hms_opt(h, m, 0).unwrap();
+
+        // without keywords, everything is work
+        let a = Activities::new_from_entries(entries, &Sentinels::default());
+        assert_eq!(a.total_work, Duration::hours(7));
+
+        let s = Sentinels {
+            arrival: vec!["start".to_string()],
+            departure: vec!["left".to_string()],
+            ..Sentinels::default()
+        };
+        let a = Activities::new_from_entries(entries, &s);
+        // not 10:00 to 13:00 back at work, and not the doctor's appointment after leaving
+        assert_eq!(a.total_work, Duration::hours(3));
+        assert_eq!(a.activities.len(), 3);
+        assert_eq!(a.activities[1].name, "left");
+
+        assert_eq!(
+            continuous_work(entries, at(14, 30), &s),
+            Duration::minutes(90)
+        );
+        assert_eq!(
+            continuous_work(&entries[..3], at(9, 30), &s),
+            Duration::zero()
+        );
+        assert_eq!(
+            continuous_work(&entries[..4], at(10, 30), &s),
+            Duration::minutes(30)
+        );
+    }
+
+    #[test]
+    fn test_activities_virtual_midnight() {
+        let tl = Timelog::new_from_string(
+            "2022-06-09 22:00: arrived\n2022-06-10 00:30: code\n2022-06-10 01:30: review\n",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        // 00:30 starts a new day by default
+        let a = Activities::new_from_entries(entries, &Sentinels::default());
+        assert_eq!(a.total_work, Duration::hours(1));
+
+        let s = Sentinels {
+            virtual_midnight: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+            ..Sentinels::default()
+        };
+        let a = Activities::new_from_entries(entries, &s);
+        assert_eq!(a.total_work, Duration::minutes(210));
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(duration_in_words(Duration::minutes(0)), "0 minutes");
+        assert_eq!(duration_in_words(Duration::minutes(1)), "1 minute");
+        assert_eq!(duration_in_words(Duration::minutes(60)), "1 hour");
+        assert_eq!(
+            duration_in_words(Duration::minutes(121)),
+            "2 hours 1 minute"
+        );
+        assert_eq!(duration_in_words(Duration::minutes(65)), "1 hour 5 minutes");
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(
+                &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+                1,
+                NaiveTime::MIN,
+            ),
+            &Sentinels::default(),
+        );
+        assert_eq!(
+            a.to_plain_string(),
+            "gtimelog: code: 1 hour 45 minutes
+** tea: 15 minutes
+
+Total work done: 1 hour 45 minutes
+Total slacking: 15 minutes
 "
         );
     }
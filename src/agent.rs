@@ -0,0 +1,105 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate dirs;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const LABEL: &str = "de.piware.rtimelog.remind";
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/**
+ * launchd agent which runs `rtimelog remind` for the logged in user
+ */
+pub fn launchd_plist(program: &Path, log: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>remind</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StandardErrorPath</key>
+    <string>{}</string>
+</dict>
+</plist>
+"#,
+        xml_escape(&program.to_string_lossy()),
+        xml_escape(&log.to_string_lossy())
+    )
+}
+
+// ~/Library/LaunchAgents/<label>.plist
+pub fn get_agent_file() -> PathBuf {
+    let mut path = dirs::home_dir().unwrap();
+    path.push("Library");
+    path.push("LaunchAgents");
+    path.push(format!("{LABEL}.plist"));
+    path
+}
+
+// write the agent file for `program`; it gets started on the next login, or with `launchctl load`
+pub fn install(program: &Path) -> Result<PathBuf, io::Error> {
+    let mut log = dirs::home_dir().unwrap();
+    log.push("Library");
+    log.push("Logs");
+    log.push("rtimelog.log");
+
+    let path = get_agent_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, launchd_plist(program, &log))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            Path::new("/Applications/R&D/rtimelog"),
+            Path::new("/Users/joe/Library/Logs/rtimelog.log"),
+        );
+        assert!(plist.contains("<string>de.piware.rtimelog.remind</string>"));
+        assert!(plist.contains(
+            "
+        <string>/Applications/R&amp;D/rtimelog</string>
+        <string>remind</string>
+"
+        ));
+        assert_eq!(plist.lines().filter(|l| l.contains("<true/>")).count(), 2);
+        assert!(plist.contains("<string>/Users/joe/Library/Logs/rtimelog.log</string>"));
+    }
+}
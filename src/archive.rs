@@ -0,0 +1,266 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+
+use crate::storage::{self, FileStorage, Storage};
+use crate::store::{Timelog, TimelogError};
+
+// like timelog-2023.txt next to timelog.txt
+pub fn archive_path(timelog: &Path, year: i32) -> PathBuf {
+    let stem = timelog.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match timelog.extension() {
+        Some(ext) => format!("{stem}-{year}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{year}"),
+    };
+    timelog.with_file_name(name)
+}
+
+// the existing archives of `timelog`, by year
+pub fn find(timelog: &Path) -> Vec<(i32, PathBuf)> {
+    let dir = match timelog.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(files) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<(i32, PathBuf)> = files
+        .filter_map(|f| f.ok())
+        .filter_map(|f| {
+            let name = f.file_name().to_string_lossy().to_string();
+            // the name tells the year; check that it is really the one of this timelog
+            let year = name
+                .strip_prefix(&format!("{}-", timelog.file_stem()?.to_string_lossy()))?
+                .get(..4)?
+                .parse::<i32>()
+                .ok()?;
+            let path = archive_path(timelog, year);
+            (path.file_name() == Some(f.file_name().as_os_str())).then_some((year, path))
+        })
+        .collect();
+    archives.sort();
+    archives
+}
+
+/**
+ * Read-only storage of the archives of a timelog and the timelog itself, one after another
+ *
+ * The archives have the earlier years, so together they read like one big timelog.
+ */
+#[derive(Debug)]
+pub struct ArchiveStorage {
+    files: Vec<FileStorage>,
+}
+
+impl ArchiveStorage {
+    // the archives of the years from `begin` to `end`, for reports which span years
+    pub fn new(timelog: &Path, begin: NaiveDateTime, end: NaiveDateTime) -> ArchiveStorage {
+        let mut files: Vec<FileStorage> = find(timelog)
+            .into_iter()
+            .filter(|(year, _)| (begin.year()..=end.year()).contains(year))
+            .map(|(_, path)| FileStorage::new(&path))
+            .collect();
+        files.push(FileStorage::new(timelog));
+        ArchiveStorage { files }
+    }
+}
+
+impl Storage for ArchiveStorage {
+    fn load(&self) -> Result<String, io::Error> {
+        let mut contents = Vec::new();
+        self.reader()?.read_to_end(&mut contents)?;
+        Ok(storage::decode(contents))
+    }
+
+    fn save(&self, _contents: &str) -> Result<(), io::Error> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Archives are read-only",
+        ))
+    }
+
+    fn reader(&self) -> Result<Box<dyn BufRead + '_>, io::Error> {
+        let mut reader: Box<dyn BufRead + '_> = Box::new(io::empty());
+        for file in &self.files {
+            // in case a file does not end with a newline
+            reader = Box::new(reader.chain(&b"\n"[..]).chain(file.reader()?));
+        }
+        Ok(reader)
+    }
+
+    // the current timelog, not the archives
+    fn path(&self) -> Option<&Path> {
+        self.files.last()?.path()
+    }
+}
+
+// Move the entries before `year` from `timelog` into one archive per year, and return the
+// archives with how many entries they got. Archives get written first, so that an interruption
+// at most leaves entries in both places; running this again then skips them in the archives.
+pub fn split(timelog: &Path, year: i32) -> Result<Vec<(PathBuf, usize)>, TimelogError> {
+    let storage = FileStorage::new(timelog);
+    // Timelog::save() would take the lock again
+    let _lock = storage.lock()?;
+    let mut current = Timelog::new_from_file(timelog)?;
+    let begin = |year: i32| {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid year"))
+    };
+    let mut older = current.split_off_before(begin(year)?)?;
+    if older.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut archived = Vec::new();
+    while let Some(first) = older
+        .get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX)
+        .first()
+    {
+        let y = first.stop.year();
+        let mut entries = older.split_off_before(begin(y + 1)?)?;
+        let path = archive_path(timelog, y);
+        let count = entries.len();
+        let contents = if path.exists() {
+            let mut archive = Timelog::new_from_file(&path)?;
+            let lines: Vec<String> = entries
+                .contents_to_save()?
+                .lines()
+                .map(String::from)
+                .collect();
+            archive.recover(&lines)?;
+            archive.contents_to_save()?
+        } else {
+            entries.contents_to_save()?
+        };
+        FileStorage::new(&path).save(&contents)?;
+        archived.push((path, count));
+    }
+    storage.save(&current.contents_to_save()?)?;
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_archive_path() {
+        assert_eq!(
+            archive_path(Path::new("/home/joe/timelog.txt"), 2023),
+            PathBuf::from("/home/joe/timelog-2023.txt")
+        );
+        assert_eq!(
+            archive_path(Path::new("work"), 2023),
+            PathBuf::from("work-2023")
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let dir = std::env::temp_dir().join(format!("rtimelog-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timelog.txt");
+        fs::write(
+            &path,
+            "2022-12-30 09:00: arrived
+2022-12-30 10:00: code
+  before the holidays
+
+2023-06-09 09:00: arrived
+2023-06-09 10:00: code
+
+2024-01-02 09:00: arrived
+",
+        )
+        .unwrap();
+        // an older archive already has part of 2022
+        fs::write(
+            archive_path(&path, 2022),
+            "2022-06-10 09:00: arrived\n2022-06-10 10:00: docs\n",
+        )
+        .unwrap();
+        fs::write(dir.join("timelog-notes.txt"), "unrelated").unwrap();
+
+        let archived = split(&path, 2024).unwrap();
+        assert_eq!(
+            archived,
+            vec![
+                (archive_path(&path, 2022), 2),
+                (archive_path(&path, 2023), 2)
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2024-01-02 09:00: arrived\n"
+        );
+        assert_eq!(
+            fs::read_to_string(archive_path(&path, 2022)).unwrap(),
+            "2022-06-10 09:00: arrived
+2022-06-10 10:00: docs
+
+2022-12-30 09:00: arrived
+2022-12-30 10:00: code
+  before the holidays
+"
+        );
+        assert_eq!(
+            find(&path),
+            vec![
+                (2022, archive_path(&path, 2022)),
+                (2023, archive_path(&path, 2023))
+            ]
+        );
+        // nothing left to archive
+        assert_eq!(split(&path, 2024).unwrap(), Vec::new());
+
+        // reports see all years again
+        let range = |y1, y2| {
+            (
+                NaiveDate::from_ymd_opt(y1, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+                NaiveDate::from_ymd_opt(y2, 12, 31)
+                    .unwrap()
+                    .and_hms_opt(23, 59, 59)
+                    .unwrap(),
+            )
+        };
+        let (begin, end) = range(2022, 2024);
+        let storage = ArchiveStorage::new(&path, begin, end);
+        let tl = Timelog::new_from_storage_range(&storage, begin, end).unwrap();
+        assert_eq!(tl.len(), 7);
+        let note = tl.get_time_range(begin, end)[3].clone();
+        assert_eq!(tl.note(&note), Some("before the holidays"));
+        // only the archives of the range get read
+        let (begin, end) = range(2023, 2023);
+        let storage = ArchiveStorage::new(&path, begin, end);
+        assert_eq!(storage.files.len(), 2);
+        assert_eq!(storage.path(), Some(path.as_path()));
+        let tl = Timelog::new_from_storage_range(&storage, begin, end).unwrap();
+        assert_eq!(tl.len(), 2);
+        assert!(storage.save("").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::archive;
+use crate::config::{expand_home, Config};
+use crate::journal::{self, checksum};
+
+// list of the files in the archive, with their checksums and where they belong
+const MANIFEST: &str = "MANIFEST";
+const MANIFEST_HEADER: &str = "rtimelog backup 1";
+
+/**
+ * A file in a backup: its name in the archive, and where it gets restored to
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl BackupFile {
+    fn new(name: &str, path: PathBuf) -> BackupFile {
+        BackupFile {
+            name: name.to_string(),
+            path,
+        }
+    }
+}
+
+// everything that belongs to the timelog: the log with its journal, damaged lines, and archives,
+// gtimelog's task list, the configuration, and the calendar
+pub fn default_files(timelog: &Path, config: &Config) -> Vec<BackupFile> {
+    let dir = timelog.parent().unwrap_or(Path::new("."));
+    let mut files = vec![
+        BackupFile::new("timelog.txt", timelog.to_path_buf()),
+        BackupFile::new("timelog.txt.journal", journal::journal_path(timelog)),
+        BackupFile::new("timelog.txt.damaged", journal::damaged_path(timelog)),
+        BackupFile::new("tasks.txt", dir.join("tasks.txt")),
+        BackupFile::new("gtimelogrc", Config::get_default_file()),
+    ];
+    for (year, path) in archive::find(timelog) {
+        files.push(BackupFile::new(&format!("timelog-{year}.txt"), path));
+    }
+    if let Some(calendar) = &config.calendar {
+        files.push(BackupFile::new("calendar.ics", calendar.clone()));
+    }
+    files
+}
+
+// write the existing `files` into a new tar archive `out`, and return the backed up ones
+pub fn create(out: &Path, files: &[BackupFile]) -> Result<Vec<BackupFile>, io::Error> {
+    // never overwrite an older backup
+    let archive = OpenOptions::new().write(true).create_new(true).open(out)?;
+    let mut builder = tar::Builder::new(archive);
+    let mut manifest = format!("{MANIFEST_HEADER}\n");
+    let mut saved = Vec::new();
+
+    for file in files {
+        let data = match fs::read(&file.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("{}: {e}", file.path.display()),
+                ))
+            }
+        };
+        let mtime = fs::metadata(&file.path)?.modified()?;
+        append(&mut builder, &file.name, &data, mtime)?;
+        manifest.push_str(&format!(
+            "{:016x} {} {} {}\n",
+            checksum(&data),
+            data.len(),
+            file.name,
+            home_relative(&file.path)
+        ));
+        saved.push(file.clone());
+    }
+
+    append(
+        &mut builder,
+        MANIFEST,
+        manifest.as_bytes(),
+        SystemTime::now(),
+    )?;
+    builder.into_inner()?.sync_all()?;
+    Ok(saved)
+}
+
+// read the files from the archive, and check that they are complete and undamaged
+pub fn verify(archive: &Path) -> Result<Vec<(BackupFile, Vec<u8>)>, io::Error> {
+    let damaged = |what: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is damaged: {what}", archive.display()),
+        )
+    };
+
+    let mut contents = Vec::new();
+    let mut manifest = None;
+    for entry in tar::Archive::new(File::open(archive)?).entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if name == MANIFEST {
+            manifest = Some(String::from_utf8_lossy(&data).to_string());
+        } else {
+            contents.push((name, data));
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| damaged(format!("no {MANIFEST}")))?;
+    let mut lines = manifest.lines();
+    if lines.next() != Some(MANIFEST_HEADER) {
+        return Err(damaged(format!("unknown {MANIFEST} format")));
+    }
+    let mut files = Vec::new();
+    for line in lines {
+        let mut fields = line.splitn(4, ' ');
+        let (Some(sum), Some(len), Some(name), Some(path)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(damaged(format!("invalid {MANIFEST} line {line}")));
+        };
+        let i = contents
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| damaged(format!("{name} is missing")))?;
+        let (_, data) = contents.swap_remove(i);
+        if len != data.len().to_string() || sum != format!("{:016x}", checksum(&data)) {
+            return Err(damaged(format!("{name} has a wrong checksum")));
+        }
+        files.push((BackupFile::new(name, expand_home(path)), data));
+    }
+    if let Some((name, _)) = contents.first() {
+        return Err(damaged(format!("{name} is not in the {MANIFEST}")));
+    }
+    Ok(files)
+}
+
+// put the files from a verified archive back into place, and return them
+pub fn restore(archive: &Path) -> Result<Vec<BackupFile>, io::Error> {
+    let files = verify(archive)?;
+    for (file, data) in &files {
+        if let Some(parent) = file.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // don't leave a half-written file behind if this gets interrupted
+        let mut tmp = file.path.clone().into_os_string();
+        tmp.push(".restore");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &file.path)?;
+    }
+    Ok(files.into_iter().map(|(file, _)| file).collect())
+}
+
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    data: &[u8],
+    mtime: SystemTime,
+) -> Result<(), io::Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(
+        mtime
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    );
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)
+}
+
+// "~/..." for files in the home directory, so that a backup can be restored for another user
+fn home_relative(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    #[test]
+    fn test_backup_restore() {
+        let dir = env::temp_dir().join(format!("rtimelog-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("gtimelog")).unwrap();
+        let timelog = dir.join("gtimelog/timelog.txt");
+        let config = dir.join("gtimelog/gtimelogrc");
+        fs::write(&timelog, "2022-06-10 09:00: arrived\n").unwrap();
+        fs::write(&config, "[gtimelog]\nhours = 7\n").unwrap();
+        let files = [
+            BackupFile::new("timelog.txt", timelog.clone()),
+            BackupFile::new("tasks.txt", dir.join("gtimelog/tasks.txt")),
+            BackupFile::new("gtimelogrc", config.clone()),
+        ];
+
+        let archive = dir.join("backup.tar");
+        let saved = create(&archive, &files).unwrap();
+        // missing files get skipped
+        assert_eq!(saved, vec![files[0].clone(), files[2].clone()]);
+        // never overwrites a backup
+        assert_eq!(
+            create(&archive, &files).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+
+        // a risky edit
+        fs::write(&timelog, "garbage\n").unwrap();
+        fs::remove_file(&config).unwrap();
+        assert_eq!(restore(&archive).unwrap(), saved);
+        assert_eq!(
+            fs::read_to_string(&timelog).unwrap(),
+            "2022-06-10 09:00: arrived\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            "[gtimelog]\nhours = 7\n"
+        );
+
+        // flip a byte in the timelog's data
+        let mut raw = fs::read(&archive).unwrap();
+        let pos = raw.windows(7).position(|w| w == b"arrived").unwrap();
+        raw[pos] = b'A';
+        let damaged = dir.join("damaged.tar");
+        fs::write(&damaged, &raw).unwrap();
+        let e = verify(&damaged).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e
+            .to_string()
+            .ends_with("is damaged: timelog.txt has a wrong checksum"));
+        // nothing gets restored from a damaged backup
+        fs::write(&timelog, "edited\n").unwrap();
+        assert!(restore(&damaged).is_err());
+        assert_eq!(fs::read_to_string(&timelog).unwrap(), "edited\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,442 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::Duration;
+
+use crate::activity::{duration_in_words, is_slack, project, Activities};
+use crate::config::{Config, Rate};
+use crate::store::Entry;
+use crate::table::{Cell, Table};
+
+// like "1234.50"
+fn money(cents: i64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+// decimal hours like "12.25", as invoices have them
+pub(crate) fn decimal_hours(d: Duration) -> String {
+    let minutes = d.num_minutes();
+    format!("{}.{:02}", minutes / 60, (minutes % 60 * 100 + 30) / 60)
+}
+
+/**
+ * Billed time and its amount, for one project in one currency
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Billed {
+    pub project: String,
+    pub duration: Duration,
+    pub cents: i64,
+}
+
+/**
+ * Earnings of a time range, grouped by currency and project, with the rates from [rates]
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Earnings {
+    // (currency, projects), by currency name
+    pub currencies: Vec<(String, Vec<Billed>)>,
+    // work on tasks without a rate
+    pub unbilled: Duration,
+}
+
+impl Earnings {
+    pub fn new(entries: &[Entry], config: &Config) -> Earnings {
+        let a = Activities::new_for_report(entries, config);
+        let work = a.iter().filter(|(task, _)| !is_slack(task));
+        Earnings::bill(work, |task| config.rate_for(task))
+    }
+
+    // `tasks` with their durations, at the (rate in cents, currency) of each
+    fn bill<'a>(
+        tasks: impl Iterator<Item = (&'a str, Duration)>,
+        rate_for: impl Fn(&str) -> Option<(i64, &'a str)>,
+    ) -> Earnings {
+        let mut earnings = Earnings {
+            currencies: Vec::new(),
+            unbilled: Duration::minutes(0),
+        };
+
+        for (task, duration) in tasks {
+            let Some((rate, currency)) = rate_for(task) else {
+                earnings.unbilled += duration;
+                continue;
+            };
+            // different tasks of a project may have different rates, so charge per task
+            let cents = (rate * duration.num_minutes() + 30) / 60;
+            let projects = match earnings.currencies.iter().position(|(c, _)| c == currency) {
+                Some(i) => &mut earnings.currencies[i].1,
+                None => {
+                    earnings.currencies.push((currency.to_string(), Vec::new()));
+                    &mut earnings.currencies.last_mut().unwrap().1
+                }
+            };
+            match projects.iter_mut().find(|b| b.project == project(task)) {
+                Some(b) => {
+                    b.duration += duration;
+                    b.cents += cents;
+                }
+                None => projects.push(Billed {
+                    project: project(task).to_string(),
+                    duration,
+                    cents,
+                }),
+            }
+        }
+        earnings.currencies.sort_by(|a, b| a.0.cmp(&b.0));
+        earnings
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let duration = |d: Duration| {
+            if config.plain {
+                duration_in_words(d)
+            } else {
+                format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+            }
+        };
+        let mut out = String::new();
+        if self.currencies.is_empty() {
+            writeln!(out, "Nothing to bill, see the [rates] section").unwrap();
+        }
+        for (currency, projects) in &self.currencies {
+            writeln!(out, "{currency}:").unwrap();
+            for b in projects {
+                if config.plain {
+                    writeln!(
+                        out,
+                        "{}: {}, {} {currency}",
+                        b.project,
+                        duration(b.duration),
+                        money(b.cents)
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "{:>4} h {:>2} min {:>10} {currency}: {}",
+                        b.duration.num_hours(),
+                        b.duration.num_minutes() % 60,
+                        money(b.cents),
+                        b.project
+                    )
+                    .unwrap();
+                }
+            }
+            let total = projects
+                .iter()
+                .fold(Duration::minutes(0), |sum, b| sum + b.duration);
+            let cents: i64 = projects.iter().map(|b| b.cents).sum();
+            writeln!(
+                out,
+                "Total: {}, {} {currency}\n",
+                duration(total),
+                money(cents)
+            )
+            .unwrap();
+        }
+        if self.unbilled > Duration::minutes(0) {
+            writeln!(out, "Not billed: {}", duration(self.unbilled)).unwrap();
+        }
+        out
+    }
+
+    // the billed projects, for the other output formats
+    pub fn table(&self) -> Table {
+        let mut t = Table::new(&["currency", "project", "minutes", "amount"]);
+        for (currency, projects) in &self.currencies {
+            for b in projects {
+                t.push(vec![
+                    Cell::text(currency),
+                    Cell::text(&b.project),
+                    Cell::Time(b.duration),
+                    Cell::Money { cents: b.cents },
+                ]);
+            }
+        }
+        t
+    }
+}
+
+/**
+ * Invoice of the billable tasks (see Config::is_billable()) of a time range
+ *
+ * All of them are billed at the one given rate, or else at their rate from [rates].
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Invoice {
+    // billable work without a rate is `unbilled`
+    pub earnings: Earnings,
+    // work which is not billable
+    pub not_billable: Duration,
+}
+
+impl Invoice {
+    pub fn new(entries: &[Entry], rate: Option<&Rate>, config: &Config) -> Invoice {
+        let a = Activities::new_for_report(entries, config);
+        let (billable, other): (Vec<_>, Vec<_>) = a
+            .iter()
+            .filter(|(task, _)| !is_slack(task))
+            .partition(|(task, _)| config.is_billable(task));
+        let earnings = match rate {
+            Some(r) => {
+                let currency = r.currency.as_deref().unwrap_or(&config.currency);
+                Earnings::bill(billable.into_iter(), |_| Some((r.cents, currency)))
+            }
+            None => Earnings::bill(billable.into_iter(), |task| config.rate_for(task)),
+        };
+        Invoice {
+            earnings,
+            not_billable: other.iter().map(|(_, d)| *d).sum(),
+        }
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let mut out = String::new();
+        if self.earnings.currencies.is_empty() {
+            writeln!(
+                out,
+                "Nothing to bill, see the billable setting and the [rates] section"
+            )
+            .unwrap();
+        }
+        for (currency, projects) in &self.earnings.currencies {
+            writeln!(out, "{currency}:").unwrap();
+            for b in projects {
+                if config.plain {
+                    writeln!(
+                        out,
+                        "{}: {} hours, {} {currency}",
+                        b.project,
+                        decimal_hours(b.duration),
+                        money(b.cents)
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "{:>8} h {:>10} {currency}: {}",
+                        decimal_hours(b.duration),
+                        money(b.cents),
+                        b.project
+                    )
+                    .unwrap();
+                }
+            }
+            let total = projects.iter().map(|b| b.duration).sum();
+            let cents: i64 = projects.iter().map(|b| b.cents).sum();
+            writeln!(
+                out,
+                "Total: {} hours, {} {currency}\n",
+                decimal_hours(total),
+                money(cents)
+            )
+            .unwrap();
+        }
+        if self.earnings.unbilled > Duration::minutes(0) {
+            writeln!(
+                out,
+                "Billable without a rate: {} hours",
+                decimal_hours(self.earnings.unbilled)
+            )
+            .unwrap();
+        }
+        if self.not_billable > Duration::minutes(0) {
+            writeln!(
+                out,
+                "Not billable: {} hours",
+                decimal_hours(self.not_billable)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    // the billed projects, for the other output formats
+    pub fn table(&self) -> Table {
+        self.earnings.table()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use crate::table::OutputFormat;
+    use chrono::NaiveDateTime;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_earnings() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 09:00: arrived
+2022-06-10 11:00: customer joe: support
+2022-06-10 11:20: customer joe: emergency
+2022-06-10 11:30: ** tea
+2022-06-10 13:00: acme: website
+2022-06-10 14:00: rtimelog: code
+",
+        );
+        let config = Config::new_from_string(
+            "
+[rates]
+customer joe = 100
+customer joe: emergency = 150
+acme = 95.50 USD
+",
+        );
+        let e = Earnings::new(
+            tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX),
+            &config,
+        );
+        assert_eq!(
+            e,
+            Earnings {
+                currencies: vec![
+                    (
+                        "EUR".to_string(),
+                        vec![Billed {
+                            project: "customer joe".to_string(),
+                            duration: Duration::minutes(140),
+                            cents: 25000,
+                        }]
+                    ),
+                    (
+                        "USD".to_string(),
+                        vec![Billed {
+                            project: "acme".to_string(),
+                            duration: Duration::minutes(90),
+                            cents: 14325,
+                        }]
+                    ),
+                ],
+                unbilled: Duration::hours(1),
+            }
+        );
+        assert_eq!(
+            e.format(&config),
+            "EUR:
+   2 h 20 min     250.00 EUR: customer joe
+Total: 2 h 20 min, 250.00 EUR
+
+USD:
+   1 h 30 min     143.25 USD: acme
+Total: 1 h 30 min, 143.25 USD
+
+Not billed: 1 h 0 min
+"
+        );
+        assert_eq!(
+            e.table().format(OutputFormat::Json),
+            r#"[
+  {"currency": "EUR", "project": "customer joe", "minutes": 140, "amount": 250.00},
+  {"currency": "USD", "project": "acme", "minutes": 90, "amount": 143.25}
+]
+"#
+        );
+
+        // billed by the started hour, per task and day
+        let hourly = Config::new_from_string(
+            "[rtimelog]\nround = 1h\nround_mode = up\n[rates]\ncustomer joe = 100\n",
+        );
+        let e = Earnings::new(
+            tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX),
+            &hourly,
+        );
+        assert_eq!(e.currencies[0].1[0].duration, Duration::hours(3));
+        assert_eq!(e.currencies[0].1[0].cents, 30000);
+
+        let empty = Earnings::new(&[], &config);
+        assert_eq!(
+            empty.format(&Config::default()),
+            "Nothing to bill, see the [rates] section\n"
+        );
+    }
+
+    #[test]
+    fn test_invoice() {
+        let tl = Timelog::new_from_string(
+            "
+2024-05-10 09:00: arrived
+2024-05-10 11:00: customer joe: support
+2024-05-10 11:20: customer joe: emergency
+2024-05-10 11:30: ** tea
+2024-05-10 13:00: acme: website
+2024-05-10 14:00: rtimelog: consulting $
+2024-05-10 14:10: rtimelog: code
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let config = Config::new_from_string(
+            "
+[rtimelog]
+billable = customer joe
+round = 15m
+round_mode = up
+
+[rates]
+customer joe = 100
+",
+        );
+        let rate = Rate {
+            cents: 9000,
+            currency: None,
+        };
+        let invoice = Invoice::new(entries, Some(&rate), &config);
+        assert_eq!(
+            invoice.format(&config),
+            "EUR:
+    2.50 h     225.00 EUR: customer joe
+    1.00 h      90.00 EUR: rtimelog
+Total: 3.50 hours, 315.00 EUR
+
+Not billable: 1.75 hours
+"
+        );
+        assert_eq!(
+            invoice.table().format(OutputFormat::Csv),
+            "currency,project,minutes,amount
+EUR,customer joe,150,225.00
+EUR,rtimelog,60,90.00
+"
+        );
+
+        // the rates of the tasks
+        let invoice = Invoice::new(entries, None, &config);
+        assert_eq!(invoice.earnings.currencies[0].1[0].cents, 25000);
+        assert_eq!(invoice.earnings.unbilled, Duration::hours(1));
+        let plain = Config {
+            plain: true,
+            ..config.clone()
+        };
+        assert_eq!(
+            invoice.format(&plain),
+            "EUR:
+customer joe: 2.50 hours, 250.00 EUR
+Total: 2.50 hours, 250.00 EUR
+
+Billable without a rate: 1.00 hours
+Not billable: 1.75 hours
+"
+        );
+        assert_eq!(
+            Invoice::new(&[], None, &config).format(&config),
+            "Nothing to bill, see the billable setting and the [rates] section\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use chrono::Duration;
+
+use crate::activity::{duration_in_words, Activities};
+use crate::config::Config;
+use crate::store::Entry;
+
+/**
+ * Time spent in a month on the tasks of a monthly budget from the [budgets] section
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetUse {
+    // task prefix, like the one of [goals]
+    pub prefix: String,
+    pub budget: Duration,
+    pub used: Duration,
+}
+
+impl BudgetUse {
+    // use of all configured budgets by `entries`, like the ones of a month
+    pub fn for_entries(entries: &[Entry], config: &Config) -> Vec<BudgetUse> {
+        if config.budgets.is_empty() {
+            return Vec::new();
+        }
+        let a = Activities::new_from_entries(entries, &config.sentinels);
+        config
+            .budgets
+            .iter()
+            .map(|(prefix, budget)| BudgetUse {
+                prefix: prefix.clone(),
+                budget: *budget,
+                used: a.duration_matching(prefix),
+            })
+            .collect()
+    }
+
+    // the budgets of `after` which are used up, but were not in `before`, like after adding an
+    // entry
+    pub fn crossed<'a>(before: &[BudgetUse], after: &'a [BudgetUse]) -> Vec<&'a BudgetUse> {
+        after
+            .iter()
+            .filter(|a| a.is_exceeded())
+            .filter(|a| {
+                !before
+                    .iter()
+                    .any(|b| b.prefix == a.prefix && b.is_exceeded())
+            })
+            .collect()
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.used > self.budget
+    }
+
+    pub fn percent(&self) -> i64 {
+        self.used.num_minutes() * 100 / self.budget.num_minutes().max(1)
+    }
+
+    pub fn to_plain_string(&self) -> String {
+        let rest = match self.is_exceeded() {
+            true => format!("{} over", duration_in_words(self.used - self.budget)),
+            false => format!("{} left", duration_in_words(self.budget - self.used)),
+        };
+        format!(
+            "{}: {} of {}, {} percent, {rest}",
+            self.prefix,
+            duration_in_words(self.used),
+            duration_in_words(self.budget),
+            self.percent()
+        )
+    }
+}
+
+impl fmt::Display for BudgetUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hours_min = |d: Duration| format!("{} h {} min", d.num_hours(), d.num_minutes() % 60);
+        let rest = match self.is_exceeded() {
+            true => format!("{} over", hours_min(self.used - self.budget)),
+            false => format!("{} left", hours_min(self.budget - self.used)),
+        };
+        write!(
+            f,
+            "{}: {} of {} ({}%), {rest}",
+            self.prefix,
+            hours_min(self.used),
+            hours_min(self.budget),
+            self.percent()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_budgets() {
+        let tl = Timelog::new_from_string(
+            "2024-02-01 09:00: arrived
+2024-02-01 12:00: customer joe: support
+2024-02-01 13:00: rtimelog: code
+
+2024-02-02 09:00: arrived
+2024-02-02 10:30: customer joe: emergency: fix login
+",
+        );
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        assert_eq!(
+            BudgetUse::for_entries(&entries, &Config::default()),
+            Vec::new()
+        );
+
+        let config = Config::new_from_string("[budgets]\ncustomer joe = 4h\nrtimelog = 10h\n");
+        let uses = BudgetUse::for_entries(&entries, &config);
+        assert_eq!(
+            uses,
+            vec![
+                BudgetUse {
+                    prefix: "customer joe".to_string(),
+                    budget: Duration::hours(4),
+                    used: Duration::minutes(270),
+                },
+                BudgetUse {
+                    prefix: "rtimelog".to_string(),
+                    budget: Duration::hours(10),
+                    used: Duration::hours(1),
+                },
+            ]
+        );
+        assert_eq!(
+            uses[0].to_string(),
+            "customer joe: 4 h 30 min of 4 h 0 min (112%), 0 h 30 min over"
+        );
+        assert_eq!(
+            uses[1].to_plain_string(),
+            "rtimelog: 1 hour of 10 hours, 10 percent, 9 hours left"
+        );
+
+        // only the second day pushed customer joe over the budget
+        let before = BudgetUse::for_entries(&entries[..3], &config);
+        assert_eq!(BudgetUse::crossed(&before, &uses), vec![&uses[0]]);
+        assert_eq!(BudgetUse::crossed(&uses, &uses), Vec::<&BudgetUse>::new());
+
+        // a project with a longer name is another one
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived\n2024-02-05 11:00: customer joey: support\n",
+        );
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        assert_eq!(
+            BudgetUse::for_entries(&entries, &config)[0].used,
+            Duration::zero()
+        );
+    }
+}
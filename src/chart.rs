@@ -0,0 +1,166 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::Duration;
+
+use crate::activity::duration_in_words;
+use crate::config::Config;
+use crate::report::{short, PeriodReport};
+
+// characters of the longest bar
+const WIDTH: i64 = 40;
+
+// the ends of the bars, in eighths of a character
+const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+// ANSI colors of the projects, in turn
+const COLORS: [u8; 6] = [36, 32, 33, 35, 34, 31];
+
+// `d` as a bar of up to WIDTH characters, for `max`
+fn bar(d: Duration, max: Duration) -> String {
+    let eighths = (d.num_minutes().max(0) * WIDTH * 8 / max.num_minutes().max(1)) as usize;
+    "█".repeat(eighths / 8) + EIGHTHS[eighths % 8]
+}
+
+// rows of (label, work) with aligned labels and durations, and bars in the `colors` in turn
+fn format_bars(out: &mut String, rows: &[(String, Duration)], colors: &[u8], plain: bool) {
+    if plain {
+        for (label, d) in rows {
+            writeln!(out, "{label}: {}", duration_in_words(*d)).unwrap();
+        }
+        return;
+    }
+    let max = rows.iter().map(|(_, d)| *d).max().unwrap_or_default();
+    let label_width = rows
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+    let time_width = rows.iter().map(|(_, d)| short(*d).len()).max().unwrap_or(0);
+    for (i, (label, d)) in rows.iter().enumerate() {
+        let bar = match (bar(*d, max), colors.len()) {
+            (bar, n) if n == 0 || bar.is_empty() => bar,
+            (bar, n) => format!("\x1b[{}m{bar}\x1b[0m", colors[i % n]),
+        };
+        let line = format!("{label:<label_width$}  {:>time_width$}  {bar}", short(*d));
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+}
+
+// bar charts of the work per day and per project of `report`; `color` with ANSI colors
+pub fn format(report: &PeriodReport, config: &Config, color: bool) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Work from {} to {}\n",
+        config.date_format.format(&report.first),
+        config.date_format.format(&report.last)
+    )
+    .unwrap();
+    if report.projects.is_empty() {
+        writeln!(out, "No work logged").unwrap();
+        return out;
+    }
+    let days: Vec<(String, Duration)> = report
+        .days
+        .iter()
+        .map(|(day, d)| (config.date_format.format_with(day, "%a %d"), *d))
+        .collect();
+    writeln!(out, "Days:").unwrap();
+    let colors: &[u8] = if color { &COLORS } else { &[] };
+    // days in one color, projects in one each
+    format_bars(
+        &mut out,
+        &days,
+        &colors[..colors.len().min(1)],
+        config.plain,
+    );
+    writeln!(out, "\nProjects:").unwrap();
+    format_bars(&mut out, &report.projects, colors, config.plain);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::{NaiveDate, NaiveDateTime};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_bar() {
+        let h = Duration::hours;
+        assert_eq!(bar(h(4), h(4)), "█".repeat(40));
+        assert_eq!(bar(h(1), h(4)), "█".repeat(10));
+        assert_eq!(bar(Duration::minutes(3), h(4)), "▌");
+        assert_eq!(bar(Duration::minutes(0), h(4)), "");
+        assert_eq!(bar(Duration::minutes(0), Duration::minutes(0)), "");
+    }
+
+    #[test]
+    fn test_chart() {
+        let tl = Timelog::new_from_string(
+            "
+2024-01-30 09:00: arrived
+2024-01-30 10:00: customer joe: support
+2024-01-30 10:30: ** tea
+2024-02-01 09:00: arrived
+2024-02-01 12:00: customer joe: support
+2024-02-01 12:30: rtimelog: docs
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let config = Config::default();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let r = PeriodReport::new(entries, day(1, 29), day(2, 4), &config);
+        let full = "█".repeat(40);
+        let hour = "█".repeat(11) + "▍";
+        let eighth = "█".repeat(5);
+        assert_eq!(
+            format(&r, &config, false),
+            format!(
+                "Work from Monday, 2024-01-29 to Sunday, 2024-02-04
+
+Days:
+Mon 29      0m
+Tue 30      1h  {hour}
+Wed 31      0m
+Thu 01  3h 30m  {full}
+Fri 02      0m
+Sat 03      0m
+Sun 04      0m
+
+Projects:
+customer joe   4h  {full}
+rtimelog      30m  {eighth}
+"
+            )
+        );
+        assert!(
+            format(&r, &config, true).contains(&format!("Tue 30      1h  \x1b[36m{hour}\x1b[0m\n"))
+        );
+        assert!(format(&r, &config, true)
+            .ends_with(&format!("rtimelog      30m  \x1b[32m{eighth}\x1b[0m\n")));
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(format(&r, &plain, false).contains("\nThu 01: 3 hours 30 minutes\n"));
+        let r = PeriodReport::new(&[], day(3, 4), day(3, 10), &config);
+        assert!(format(&r, &config, false).ends_with("\n\nNo work logged\n"));
+    }
+}
@@ -0,0 +1,2101 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::env;
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use regex::Regex;
+
+use crate::config::{parse_duration, parse_rate, Config, Rate, RoundingMode, TaskFilter};
+use crate::export::ExportFormat;
+use crate::prompt::PromptFormat;
+use crate::report::{GroupBy, PeriodReport};
+use crate::store::Timelog;
+use crate::table::OutputFormat;
+
+// timelog file, if there is no --file option
+const FILE_ENV: &str = "RTIMELOG_FILE";
+
+pub const USAGE: &str = "Usage: rtimelog [--plain] [--file FILE] [COMMAND]
+  rtimelog                              interactive mode
+  rtimelog pomodoro [--cycles N] [TASK] work on TASK in pomodoro cycles
+  rtimelog add TASK                     log that you just finished TASK
+  rtimelog add --at HH:MM TASK          log that you finished TASK at HH:MM today
+  rtimelog status                       show today's work and the last entry
+  rtimelog standup                      the tasks of the last working day and of
+                                        today so far, for pasting into a chat
+  rtimelog prompt [--format FORMAT]     short status for the shell prompt;
+                                        FORMAT is raw (default), starship, or p10k
+  rtimelog daemon                       keep the log in memory for fast add/status,
+                                        and run the idle tracking and reminders
+  rtimelog remind                       only run the reminders, without a prompt
+  rtimelog report [--yearly [YEAR]]     year in review, of this year by default
+  rtimelog report --daily [YYYY-MM-DD]  time per task and slacking, of today by default
+  rtimelog report --weekly [YYYY-MM-DD] same for the week of that day, with the work
+                                        per day; of this week by default
+  rtimelog report --monthly [YYYY-MM]   time per project and per week of a month, of
+                                        this month by default
+  rtimelog report --from YYYY-MM-DD --to YYYY-MM-DD
+                                        time per task of any days
+  rtimelog report ... --group-by task|day|week|project|category|client [--depth N]
+                                        work per task, day, week, project, category,
+                                        or client of the report; projects get split at
+                                        each colon into N levels, by default all; the
+                                        category is all before the last colon; clients
+                                        come from projects.toml
+  rtimelog report ... --presence        first and last entry, work, slacking, and gap
+                                        to the hours target of each day
+  rtimelog report ... --compare         work per project next to its change since the
+                                        period before, like the week before
+  rtimelog report ... --xlsx FILE       Excel timesheet with a sheet per week, a column
+                                        per day, and a row per project (needs the xlsx
+                                        feature)
+  rtimelog report --merge [--label-by-file] FILE...
+                                        combine this week of several people's
+                                        timelogs, named by path or file name
+  rtimelog report ... --format FORMAT   FORMAT is text (default), csv, json, or
+                                        markdown, also for earnings and invoices
+  rtimelog report ... --round DURATION [--round-mode MODE]
+                                        round the time per task and day to DURATION,
+                                        like 15m; MODE is nearest (default), up, or
+                                        down; also for earnings and invoices
+  rtimelog report ... [--project PROJECT] [--tag TAG] [--match REGEX]
+                                        only count the tasks of PROJECT, like
+                                        \"customer joe\", which have #TAG and match
+                                        REGEX; also for earnings and invoices
+  rtimelog report ... --mail [FILE]     the daily, weekly, monthly, or date range
+                                        report as an email, like gtimelog's, to
+                                        stdout or a .eml FILE
+  rtimelog report ... --send            send that email to the list-email setting
+                                        (needs the smtp feature)
+  rtimelog flexitime [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+                                        overtime per week and its balance, from the
+                                        start of this year until today by default
+  rtimelog chart [--weekly [YYYY-MM-DD]]
+                                        bar charts of the work per day and project of
+                                        this week, or the days of another report
+  rtimelog stats [--last DAYS]          average work, workday, and start, the top
+                                        tasks, and the trend per week of the last
+                                        DAYS like 90d (default) or 12w
+  rtimelog stats --pomodoro [--last DAYS]
+                                        completed pomodoros per day and project, and
+                                        the average length of the work blocks
+  rtimelog stats --heatmap [--last DAYS]
+                                        grid of the work per weekday and hour of the
+                                        day, and how much was outside of work hours
+  rtimelog top [--last DAYS] [-n N] [--projects]
+                                        the N (10) tasks or projects with the most work
+                                        of the last DAYS (30d), with their share; also
+                                        with the report options
+  rtimelog grep PATTERN [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+                                        the entries with tasks which match the regular
+                                        expression or text PATTERN, with their time
+  rtimelog export --format FORMAT [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+                                        the entries as calendar events (FORMAT ics),
+                                        as hledger timeclock check-ins (timeclock),
+                                        or as a web page with charts and tables of
+                                        each week (html); of all days by default
+  rtimelog earnings [--month YYYY-MM]   earnings per currency and project, of this
+                                        month by default
+  rtimelog invoice [--month YYYY-MM] [--rate RATE]
+                                        hours and amounts of the billable projects,
+                                        at RATE like 90 or \"90 USD\", or else their
+                                        [rates]; of this month by default
+  rtimelog archive [--before YEAR]      move the entries of the years before YEAR, by
+                                        default of last year and earlier, to
+                                        timelog-YEAR.txt files; reports still see them
+  rtimelog merge FILE                   add the entries of another timelog FILE, e.g.
+                                        of another computer, which are not there yet
+  rtimelog backup [FILE]                save the timelog, configuration, and calendar
+  rtimelog restore [--check] FILE       verify a backup and put its files back
+  rtimelog doctor                       check the timelog for implausible entries
+  rtimelog dedupe                       remove repeated entries and ones with the time
+                                        of the entry before, after asking
+  rtimelog missing [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--fill]
+                                        the work days without entries, of this month
+                                        until today by default; --fill asks for their
+                                        entries
+  rtimelog migrate [--check]            move ~/.gtimelog to the XDG directories;
+                                        --check only shows what would move
+  rtimelog install-agent                start the reminders on login (macOS)
+  rtimelog --help                       show this help
+
+Options:
+  --plain                               plain text output for screen readers
+  -f, --file FILE                       use FILE as the timelog, instead of
+                                        $RTIMELOG_FILE or gtimelog's timelog.txt";
+
+/**
+ * Options for all commands, before the command
+ */
+#[derive(PartialEq, Debug, Default)]
+pub struct GlobalOptions {
+    pub plain: bool,
+    pub file: Option<PathBuf>,
+}
+
+impl GlobalOptions {
+    // split off the global options from the command and its arguments
+    pub fn parse(mut args: Vec<String>) -> (GlobalOptions, Vec<String>) {
+        let mut options = GlobalOptions::default();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--plain" => options.plain = true,
+                // without a path, Subcommand::parse() complains
+                "-f" | "--file" if i + 1 < args.len() => {
+                    options.file = Some(args[i + 1].clone().into());
+                    i += 1;
+                }
+                a if a.starts_with("--file=") => options.file = Some(a["--file=".len()..].into()),
+                _ => break,
+            }
+            i += 1;
+        }
+        (options, args.split_off(i))
+    }
+
+    pub fn timelog_file(&self) -> PathBuf {
+        if let Some(file) = &self.file {
+            return file.clone();
+        }
+        match env::var_os(FILE_ENV) {
+            Some(file) if !file.is_empty() => PathBuf::from(file),
+            _ => Timelog::get_default_file(),
+        }
+    }
+
+    // the daemon only keeps the default timelog
+    pub fn is_default_file(&self) -> bool {
+        self.timelog_file() == Timelog::get_default_file()
+    }
+}
+
+/**
+ * Days of a report, around the given day or today
+ */
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Period {
+    Day(Option<NaiveDate>),
+    // see Config::week_start
+    Week(Option<NaiveDate>),
+    // any day of the month
+    Month(Option<NaiveDate>),
+    // first and last day
+    Range(NaiveDate, NaiveDate),
+}
+
+impl Period {
+    // (first, last) day
+    pub fn days(&self, today: NaiveDate, config: &Config) -> (NaiveDate, NaiveDate) {
+        match *self {
+            Period::Day(day) => (day.unwrap_or(today), day.unwrap_or(today)),
+            Period::Week(day) => PeriodReport::week(day.unwrap_or(today), config),
+            Period::Month(day) => PeriodReport::month(day.unwrap_or(today)),
+            Period::Range(first, last) => (first, last),
+        }
+    }
+}
+
+/**
+ * Where report --mail or --send puts the email
+ */
+#[derive(PartialEq, Debug, Clone)]
+pub enum Mail {
+    Stdout,
+    // .eml file
+    File(PathBuf),
+    // over SMTP, to the list-email setting
+    Send,
+}
+
+/**
+ * Options of all reports and earnings
+ */
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct ReportOptions {
+    pub format: OutputFormat,
+    // instead of the round and round_mode settings; zero turns rounding off
+    pub round: Option<Duration>,
+    pub round_mode: Option<RoundingMode>,
+    pub filter: TaskFilter,
+}
+
+impl ReportOptions {
+    // take `arg` and its value from `args` if it is one of these options
+    fn parse_arg(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "--format" => match args.next().as_deref().and_then(OutputFormat::parse) {
+                Some(f) => self.format = f,
+                None => return Err("--format needs text, csv, json, or markdown".to_string()),
+            },
+            "--round" => match args.next().as_deref().and_then(parse_duration) {
+                Some(d) => self.round = Some(d),
+                None => return Err("--round needs a duration like 15m".to_string()),
+            },
+            "--round-mode" => match args.next().as_deref().and_then(RoundingMode::parse) {
+                Some(m) => self.round_mode = Some(m),
+                None => return Err("--round-mode needs nearest, up, or down".to_string()),
+            },
+            "--project" => match args.next() {
+                Some(p) => self.filter.project = Some(p),
+                None => return Err("--project needs a project like \"customer joe\"".to_string()),
+            },
+            "--tag" => match args.next() {
+                Some(t) => self.filter.tag = Some(t.trim_start_matches('#').to_string()),
+                None => return Err("--tag needs a tag".to_string()),
+            },
+            "--match" => match args.next().map(|r| Regex::new(&r)) {
+                Some(Ok(r)) => self.filter.pattern = Some(r),
+                Some(Err(e)) => return Err(format!("Invalid --match regular expression: {e}")),
+                None => return Err("--match needs a regular expression".to_string()),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // the configuration with these options
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(round) = self.round {
+            config.rounding.every = (round > Duration::zero()).then_some(round);
+        }
+        if let Some(mode) = self.round_mode {
+            config.rounding.mode = mode;
+        }
+        config.filter = self.filter.clone();
+    }
+}
+
+/**
+ * Command line invocation
+ */
+#[derive(PartialEq, Debug)]
+pub enum Subcommand {
+    Interactive,
+    Help,
+    Pomodoro {
+        task: Option<String>,
+        cycles: u32,
+    },
+    Add(String),
+    // backdated to that time today, like :a in the interactive mode
+    AddAt(NaiveTime, String),
+    Status,
+    Standup,
+    Prompt(PromptFormat),
+    Daemon,
+    Remind,
+    InstallAgent,
+    Report {
+        year: Option<i32>,
+        options: ReportOptions,
+    },
+    PeriodReport {
+        period: Period,
+        // instead of the layout of the period
+        group_by: Option<GroupBy>,
+        options: ReportOptions,
+        mail: Option<Mail>,
+        // with the work per project of the period before
+        compare: bool,
+        // a timesheet workbook instead, see xlsx::timesheet()
+        xlsx: Option<PathBuf>,
+    },
+    TeamReport {
+        files: Vec<PathBuf>,
+        label_by_file: bool,
+        options: ReportOptions,
+    },
+    // from the beginning of the year until today by default
+    Flexitime {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    Chart {
+        period: Period,
+        options: ReportOptions,
+    },
+    // regular expression; all days without --from or --to
+    Grep {
+        pattern: String,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    // until today; or the pomodoros of these days, or their work per weekday and hour
+    Stats {
+        days: i64,
+        pomodoro: bool,
+        heatmap: bool,
+    },
+    // the `count` tasks or projects with the most work in the last `days` until today
+    Top {
+        days: i64,
+        count: usize,
+        projects: bool,
+        options: ReportOptions,
+    },
+    // all days without --from or --to
+    Export {
+        format: ExportFormat,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    // first day of the month
+    Earnings {
+        month: Option<NaiveDate>,
+        options: ReportOptions,
+    },
+    // first day of the month; instead of the [rates]
+    Invoice {
+        month: Option<NaiveDate>,
+        rate: Option<Rate>,
+        options: ReportOptions,
+    },
+    // move the entries before that year to the archives
+    Archive {
+        before: Option<i32>,
+    },
+    // the other timelog
+    Merge(PathBuf),
+    Backup(Option<PathBuf>),
+    Restore {
+        archive: PathBuf,
+        check: bool,
+    },
+    Doctor,
+    // remove repeated entries after asking, see dedupe::find()
+    Dedupe,
+    // work days without entries from `from` (the start of this month) to `to` (today)
+    Missing {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        fill: bool,
+    },
+    // only show what would move, with check
+    Migrate {
+        check: bool,
+    },
+    Error(String),
+}
+
+impl Subcommand {
+    pub fn parse(args: Vec<String>) -> Subcommand {
+        let mut args = args.into_iter();
+        match args.next().as_deref() {
+            None => Subcommand::Interactive,
+            Some("-h") | Some("--help") | Some("help") => Subcommand::Help,
+            Some("pomodoro") => Subcommand::parse_pomodoro(args),
+            Some("add") => Subcommand::parse_add(args),
+            Some("status") => Subcommand::no_args(Subcommand::Status, args),
+            Some("standup") => Subcommand::no_args(Subcommand::Standup, args),
+            Some("prompt") => Subcommand::parse_prompt(args),
+            Some("daemon") => Subcommand::no_args(Subcommand::Daemon, args),
+            Some("remind") => Subcommand::no_args(Subcommand::Remind, args),
+            Some("install-agent") => Subcommand::no_args(Subcommand::InstallAgent, args),
+            Some("report") => Subcommand::parse_report(args),
+            Some("earnings") => Subcommand::parse_earnings(args, false),
+            Some("invoice") => Subcommand::parse_earnings(args, true),
+            Some("flexitime") => Subcommand::parse_flexitime(args),
+            Some("export") => Subcommand::parse_export(args),
+            Some("chart") => Subcommand::parse_chart(args),
+            Some("stats") => Subcommand::parse_stats(args),
+            Some("top") => Subcommand::parse_top(args),
+            Some("grep") => Subcommand::parse_grep(args),
+            Some("archive") => Subcommand::parse_archive(args),
+            Some("merge") => match args.next() {
+                Some(file) => Subcommand::no_args(Subcommand::Merge(file.into()), args),
+                None => Subcommand::Error("merge needs a timelog file".to_string()),
+            },
+            Some("backup") => match args.next() {
+                Some(file) => Subcommand::no_args(Subcommand::Backup(Some(file.into())), args),
+                None => Subcommand::Backup(None),
+            },
+            Some("restore") => Subcommand::parse_restore(args),
+            Some("doctor") => Subcommand::no_args(Subcommand::Doctor, args),
+            Some("dedupe") => Subcommand::no_args(Subcommand::Dedupe, args),
+            Some("missing") => Subcommand::parse_missing(args),
+            Some("migrate") => match args.next().as_deref() {
+                None => Subcommand::Migrate { check: false },
+                Some("--check") => Subcommand::no_args(Subcommand::Migrate { check: true }, args),
+                Some(arg) => Subcommand::Error(format!("Unexpected argument {arg}")),
+            },
+            Some("-f") | Some("--file") => Subcommand::Error("--file needs a path".to_string()),
+            Some(other) => Subcommand::Error(format!("Unknown command {other}")),
+        }
+    }
+
+    fn no_args(command: Subcommand, mut args: impl Iterator<Item = String>) -> Subcommand {
+        match args.next() {
+            None => command,
+            Some(arg) => Subcommand::Error(format!("Unexpected argument {arg}")),
+        }
+    }
+
+    fn parse_report(args: impl Iterator<Item = String>) -> Subcommand {
+        let mut args = args.peekable();
+        let mut year = None;
+        // --year or --yearly, which may be without a year
+        let mut yearly = false;
+        let mut period = None;
+        let (mut from, mut to) = (None, None);
+        let mut group_by = None;
+        let mut depth = None;
+        let mut presence = false;
+        let mut compare = false;
+        let mut xlsx = None;
+        let mut options = ReportOptions::default();
+        let mut mail = None;
+        let mut merge = false;
+        let mut label_by_file = false;
+        let mut files = Vec::new();
+        while let Some(arg) = args.next() {
+            match options.parse_arg(&arg, &mut args) {
+                Ok(true) => continue,
+                Ok(false) => (),
+                Err(e) => return Subcommand::Error(e),
+            }
+            match arg.as_str() {
+                "--year" | "--yearly" => {
+                    let y = match arg.as_str() {
+                        "--year" => args.next(),
+                        _ => args.next_if(|a| !a.starts_with('-')),
+                    };
+                    match y.map(|y| y.parse::<i32>()) {
+                        Some(Ok(y)) if (1..=9999).contains(&y) => year = Some(y),
+                        None if arg == "--yearly" => (),
+                        _ => return Subcommand::Error("Invalid year".to_string()),
+                    }
+                    yearly = true;
+                }
+                "--monthly" => {
+                    let month = match args.next_if(|a| !a.starts_with('-')) {
+                        Some(m) => {
+                            match NaiveDate::parse_from_str(&format!("{m}-01"), "%Y-%m-%d") {
+                                Ok(m) => Some(m),
+                                Err(_) => return Subcommand::Error(format!("Invalid month {m}")),
+                            }
+                        }
+                        None => None,
+                    };
+                    period = Some(Period::Month(month));
+                }
+                "--daily" | "--weekly" => {
+                    let day = match args.next_if(|a| !a.starts_with('-')) {
+                        Some(d) => match NaiveDate::parse_from_str(&d, "%Y-%m-%d") {
+                            Ok(d) => Some(d),
+                            Err(_) => return Subcommand::Error(format!("Invalid date {d}")),
+                        },
+                        None => None,
+                    };
+                    period = Some(match arg.as_str() {
+                        "--daily" => Period::Day(day),
+                        _ => Period::Week(day),
+                    });
+                }
+                "--from" | "--to" => {
+                    let day = match args
+                        .next()
+                        .map(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
+                    {
+                        Some(Ok(d)) => d,
+                        _ => return Subcommand::Error(format!("{arg} needs a YYYY-MM-DD date")),
+                    };
+                    match arg.as_str() {
+                        "--from" => from = Some(day),
+                        _ => to = Some(day),
+                    }
+                }
+                "--group-by" => match args.next().as_deref() {
+                    Some("task") => group_by = Some(GroupBy::Task),
+                    Some("day") => group_by = Some(GroupBy::Day),
+                    Some("week") => group_by = Some(GroupBy::Week),
+                    Some("project") => group_by = Some(GroupBy::Project { depth: usize::MAX }),
+                    Some("category") => group_by = Some(GroupBy::Category),
+                    Some("client") => group_by = Some(GroupBy::Client),
+                    _ => {
+                        return Subcommand::Error(
+                            "--group-by needs task, day, week, project, category, or client"
+                                .to_string(),
+                        )
+                    }
+                },
+                "--presence" => presence = true,
+                "--compare" => compare = true,
+                "--xlsx" => match args.next() {
+                    Some(file) => xlsx = Some(PathBuf::from(file)),
+                    None => return Subcommand::Error("--xlsx needs a file".to_string()),
+                },
+                "--depth" => match args.next().map(|d| d.parse::<usize>()) {
+                    Some(Ok(d)) if d > 0 => depth = Some(d),
+                    _ => return Subcommand::Error("--depth needs a number of levels".to_string()),
+                },
+                "--mail" | "--send" if mail.is_some() => {
+                    return Subcommand::Error("Use either --mail or --send".to_string())
+                }
+                "--send" => mail = Some(Mail::Send),
+                "--mail" => {
+                    mail = Some(match args.next_if(|a| !a.starts_with('-')) {
+                        Some(file) => Mail::File(PathBuf::from(file)),
+                        None => Mail::Stdout,
+                    })
+                }
+                "--merge" => merge = true,
+                "--label-by-file" => label_by_file = true,
+                _ if merge && !arg.starts_with('-') => files.push(PathBuf::from(arg)),
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        if presence {
+            if group_by.is_some() {
+                return Subcommand::Error("--presence replaces --group-by".to_string());
+            }
+            group_by = Some(GroupBy::Presence);
+        }
+        // splitting the tasks deeper implies grouping them by project
+        match (group_by, depth) {
+            (None | Some(GroupBy::Project { .. }), Some(depth)) => {
+                group_by = Some(GroupBy::Project { depth })
+            }
+            (Some(_), Some(_)) => {
+                return Subcommand::Error("--depth only works for projects".to_string())
+            }
+            (_, None) => (),
+        }
+        match (from, to, period) {
+            (Some(from), Some(to), None) if from <= to => period = Some(Period::Range(from, to)),
+            (Some(_), Some(_), None) => {
+                return Subcommand::Error("--from has to be before --to".to_string())
+            }
+            (Some(_), None, _) | (None, Some(_), _) => {
+                return Subcommand::Error("--from and --to go together".to_string())
+            }
+            (Some(_), Some(_), Some(_)) => {
+                return Subcommand::Error(
+                    "--from and --to replace --daily, --weekly, and --monthly".to_string(),
+                )
+            }
+            (None, None, _) => (),
+        }
+        if xlsx.is_some()
+            && (mail.is_some()
+                || compare
+                || group_by.is_some()
+                || options.format != OutputFormat::Text)
+        {
+            return Subcommand::Error(
+                "--xlsx has its own layout, without --format, --group-by, --compare, or --mail"
+                    .to_string(),
+            );
+        }
+        if mail.is_some() && options.format != OutputFormat::Text {
+            return Subcommand::Error(
+                "--mail and --send take the text report, not --format".to_string(),
+            );
+        }
+        if let Some(period) = period {
+            return match (merge, yearly) {
+                (false, false) => Subcommand::PeriodReport {
+                    period,
+                    group_by,
+                    options,
+                    mail,
+                    compare,
+                    xlsx,
+                },
+                _ => Subcommand::Error(
+                    "--daily, --weekly, and --monthly report your own days, not a year or team"
+                        .to_string(),
+                ),
+            };
+        }
+        if presence {
+            return Subcommand::Error("--presence needs the days to report".to_string());
+        }
+        if compare {
+            return Subcommand::Error(
+                "--compare needs --daily, --weekly, --monthly, or --from and --to".to_string(),
+            );
+        }
+        if xlsx.is_some() {
+            return Subcommand::Error(
+                "--xlsx needs --daily, --weekly, --monthly, or --from and --to".to_string(),
+            );
+        }
+        if group_by.is_some() {
+            return Subcommand::Error("--group-by needs the days to report".to_string());
+        }
+        if mail.is_some() {
+            return Subcommand::Error(
+                "--mail and --send need --daily, --weekly, --monthly, or --from and --to"
+                    .to_string(),
+            );
+        }
+        match (merge, yearly) {
+            (false, _) if label_by_file => {
+                Subcommand::Error("--label-by-file needs --merge".to_string())
+            }
+            (false, _) => Subcommand::Report { year, options },
+            (true, true) => Subcommand::Error("--merge reports a week, not a year".to_string()),
+            (true, false) if files.is_empty() => {
+                Subcommand::Error("--merge needs timelog files".to_string())
+            }
+            (true, false) => Subcommand::TeamReport {
+                files,
+                label_by_file,
+                options,
+            },
+        }
+    }
+
+    // also for `invoice`, which has a --rate
+    fn parse_earnings(mut args: impl Iterator<Item = String>, invoice: bool) -> Subcommand {
+        let mut month = None;
+        let mut rate = None;
+        let mut options = ReportOptions::default();
+        while let Some(arg) = args.next() {
+            match options.parse_arg(&arg, &mut args) {
+                Ok(true) => continue,
+                Ok(false) => (),
+                Err(e) => return Subcommand::Error(e),
+            }
+            match arg.as_str() {
+                "--month" => {
+                    match args.next().and_then(|m| {
+                        NaiveDate::parse_from_str(&format!("{m}-01"), "%Y-%m-%d").ok()
+                    }) {
+                        Some(m) => month = Some(m),
+                        None => return Subcommand::Error("Invalid month".to_string()),
+                    }
+                }
+                "--rate" if invoice => match args.next().as_deref().and_then(parse_rate) {
+                    Some(r) => rate = Some(r),
+                    None => {
+                        return Subcommand::Error("--rate needs an hourly rate like 90".to_string())
+                    }
+                },
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        match invoice {
+            true => Subcommand::Invoice {
+                month,
+                rate,
+                options,
+            },
+            false => Subcommand::Earnings { month, options },
+        }
+    }
+
+    fn parse_flexitime(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let (mut from, mut to) = (None, None);
+        while let Some(arg) = args.next() {
+            let day = match arg.as_str() {
+                "--from" | "--to" => args
+                    .next()
+                    .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            };
+            let Some(day) = day else {
+                return Subcommand::Error(format!("{arg} needs a YYYY-MM-DD date"));
+            };
+            match arg.as_str() {
+                "--from" => from = Some(day),
+                _ => to = Some(day),
+            }
+        }
+        match (from, to) {
+            (Some(from), Some(to)) if from > to => {
+                Subcommand::Error("--from has to be before --to".to_string())
+            }
+            _ => Subcommand::Flexitime { from, to },
+        }
+    }
+
+    fn parse_missing(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let (mut from, mut to) = (None, None);
+        let mut fill = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" | "--to" => {
+                    let Some(day) = args
+                        .next()
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+                    else {
+                        return Subcommand::Error(format!("{arg} needs a YYYY-MM-DD date"));
+                    };
+                    match arg.as_str() {
+                        "--from" => from = Some(day),
+                        _ => to = Some(day),
+                    }
+                }
+                "--fill" => fill = true,
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        match (from, to) {
+            (Some(from), Some(to)) if from > to => {
+                Subcommand::Error("--from has to be before --to".to_string())
+            }
+            _ => Subcommand::Missing { from, to, fill },
+        }
+    }
+
+    fn parse_grep(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut pattern = None;
+        let (mut from, mut to) = (None, None);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" | "--to" => {
+                    let Some(day) = args
+                        .next()
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+                    else {
+                        return Subcommand::Error(format!("{arg} needs a YYYY-MM-DD date"));
+                    };
+                    match arg.as_str() {
+                        "--from" => from = Some(day),
+                        _ => to = Some(day),
+                    }
+                }
+                _ if pattern.is_none() => pattern = Some(arg),
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        let Some(pattern) = pattern else {
+            return Subcommand::Error("grep needs a pattern".to_string());
+        };
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Subcommand::Error("--from has to be before --to".to_string());
+            }
+        }
+        Subcommand::Grep {
+            // anything else is a plain text to look for, like "C++"
+            pattern: match Regex::new(&pattern) {
+                Ok(_) => pattern,
+                Err(_) => regex::escape(&pattern),
+            },
+            from,
+            to,
+        }
+    }
+
+    fn parse_add(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut at = None;
+        let mut words = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--at" => match args
+                    .next()
+                    .and_then(|t| NaiveTime::parse_from_str(&t, "%H:%M").ok())
+                {
+                    Some(time) => at = Some(time),
+                    None => return Subcommand::Error("--at needs a HH:MM time".to_string()),
+                },
+                _ => words.push(arg),
+            }
+        }
+        let task = words.join(" ").trim().to_string();
+        match (task.is_empty(), at) {
+            (true, _) => Subcommand::Error("add needs a task".to_string()),
+            (false, Some(time)) => Subcommand::AddAt(time, task),
+            (false, None) => Subcommand::Add(task),
+        }
+    }
+
+    fn parse_stats(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut days = None;
+        let mut pomodoro = false;
+        let mut heatmap = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--last" => match args.next().as_deref().and_then(parse_days) {
+                    Some(d) => days = Some(d),
+                    None => {
+                        return Subcommand::Error(
+                            "--last needs a number of days or weeks, like 90d or 12w".to_string(),
+                        )
+                    }
+                },
+                "--pomodoro" => pomodoro = true,
+                "--heatmap" => heatmap = true,
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        if pomodoro && heatmap {
+            return Subcommand::Error("--pomodoro and --heatmap are separate stats".to_string());
+        }
+        Subcommand::Stats {
+            days: days.unwrap_or(90),
+            pomodoro,
+            heatmap,
+        }
+    }
+
+    // the last 30 days by default
+    fn parse_top(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut days = None;
+        let mut count = None;
+        let mut projects = false;
+        let mut options = ReportOptions::default();
+        while let Some(arg) = args.next() {
+            match options.parse_arg(&arg, &mut args) {
+                Ok(true) => continue,
+                Ok(false) => (),
+                Err(e) => return Subcommand::Error(e),
+            }
+            match arg.as_str() {
+                "--last" => match args.next().as_deref().and_then(parse_days) {
+                    Some(d) => days = Some(d),
+                    None => {
+                        return Subcommand::Error(
+                            "--last needs a number of days or weeks, like 30d or 4w".to_string(),
+                        )
+                    }
+                },
+                "-n" => match args.next().map(|n| n.parse::<usize>()) {
+                    Some(Ok(n)) if n > 0 => count = Some(n),
+                    _ => return Subcommand::Error("-n needs a number of tasks".to_string()),
+                },
+                "--projects" => projects = true,
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        Subcommand::Top {
+            days: days.unwrap_or(30),
+            count: count.unwrap_or(10),
+            projects,
+            options,
+        }
+    }
+
+    // the days of a report, this week by default
+    fn parse_chart(args: impl Iterator<Item = String>) -> Subcommand {
+        let args: Vec<String> = args.collect();
+        // Report { year: None } is also the plain `report`
+        if args.iter().any(|a| a == "--year" || a == "--yearly") {
+            return Subcommand::Error(
+                "chart needs --daily, --weekly, --monthly, or --from and --to".to_string(),
+            );
+        }
+        let (period, options) = match Subcommand::parse_report(args.into_iter()) {
+            Subcommand::Report {
+                year: None,
+                options,
+            } => (Period::Week(None), options),
+            Subcommand::PeriodReport {
+                period,
+                group_by: None,
+                options,
+                mail: None,
+                compare: false,
+                xlsx: None,
+            } => (period, options),
+            Subcommand::PeriodReport { compare: true, .. } => {
+                return Subcommand::Error("chart has no --compare".to_string())
+            }
+            Subcommand::PeriodReport { xlsx: Some(_), .. } => {
+                return Subcommand::Error("chart has no --xlsx".to_string())
+            }
+            Subcommand::Error(e) => return Subcommand::Error(e),
+            _ => {
+                return Subcommand::Error(
+                    "chart needs --daily, --weekly, --monthly, or --from and --to".to_string(),
+                )
+            }
+        };
+        if options.format != OutputFormat::Text {
+            return Subcommand::Error("chart has no --format".to_string());
+        }
+        Subcommand::Chart { period, options }
+    }
+
+    fn parse_export(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut format = None;
+        let (mut from, mut to) = (None, None);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => match args.next().as_deref().and_then(ExportFormat::parse) {
+                    Some(f) => format = Some(f),
+                    None => {
+                        return Subcommand::Error(
+                            "--format needs ics, timeclock, or html".to_string(),
+                        )
+                    }
+                },
+                "--from" | "--to" => {
+                    let Some(day) = args
+                        .next()
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+                    else {
+                        return Subcommand::Error(format!("{arg} needs a YYYY-MM-DD date"));
+                    };
+                    match arg.as_str() {
+                        "--from" => from = Some(day),
+                        _ => to = Some(day),
+                    }
+                }
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        match (format, from, to) {
+            (None, _, _) => {
+                Subcommand::Error("export needs --format ics, timeclock, or html".to_string())
+            }
+            (_, Some(from), Some(to)) if from > to => {
+                Subcommand::Error("--from has to be before --to".to_string())
+            }
+            (Some(format), _, _) => Subcommand::Export { format, from, to },
+        }
+    }
+
+    fn parse_archive(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut before = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--before" => match args.next().map(|y| y.parse::<i32>()) {
+                    Some(Ok(y)) if (1..=9999).contains(&y) => before = Some(y),
+                    _ => return Subcommand::Error("Invalid year".to_string()),
+                },
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        Subcommand::Archive { before }
+    }
+
+    fn parse_restore(args: impl Iterator<Item = String>) -> Subcommand {
+        let mut check = false;
+        let mut archive = None;
+        for arg in args {
+            match arg.as_str() {
+                "--check" => check = true,
+                _ if arg.starts_with('-') => {
+                    return Subcommand::Error(format!("Unknown option {arg}"))
+                }
+                _ if archive.is_none() => archive = Some(PathBuf::from(arg)),
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        match archive {
+            Some(archive) => Subcommand::Restore { archive, check },
+            None => Subcommand::Error("restore needs a backup file".to_string()),
+        }
+    }
+
+    fn parse_prompt(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut format = PromptFormat::Raw;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => match args.next().as_deref().and_then(PromptFormat::parse) {
+                    Some(f) => format = f,
+                    None => return Subcommand::Error("Invalid prompt format".to_string()),
+                },
+                _ => return Subcommand::Error(format!("Unexpected argument {arg}")),
+            }
+        }
+        Subcommand::Prompt(format)
+    }
+
+    fn parse_pomodoro(mut args: impl Iterator<Item = String>) -> Subcommand {
+        let mut cycles = 4;
+        let mut task: Option<String> = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--cycles" | "-n" => match args.next().map(|n| n.parse::<u32>()) {
+                    Some(Ok(n)) if n > 0 => cycles = n,
+                    _ => return Subcommand::Error("Invalid number of cycles".to_string()),
+                },
+                _ if arg.starts_with('-') => {
+                    return Subcommand::Error(format!("Unknown option {arg}"))
+                }
+                // allow an unquoted multi-word task
+                _ => match task.as_mut() {
+                    Some(t) => {
+                        t.push(' ');
+                        t.push_str(&arg);
+                    }
+                    None => task = Some(arg),
+                },
+            }
+        }
+        Subcommand::Pomodoro { task, cycles }
+    }
+}
+
+// like "90d" or "12w", or plain days
+fn parse_days(s: &str) -> Option<i64> {
+    let (number, unit) = match s.strip_suffix(['d', 'w']) {
+        Some(n) => (n, &s[n.len()..]),
+        None => (s, "d"),
+    };
+    let n = number.parse::<i64>().ok().filter(|n| *n > 0)?;
+    n.checked_mul(if unit == "w" { 7 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Subcommand {
+        Subcommand::parse(args.iter().map(|a| a.to_string()).collect())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Subcommand::Interactive);
+        assert_eq!(parse(&["--help"]), Subcommand::Help);
+        assert_eq!(parse(&["-h"]), Subcommand::Help);
+        assert_eq!(parse(&["remind"]), Subcommand::Remind);
+        assert_eq!(parse(&["status"]), Subcommand::Status);
+        assert_eq!(parse(&["standup"]), Subcommand::Standup);
+        assert_eq!(parse(&["daemon"]), Subcommand::Daemon);
+        assert_eq!(parse(&["doctor"]), Subcommand::Doctor);
+        assert_eq!(parse(&["dedupe"]), Subcommand::Dedupe);
+        assert_eq!(
+            parse(&["missing"]),
+            Subcommand::Missing {
+                from: None,
+                to: None,
+                fill: false
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "missing",
+                "--from",
+                "2024-02-01",
+                "--to",
+                "2024-02-29",
+                "--fill"
+            ]),
+            Subcommand::Missing {
+                from: NaiveDate::from_ymd_opt(2024, 2, 1),
+                to: NaiveDate::from_ymd_opt(2024, 2, 29),
+                fill: true
+            }
+        );
+        assert_eq!(
+            parse(&["missing", "--from", "2024-03-01", "--to", "2024-02-29"]),
+            Subcommand::Error("--from has to be before --to".to_string())
+        );
+        assert_eq!(
+            parse(&["missing", "--to", "february"]),
+            Subcommand::Error("--to needs a YYYY-MM-DD date".to_string())
+        );
+        assert_eq!(parse(&["migrate"]), Subcommand::Migrate { check: false });
+        assert_eq!(
+            parse(&["migrate", "--check"]),
+            Subcommand::Migrate { check: true }
+        );
+        assert_eq!(
+            parse(&["migrate", "--force"]),
+            Subcommand::Error("Unexpected argument --force".to_string())
+        );
+        assert_eq!(
+            parse(&["add", "customer", "joe: support"]),
+            Subcommand::Add("customer joe: support".to_string())
+        );
+        assert_eq!(
+            parse(&["add"]),
+            Subcommand::Error("add needs a task".to_string())
+        );
+        let at = NaiveTime::from_hms_opt(10, 30, 0).unwrap();
+        assert_eq!(
+            parse(&["add", "--at", "10:30", "customer joe: support"]),
+            Subcommand::AddAt(at, "customer joe: support".to_string())
+        );
+        assert_eq!(
+            parse(&["add", "code", "--at", "10:30"]),
+            Subcommand::AddAt(at, "code".to_string())
+        );
+        assert_eq!(
+            parse(&["add", "--at", "10:30"]),
+            Subcommand::Error("add needs a task".to_string())
+        );
+        assert_eq!(
+            parse(&["add", "--at", "half past ten", "code"]),
+            Subcommand::Error("--at needs a HH:MM time".to_string())
+        );
+        assert_eq!(parse(&["install-agent"]), Subcommand::InstallAgent);
+        assert_eq!(
+            parse(&["remind", "now"]),
+            Subcommand::Error("Unexpected argument now".to_string())
+        );
+        assert_eq!(
+            parse(&["frobnicate"]),
+            Subcommand::Error("Unknown command frobnicate".to_string())
+        );
+    }
+
+    #[test]
+    fn test_global_options() {
+        let args = |a: &[&str]| a.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            GlobalOptions::parse(args(&[])),
+            (GlobalOptions::default(), args(&[]))
+        );
+        assert_eq!(
+            GlobalOptions::parse(args(&["--plain", "pomodoro", "--plain"])),
+            (
+                GlobalOptions {
+                    plain: true,
+                    file: None
+                },
+                args(&["pomodoro", "--plain"])
+            )
+        );
+        assert_eq!(
+            GlobalOptions::parse(args(&["-f", "joe.txt", "--plain", "status"])),
+            (
+                GlobalOptions {
+                    plain: true,
+                    file: Some("joe.txt".into())
+                },
+                args(&["status"])
+            )
+        );
+        assert_eq!(
+            GlobalOptions::parse(args(&["--file=acme/timelog.txt", "report"])).0,
+            GlobalOptions {
+                plain: false,
+                file: Some("acme/timelog.txt".into())
+            }
+        );
+        assert_eq!(
+            GlobalOptions::parse(args(&["--file=x.txt"]))
+                .0
+                .timelog_file(),
+            PathBuf::from("x.txt")
+        );
+        let (options, rest) = GlobalOptions::parse(args(&["--plain", "--file"]));
+        assert_eq!(options.file, None);
+        assert_eq!(
+            Subcommand::parse(rest),
+            Subcommand::Error("--file needs a path".to_string())
+        );
+        assert_eq!(
+            GlobalOptions::parse(args(&["status"])),
+            (GlobalOptions::default(), args(&["status"]))
+        );
+    }
+
+    #[test]
+    fn test_parse_pomodoro() {
+        assert_eq!(
+            parse(&["pomodoro"]),
+            Subcommand::Pomodoro {
+                task: None,
+                cycles: 4
+            }
+        );
+        assert_eq!(
+            parse(&["pomodoro", "rtimelog: code"]),
+            Subcommand::Pomodoro {
+                task: Some("rtimelog: code".to_string()),
+                cycles: 4
+            }
+        );
+        assert_eq!(
+            parse(&["pomodoro", "-n", "2", "rtimelog:", "code"]),
+            Subcommand::Pomodoro {
+                task: Some("rtimelog: code".to_string()),
+                cycles: 2
+            }
+        );
+        assert_eq!(
+            parse(&["pomodoro", "--cycles", "0"]),
+            Subcommand::Error("Invalid number of cycles".to_string())
+        );
+        assert_eq!(
+            parse(&["pomodoro", "--cycles"]),
+            Subcommand::Error("Invalid number of cycles".to_string())
+        );
+        assert_eq!(
+            parse(&["pomodoro", "--long"]),
+            Subcommand::Error("Unknown option --long".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            parse(&["report"]),
+            Subcommand::Report {
+                year: None,
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--year", "2024"]),
+            Subcommand::Report {
+                year: Some(2024),
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--year", "last"]),
+            Subcommand::Error("Invalid year".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--yearly", "2024", "--format", "markdown"]),
+            Subcommand::Report {
+                year: Some(2024),
+                options: ReportOptions {
+                    format: OutputFormat::Markdown,
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--yearly"]),
+            Subcommand::Report {
+                year: None,
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--yearly", "--weekly"]),
+            Subcommand::Error(
+                "--daily, --weekly, and --monthly report your own days, not a year or team"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--month"]),
+            Subcommand::Error("Unexpected argument --month".to_string())
+        );
+        assert_eq!(
+            parse(&[
+                "report",
+                "--merge",
+                "alice.txt",
+                "--label-by-file",
+                "bob.txt"
+            ]),
+            Subcommand::TeamReport {
+                files: vec![PathBuf::from("alice.txt"), PathBuf::from("bob.txt")],
+                label_by_file: true,
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--merge"]),
+            Subcommand::Error("--merge needs timelog files".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--year", "2024", "--merge", "a.txt"]),
+            Subcommand::Error("--merge reports a week, not a year".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--daily"]),
+            Subcommand::PeriodReport {
+                period: Period::Day(None),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--daily", "2024-02-01"]),
+            Subcommand::PeriodReport {
+                period: Period::Day(NaiveDate::from_ymd_opt(2024, 2, 1)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "2024-02-01"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(NaiveDate::from_ymd_opt(2024, 2, 1)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "2024-02"]),
+            Subcommand::PeriodReport {
+                period: Period::Month(NaiveDate::from_ymd_opt(2024, 2, 1)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        let day = |d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        assert_eq!(
+            parse(&[
+                "report",
+                "--from",
+                "2024-01-01",
+                "--to",
+                "2024-03-31",
+                "--group-by",
+                "week"
+            ]),
+            Subcommand::PeriodReport {
+                period: Period::Range(day("2024-01-01"), day("2024-03-31")),
+                group_by: Some(GroupBy::Week),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--group-by", "day"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: Some(GroupBy::Day),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--daily", "--group-by", "project"]),
+            Subcommand::PeriodReport {
+                period: Period::Day(None),
+                group_by: Some(GroupBy::Project { depth: usize::MAX }),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--group-by", "category"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: Some(GroupBy::Category),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "--group-by", "client"]),
+            Subcommand::PeriodReport {
+                period: Period::Month(None),
+                group_by: Some(GroupBy::Client),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "--depth", "2"]),
+            Subcommand::PeriodReport {
+                period: Period::Month(None),
+                group_by: Some(GroupBy::Project { depth: 2 }),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--depth", "0"]),
+            Subcommand::Error("--depth needs a number of levels".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--depth", "1", "--group-by", "day"]),
+            Subcommand::Error("--depth only works for projects".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--from", "2024-01-01"]),
+            Subcommand::Error("--from and --to go together".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--from", "2024-02-01", "--to", "2024-01-31"]),
+            Subcommand::Error("--from has to be before --to".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--to", "tomorrow"]),
+            Subcommand::Error("--to needs a YYYY-MM-DD date".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--presence"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: Some(GroupBy::Presence),
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--presence", "--group-by", "day"]),
+            Subcommand::Error("--presence replaces --group-by".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--presence"]),
+            Subcommand::Error("--presence needs the days to report".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--compare", "--weekly", "2024-02-05"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(NaiveDate::from_ymd_opt(2024, 2, 5)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: true,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "2024-02", "--xlsx", "feb.xlsx"]),
+            Subcommand::PeriodReport {
+                period: Period::Month(NaiveDate::from_ymd_opt(2024, 2, 1)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: Some(PathBuf::from("feb.xlsx")),
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--xlsx"]),
+            Subcommand::Error("--xlsx needs a file".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--xlsx", "w.xlsx", "--format", "csv"]),
+            Subcommand::Error(
+                "--xlsx has its own layout, without --format, --group-by, --compare, or --mail"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--xlsx", "w.xlsx"]),
+            Subcommand::Error(
+                "--xlsx needs --daily, --weekly, --monthly, or --from and --to".to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--compare"]),
+            Subcommand::Error(
+                "--compare needs --daily, --weekly, --monthly, or --from and --to".to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--group-by", "month"]),
+            Subcommand::Error(
+                "--group-by needs task, day, week, project, category, or client".to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--year", "2024", "--group-by", "day"]),
+            Subcommand::Error("--group-by needs the days to report".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "2024-13"]),
+            Subcommand::Error("Invalid month 2024-13".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--weekly"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--daily", "yesterday"]),
+            Subcommand::Error("Invalid date yesterday".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--daily", "--year", "2024"]),
+            Subcommand::Error(
+                "--daily, --weekly, and --monthly report your own days, not a year or team"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--label-by-file"]),
+            Subcommand::Error("--label-by-file needs --merge".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "a.txt"]),
+            Subcommand::Error("Unexpected argument a.txt".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--format", "csv", "--year", "2024"]),
+            Subcommand::Report {
+                year: Some(2024),
+                options: ReportOptions {
+                    format: OutputFormat::Csv,
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--format", "md"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: None,
+                options: ReportOptions {
+                    format: OutputFormat::Markdown,
+                    ..Default::default()
+                },
+                mail: None,
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--format", "xml"]),
+            Subcommand::Error("--format needs text, csv, json, or markdown".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--mail"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(None),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: Some(Mail::Stdout),
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--mail", "week.eml", "--weekly", "2024-02-01"]),
+            Subcommand::PeriodReport {
+                period: Period::Week(NaiveDate::from_ymd_opt(2024, 2, 1)),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: Some(Mail::File(PathBuf::from("week.eml"))),
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--mail"]),
+            Subcommand::Error(
+                "--mail and --send need --daily, --weekly, --monthly, or --from and --to"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["report", "--daily", "--mail", "--format", "csv"]),
+            Subcommand::Error("--mail and --send take the text report, not --format".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--monthly", "--send"]),
+            Subcommand::PeriodReport {
+                period: Period::Month(None),
+                group_by: None,
+                options: ReportOptions::default(),
+                mail: Some(Mail::Send),
+                compare: false,
+                xlsx: None,
+            }
+        );
+        assert_eq!(
+            parse(&["report", "--weekly", "--send", "--mail"]),
+            Subcommand::Error("Use either --mail or --send".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_earnings() {
+        assert_eq!(
+            parse(&["earnings"]),
+            Subcommand::Earnings {
+                month: None,
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["earnings", "--month", "2022-06"]),
+            Subcommand::Earnings {
+                month: NaiveDate::from_ymd_opt(2022, 6, 1),
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["earnings", "--month", "2022-13"]),
+            Subcommand::Error("Invalid month".to_string())
+        );
+        assert_eq!(
+            parse(&["earnings", "--round", "15m", "--round-mode", "up"]),
+            Subcommand::Earnings {
+                month: None,
+                options: ReportOptions {
+                    round: Some(Duration::minutes(15)),
+                    round_mode: Some(RoundingMode::Up),
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["earnings", "--round", "often"]),
+            Subcommand::Error("--round needs a duration like 15m".to_string())
+        );
+        assert_eq!(
+            parse(&["report", "--round-mode", "sideways"]),
+            Subcommand::Error("--round-mode needs nearest, up, or down".to_string())
+        );
+
+        assert_eq!(
+            parse(&["invoice", "--month", "2024-05", "--rate", "90", "--round", "15m"]),
+            Subcommand::Invoice {
+                month: NaiveDate::from_ymd_opt(2024, 5, 1),
+                rate: Some(Rate {
+                    cents: 9000,
+                    currency: None
+                }),
+                options: ReportOptions {
+                    round: Some(Duration::minutes(15)),
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["invoice", "--rate", "lots"]),
+            Subcommand::Error("--rate needs an hourly rate like 90".to_string())
+        );
+        assert_eq!(
+            parse(&["earnings", "--rate", "90"]),
+            Subcommand::Error("Unexpected argument --rate".to_string())
+        );
+        assert_eq!(
+            parse(&["earnings", "--format", "json"]),
+            Subcommand::Earnings {
+                month: None,
+                options: ReportOptions {
+                    format: OutputFormat::Json,
+                    ..Default::default()
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_grep() {
+        assert_eq!(
+            parse(&["grep", "--from", "2024-05-01", "(?i)login"]),
+            Subcommand::Grep {
+                pattern: "(?i)login".to_string(),
+                from: NaiveDate::from_ymd_opt(2024, 5, 1),
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["grep", "C++ (build"]),
+            Subcommand::Grep {
+                pattern: r"C\+\+ \(build".to_string(),
+                from: None,
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["grep"]),
+            Subcommand::Error("grep needs a pattern".to_string())
+        );
+        assert_eq!(
+            parse(&["grep", "a", "b"]),
+            Subcommand::Error("Unexpected argument b".to_string())
+        );
+        assert_eq!(
+            parse(&["grep", "a", "--from", "2024-05-02", "--to", "2024-05-01"]),
+            Subcommand::Error("--from has to be before --to".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let stats = |days| Subcommand::Stats {
+            days,
+            pomodoro: false,
+            heatmap: false,
+        };
+        assert_eq!(parse(&["stats"]), stats(90));
+        assert_eq!(parse(&["stats", "--last", "30d"]), stats(30));
+        assert_eq!(parse(&["stats", "--last", "12w"]), stats(84));
+        assert_eq!(parse(&["stats", "--last", "14"]), stats(14));
+        assert_eq!(
+            parse(&["stats", "--pomodoro", "--last", "2w"]),
+            Subcommand::Stats {
+                days: 14,
+                pomodoro: true,
+                heatmap: false
+            }
+        );
+        assert_eq!(
+            parse(&["stats", "--heatmap"]),
+            Subcommand::Stats {
+                days: 90,
+                pomodoro: false,
+                heatmap: true
+            }
+        );
+        assert_eq!(
+            parse(&["stats", "--heatmap", "--pomodoro"]),
+            Subcommand::Error("--pomodoro and --heatmap are separate stats".to_string())
+        );
+        for last in ["0d", "3m", "d"] {
+            assert_eq!(
+                parse(&["stats", "--last", last]),
+                Subcommand::Error(
+                    "--last needs a number of days or weeks, like 90d or 12w".to_string()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_top() {
+        assert_eq!(
+            parse(&["top"]),
+            Subcommand::Top {
+                days: 30,
+                count: 10,
+                projects: false,
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "top",
+                "--last",
+                "4w",
+                "-n",
+                "3",
+                "--projects",
+                "--format",
+                "csv"
+            ]),
+            Subcommand::Top {
+                days: 28,
+                count: 3,
+                projects: true,
+                options: ReportOptions {
+                    format: OutputFormat::Csv,
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["top", "-n", "0"]),
+            Subcommand::Error("-n needs a number of tasks".to_string())
+        );
+        assert_eq!(
+            parse(&["top", "--last"]),
+            Subcommand::Error("--last needs a number of days or weeks, like 30d or 4w".to_string())
+        );
+        assert_eq!(
+            parse(&["top", "10"]),
+            Subcommand::Error("Unexpected argument 10".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_chart() {
+        assert_eq!(
+            parse(&["chart"]),
+            Subcommand::Chart {
+                period: Period::Week(None),
+                options: ReportOptions::default()
+            }
+        );
+        assert_eq!(
+            parse(&["chart", "--monthly", "2024-05", "--project", "acme"]),
+            Subcommand::Chart {
+                period: Period::Month(NaiveDate::from_ymd_opt(2024, 5, 1)),
+                options: ReportOptions {
+                    filter: TaskFilter {
+                        project: Some("acme".to_string()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            parse(&["chart", "--weekly", "--group-by", "day"]),
+            Subcommand::Error(
+                "chart needs --daily, --weekly, --monthly, or --from and --to".to_string()
+            )
+        );
+        assert_eq!(
+            parse(&["chart", "--format", "csv"]),
+            Subcommand::Error("chart has no --format".to_string())
+        );
+        assert_eq!(
+            parse(&["chart", "--weekly", "--compare"]),
+            Subcommand::Error("chart has no --compare".to_string())
+        );
+        assert_eq!(
+            parse(&["chart", "--yearly"]),
+            Subcommand::Error(
+                "chart needs --daily, --weekly, --monthly, or --from and --to".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let day = |d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok();
+        assert_eq!(
+            parse(&["export", "--format", "ics", "--from", "2024-05-01"]),
+            Subcommand::Export {
+                format: ExportFormat::Ics,
+                from: day("2024-05-01"),
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["export", "--format", "timeclock"]),
+            Subcommand::Export {
+                format: ExportFormat::Timeclock,
+                from: None,
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["export", "--format", "html"]),
+            Subcommand::Export {
+                format: ExportFormat::Html,
+                from: None,
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["export", "--to", "2024-05-31"]),
+            Subcommand::Error("export needs --format ics, timeclock, or html".to_string())
+        );
+        assert_eq!(
+            parse(&["export", "--format", "pdf"]),
+            Subcommand::Error("--format needs ics, timeclock, or html".to_string())
+        );
+        assert_eq!(
+            parse(&[
+                "export",
+                "--format",
+                "ics",
+                "--from",
+                "2024-06-01",
+                "--to",
+                "2024-05-01"
+            ]),
+            Subcommand::Error("--from has to be before --to".to_string())
+        );
+        assert_eq!(
+            parse(&["export", "--format", "ics", "--to", "May"]),
+            Subcommand::Error("--to needs a YYYY-MM-DD date".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_flexitime() {
+        let day = |d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok();
+        assert_eq!(
+            parse(&["flexitime"]),
+            Subcommand::Flexitime {
+                from: None,
+                to: None
+            }
+        );
+        assert_eq!(
+            parse(&["flexitime", "--to", "2024-03-31", "--from", "2024-01-01"]),
+            Subcommand::Flexitime {
+                from: day("2024-01-01"),
+                to: day("2024-03-31")
+            }
+        );
+        assert_eq!(
+            parse(&["flexitime", "--from", "2024-03-31", "--to", "2024-01-01"]),
+            Subcommand::Error("--from has to be before --to".to_string())
+        );
+        assert_eq!(
+            parse(&["flexitime", "--from"]),
+            Subcommand::Error("--from needs a YYYY-MM-DD date".to_string())
+        );
+        assert_eq!(
+            parse(&["flexitime", "--weekly"]),
+            Subcommand::Error("Unexpected argument --weekly".to_string())
+        );
+    }
+
+    #[test]
+    fn test_report_options() {
+        // the options override the settings
+        let mut config = Config::new_from_string("[rtimelog]\nround = 6m\nround_mode = up\n");
+        ReportOptions {
+            round: Some(Duration::minutes(15)),
+            ..Default::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.rounding.every, Some(Duration::minutes(15)));
+        assert_eq!(config.rounding.mode, RoundingMode::Up);
+        ReportOptions {
+            round: Some(Duration::zero()),
+            ..Default::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.rounding.every, None);
+        assert!(config.filter.is_empty());
+    }
+
+    #[test]
+    fn test_report_filter() {
+        let filter = |args: &[&str]| match parse(args) {
+            Subcommand::PeriodReport { options, .. } => Ok(options.filter),
+            Subcommand::Error(e) => Err(e),
+            other => panic!("{other:?}"),
+        };
+        let f = filter(&[
+            "report",
+            "--monthly",
+            "--project",
+            "customer joe",
+            "--tag",
+            "#urgent",
+            "--match",
+            "log(in|out)",
+        ])
+        .unwrap();
+        assert_eq!(f.project.as_deref(), Some("customer joe"));
+        assert_eq!(f.tag.as_deref(), Some("urgent"));
+        assert_eq!(
+            f.pattern.map(|r| r.to_string()).as_deref(),
+            Some("log(in|out)")
+        );
+        assert_eq!(
+            filter(&["report", "--daily", "--project"]),
+            Err("--project needs a project like \"customer joe\"".to_string())
+        );
+        assert_eq!(
+            filter(&["report", "--daily", "--tag"]),
+            Err("--tag needs a tag".to_string())
+        );
+        assert_eq!(
+            filter(&["report", "--daily", "--match"]),
+            Err("--match needs a regular expression".to_string())
+        );
+        assert!(filter(&["report", "--daily", "--match", "("])
+            .unwrap_err()
+            .starts_with("Invalid --match regular expression: "));
+        assert_eq!(
+            parse(&["earnings", "--tag", "ops"]),
+            Subcommand::Earnings {
+                month: None,
+                options: ReportOptions {
+                    filter: TaskFilter {
+                        tag: Some("ops".to_string()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            }
+        );
+        let mut config = Config::default();
+        ReportOptions {
+            filter: TaskFilter {
+                project: Some("acme".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.filter.project.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn test_parse_backup() {
+        assert_eq!(parse(&["archive"]), Subcommand::Archive { before: None });
+        assert_eq!(
+            parse(&["archive", "--before", "2023"]),
+            Subcommand::Archive { before: Some(2023) }
+        );
+        assert_eq!(
+            parse(&["archive", "--before", "last"]),
+            Subcommand::Error("Invalid year".to_string())
+        );
+        assert_eq!(
+            parse(&["merge", "laptop.txt"]),
+            Subcommand::Merge(PathBuf::from("laptop.txt"))
+        );
+        assert_eq!(
+            parse(&["merge"]),
+            Subcommand::Error("merge needs a timelog file".to_string())
+        );
+        assert_eq!(parse(&["backup"]), Subcommand::Backup(None));
+        assert_eq!(
+            parse(&["backup", "/tmp/b.tar"]),
+            Subcommand::Backup(Some(PathBuf::from("/tmp/b.tar")))
+        );
+        assert_eq!(
+            parse(&["restore", "--check", "b.tar"]),
+            Subcommand::Restore {
+                archive: PathBuf::from("b.tar"),
+                check: true
+            }
+        );
+        assert_eq!(
+            parse(&["restore", "b.tar"]),
+            Subcommand::Restore {
+                archive: PathBuf::from("b.tar"),
+                check: false
+            }
+        );
+        assert_eq!(
+            parse(&["restore"]),
+            Subcommand::Error("restore needs a backup file".to_string())
+        );
+        assert_eq!(
+            parse(&["restore", "a.tar", "b.tar"]),
+            Subcommand::Error("Unexpected argument b.tar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_prompt() {
+        assert_eq!(parse(&["prompt"]), Subcommand::Prompt(PromptFormat::Raw));
+        assert_eq!(
+            parse(&["prompt", "--format", "starship"]),
+            Subcommand::Prompt(PromptFormat::Starship)
+        );
+        assert_eq!(
+            parse(&["prompt", "--format", "fish"]),
+            Subcommand::Error("Invalid prompt format".to_string())
+        );
+        assert_eq!(
+            parse(&["prompt", "now"]),
+            Subcommand::Error("Unexpected argument now".to_string())
+        );
+    }
+}
@@ -13,6 +13,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use chrono::NaiveTime;
+
 #[derive(PartialEq, Debug)]
 pub enum TimeMode {
     Day(u32),
@@ -25,8 +27,13 @@ pub enum Command {
     Quit,
     Help,
     Edit,
+    Log,
+    Note(String),
+    Undo,
     SwitchMode(TimeMode),
     Add(String),
+    // a forgotten entry which stopped at that time today
+    AddAt(NaiveTime, String),
     Error(String),
 }
 
@@ -39,11 +46,22 @@ impl Command {
                 ":q" => Command::Quit,
                 ":h" => Command::Help,
                 ":e" => Command::Edit,
+                ":l" => Command::Log,
+                ":u" => Command::Undo,
+                ":n" => Command::Error("Note needs a text".to_string()),
+                ":a" => Command::Error("Missing time and task".to_string()),
                 ":w" => Command::SwitchMode(TimeMode::Week(1)),
                 ":d" => Command::SwitchMode(TimeMode::Day(1)),
 
                 _ => {
-                    if let Some(arg) = input.strip_prefix(":d") {
+                    if let Some(note) = input.strip_prefix(":n ") {
+                        match note.trim() {
+                            "" => Command::Error("Note needs a text".to_string()),
+                            note => Command::Note(note.to_string()),
+                        }
+                    } else if let Some(arg) = input.strip_prefix(":a ") {
+                        Command::parse_add_at(arg)
+                    } else if let Some(arg) = input.strip_prefix(":d") {
                         match arg.parse::<u32>() {
                             Ok(n) => Command::SwitchMode(TimeMode::Day(n)),
                             Err(_) => Command::Error("Invalid day number".to_string()),
@@ -62,6 +80,17 @@ impl Command {
             Some(_) => Command::Add(input),
         }
     }
+
+    // "HH:MM TASK", also for backfilling other days
+    pub fn parse_add_at(arg: &str) -> Command {
+        let Some((time, task)) = arg.trim().split_once(' ') else {
+            return Command::Error("Missing time and task".to_string());
+        };
+        match NaiveTime::parse_from_str(time, "%H:%M") {
+            Ok(time) => Command::AddAt(time, task.trim().to_string()),
+            Err(_) => Command::Error("Invalid time, use HH:MM".to_string()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +103,16 @@ mod tests {
         assert_eq!(Command::parse(":q".to_string()), Command::Quit);
         assert_eq!(Command::parse(":h".to_string()), Command::Help);
         assert_eq!(Command::parse(":e".to_string()), Command::Edit);
+        assert_eq!(Command::parse(":l".to_string()), Command::Log);
+        assert_eq!(Command::parse(":u".to_string()), Command::Undo);
+        assert_eq!(
+            Command::parse(":n  fixed the parser ".to_string()),
+            Command::Note("fixed the parser".to_string())
+        );
+        assert_eq!(
+            Command::parse(":n ".to_string()),
+            Command::Error("Note needs a text".to_string())
+        );
         assert_eq!(
             Command::parse(":w".to_string()),
             Command::SwitchMode(TimeMode::Week(1))
@@ -94,6 +133,21 @@ mod tests {
             Command::parse("foo".to_string()),
             Command::Add("foo".to_string())
         );
+        assert_eq!(
+            Command::parse(":a 14:30  team meeting".to_string()),
+            Command::AddAt(
+                NaiveTime::from_hms_opt(14, 30, 0).unwrap(),
+                "team meeting".to_string()
+            )
+        );
+        assert_eq!(
+            Command::parse(":a 2:30pm meeting".to_string()),
+            Command::Error("Invalid time, use HH:MM".to_string())
+        );
+        assert_eq!(
+            Command::parse(":a 14:30".to_string()),
+            Command::Error("Missing time and task".to_string())
+        );
         // unknown command letter
         assert_eq!(
             Command::parse(":x".to_string()),
@@ -0,0 +1,1489 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+extern crate dirs;
+
+use std::env;
+use std::fmt::Write as _; // import without risk of name clashing
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Duration, Locale, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use regex::Regex;
+
+use crate::activity;
+use crate::ics;
+use crate::projects::{self, Project};
+
+// environment variable which overrides the configuration directory
+#[cfg(not(windows))]
+const CONFIG_DIR_ENV: &str = "XDG_CONFIG_HOME";
+#[cfg(windows)]
+const CONFIG_DIR_ENV: &str = "APPDATA";
+
+/**
+ * How to show dates in reports and summaries; the timelog file always has ISO dates
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFormat {
+    // strftime format
+    pub format: String,
+    // for day and month names
+    pub locale: Locale,
+    // times like 02:05 PM instead of 14:05
+    pub twelve_hour: bool,
+}
+
+impl Default for DateFormat {
+    fn default() -> DateFormat {
+        DateFormat {
+            format: "%A, %F".to_string(),
+            locale: Locale::POSIX,
+            twelve_hour: false,
+        }
+    }
+}
+
+impl DateFormat {
+    pub fn format(&self, date: &NaiveDate) -> String {
+        self.format_with(date, &self.format)
+    }
+
+    // a different format in the same locale, e.g. just the month name
+    pub fn format_with(&self, date: &NaiveDate, format: &str) -> String {
+        let mut output = String::new();
+        match write!(output, "{}", date.format_localized(format, self.locale)) {
+            Ok(()) => output,
+            // time fields like %H, which a date does not have
+            Err(_) => date.format("%F").to_string(),
+        }
+    }
+
+    // a time of the clock, always equally wide for lining up entries
+    pub fn time(&self, time: NaiveTime) -> String {
+        match self.twelve_hour {
+            true => time.format("%I:%M %p").to_string(),
+            false => time.format("%H:%M").to_string(),
+        }
+    }
+}
+
+/**
+ * Tasks which mark arriving at work and leaving it, instead of finishing a task, and the time
+ * when a day ends
+ *
+ * The interval which ends with an arrival, and the one which starts with a departure, are not
+ * working time. The first entry of a day always just marks the start, whatever its task.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sentinels {
+    pub arrival: Vec<String>,
+    pub departure: Vec<String>,
+    // entries before this time count towards the previous day; gtimelog's "virtual_midnight"
+    pub virtual_midnight: NaiveTime,
+}
+
+impl Default for Sentinels {
+    fn default() -> Sentinels {
+        Sentinels {
+            arrival: vec!["arrived".to_string()],
+            departure: Vec::new(),
+            virtual_midnight: NaiveTime::MIN,
+        }
+    }
+}
+
+impl Sentinels {
+    // the day which `time` counts towards
+    pub fn day_of(&self, time: NaiveDateTime) -> NaiveDate {
+        (time - self.virtual_midnight.signed_duration_since(NaiveTime::MIN)).date()
+    }
+
+    // `time` on the (virtual) `day`; before virtual_midnight, that's on the next calendar day
+    pub fn time_on(&self, day: NaiveDate, time: NaiveTime) -> NaiveDateTime {
+        let stop = day.and_time(time);
+        match time < self.virtual_midnight {
+            true => stop + Duration::days(1),
+            false => stop,
+        }
+    }
+
+    pub fn is_arrival(&self, task: &str) -> bool {
+        self.arrival
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(task.trim()))
+    }
+
+    pub fn is_departure(&self, task: &str) -> bool {
+        self.departure
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(task.trim()))
+    }
+
+    // task to log for arriving
+    pub fn arrival_task(&self) -> &str {
+        self.arrival.first().map_or("arrived", String::as_str)
+    }
+}
+
+/**
+ * Kind of a whole day away from work, see Config::absence()
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Absence {
+    Vacation,
+    Sick,
+}
+
+/**
+ * Rounding of the times of new entries, for workplaces which want coarse timesheets
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snap {
+    // granularity; no rounding if None
+    pub every: Option<Duration>,
+    // round up instead of to the nearest time
+    pub up: bool,
+    // keep the actual time in the note of the entry
+    pub keep_raw: bool,
+    // keep the seconds of the time, instead of only minutes like gtimelog
+    pub seconds: bool,
+}
+
+impl Snap {
+    // round `time` to the granularity, within its day
+    pub fn apply(&self, time: NaiveDateTime) -> NaiveDateTime {
+        let Some(every) = self.every.map(|d| d.num_seconds()).filter(|s| *s > 0) else {
+            return time;
+        };
+        let midnight = time.date().and_hms_opt(0, 0, 0).unwrap();
+        let seconds = time.signed_duration_since(midnight).num_seconds();
+        let steps = if self.up {
+            (seconds + every - 1) / every
+        } else {
+            (seconds + every / 2) / every
+        };
+        let last_minute = time.date().and_hms_opt(23, 59, 0).unwrap();
+        (midnight + Duration::seconds(steps * every)).min(last_minute)
+    }
+}
+
+/**
+ * How RoundingPolicy rounds: to the nearest multiple, or always up or down
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+impl RoundingMode {
+    pub fn parse(name: &str) -> Option<RoundingMode> {
+        match name {
+            "nearest" => Some(RoundingMode::Nearest),
+            "up" => Some(RoundingMode::Up),
+            "down" => Some(RoundingMode::Down),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Rounding of the time per task and day in reports, like the quarter hours which clients
+ * bill; unlike Snap, this leaves the timelog alone
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RoundingPolicy {
+    // granularity; no rounding if None
+    pub every: Option<Duration>,
+    pub mode: RoundingMode,
+}
+
+impl RoundingPolicy {
+    pub fn apply(&self, d: Duration) -> Duration {
+        let Some(every) = self.every.map(|e| e.num_seconds()).filter(|s| *s > 0) else {
+            return d;
+        };
+        let seconds = d.num_seconds();
+        let steps = match self.mode {
+            RoundingMode::Nearest => (seconds + every / 2).div_euclid(every),
+            RoundingMode::Up => (seconds + every - 1).div_euclid(every),
+            RoundingMode::Down => seconds.div_euclid(every),
+        };
+        Duration::seconds(steps * every)
+    }
+}
+
+// whether `task` is in the part of the ": " hierarchy `project`, like "customer joe: support" in
+// "customer joe", but not "customer joey: support"
+pub(crate) fn in_project(task: &str, project: &str) -> bool {
+    task.strip_prefix(project)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(": "))
+}
+
+/**
+ * Which tasks reports count, like only those of one customer; all of them by default
+ *
+ * A task has to match all of the given criteria.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    // the first part(s) of the ": " hierarchy, like "customer joe" or "customer joe: emergency"
+    pub project: Option<String>,
+    // without the "#", see activity::tags()
+    pub tag: Option<String>,
+    pub pattern: Option<Regex>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.project.is_none() && self.tag.is_none() && self.pattern.is_none()
+    }
+
+    pub fn matches(&self, task: &str) -> bool {
+        self.project.as_ref().is_none_or(|p| in_project(task, p))
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|t| activity::tags(task).contains(&t.as_str()))
+            && self.pattern.as_ref().is_none_or(|r| r.is_match(task))
+    }
+}
+
+// Regex has no PartialEq; the same pattern is the same filter
+impl PartialEq for TaskFilter {
+    fn eq(&self, other: &TaskFilter) -> bool {
+        self.project == other.project
+            && self.tag == other.tag
+            && self.pattern.as_ref().map(Regex::as_str) == other.pattern.as_ref().map(Regex::as_str)
+    }
+}
+
+impl Eq for TaskFilter {}
+
+/**
+ * Mail server for report --send; without a server, the local one on port 25
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Smtp {
+    pub server: Option<String>,
+    // 587 with STARTTLS by default; 465 is TLS from the start
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/**
+ * Hourly rate for billing, in cents; without a currency, the `currency` setting applies
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rate {
+    pub cents: i64,
+    pub currency: Option<String>,
+}
+
+/**
+ * Settings from the gtimelogrc file
+ *
+ * This is an INI file. gtimelog's own settings live in the [gtimelog] section, and rtimelog
+ * specific ones in the [rtimelog] section.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    // nag if there was no entry for that long during working hours
+    pub remind_after: Option<Duration>,
+    // times of the day for notifications about the progress towards `hours`
+    pub progress_at: Vec<NaiveTime>,
+    // number of recent tasks offered as notification buttons
+    pub remind_actions: usize,
+    // suggest a break after working that long without a "**" entry
+    pub break_after: Option<Duration>,
+    pub work_start: NaiveTime,
+    pub work_end: NaiveTime,
+    pub work_days: Vec<Weekday>,
+    // first day of the weeks in reports
+    pub week_start: Weekday,
+    // expected work time per day; gtimelog's "hours" setting
+    pub hours: Duration,
+    // expected work time per week instead, spread over the work_days
+    pub week_hours: Option<Duration>,
+    // who the reports are about; gtimelog's "name" and "sender" settings
+    pub name: Option<String>,
+    pub email: Option<String>,
+    // where report emails go; gtimelog's "list-email" setting
+    pub report_to: Option<String>,
+    // for the :e command, instead of $EDITOR; gtimelog's "editor" setting
+    pub editor: Option<String>,
+    // iCalendar file with meetings for the morning summary
+    pub calendar: Option<PathBuf>,
+    // automatically log "arrived" at the first start on a working day
+    pub auto_arrive: bool,
+    pub pomodoro_work: Duration,
+    pub pomodoro_break: Duration,
+    // warn if the running interval gets longer than this
+    pub max_pending: Option<Duration>,
+    // longer intervals of one task are certainly a mistake, see doctor::check()
+    pub max_interval: Option<Duration>,
+    // (task prefix, maximum duration) from the [thresholds] section
+    pub thresholds: Vec<(String, Duration)>,
+    // (task prefix, time to spend on it per week) from the [goals] section
+    pub goals: Vec<(String, Duration)>,
+    // (task prefix, time to spend on it at most per month) from the [budgets] section
+    pub budgets: Vec<(String, Duration)>,
+    // (task prefix, hourly rate) from the [rates] section
+    pub rates: Vec<(String, Rate)>,
+    // for rates without a currency
+    pub currency: String,
+    // the registry of projects.toml, see client_for()
+    pub projects: Vec<Project>,
+    // task prefixes of the projects which go on invoices, see is_billable()
+    pub billable: Vec<String>,
+    // task prefixes which mark a whole day of vacation or sick leave, see absence()
+    pub vacation: Vec<String>,
+    pub sick: Vec<String>,
+    // vacation allowance per year
+    pub vacation_days: Option<u32>,
+    // public holidays, which have no target; see day_target()
+    pub holidays: Vec<NaiveDate>,
+    pub snap: Snap,
+    pub rounding: RoundingPolicy,
+    // only from the command line, for reports
+    pub filter: TaskFilter,
+    pub smtp: Smtp,
+    pub date_format: DateFormat,
+    pub sentinels: Sentinels,
+    // plain text output for screen readers: no alignment, separator lines, or screen clearing
+    pub plain: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            remind_after: None,
+            progress_at: Vec::new(),
+            remind_actions: 3,
+            break_after: None,
+            work_start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            work_end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            work_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            week_start: Weekday::Mon,
+            hours: Duration::hours(8),
+            week_hours: None,
+            name: None,
+            email: None,
+            report_to: None,
+            editor: None,
+            calendar: None,
+            auto_arrive: false,
+            pomodoro_work: Duration::minutes(25),
+            pomodoro_break: Duration::minutes(5),
+            max_pending: None,
+            max_interval: Some(Duration::hours(12)),
+            thresholds: Vec::new(),
+            goals: Vec::new(),
+            budgets: Vec::new(),
+            rates: Vec::new(),
+            currency: "EUR".to_string(),
+            projects: Vec::new(),
+            billable: Vec::new(),
+            vacation: vec!["vacation".to_string()],
+            sick: vec!["sick".to_string()],
+            vacation_days: None,
+            holidays: Vec::new(),
+            snap: Snap::default(),
+            rounding: RoundingPolicy::default(),
+            filter: TaskFilter::default(),
+            smtp: Smtp::default(),
+            date_format: DateFormat::default(),
+            sentinels: Sentinels::default(),
+            plain: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn new_from_default_file() -> Config {
+        let mut config = Config::new_from_file(&Config::get_default_file());
+        config.projects = projects::load(&projects::path());
+        config
+    }
+
+    pub fn new_from_file(path: &PathBuf) -> Config {
+        let mut config = Config::default();
+        // the configuration file can override this
+        if let Some(locale) = locale_from_env() {
+            config.date_format.locale = locale;
+        }
+        match fs::read_to_string(path) {
+            Ok(contents) => config.parse(&contents),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    eprintln!("WARNING: Could not read {}: {:?}", path.display(), e);
+                }
+            }
+        }
+        config
+    }
+
+    pub fn new_from_string(contents: &str) -> Config {
+        let mut config = Config::default();
+        config.parse(contents);
+        config
+    }
+
+    fn parse(&mut self, contents: &str) {
+        for (section, key, value) in parse_ini(contents) {
+            self.set(&section, &key, &value);
+        }
+    }
+
+    // same logic as gtimelog: legacy ~/.gtimelog/ if it exists, otherwise XDG config dir
+    pub fn get_default_file() -> PathBuf {
+        let mut legacy_dir = dirs::home_dir().unwrap();
+        legacy_dir.push(".gtimelog");
+        let mut path = if legacy_dir.is_dir() {
+            legacy_dir
+        } else {
+            Config::get_config_dir()
+        };
+        path.push("gtimelogrc");
+        path
+    }
+
+    // gtimelog's directory in the XDG config directory, for when there is no ~/.gtimelog
+    pub fn get_config_dir() -> PathBuf {
+        let mut config_dir = match env::var_os(CONFIG_DIR_ENV) {
+            Some(val) => PathBuf::from(val),
+            None => dirs::config_dir().unwrap(),
+        };
+        config_dir.push("gtimelog");
+        config_dir
+    }
+
+    // expected work time of the days from `first` to `last`; nothing on days off
+    pub fn target(&self, first: NaiveDate, last: NaiveDate) -> Duration {
+        first
+            .iter_days()
+            .take_while(|d| *d <= last)
+            .fold(Duration::zero(), |sum, d| sum + self.day_target(d))
+    }
+
+    // expected work time of `day`, see target()
+    pub fn day_target(&self, day: NaiveDate) -> Duration {
+        if !self.is_work_day(day) {
+            return Duration::zero();
+        }
+        match self.week_hours {
+            Some(week) => week / self.work_days.len() as i32,
+            None => self.hours,
+        }
+    }
+
+    // one of the `work_days`, and no holiday
+    pub fn is_work_day(&self, day: NaiveDate) -> bool {
+        self.work_days.contains(&day.weekday()) && self.holidays.binary_search(&day).is_err()
+    }
+
+    // the whole day away which `task` marks, like "vacation **" or "** sick: flu"; the case and
+    // the slacking marker don't matter
+    pub fn absence(&self, task: &str) -> Option<Absence> {
+        let task = task.replace("**", "").trim().to_lowercase();
+        let marks = |prefixes: &[String]| {
+            prefixes.iter().any(|p| {
+                task.strip_prefix(&p.to_lowercase())
+                    .is_some_and(|rest| !rest.starts_with(char::is_alphanumeric))
+            })
+        };
+        if marks(&self.vacation) {
+            Some(Absence::Vacation)
+        } else if marks(&self.sick) {
+            Some(Absence::Sick)
+        } else {
+            None
+        }
+    }
+
+    // (hourly rate in cents, currency) for `task`: the longest matching [rates] prefix
+    pub fn rate_for(&self, task: &str) -> Option<(i64, &str)> {
+        self.rates
+            .iter()
+            .filter(|(prefix, _)| in_project(task, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, r)| (r.cents, r.currency.as_deref().unwrap_or(&self.currency)))
+    }
+
+    // the project of the registry for `task`: the longest matching prefix
+    pub fn client_for(&self, task: &str) -> Option<&Project> {
+        self.projects
+            .iter()
+            .filter(|p| in_project(task, &p.prefix))
+            .max_by_key(|p| p.prefix.len())
+    }
+
+    // whether `task` goes on invoices: it is in a billable project or has a "$" marker;
+    // slacking never does
+    pub fn is_billable(&self, task: &str) -> bool {
+        !activity::is_slack(task)
+            && (activity::has_billable_marker(task)
+                || self.billable.iter().any(|p| in_project(task, p)))
+    }
+
+    // maximum duration for an interval of `task`: the longest matching [thresholds] prefix,
+    // otherwise max_pending or max_interval
+    pub fn threshold_for(&self, task: &str) -> Option<Duration> {
+        self.task_threshold(task)
+            .or(self.max_pending)
+            .or(self.max_interval)
+    }
+
+    // plausible duration for a logged interval of `task`; unlike threshold_for(), this ignores
+    // max_pending, which is about forgetting to log
+    pub fn interval_limit(&self, task: &str) -> Option<Duration> {
+        self.task_threshold(task).or(self.max_interval)
+    }
+
+    fn task_threshold(&self, task: &str) -> Option<Duration> {
+        self.thresholds
+            .iter()
+            .filter(|(prefix, _)| in_project(task, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, d)| *d)
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: &str) {
+        let valid = match (section, key) {
+            ("gtimelog", "hours") => parse_hours(value).map(|d| self.hours = d).is_some(),
+            ("gtimelog", "virtual_midnight") => parse_time(value)
+                .map(|t| self.sentinels.virtual_midnight = t)
+                .is_some(),
+            ("gtimelog", "name") => {
+                self.name = non_empty(value);
+                true
+            }
+            ("gtimelog", "sender") => {
+                self.email = non_empty(value);
+                true
+            }
+            ("gtimelog", "list-email") => {
+                self.report_to = non_empty(value);
+                true
+            }
+            ("gtimelog", "editor") => {
+                self.editor = non_empty(value);
+                true
+            }
+            // there are many more gtimelog settings which don't apply to rtimelog
+            ("gtimelog", _) => true,
+
+            ("rtimelog", "remind_after") => parse_optional_duration(value)
+                .map(|d| self.remind_after = d)
+                .is_some(),
+            ("rtimelog", "remind_actions") => {
+                value.parse().map(|n| self.remind_actions = n).is_ok()
+            }
+            ("rtimelog", "break_after") => parse_optional_duration(value)
+                .map(|d| self.break_after = d)
+                .is_some(),
+            ("rtimelog", "progress_at") => value
+                .split(',')
+                .map(|t| parse_time(t.trim()))
+                .collect::<Option<Vec<_>>>()
+                .map(|t| self.progress_at = t)
+                .is_some(),
+            ("rtimelog", "work_start") => parse_time(value).map(|t| self.work_start = t).is_some(),
+            ("rtimelog", "work_end") => parse_time(value).map(|t| self.work_end = t).is_some(),
+            ("rtimelog", "work_days") => {
+                parse_weekdays(value).map(|d| self.work_days = d).is_some()
+            }
+            ("rtimelog", "week_start") => match value.parse::<Weekday>() {
+                Ok(day) => {
+                    self.week_start = day;
+                    true
+                }
+                Err(_) => false,
+            },
+            ("rtimelog", "calendar") => {
+                self.calendar = Some(expand_home(value));
+                true
+            }
+            ("rtimelog", "auto_arrive") => {
+                parse_bool(value).map(|b| self.auto_arrive = b).is_some()
+            }
+            ("rtimelog", "snap") => parse_positive_duration(value)
+                .map(|d| self.snap.every = Some(d))
+                .is_some(),
+            ("rtimelog", "snap_mode") => match value {
+                "nearest" => {
+                    self.snap.up = false;
+                    true
+                }
+                "up" => {
+                    self.snap.up = true;
+                    true
+                }
+                _ => false,
+            },
+            ("rtimelog", "snap_keep_raw") => {
+                parse_bool(value).map(|b| self.snap.keep_raw = b).is_some()
+            }
+            ("rtimelog", "seconds") => parse_bool(value).map(|b| self.snap.seconds = b).is_some(),
+            ("rtimelog", "week_hours") => parse_hours(value)
+                .map(|d| self.week_hours = Some(d))
+                .is_some(),
+            ("rtimelog", "round") => parse_optional_duration(value)
+                .map(|d| self.rounding.every = d)
+                .is_some(),
+            ("rtimelog", "round_mode") => RoundingMode::parse(value)
+                .map(|m| self.rounding.mode = m)
+                .is_some(),
+            ("rtimelog", "smtp_server") => {
+                self.smtp.server = non_empty(value);
+                true
+            }
+            ("rtimelog", "smtp_port") => value
+                .parse::<u16>()
+                .map(|p| self.smtp.port = Some(p))
+                .is_ok(),
+            ("rtimelog", "smtp_username") => {
+                self.smtp.username = non_empty(value);
+                true
+            }
+            ("rtimelog", "smtp_password") => {
+                self.smtp.password = non_empty(value);
+                true
+            }
+            ("rtimelog", "billable") => {
+                self.billable = parse_list(value).unwrap_or_default();
+                true
+            }
+            ("rtimelog", "vacation") => {
+                self.vacation = parse_list(value).unwrap_or_default();
+                true
+            }
+            ("rtimelog", "sick") => {
+                self.sick = parse_list(value).unwrap_or_default();
+                true
+            }
+            ("rtimelog", "holidays") => {
+                self.holidays = parse_holidays(value);
+                true
+            }
+            ("rtimelog", "vacation_days") => value
+                .parse::<u32>()
+                .map(|n| self.vacation_days = Some(n))
+                .is_ok(),
+            ("rtimelog", "currency") => parse_currency(value).map(|c| self.currency = c).is_some(),
+            ("rtimelog", "plain") => parse_bool(value).map(|b| self.plain = b).is_some(),
+            ("rtimelog", "pomodoro_work") => parse_positive_duration(value)
+                .map(|d| self.pomodoro_work = d)
+                .is_some(),
+            ("rtimelog", "pomodoro_break") => parse_positive_duration(value)
+                .map(|d| self.pomodoro_break = d)
+                .is_some(),
+            ("rtimelog", "max_pending") => parse_optional_duration(value)
+                .map(|d| self.max_pending = d)
+                .is_some(),
+            ("rtimelog", "max_interval") => parse_optional_duration(value)
+                .map(|d| self.max_interval = d)
+                .is_some(),
+            ("rtimelog", "date_format") => {
+                let valid =
+                    !value.is_empty() && !StrftimeItems::new(value).any(|item| item == Item::Error);
+                if valid {
+                    self.date_format.format = value.to_string();
+                }
+                valid
+            }
+            ("rtimelog", "locale") => parse_locale(value)
+                .map(|l| self.date_format.locale = l)
+                .is_some(),
+            ("rtimelog", "clock") => match value.trim_end_matches('h') {
+                "12" => {
+                    self.date_format.twelve_hour = true;
+                    true
+                }
+                "24" => {
+                    self.date_format.twelve_hour = false;
+                    true
+                }
+                _ => false,
+            },
+            ("rtimelog", "arrival") => parse_list(value)
+                .map(|l| self.sentinels.arrival = l)
+                .is_some(),
+            ("rtimelog", "departure") => {
+                self.sentinels.departure = parse_list(value).unwrap_or_default();
+                true
+            }
+            ("thresholds", _) => match parse_positive_duration(value) {
+                Some(d) => {
+                    self.thresholds.push((key.to_string(), d));
+                    true
+                }
+                None => false,
+            },
+            ("rates", _) => match parse_rate(value) {
+                Some(r) => {
+                    self.rates.push((key.to_string(), r));
+                    true
+                }
+                None => false,
+            },
+            ("goals", _) => match parse_positive_duration(value) {
+                Some(d) => {
+                    self.goals.push((key.to_string(), d));
+                    true
+                }
+                None => false,
+            },
+            ("budgets", _) => match parse_positive_duration(value) {
+                Some(d) => {
+                    self.budgets.push((key.to_string(), d));
+                    true
+                }
+                None => false,
+            },
+            _ => {
+                eprintln!("WARNING: ignoring unknown configuration option {key} in [{section}]");
+                true
+            }
+        };
+        if !valid {
+            eprintln!("WARNING: ignoring invalid value for configuration option {key}: {value}");
+        }
+    }
+}
+
+// return (section, key, value) triples of an INI file
+fn parse_ini(contents: &str) -> Vec<(String, String, String)> {
+    let mut result = Vec::new();
+    let mut section = String::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            result.push((
+                section.clone(),
+                key.trim().to_string(),
+                value.trim().to_string(),
+            ));
+        } else {
+            eprintln!("WARNING: ignoring invalid line in configuration: {line}");
+        }
+    }
+    result
+}
+
+/// Parse a human readable duration like "90", "45m", "2h", "1h30m", or "1.5h"
+///
+/// A plain number is in minutes.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(minutes) = s.parse::<i64>() {
+        return Duration::try_minutes(minutes);
+    }
+
+    let mut total = Duration::zero();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let num_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..num_len].parse().ok()?;
+        rest = rest[num_len..].trim_start();
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let minutes = match &rest[..unit_len] {
+            "h" => number * 60.0,
+            "m" | "min" => number,
+            _ => return None,
+        };
+        // the cast would saturate, and TimeDelta has a narrower range still
+        let seconds = (minutes * 60.0).round();
+        if !seconds.is_finite() || seconds.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        total = total.checked_add(&Duration::try_seconds(seconds as i64)?)?;
+        rest = rest[unit_len..].trim_start();
+    }
+    Some(total)
+}
+
+// a zero duration disables the setting
+fn parse_optional_duration(s: &str) -> Option<Option<Duration>> {
+    parse_duration(s).map(|d| if d > Duration::zero() { Some(d) } else { None })
+}
+
+// like gtimelog's hours setting, e.g. "7.5"
+fn parse_hours(s: &str) -> Option<Duration> {
+    let h = s
+        .parse::<f64>()
+        .ok()
+        .filter(|h| h.is_finite() && *h >= 0.0 && h * 60.0 < i64::MAX as f64)?;
+    Duration::try_minutes((h * 60.0).round() as i64)
+}
+
+fn parse_positive_duration(s: &str) -> Option<Duration> {
+    parse_duration(s).filter(|d| *d > Duration::zero())
+}
+
+// like "EUR", "USD", or "$"
+fn parse_currency(s: &str) -> Option<String> {
+    (!s.is_empty() && !s.chars().any(|c| c.is_whitespace() || c.is_ascii_digit()))
+        .then(|| s.to_string())
+}
+
+// hourly rate like "120", "95.50 USD", or "80 EUR/h"
+pub fn parse_rate(s: &str) -> Option<Rate> {
+    let s = s.trim().trim_end_matches("/h");
+    let (amount, currency) = match s.split_once(char::is_whitespace) {
+        Some((amount, currency)) => (amount, Some(parse_currency(currency.trim())?)),
+        None => (s, None),
+    };
+    let (units, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if units.is_empty() || fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let units: u32 = units.parse().ok()?;
+    let fraction: i64 = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<i64>().ok()? * 10,
+        _ => fraction.parse().ok()?,
+    };
+    Some(Rate {
+        cents: units as i64 * 100 + fraction,
+        currency,
+    })
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+// gtimelog writes unset settings as empty values
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+pub(crate) fn expand_home(s: &str) -> PathBuf {
+    match s.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap().join(rest),
+        None => PathBuf::from(s),
+    }
+}
+
+// comma separated dates like 2024-12-25, and iCalendar files with the holidays as events
+fn parse_holidays(s: &str) -> Vec<NaiveDate> {
+    let mut days = Vec::new();
+    for item in parse_list(s).unwrap_or_default() {
+        if let Ok(day) = NaiveDate::parse_from_str(&item, "%Y-%m-%d") {
+            days.push(day);
+            continue;
+        }
+        for event in ics::read(&expand_home(&item)) {
+            let first = event.start.date();
+            days.extend(first.iter_days().take_while(|d| event.is_on(*d)));
+        }
+    }
+    days.sort();
+    days.dedup();
+    days
+}
+
+// comma separated, non-empty list
+fn parse_list(s: &str) -> Option<Vec<String>> {
+    let items: Vec<String> = s
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+// locale name like "de_DE", also with encoding or modifier like "de_DE.UTF-8" or "de_DE@euro"
+fn parse_locale(s: &str) -> Option<Locale> {
+    let name = s.split(['.', '@']).next()?;
+    Locale::try_from(name).ok()
+}
+
+// same order as for LC_TIME in C programs
+fn locale_from_env() -> Option<Locale> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| parse_locale(&value))
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").ok()
+}
+
+// comma separated list of days or day ranges, like "mon-thu, sat"
+fn parse_weekdays(s: &str) -> Option<Vec<Weekday>> {
+    let mut days = Vec::new();
+    for item in s.split(',') {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let mut day = first.trim().parse::<Weekday>().ok()?;
+                let last = last.trim().parse::<Weekday>().ok()?;
+                days.push(day);
+                while day != last {
+                    day = day.succ();
+                    days.push(day);
+                }
+            }
+            None => days.push(item.trim().parse::<Weekday>().ok()?),
+        }
+    }
+    Some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_ini() {
+        assert_eq!(parse_ini(""), vec![]);
+        assert_eq!(
+            parse_ini(
+                "
+# comment
+[gtimelog]
+name = Joe Developer
+hours=8
+; another comment
+
+[rtimelog]
+remind_after = 30m
+"
+            ),
+            vec![
+                (
+                    "gtimelog".to_string(),
+                    "name".to_string(),
+                    "Joe Developer".to_string()
+                ),
+                ("gtimelog".to_string(), "hours".to_string(), "8".to_string()),
+                (
+                    "rtimelog".to_string(),
+                    "remind_after".to_string(),
+                    "30m".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("45m"), Some(Duration::minutes(45)));
+        assert_eq!(parse_duration("45min"), Some(Duration::minutes(45)));
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1h 30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1.5h"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("3 days"), None);
+        assert_eq!(parse_duration("1x"), None);
+        // out of range instead of panicking
+        assert_eq!(parse_duration("99999999999999999"), None);
+        assert_eq!(parse_duration("9999999999999h"), None);
+        assert_eq!(parse_duration("1e400h"), None);
+        assert_eq!(parse_duration("99999999999999999999999h"), None);
+        assert_eq!(parse_hours("1e300"), None);
+    }
+
+    #[test]
+    fn test_parse_weekdays() {
+        assert_eq!(parse_weekdays("mon"), Some(vec![Weekday::Mon]));
+        assert_eq!(
+            parse_weekdays("Mon-Wed, sat"),
+            Some(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Sat])
+        );
+        // wrap around
+        assert_eq!(
+            parse_weekdays("sun-mon"),
+            Some(vec![Weekday::Sun, Weekday::Mon])
+        );
+        assert_eq!(parse_weekdays("mon,funday"), None);
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        assert_eq!(Config::new_from_string(""), Config::default());
+        // apart from the locale, which comes from the environment
+        let c = Config::new_from_file(&PathBuf::from("/nonexisting"));
+        assert_eq!(
+            Config {
+                date_format: DateFormat::default(),
+                ..c
+            },
+            Config::default()
+        );
+        let c = Config::new_from_string("[gtimelog]\nremind_after = 10\n");
+        assert_eq!(c.remind_after, None);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let c = Config::new_from_string("");
+        assert_eq!(
+            c.threshold_for("customer joe: support"),
+            Some(Duration::hours(12))
+        );
+        let c = Config::new_from_string("[rtimelog]\nmax_interval = 0\n");
+        assert_eq!(c.threshold_for("customer joe: support"), None);
+
+        let c = Config::new_from_string(
+            "
+[rtimelog]
+max_pending = 3h
+
+[thresholds]
+customer joe = 1h
+customer joe: support = 30m
+meeting = nonsense
+",
+        );
+        assert_eq!(c.max_pending, Some(Duration::hours(3)));
+        assert_eq!(c.thresholds.len(), 2);
+        assert_eq!(
+            c.threshold_for("customer joe: support"),
+            Some(Duration::minutes(30))
+        );
+        assert_eq!(
+            c.threshold_for("customer joe: inquiry"),
+            Some(Duration::hours(1))
+        );
+        assert_eq!(c.threshold_for("meeting"), Some(Duration::hours(3)));
+        assert_eq!(
+            c.threshold_for("customer joey: support"),
+            Some(Duration::hours(3))
+        );
+        assert_eq!(c.interval_limit("meeting"), Some(Duration::hours(12)));
+        assert_eq!(
+            c.interval_limit("customer joe: inquiry"),
+            Some(Duration::hours(1))
+        );
+    }
+
+    #[test]
+    fn test_snap() {
+        let t = |h, m, s| {
+            NaiveDate::from_ymd_opt(2022, 6, 10)
+                .unwrap()
+                .and_hms_opt(h, m, s)
+                .unwrap()
+        };
+        assert_eq!(Snap::default().apply(t(10, 7, 30)), t(10, 7, 30));
+
+        let c = Config::new_from_string("[rtimelog]\nsnap = 15m\n");
+        assert_eq!(c.snap.apply(t(10, 7, 29)), t(10, 0, 0));
+        assert_eq!(c.snap.apply(t(10, 7, 30)), t(10, 15, 0));
+        assert_eq!(c.snap.apply(t(23, 55, 0)), t(23, 59, 0));
+
+        let c =
+            Config::new_from_string("[rtimelog]\nsnap = 5\nsnap_mode = up\nsnap_keep_raw = yes\n");
+        assert!(c.snap.keep_raw);
+        assert!(!c.snap.seconds);
+        assert!(
+            Config::new_from_string("[rtimelog]\nseconds = yes\n")
+                .snap
+                .seconds
+        );
+        assert_eq!(c.snap.apply(t(10, 1, 0)), t(10, 5, 0));
+        assert_eq!(c.snap.apply(t(10, 5, 0)), t(10, 5, 0));
+        assert_eq!(c.snap.apply(t(0, 0, 0)), t(0, 0, 0));
+    }
+
+    #[test]
+    fn test_target() {
+        // Monday to Sunday
+        let first = NaiveDate::from_ymd_opt(2024, 2, 5).unwrap();
+        let sunday = first + Duration::days(6);
+        let c = Config::new_from_string("[gtimelog]\nhours = 7.5\n");
+        assert_eq!(c.target(first, first), Duration::minutes(450));
+        assert_eq!(c.target(first, sunday), Duration::minutes(5 * 450));
+        assert_eq!(c.target(sunday, sunday), Duration::zero());
+        assert_eq!(c.target(sunday, first), Duration::zero());
+        let c = Config::new_from_string("[rtimelog]\nweek_hours = 30\nwork_days = mon-thu\n");
+        assert_eq!(c.week_hours, Some(Duration::hours(30)));
+        assert_eq!(c.target(first, first), Duration::minutes(450));
+        assert_eq!(c.target(first, sunday), Duration::hours(30));
+    }
+
+    #[test]
+    fn test_task_filter() {
+        let mut f = TaskFilter::default();
+        assert!(f.is_empty());
+        assert!(f.matches("anything"));
+        f.project = Some("customer joe".to_string());
+        assert!(f.matches("customer joe"));
+        assert!(f.matches("customer joe: emergency: fix login"));
+        assert!(!f.matches("customer joey: support"));
+        f.project = Some("customer joe: emergency".to_string());
+        assert!(f.matches("customer joe: emergency: fix login"));
+        assert!(!f.matches("customer joe: support"));
+        f.tag = Some("urgent".to_string());
+        assert!(!f.matches("customer joe: emergency: fix login"));
+        assert!(f.matches("customer joe: emergency: fix login #urgent"));
+        f.pattern = Some(Regex::new("(?i)LOGIN").unwrap());
+        assert!(f.matches("customer joe: emergency: fix login #urgent"));
+        assert!(!f.matches("customer joe: emergency: fix logout #urgent"));
+        assert_eq!(f.clone(), f);
+        assert_ne!(f, TaskFilter::default());
+    }
+
+    #[test]
+    fn test_rounding() {
+        let m = Duration::minutes;
+        let c = Config::new_from_string(
+            "[rtimelog]
+round = 15m
+",
+        );
+        assert_eq!(c.rounding.apply(m(7)), m(0));
+        assert_eq!(c.rounding.apply(Duration::seconds(450)), m(15));
+        assert_eq!(c.rounding.apply(m(40)), m(45));
+        let c = Config::new_from_string(
+            "[rtimelog]
+round = 15m
+round_mode = up
+",
+        );
+        assert_eq!(c.rounding.apply(m(1)), m(15));
+        assert_eq!(c.rounding.apply(m(15)), m(15));
+        assert_eq!(c.rounding.apply(m(0)), m(0));
+        let c = Config::new_from_string(
+            "[rtimelog]
+round = 6
+round_mode = down
+",
+        );
+        assert_eq!(c.rounding.apply(m(11)), m(6));
+        // no rounding by default, or with invalid values
+        assert_eq!(Config::default().rounding.apply(m(7)), m(7));
+        let c = Config::new_from_string(
+            "[rtimelog]
+round = often
+round_mode = sideways
+",
+        );
+        assert_eq!(c.rounding, RoundingPolicy::default());
+    }
+
+    #[test]
+    fn test_rates() {
+        assert_eq!(
+            parse_rate("95.5 USD/h"),
+            Some(Rate {
+                cents: 9550,
+                currency: Some("USD".to_string())
+            })
+        );
+        assert_eq!(
+            parse_rate("120"),
+            Some(Rate {
+                cents: 12000,
+                currency: None
+            })
+        );
+        assert_eq!(parse_rate("12.345"), None);
+        assert_eq!(parse_rate("-5"), None);
+        assert_eq!(parse_rate("5 E1"), None);
+
+        let c = Config::new_from_string(
+            "
+[rtimelog]
+currency = CHF
+
+[rates]
+customer joe = 100 EUR
+customer joe: emergency = 150 EUR
+acme = 80
+bad = lots
+",
+        );
+        assert_eq!(c.rates.len(), 3);
+        assert_eq!(c.rate_for("customer joe: support"), Some((10000, "EUR")));
+        assert_eq!(c.rate_for("customer joe: emergency"), Some((15000, "EUR")));
+        assert_eq!(c.rate_for("acme: website"), Some((8000, "CHF")));
+        assert_eq!(c.rate_for("rtimelog: code"), None);
+        // only whole parts of the task
+        assert_eq!(c.rate_for("customer joey: support"), None);
+        assert_eq!(c.rate_for("acme"), Some((8000, "CHF")));
+    }
+
+    #[test]
+    fn test_billable() {
+        let c = Config::new_from_string("[rtimelog]\nbillable = customer joe, acme\n");
+        assert_eq!(c.billable, vec!["customer joe", "acme"]);
+        assert!(c.is_billable("customer joe: support"));
+        assert!(c.is_billable("acme"));
+        assert!(!c.is_billable("rtimelog: code"));
+        assert!(!c.is_billable("customer joey: support"));
+        assert!(c.is_billable("rtimelog: consulting for joe $"));
+        assert!(!c.is_billable("** customer joe: lunch"));
+        assert!(!Config::default().is_billable("customer joe: support"));
+    }
+
+    #[test]
+    fn test_holidays() {
+        let path = env::temp_dir().join(format!("rtimelog-holidays-{}.ics", std::process::id()));
+        fs::write(
+            &path,
+            "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Christmas\r
+DTSTART;VALUE=DATE:20241225\r
+DTEND;VALUE=DATE:20241227\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:New Year\r
+DTSTART;VALUE=DATE:20250101\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+        )
+        .unwrap();
+        let c = Config::new_from_string(&format!(
+            "[rtimelog]\nholidays = 2024-12-24, {}, 2024-12-25\n",
+            path.display()
+        ));
+        fs::remove_file(&path).unwrap();
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            c.holidays,
+            vec![
+                day(2024, 12, 24),
+                day(2024, 12, 25),
+                day(2024, 12, 26),
+                day(2025, 1, 1)
+            ]
+        );
+        assert!(!c.is_work_day(day(2024, 12, 24)));
+        assert!(c.is_work_day(day(2024, 12, 27)));
+        // Monday to Friday, with three holidays
+        assert_eq!(
+            c.target(day(2024, 12, 23), day(2024, 12, 29)),
+            Duration::hours(16)
+        );
+    }
+
+    #[test]
+    fn test_absence() {
+        let c = Config::default();
+        assert_eq!(c.absence("vacation **"), Some(Absence::Vacation));
+        assert_eq!(c.absence("** Vacation: skiing"), Some(Absence::Vacation));
+        assert_eq!(c.absence("sick"), Some(Absence::Sick));
+        assert_eq!(c.absence("sickness report"), None);
+        assert_eq!(c.absence("rtimelog: vacation planning"), None);
+        assert_eq!(c.vacation_days, None);
+
+        let c = Config::new_from_string(
+            "[rtimelog]\nvacation = holiday, pto\nsick =\nvacation_days = 28\n",
+        );
+        assert_eq!(c.absence("PTO **"), Some(Absence::Vacation));
+        assert_eq!(c.absence("vacation"), None);
+        assert_eq!(c.absence("sick"), None);
+        assert_eq!(c.vacation_days, Some(28));
+    }
+
+    #[test]
+    fn test_config_gtimelog() {
+        let c = Config::new_from_string(
+            "[gtimelog]\nhours = 7.5\nname = Joe\nsender = joe@example.com\nlist-email = team@example.com\neditor = gvim\nmailer = mutt\n",
+        );
+        assert_eq!(c.hours, Duration::minutes(450));
+        assert_eq!(c.name.as_deref(), Some("Joe"));
+        assert_eq!(c.email.as_deref(), Some("joe@example.com"));
+        assert_eq!(c.report_to.as_deref(), Some("team@example.com"));
+        assert_eq!(c.editor.as_deref(), Some("gvim"));
+        // gtimelog writes empty settings
+        let c = Config::new_from_string("[gtimelog]\nname =\nsender = \n");
+        assert_eq!((c.name, c.email), (None, None));
+        // the rtimelog section does not have gtimelog settings
+        let c = Config::new_from_string("[rtimelog]\nhours = 7.5\n");
+        assert_eq!(c.hours, Duration::hours(8));
+    }
+
+    #[test]
+    fn test_config_rtimelog() {
+        let c = Config::new_from_string(
+            "[rtimelog]
+remind_after = 45m
+remind_actions = 5
+progress_at = 12:00, 16:00
+break_after = 2h
+work_start = 08:30
+work_end = 16:00
+work_days = mon-thu
+week_start = sunday
+calendar = /tmp/cal.ics
+auto_arrive = yes
+plain = on
+pomodoro_work = 50m
+pomodoro_break = 0
+smtp_server = mail.example.com
+smtp_port = 465
+smtp_username = joe
+",
+        );
+        assert_eq!(c.pomodoro_work, Duration::minutes(50));
+        // invalid, keeps default
+        assert_eq!(c.pomodoro_break, Duration::minutes(5));
+        assert_eq!(c.calendar, Some(PathBuf::from("/tmp/cal.ics")));
+        assert!(c.auto_arrive);
+        assert!(c.plain);
+        assert_eq!(c.remind_after, Some(Duration::minutes(45)));
+        assert_eq!(c.remind_actions, 5);
+        assert_eq!(
+            c.progress_at,
+            vec![
+                NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(16, 0, 0).unwrap()
+            ]
+        );
+        assert_eq!(c.break_after, Some(Duration::hours(2)));
+        assert_eq!(c.work_start, NaiveTime::from_hms_opt(8, 30, 0).unwrap());
+        assert_eq!(c.work_end, NaiveTime::from_hms_opt(16, 0, 0).unwrap());
+        assert_eq!(
+            c.work_days,
+            vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu]
+        );
+        assert_eq!(c.week_start, Weekday::Sun);
+        assert_eq!(
+            c.smtp,
+            Smtp {
+                server: Some("mail.example.com".to_string()),
+                port: Some(465),
+                username: Some("joe".to_string()),
+                password: None,
+            }
+        );
+
+        // zero disables, invalid values keep the default
+        let c = Config::new_from_string("[rtimelog]\nremind_after = 0\nwork_start = late\n");
+        assert_eq!(c.remind_after, None);
+        assert_eq!(c.work_start, Config::default().work_start);
+    }
+
+    #[test]
+    fn test_date_format() {
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let c = Config::new_from_string("");
+        assert_eq!(c.date_format.format(&day), "Friday, 2022-06-10");
+
+        let c =
+            Config::new_from_string("[rtimelog]\ndate_format = %a %e %b\nlocale = de_DE.UTF-8\n");
+        assert_eq!(c.date_format.locale, Locale::de_DE);
+        assert_eq!(c.date_format.format(&day), "Fr 10 Jun");
+        assert_eq!(c.date_format.format_with(&day, "%B"), "Juni");
+
+        // invalid values keep the default
+        let c = Config::new_from_string("[rtimelog]\ndate_format = %Q\nlocale = xx_YY\n");
+        assert_eq!(c.date_format, DateFormat::default());
+        // dates don't have times
+        let c = Config::new_from_string("[rtimelog]\ndate_format = %H:%M\n");
+        assert_eq!(c.date_format.format(&day), "2022-06-10");
+
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(c.date_format.time(time(14, 5)), "14:05");
+        let c = Config::new_from_string("[rtimelog]\nclock = 12h\n");
+        assert_eq!(c.date_format.time(time(14, 5)), "02:05 PM");
+        assert_eq!(c.date_format.time(time(0, 30)), "12:30 AM");
+        let c = Config::new_from_string("[rtimelog]\nclock = 24\n");
+        assert!(!c.date_format.twelve_hour);
+
+        assert_eq!(parse_locale("fr_FR@euro"), Some(Locale::fr_FR));
+        assert_eq!(parse_locale("C.UTF-8"), None);
+    }
+
+    #[test]
+    fn test_sentinels() {
+        let s = Sentinels::default();
+        assert!(s.is_arrival("arrived"));
+        assert!(s.is_arrival(" Arrived "));
+        assert!(!s.is_arrival("arrived late"));
+        assert!(!s.is_departure("left"));
+        assert_eq!(s.arrival_task(), "arrived");
+
+        let c = Config::new_from_string(
+            "[rtimelog]\narrival = angekommen, start\ndeparture = Feierabend,, left\n",
+        );
+        assert_eq!(c.sentinels.arrival, ["angekommen", "start"]);
+        assert_eq!(c.sentinels.arrival_task(), "angekommen");
+        assert!(!c.sentinels.is_arrival("arrived"));
+        assert!(c.sentinels.is_departure("feierabend"));
+        assert!(c.sentinels.is_departure("left"));
+
+        // there must be an arrival keyword, but departures are optional
+        let c = Config::new_from_string("[rtimelog]\narrival = ,\ndeparture =\n");
+        assert_eq!(c.sentinels, Sentinels::default());
+
+        let time = |t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M").unwrap();
+        let day = |d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        assert_eq!(s.day_of(time("2022-06-10 01:59")), day("2022-06-10"));
+        let c = Config::new_from_string("[gtimelog]\nvirtual_midnight = 02:00\n");
+        assert_eq!(
+            c.sentinels.day_of(time("2022-06-10 01:59")),
+            day("2022-06-09")
+        );
+        assert_eq!(
+            c.sentinels.day_of(time("2022-06-10 02:00")),
+            day("2022-06-10")
+        );
+        let at = |t| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+        assert_eq!(
+            c.sentinels.time_on(day("2022-06-09"), at("01:59")),
+            time("2022-06-10 01:59")
+        );
+        assert_eq!(
+            c.sentinels.time_on(day("2022-06-09"), at("02:00")),
+            time("2022-06-09 02:00")
+        );
+    }
+}
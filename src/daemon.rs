@@ -0,0 +1,629 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+
+use crate::activity::{duration_in_words, Activities};
+use crate::commands::Command;
+use crate::config::Config;
+use crate::doctor::check_interval;
+use crate::goals::GoalProgress;
+use crate::idle::AwaySpan;
+use crate::prompt::{PromptFormat, Segment};
+use crate::store::{Entry, Timelog};
+
+/**
+ * Request to the daemon; one line per connection
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum Request {
+    Add(String),
+    // finished at that time today, see Sentinels::time_on()
+    AddAt(NaiveTime, String),
+    Status,
+    // shell prompt segment, see prompt.rs
+    Prompt(PromptFormat),
+    Error(String),
+}
+
+impl Request {
+    pub fn parse(line: &str) -> Request {
+        let line = line.trim();
+        match line.split_once(' ') {
+            Some(("add", task)) if !task.trim().is_empty() => Request::Add(task.trim().to_string()),
+            Some(("add-at", arg)) => match Command::parse_add_at(arg) {
+                Command::AddAt(time, task) if !task.is_empty() => Request::AddAt(time, task),
+                _ => Request::Error(format!("Invalid request {line}")),
+            },
+            Some(("prompt", format)) => match PromptFormat::parse(format.trim()) {
+                Some(f) => Request::Prompt(f),
+                None => Request::Error(format!("Invalid prompt format {}", format.trim())),
+            },
+            _ if line == "status" => Request::Status,
+            _ => Request::Error(format!("Invalid request {line}")),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            Request::Add(task) => format!("add {task}\n"),
+            Request::AddAt(time, task) => format!("add-at {} {task}\n", time.format("%H:%M")),
+            Request::Status => "status\n".to_string(),
+            Request::Prompt(format) => format!("prompt {}\n", format.name()),
+            Request::Error(e) => format!("{e}\n"),
+        }
+    }
+}
+
+/**
+ * Answer of the daemon: "ok" or "error" on the first line, then the text
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub ok: bool,
+    pub text: String,
+}
+
+impl Response {
+    pub fn parse(raw: &str) -> Response {
+        let (status, text) = raw.split_once('\n').unwrap_or((raw, ""));
+        Response {
+            ok: status == "ok",
+            text: text.to_string(),
+        }
+    }
+
+    pub fn format(&self) -> String {
+        format!("{}\n{}", if self.ok { "ok" } else { "error" }, self.text)
+    }
+}
+
+// today's totals and the last entry
+pub fn status(timelog: &Timelog, config: &Config, away: &[AwaySpan], now: NaiveDateTime) -> String {
+    let mut output = String::new();
+    let today = timelog.get_today(now, &config.sentinels);
+    let a = Activities::new_from_entries(today, &config.sentinels);
+    let duration = |d: Duration| {
+        if config.plain {
+            duration_in_words(d)
+        } else {
+            format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+        }
+    };
+    writeln!(
+        output,
+        "Today: {} work, {} slacking",
+        duration(a.total_work()),
+        duration(a.total_slack())
+    )
+    .unwrap();
+
+    let week = timelog.get_this_week(now, &config.sentinels, config.week_start);
+    for goal in GoalProgress::for_week(week, config) {
+        if config.plain {
+            writeln!(output, "Goal {}", goal.to_plain_string()).unwrap();
+        } else {
+            writeln!(output, "Goal {goal}").unwrap();
+        }
+    }
+
+    match today.last() {
+        Some(last) => {
+            let since = now.signed_duration_since(last.stop);
+            writeln!(output, "Last entry: {last} ({} ago)", duration(since)).unwrap();
+        }
+        None => writeln!(output, "No entries yet today").unwrap(),
+    }
+
+    for span in away {
+        writeln!(
+            output,
+            "Away ({}) from {} to {}",
+            span.reason,
+            config.date_format.time(span.begin.time()),
+            config.date_format.time(span.end.time())
+        )
+        .unwrap();
+    }
+    output
+}
+
+// the last of today's entries, with a warning if its interval is implausible
+fn added(today: &[Entry], index: usize, config: &Config) -> String {
+    let entry = &today[index];
+    let mut text = format!("{entry}\n");
+    if let Some(prev) = index.checked_sub(1).map(|i| &today[i]) {
+        if let Some(problem) =
+            check_interval((prev.stop, &prev.task), entry.stop, &entry.task, config)
+        {
+            let problem = if config.plain {
+                problem.to_plain_string()
+            } else {
+                problem.to_string()
+            };
+            writeln!(text, "WARNING: this entry {problem}").unwrap();
+        }
+    }
+    text
+}
+
+// Answer a request; `away` are the away spans since the last entry. They get attributed to an
+// added task, as there is nobody to ask about them.
+pub fn handle(
+    timelog: &mut Timelog,
+    config: &Config,
+    request: Request,
+    away: &[AwaySpan],
+    now: NaiveDateTime,
+) -> Response {
+    match request {
+        Request::Add(task) => {
+            if let Err(e) = timelog.add_snapped(task, now, &config.snap) {
+                return Response {
+                    ok: false,
+                    text: format!("{e}\n"),
+                };
+            }
+            match timelog.save() {
+                Ok(()) => {
+                    let today = timelog.get_today(now, &config.sentinels);
+                    Response {
+                        ok: true,
+                        text: added(today, today.len() - 1, config),
+                    }
+                }
+                Err(e) => Response {
+                    ok: false,
+                    text: format!("Failed to save timelog: {e}\n"),
+                },
+            }
+        }
+        Request::AddAt(time, task) => {
+            let stop = config.sentinels.time_on(config.sentinels.day_of(now), time);
+            if stop > now {
+                return Response {
+                    ok: false,
+                    text: format!("{} is in the future\n", config.date_format.time(time)),
+                };
+            }
+            if let Err(e) = timelog.add_at(task.clone(), stop) {
+                return Response {
+                    ok: false,
+                    text: format!("{e}\n"),
+                };
+            }
+            match timelog.save() {
+                Ok(()) => {
+                    let today = timelog.get_today(now, &config.sentinels);
+                    // not necessarily the last one
+                    let index = today
+                        .iter()
+                        .rposition(|e| e.stop == stop && *e.task == *task)
+                        .unwrap();
+                    Response {
+                        ok: true,
+                        text: added(today, index, config),
+                    }
+                }
+                Err(e) => Response {
+                    ok: false,
+                    text: format!("Failed to save timelog: {e}\n"),
+                },
+            }
+        }
+        Request::Status => Response {
+            ok: true,
+            text: status(timelog, config, away, now),
+        },
+        // empty without entries today
+        Request::Prompt(format) => Response {
+            ok: true,
+            text: Segment::new(
+                timelog.get_this_week(now, &config.sentinels, config.week_start),
+                config,
+                now,
+            )
+            .map(|s| s.format(format, config.plain))
+            .unwrap_or_default(),
+        },
+        Request::Error(e) => Response {
+            ok: false,
+            text: format!("{e}\n"),
+        },
+    }
+}
+
+#[cfg(unix)]
+pub use self::socket::{send, serve, socket_path};
+
+#[cfg(unix)]
+mod socket {
+    use std::env;
+    use std::fs;
+    use std::io::{self, prelude::*, BufReader};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use chrono::Local;
+
+    use super::{handle, Request, Response};
+    use crate::config::Config;
+    use crate::idle::IdleMonitor;
+    use crate::shared::SharedTimelog;
+
+    // a client which does not send its request would keep all others waiting
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    // $XDG_RUNTIME_DIR/rtimelog.sock, or one in a private per-user directory in the temp
+    // directory, see private_dir()
+    pub fn socket_path() -> PathBuf {
+        match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => PathBuf::from(dir).join("rtimelog.sock"),
+            None => env::temp_dir()
+                .join(format!("rtimelog-{}", env::var("USER").unwrap_or_default()))
+                .join("rtimelog.sock"),
+        }
+    }
+
+    // Create `dir` only for the user. Everyone can create files in the temp directory, so an
+    // existing one must not be a symlink nor open for others; one of another user with these
+    // permissions can't be used anyway.
+    pub(super) fn private_dir(dir: &Path) -> Result<(), io::Error> {
+        match fs::DirBuilder::new().mode(0o700).create(dir) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let metadata = fs::symlink_metadata(dir)?;
+                if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        format!("{} is not a private directory", dir.display()),
+                    ));
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    // send a request to a running daemon; fails with NotFound or ConnectionRefused without one
+    pub fn send(request: &Request) -> Result<Response, io::Error> {
+        let mut stream = UnixStream::connect(socket_path())?;
+        stream.write_all(request.to_line().as_bytes())?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw)?;
+        Ok(Response::parse(&raw))
+    }
+
+    fn serve_one(
+        stream: UnixStream,
+        timelog: &SharedTimelog,
+        config: &Config,
+        idle: &IdleMonitor,
+    ) -> io::Result<()> {
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let request = Request::parse(&line);
+
+        let mut tl = timelog.write();
+        // pick up changes from the interactive mode or gtimelog
+        tl.reload_if_changed();
+        let now = Local::now().naive_local();
+        let away = match tl.get_today(now, &config.sentinels).last() {
+            Some(last) if matches!(request, Request::Add(_)) => idle.take_since(last.stop),
+            Some(last) => idle.peek_since(last.stop),
+            None => Vec::new(),
+        };
+        let response = handle(&mut tl, config, request, &away, now);
+        (&stream).write_all(response.format().as_bytes())
+    }
+
+    // answer requests forever
+    pub fn serve(
+        timelog: &SharedTimelog,
+        config: &Config,
+        idle: &IdleMonitor,
+    ) -> Result<(), io::Error> {
+        let path = socket_path();
+        if env::var_os("XDG_RUNTIME_DIR").is_none() {
+            private_dir(path.parent().unwrap())?;
+        }
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("daemon is already running on {}", path.display()),
+            ));
+        }
+        // left over from a daemon which did not shut down cleanly
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        println!("Listening on {}", path.display());
+
+        for stream in listener.incoming() {
+            let served = stream.and_then(|s| {
+                s.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+                serve_one(s, timelog, config, idle)
+            });
+            if let Err(e) = served {
+                eprintln!("WARNING: Failed to handle request: {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idle::AwayReason;
+    use crate::storage::MemoryStorage;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    fn time(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_request() {
+        assert_eq!(
+            Request::parse("add customer joe: support\n"),
+            Request::Add("customer joe: support".to_string())
+        );
+        assert_eq!(Request::parse("status\n"), Request::Status);
+        assert_eq!(
+            Request::parse("add  "),
+            Request::Error("Invalid request add".to_string())
+        );
+        assert_eq!(
+            Request::parse("frobnicate"),
+            Request::Error("Invalid request frobnicate".to_string())
+        );
+
+        let add = Request::Add("code".to_string());
+        assert_eq!(Request::parse(&add.to_line()), add);
+        let add_at = Request::AddAt(
+            NaiveTime::from_hms_opt(9, 5, 0).unwrap(),
+            "code".to_string(),
+        );
+        assert_eq!(add_at.to_line(), "add-at 09:05 code\n");
+        assert_eq!(Request::parse(&add_at.to_line()), add_at);
+        assert_eq!(
+            Request::parse("add-at 9am code"),
+            Request::Error("Invalid request add-at 9am code".to_string())
+        );
+        assert_eq!(Request::parse(&Request::Status.to_line()), Request::Status);
+        let prompt = Request::Prompt(PromptFormat::P10k);
+        assert_eq!(Request::parse(&prompt.to_line()), prompt);
+        assert_eq!(
+            Request::parse("prompt fish"),
+            Request::Error("Invalid prompt format fish".to_string())
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        let r = Response {
+            ok: true,
+            text: "line 1\nline 2\n".to_string(),
+        };
+        assert_eq!(Response::parse(&r.format()), r);
+        let r = Response {
+            ok: false,
+            text: "".to_string(),
+        };
+        assert_eq!(Response::parse(&r.format()), r);
+    }
+
+    #[test]
+    fn test_handle() {
+        let storage = MemoryStorage::new("2022-06-10 09:00: arrived\n2022-06-10 10:00: ** tea\n");
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let away = [AwaySpan {
+            begin: time(10, 15),
+            end: time(10, 45),
+            reason: AwayReason::Locked,
+        }];
+
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::Status,
+                &away,
+                time(11, 30)
+            ),
+            Response {
+                ok: true,
+                text: "Today: 0 h 0 min work, 1 h 0 min slacking
+Last entry: 2022-06-10 10:00: ** tea (1 h 30 min ago)
+Away (screen locked) from 10:15 to 10:45
+"
+                .to_string()
+            }
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            status(&tl, &plain, &[], time(11, 30)),
+            "Today: 0 minutes work, 1 hour slacking
+Last entry: 2022-06-10 10:00: ** tea (1 hour 30 minutes ago)
+"
+        );
+
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::Add("code".to_string()),
+                &[],
+                time(11, 30)
+            ),
+            Response {
+                ok: true,
+                text: "2022-06-10 11:30: code\n".to_string()
+            }
+        );
+        assert!(storage
+            .contents()
+            .ends_with("10:00: ** tea\n2022-06-10 11:30: code\n"));
+
+        // e.g. from `rtimelog add "$(cat file)"`; that would forge an entry line
+        let forged = "line one\n2022-06-10 17:00: injected".to_string();
+        for request in [
+            Request::Add(forged.clone()),
+            Request::AddAt(NaiveTime::from_hms_opt(11, 0, 0).unwrap(), forged.clone()),
+        ] {
+            let response = handle(&mut tl, &Config::default(), request, &[], time(11, 35));
+            assert!(!response.ok);
+            assert!(response.text.starts_with("Invalid task"));
+        }
+        assert!(!storage.contents().contains("injected"));
+
+        // backdated between the existing entries
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::AddAt(at(11, 0), "email".to_string()),
+                &[],
+                time(11, 35)
+            ),
+            Response {
+                ok: true,
+                text: "2022-06-10 11:00: email\n".to_string()
+            }
+        );
+        assert!(storage
+            .contents()
+            .ends_with("10:00: ** tea\n2022-06-10 11:00: email\n2022-06-10 11:30: code\n"));
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::AddAt(at(12, 0), "email".to_string()),
+                &[],
+                time(11, 35)
+            ),
+            Response {
+                ok: false,
+                text: "12:00 is in the future\n".to_string()
+            }
+        );
+
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::Prompt(PromptFormat::Starship),
+                &[],
+                time(11, 42)
+            ),
+            Response {
+                ok: true,
+                text: "code 12m".to_string()
+            }
+        );
+
+        let config = Config::new_from_string("[thresholds]\nreview = 1h\n");
+        assert_eq!(
+            handle(
+                &mut tl,
+                &config,
+                Request::Add("review".to_string()),
+                &[],
+                time(13, 0)
+            ),
+            Response {
+                ok: true,
+                text: "2022-06-10 13:00: review
+WARNING: this entry lasts 1 h 30 min, longer than the maximum of 1 h 0 min; split it, or log the time in between
+"
+                .to_string()
+            }
+        );
+
+        assert_eq!(
+            handle(
+                &mut tl,
+                &Config::default(),
+                Request::Error("bad".to_string()),
+                &[],
+                time(11, 30)
+            ),
+            Response {
+                ok: false,
+                text: "bad\n".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_status_empty() {
+        let tl = Timelog::new_from_string("2022-06-09 17:00: work\n");
+        assert_eq!(
+            status(&tl, &Config::default(), &[], time(8, 0)),
+            "Today: 0 h 0 min work, 0 h 0 min slacking\nNo entries yet today\n"
+        );
+    }
+
+    #[test]
+    fn test_status_goals() {
+        let tl = Timelog::new_from_string(
+            "2022-06-09 09:00: arrived\n2022-06-09 11:00: rtimelog: code\n\n\
+             2022-06-10 09:00: arrived\n2022-06-10 09:30: rtimelog: docs\n",
+        );
+        let config = Config::new_from_string("[goals]\nrtimelog = 5h\n");
+        assert_eq!(
+            status(&tl, &config, &[], time(10, 0)),
+            "Today: 0 h 30 min work, 0 h 0 min slacking
+Goal rtimelog: 2 h 30 min of 5 h 0 min (50%)
+Last entry: 2022-06-10 09:30: rtimelog: docs (0 h 30 min ago)
+"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("rtimelog-private-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        socket::private_dir(&dir).unwrap();
+        let mode = |dir| std::fs::metadata(dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        // again for the next start
+        socket::private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            socket::private_dir(&dir).unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
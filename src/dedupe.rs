@@ -0,0 +1,181 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use chrono::NaiveDateTime;
+
+use crate::config::Sentinels;
+use crate::store::{Entry, Timelog, TimelogError};
+
+/**
+ * Why an entry can go without changing any report
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    // same task as the entry after it on that day, which takes over its time
+    Repeated,
+    // same time as the entry before it, so it takes no time
+    SameTime,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Repeated => write!(f, "repeats the next entry, which takes over its time"),
+            Reason::SameTime => write!(f, "has the time of the entry before, so it takes no time"),
+        }
+    }
+}
+
+/**
+ * An entry to remove, see find()
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    // into all entries of the timelog, for Timelog::remove()
+    pub index: usize,
+    pub entry: Entry,
+    pub reason: Reason,
+}
+
+// The entries which repeat the task of the next one, or the time of the one before, as if the
+// ones before them were already gone: of "10:00 code, 11:00 code, 11:00 code" only the first
+// "11:00 code" stays. Entries with a note stay, so that nothing gets lost, and so do the first
+// entries of days or after a departure, as they mark the start.
+pub fn find(timelog: &Timelog, sentinels: &Sentinels) -> Vec<Duplicate> {
+    let entries: Vec<&Entry> = timelog
+        .get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX)
+        .iter()
+        .collect();
+    let mut duplicates = Vec::new();
+    // indexes of the entries which stay so far
+    let mut kept: Vec<usize> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let Some(&prev) = kept.last() else {
+            kept.push(i);
+            continue;
+        };
+        if entry.stop == entries[prev].stop && timelog.note(entry).is_none() {
+            duplicates.push(Duplicate {
+                index: i,
+                entry: (*entry).clone(),
+                reason: Reason::SameTime,
+            });
+            continue;
+        }
+        // whether the entry before has an interval of its own, which this one can take over
+        let starts_span = match kept.len().checked_sub(2).map(|k| entries[kept[k]]) {
+            Some(before) => {
+                sentinels.day_of(before.stop) != sentinels.day_of(entries[prev].stop)
+                    || sentinels.is_departure(&before.task)
+                    || sentinels.is_arrival(&entries[prev].task)
+            }
+            None => true,
+        };
+        if entry.task.trim() == entries[prev].task.trim()
+            && sentinels.day_of(entry.stop) == sentinels.day_of(entries[prev].stop)
+            && !starts_span
+            && timelog.note(entries[prev]).is_none()
+        {
+            duplicates.push(Duplicate {
+                index: prev,
+                entry: entries[prev].clone(),
+                reason: Reason::Repeated,
+            });
+            kept.pop();
+        }
+        kept.push(i);
+    }
+    duplicates.sort_by_key(|d| d.index);
+    duplicates
+}
+
+// remove the `duplicates` of find(), from the last one on so that the indexes stay valid
+pub fn remove(timelog: &mut Timelog, duplicates: &[Duplicate]) -> Result<(), TimelogError> {
+    for d in duplicates.iter().rev() {
+        timelog.remove(d.index)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::report::PeriodReport;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_dedupe() {
+        let contents = "2024-02-05 09:00: arrived
+2024-02-05 09:00: arrived
+2024-02-05 10:00: code
+2024-02-05 11:00: code
+2024-02-05 11:00: code
+2024-02-05 11:00: review
+2024-02-05 12:00: email
+  about the release
+2024-02-05 13:00: email
+2024-02-05 17:00: left
+2024-02-05 18:00: email
+2024-02-05 19:00: email
+
+2024-02-06 09:00: email
+2024-02-06 10:00: email
+";
+        let mut tl = Timelog::new_from_string(contents);
+        let config = Config {
+            sentinels: Sentinels {
+                departure: vec!["left".to_string()],
+                ..Sentinels::default()
+            },
+            ..Config::default()
+        };
+        let sentinels = &config.sentinels;
+        let found: Vec<(usize, String, Reason)> = find(&tl, sentinels)
+            .into_iter()
+            .map(|d| (d.index, d.entry.task.to_string(), d.reason))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, "arrived".to_string(), Reason::SameTime),
+                (2, "code".to_string(), Reason::Repeated),
+                (4, "code".to_string(), Reason::SameTime),
+                (5, "review".to_string(), Reason::SameTime),
+            ]
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let work = |tl: &Timelog| {
+            let all: Vec<Entry> = tl.get_all().cloned().collect();
+            let r = PeriodReport::new(&all, day(5), day(6), &config);
+            let tasks: Vec<_> = r.work.into_iter().filter(|(_, d)| !d.is_zero()).collect();
+            (tasks, r.total_work)
+        };
+        let before = work(&tl);
+        let duplicates = find(&tl, sentinels);
+        remove(&mut tl, &duplicates).unwrap();
+        assert_eq!(tl.len(), 9);
+        assert_eq!(find(&tl, sentinels), Vec::new());
+        // the same work as before, just without the empty review
+        assert_eq!(work(&tl), before);
+        assert_eq!(
+            tl.note(&tl.get_all().nth(2).unwrap().clone()),
+            Some("about the release")
+        );
+    }
+}
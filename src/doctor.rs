@@ -0,0 +1,333 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use std::collections::BTreeSet;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::activity::duration_in_words;
+use crate::config::Config;
+use crate::store::{parse_entry_line, Entry};
+
+fn hours_min(d: Duration) -> String {
+    format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+}
+
+/**
+ * What is implausible about the interval of an entry
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    // longer than max_interval, or the task's [thresholds] maximum
+    TooLong { duration: Duration, limit: Duration },
+    // earlier than the entry before it
+    BackInTime,
+    // at the same time as the entry before it, so it gets no time
+    Empty,
+    // same time and task as the entry before it
+    Duplicate,
+    // first entry of a day, but not an arrival, so its task gets no time
+    NoArrival,
+}
+
+impl Problem {
+    pub fn to_plain_string(&self) -> String {
+        match self {
+            Problem::TooLong { duration, limit } => format!(
+                "lasts {}, longer than the maximum of {}; split it, or log the time in between",
+                duration_in_words(*duration),
+                duration_in_words(*limit)
+            ),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::TooLong { duration, limit } => write!(
+                f,
+                "lasts {}, longer than the maximum of {}; split it, or log the time in between",
+                hours_min(*duration),
+                hours_min(*limit)
+            ),
+            Problem::BackInTime => write!(f, "goes back in time; fix its time, or move it"),
+            Problem::Empty => write!(f, "takes no time; remove it, or fix its time"),
+            Problem::Duplicate => write!(f, "repeats the entry before; remove it"),
+            Problem::NoArrival => write!(
+                f,
+                "starts the day without arriving, so it gets no time; log the arrival before it"
+            ),
+        }
+    }
+}
+
+/**
+ * An implausible entry in the timelog file
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    // 1-based, for the editor
+    pub line: usize,
+    pub entry: String,
+    pub problem: Problem,
+}
+
+/**
+ * Result of checking the whole timelog
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Checkup {
+    pub findings: Vec<Finding>,
+    // work days without any entries, between the first and the last day of the timelog
+    pub missing_days: Vec<NaiveDate>,
+}
+
+impl Checkup {
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty() && self.missing_days.is_empty()
+    }
+}
+
+// problem of the interval from the `prev` entry to the (`stop`, `task`) one; the first entry of a
+// day only marks the start, and the time before arriving or after leaving does not count
+pub fn check_interval(
+    prev: (NaiveDateTime, &str),
+    stop: NaiveDateTime,
+    task: &str,
+    config: &Config,
+) -> Option<Problem> {
+    let (prev_stop, prev_task) = prev;
+    if stop < prev_stop {
+        return Some(Problem::BackInTime);
+    }
+    if stop == prev_stop && task.trim() == prev_task.trim() {
+        return Some(Problem::Duplicate);
+    }
+    if config.sentinels.day_of(prev_stop) != config.sentinels.day_of(stop)
+        || config.sentinels.is_departure(prev_task)
+        || config.sentinels.is_arrival(task)
+    {
+        return None;
+    }
+    let duration = stop.signed_duration_since(prev_stop);
+    if duration == Duration::zero() {
+        return Some(Problem::Empty);
+    }
+    config
+        .interval_limit(task)
+        .filter(|limit| duration > *limit)
+        .map(|limit| Problem::TooLong { duration, limit })
+}
+
+// Go through the timelog in file order, as loading sorts the entries. Notes, empty, and damaged
+// lines get skipped; the latter get a warning when loading anyway.
+pub fn check(reader: impl BufRead, config: &Config) -> Result<Checkup, io::Error> {
+    let mut findings = Vec::new();
+    let mut days = BTreeSet::new();
+    let mut prev: Option<(NaiveDateTime, String)> = None;
+    // a vacation or sick day needs no arrival
+    let starts_day =
+        |task: &str| config.sentinels.is_arrival(task) || config.absence(task).is_some();
+    for (i, raw) in reader.split(b'\n').enumerate() {
+        let raw = raw?;
+        let line = String::from_utf8_lossy(&raw);
+        let Some((stop, task)) = parse_entry_line(&line) else {
+            continue;
+        };
+        let day = config.sentinels.day_of(stop);
+        let problem = match &prev {
+            Some((prev_stop, prev_task)) => {
+                check_interval((*prev_stop, prev_task), stop, task, config).or_else(|| {
+                    (stop > *prev_stop
+                        && config.sentinels.day_of(*prev_stop) != day
+                        && !starts_day(task))
+                    .then_some(Problem::NoArrival)
+                })
+            }
+            None => (!starts_day(task)).then_some(Problem::NoArrival),
+        };
+        if let Some(problem) = problem {
+            findings.push(Finding {
+                line: i + 1,
+                entry: line.trim().to_string(),
+                problem,
+            });
+        }
+        days.insert(day);
+        // compare later entries with the latest time, not with the one that went back
+        if prev.as_ref().is_none_or(|(p, _)| stop >= *p) {
+            prev = Some((stop, task.to_string()));
+        }
+    }
+    let missing_days = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => work_days_without(&days, *first, *last, config),
+        _ => Vec::new(),
+    };
+    Ok(Checkup {
+        findings,
+        missing_days,
+    })
+}
+
+// the work days from `first` to `last` which are not in `days`
+fn work_days_without(
+    days: &BTreeSet<NaiveDate>,
+    first: NaiveDate,
+    last: NaiveDate,
+    config: &Config,
+) -> Vec<NaiveDate> {
+    first
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .filter(|d| config.is_work_day(*d) && !days.contains(d))
+        .collect()
+}
+
+// the work days from `first` to `last` without any of `entries`, like forgotten timesheet days;
+// vacation and sick days have their entry
+pub fn missing_days(
+    entries: &[Entry],
+    first: NaiveDate,
+    last: NaiveDate,
+    config: &Config,
+) -> Vec<NaiveDate> {
+    let days = entries
+        .iter()
+        .map(|e| config.sentinels.day_of(e.stop))
+        .collect();
+    work_days_without(&days, first, last, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_check() {
+        let log = "2022-06-09 09:00: arrived
+2022-06-09 23:00: rtimelog: code
+  spread over the whole day
+
+2022-06-10 09:00: arrived
+2022-06-10 10:00: customer joe: support
+2022-06-10 09:30: email
+2022-06-10 10:00: rtimelog: review
+2022-06-10 12:00: ** lunch
+2022-06-10 13:00: departed
+2022-06-10 13:00: arrived
+2022-06-10 14:30: customer joe: support
+2022-06-10 14:30: customer joe: support
+2022-06-14 09:00: email
+2022-06-15 09:00: vacation **
+garbage
+";
+        let config = Config::new_from_string(
+            "[rtimelog]\ndeparture = departed\n[thresholds]\ncustomer joe = 1h\n",
+        );
+        let checkup = check(log.as_bytes(), &config).unwrap();
+        let findings = checkup.findings;
+        assert_eq!(
+            findings,
+            vec![
+                Finding {
+                    line: 2,
+                    entry: "2022-06-09 23:00: rtimelog: code".to_string(),
+                    problem: Problem::TooLong {
+                        duration: Duration::hours(14),
+                        limit: Duration::hours(12)
+                    },
+                },
+                Finding {
+                    line: 7,
+                    entry: "2022-06-10 09:30: email".to_string(),
+                    problem: Problem::BackInTime,
+                },
+                Finding {
+                    line: 8,
+                    entry: "2022-06-10 10:00: rtimelog: review".to_string(),
+                    problem: Problem::Empty,
+                },
+                Finding {
+                    line: 12,
+                    entry: "2022-06-10 14:30: customer joe: support".to_string(),
+                    problem: Problem::TooLong {
+                        duration: Duration::minutes(90),
+                        limit: Duration::hours(1)
+                    },
+                },
+                Finding {
+                    line: 13,
+                    entry: "2022-06-10 14:30: customer joe: support".to_string(),
+                    problem: Problem::Duplicate,
+                },
+                Finding {
+                    line: 14,
+                    entry: "2022-06-14 09:00: email".to_string(),
+                    problem: Problem::NoArrival,
+                },
+            ]
+        );
+        // the weekend does not count
+        assert_eq!(
+            checkup.missing_days,
+            vec![NaiveDate::from_ymd_opt(2022, 6, 13).unwrap()]
+        );
+        assert_eq!(
+            findings[0].problem.to_string(),
+            "lasts 14 h 0 min, longer than the maximum of 12 h 0 min; split it, or log the time in between"
+        );
+        assert_eq!(
+            findings[3].problem.to_plain_string(),
+            "lasts 1 hour 30 minutes, longer than the maximum of 1 hour; split it, or log the time in between"
+        );
+
+        let config = Config::new_from_string("[rtimelog]\nmax_interval = 0\n");
+        assert_eq!(
+            check(
+                "2022-06-10 01:00: arrived\n2022-06-10 23:00: code\n".as_bytes(),
+                &config
+            )
+            .unwrap(),
+            Checkup::default()
+        );
+    }
+
+    #[test]
+    fn test_missing_days() {
+        let tl = Timelog::new_from_string(
+            "2024-02-01 09:00: arrived
+2024-02-01 10:00: code
+2024-02-05 09:00: vacation
+2024-02-07 09:00: arrived
+",
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        let config = Config::new_from_string("[rtimelog]\nholidays = 2024-02-08\n");
+        // not the weekend, the vacation day, or the holiday
+        assert_eq!(
+            missing_days(&entries, day(1), day(9), &config),
+            vec![day(2), day(6), day(9)]
+        );
+        assert_eq!(missing_days(&[], day(3), day(4), &config), Vec::new());
+    }
+}
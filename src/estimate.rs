@@ -0,0 +1,197 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use chrono::Duration;
+
+use crate::activity::duration_in_words;
+use crate::config::parse_duration;
+
+const MARKER: &str = "[est:";
+
+// the estimate of an "[est:4h]" marker anywhere in the task, and the task without the marker
+pub fn split(task: &str) -> (String, Option<Duration>) {
+    let Some(start) = task.find(MARKER) else {
+        return (task.to_string(), None);
+    };
+    let Some(len) = task[start..].find(']') else {
+        return (task.to_string(), None);
+    };
+    let Some(estimate) = parse_duration(&task[start + MARKER.len()..start + len]) else {
+        return (task.to_string(), None);
+    };
+    let before = task[..start].trim_end();
+    let after = task[start + len + 1..].trim_start();
+    let rest = match (before.is_empty(), after.is_empty()) {
+        (false, false) => format!("{before} {after}"),
+        _ => format!("{before}{after}"),
+    };
+    (rest, Some(estimate))
+}
+
+/**
+ * Time spent on a task with an "[est:...]" marker, against that estimate
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Estimate {
+    // without the marker
+    pub task: String,
+    pub estimate: Duration,
+    pub actual: Duration,
+}
+
+impl Estimate {
+    // the tasks of `work` (task, duration) with an estimate, like the ones of a report; entries
+    // of the task without the marker count as well, and the last marker wins
+    pub fn for_tasks(work: &[(String, Duration)]) -> Vec<Estimate> {
+        let split: Vec<(String, Option<Duration>, Duration)> = work
+            .iter()
+            .map(|(task, d)| {
+                let (task, estimate) = split(task);
+                (task, estimate, *d)
+            })
+            .collect();
+        let mut estimates: Vec<Estimate> = Vec::new();
+        for (task, estimate, _) in &split {
+            let Some(estimate) = estimate else {
+                continue;
+            };
+            match estimates.iter_mut().find(|e| e.task == *task) {
+                Some(e) => e.estimate = *estimate,
+                None => estimates.push(Estimate {
+                    task: task.clone(),
+                    estimate: *estimate,
+                    actual: Duration::zero(),
+                }),
+            }
+        }
+        for e in &mut estimates {
+            e.actual = split
+                .iter()
+                .filter(|(task, _, _)| *task == e.task)
+                .map(|(_, _, d)| *d)
+                .sum();
+        }
+        estimates
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.actual > self.estimate
+    }
+
+    pub fn percent(&self) -> i64 {
+        self.actual.num_minutes() * 100 / self.estimate.num_minutes().max(1)
+    }
+
+    pub fn to_plain_string(&self) -> String {
+        let rest = match self.is_over() {
+            true => format!("{} over", duration_in_words(self.actual - self.estimate)),
+            false => format!("{} under", duration_in_words(self.estimate - self.actual)),
+        };
+        format!(
+            "{}: {} of {} estimated, {} percent, {rest}",
+            self.task,
+            duration_in_words(self.actual),
+            duration_in_words(self.estimate),
+            self.percent()
+        )
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hours_min = |d: Duration| format!("{} h {} min", d.num_hours(), d.num_minutes() % 60);
+        let rest = match self.is_over() {
+            true => format!("{} over", hours_min(self.actual - self.estimate)),
+            false => format!("{} under", hours_min(self.estimate - self.actual)),
+        };
+        write!(
+            f,
+            "{}: {} of {} estimated ({}%), {rest}",
+            self.task,
+            hours_min(self.actual),
+            hours_min(self.estimate),
+            self.percent()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("rtimelog: parser rewrite [est:4h]"),
+            (
+                "rtimelog: parser rewrite".to_string(),
+                Some(Duration::hours(4))
+            )
+        );
+        assert_eq!(
+            split("rtimelog: [est:1h30m] parser #release"),
+            (
+                "rtimelog: parser #release".to_string(),
+                Some(Duration::minutes(90))
+            )
+        );
+        assert_eq!(split("email"), ("email".to_string(), None));
+        // not a duration
+        assert_eq!(
+            split("docs [est:soon]"),
+            ("docs [est:soon]".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_estimates() {
+        let work = vec![
+            (
+                "rtimelog: parser rewrite [est:4h]".to_string(),
+                Duration::hours(3),
+            ),
+            ("email".to_string(), Duration::hours(1)),
+            ("rtimelog: parser rewrite".to_string(), Duration::hours(2)),
+            ("rtimelog: docs [est:2h]".to_string(), Duration::minutes(30)),
+        ];
+        let estimates = Estimate::for_tasks(&work);
+        assert_eq!(
+            estimates,
+            vec![
+                Estimate {
+                    task: "rtimelog: parser rewrite".to_string(),
+                    estimate: Duration::hours(4),
+                    actual: Duration::hours(5),
+                },
+                Estimate {
+                    task: "rtimelog: docs".to_string(),
+                    estimate: Duration::hours(2),
+                    actual: Duration::minutes(30),
+                },
+            ]
+        );
+        assert_eq!(
+            estimates[0].to_string(),
+            "rtimelog: parser rewrite: 5 h 0 min of 4 h 0 min estimated (125%), 1 h 0 min over"
+        );
+        assert_eq!(
+            estimates[1].to_plain_string(),
+            "rtimelog: docs: 30 minutes of 2 hours estimated, 25 percent, 1 hour 30 minutes under"
+        );
+    }
+}
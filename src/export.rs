@@ -0,0 +1,187 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+
+use crate::activity;
+use crate::config::{Config, Sentinels};
+use crate::html;
+use crate::ics::{self, Event};
+use crate::store::Entry;
+
+/**
+ * File formats of other programs, for the timelog's entries
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    // iCalendar, for calendar apps
+    Ics,
+    // check-in/out pairs for hledger and ledger
+    Timeclock,
+    // a page with charts and tables, see html::dashboard()
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name {
+            "ics" | "ical" => Some(ExportFormat::Ics),
+            "timeclock" => Some(ExportFormat::Timeclock),
+            "html" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+// one event per interval, from the previous entry's stop to this one's; the entries which only
+// start the clock, like the "arrived" of a day, have none
+pub fn events(entries: &[Entry], sentinels: &Sentinels) -> Vec<Event> {
+    activity::durations(entries, sentinels)
+        .filter(|(_, d)| *d > Duration::zero())
+        .map(|(e, d)| Event {
+            summary: e.task.to_string(),
+            start: e.stop - d,
+            end: Some(e.stop),
+            all_day: false,
+        })
+        .collect()
+}
+
+// "i" and "o" lines of each interval, with the project as account and the rest of the task as
+// description, like "customer joe:emergency  fix login"; slacking is not work for accounting
+pub fn timeclock(entries: &[Entry], sentinels: &Sentinels) -> String {
+    let mut out = String::new();
+    for e in events(entries, sentinels) {
+        if activity::is_slack(&e.summary) {
+            continue;
+        }
+        let (account, description) = match e.summary.rsplit_once(": ") {
+            Some((project, text)) => (project.replace(": ", ":"), text.trim()),
+            None => (e.summary.clone(), ""),
+        };
+        // two spaces end the account
+        let account = account.split_whitespace().collect::<Vec<_>>().join(" ");
+        let time = |t: NaiveDateTime| t.format("%Y/%m/%d %H:%M:%S");
+        let line = format!("i {} {account}  {description}", time(e.start));
+        writeln!(out, "{}", line.trim_end()).unwrap();
+        writeln!(out, "o {}", time(e.end.unwrap_or(e.start))).unwrap();
+    }
+    out
+}
+
+// `entries` in `format`; `now` is when the export got made
+pub fn export(
+    entries: &[Entry],
+    format: ExportFormat,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> String {
+    let sentinels = &config.sentinels;
+    match format {
+        ExportFormat::Ics => ics::format(&events(entries, sentinels), now),
+        ExportFormat::Timeclock => timeclock(entries, sentinels),
+        ExportFormat::Html => {
+            // the days of the entries
+            let day = |e: Option<&Entry>| e.map_or(now.date_naive(), |e| sentinels.day_of(e.stop));
+            html::dashboard(entries, day(entries.first()), day(entries.last()), config)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::{NaiveDate, TimeZone};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_export_ics() {
+        let tl = Timelog::new_from_string(
+            "
+2024-05-06 09:00: arrived
+2024-05-06 10:00: customer joe: support
+2024-05-06 10:30: ** tea
+2024-05-06 12:00: left
+2024-05-06 13:00: arrived
+2024-05-06 13:45: rtimelog: code
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let sentinels = Sentinels {
+            departure: vec!["left".to_string()],
+            ..Default::default()
+        };
+        let at = |h, m| {
+            NaiveDate::from_ymd_opt(2024, 5, 6)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+        let events = events(entries, &sentinels);
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.summary.as_str(), e.start, e.end.unwrap()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("customer joe: support", at(9, 0), at(10, 0)),
+                ("** tea", at(10, 0), at(10, 30)),
+                ("left", at(10, 30), at(12, 0)),
+                ("rtimelog: code", at(13, 0), at(13, 45)),
+            ]
+        );
+        assert_eq!(ExportFormat::parse("ics"), Some(ExportFormat::Ics));
+        assert_eq!(ExportFormat::parse("vcs"), None);
+        let now = Utc.with_ymd_and_hms(2024, 5, 7, 8, 0, 0).unwrap();
+        let config = Config {
+            sentinels: sentinels.clone(),
+            ..Config::default()
+        };
+        let out = export(entries, ExportFormat::Ics, &config, now);
+        assert_eq!(ics::parse(&out), events);
+    }
+
+    #[test]
+    fn test_export_timeclock() {
+        let tl = Timelog::new_from_string(
+            "
+2024-05-06 09:00: arrived
+2024-05-06 10:00: customer joe: emergency: fix login
+2024-05-06 10:30: ** tea
+2024-05-06 12:00: email
+2024-05-06 12:30: big  project: talk
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        assert_eq!(
+            ExportFormat::parse("timeclock"),
+            Some(ExportFormat::Timeclock)
+        );
+        let now = Utc.with_ymd_and_hms(2024, 5, 7, 8, 0, 0).unwrap();
+        assert_eq!(
+            export(entries, ExportFormat::Timeclock, &Config::default(), now),
+            "i 2024/05/06 09:00:00 customer joe:emergency  fix login
+o 2024/05/06 10:00:00
+i 2024/05/06 10:30:00 email
+o 2024/05/06 12:00:00
+i 2024/05/06 12:00:00 big project  talk
+o 2024/05/06 12:30:00
+"
+        );
+    }
+}
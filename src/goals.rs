@@ -0,0 +1,151 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use chrono::Duration;
+
+use crate::activity::{duration_in_words, Activities};
+use crate::config::{in_project, Config};
+use crate::store::Entry;
+
+/**
+ * Time spent this week on the tasks of a weekly goal from the [goals] section
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoalProgress {
+    // task prefix, like the one of [thresholds]
+    pub prefix: String,
+    pub goal: Duration,
+    pub done: Duration,
+}
+
+impl GoalProgress {
+    // progress of all configured goals, from the entries of the current week
+    pub fn for_week(week: &[Entry], config: &Config) -> Vec<GoalProgress> {
+        if config.goals.is_empty() {
+            return Vec::new();
+        }
+        let a = Activities::new_from_entries(week, &config.sentinels);
+        config
+            .goals
+            .iter()
+            .map(|(prefix, goal)| GoalProgress {
+                prefix: prefix.clone(),
+                goal: *goal,
+                done: a.duration_matching(prefix),
+            })
+            .collect()
+    }
+
+    // the goal with the longest prefix of `task`, see config::in_project()
+    pub fn find<'a>(progress: &'a [GoalProgress], task: &str) -> Option<&'a GoalProgress> {
+        progress
+            .iter()
+            .filter(|p| in_project(task, &p.prefix))
+            .max_by_key(|p| p.prefix.len())
+    }
+
+    pub fn percent(&self) -> i64 {
+        self.done.num_minutes() * 100 / self.goal.num_minutes().max(1)
+    }
+
+    pub fn to_plain_string(&self) -> String {
+        format!(
+            "{}: {} of {}, {} percent",
+            self.prefix,
+            duration_in_words(self.done),
+            duration_in_words(self.goal),
+            self.percent()
+        )
+    }
+}
+
+impl fmt::Display for GoalProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} h {} min of {} h {} min ({}%)",
+            self.prefix,
+            self.done.num_hours(),
+            self.done.num_minutes() % 60,
+            self.goal.num_hours(),
+            self.goal.num_minutes() % 60,
+            self.percent()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_goals() {
+        let tl = Timelog::new_from_string(
+            "2022-06-06 09:00: arrived
+2022-06-06 11:00: rtimelog: code
+2022-06-06 12:00: customer joe: support
+
+2022-06-10 09:00: arrived
+2022-06-10 10:20: rtimelog: docs
+2022-06-10 10:30: ** tea
+",
+        );
+        let week = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            1,
+            NaiveTime::MIN,
+            Weekday::Mon,
+        );
+        assert_eq!(GoalProgress::for_week(week, &Config::default()), Vec::new());
+
+        let config = Config::new_from_string("[goals]\nrtimelog = 5h\nreading = 2h\n");
+        let progress = GoalProgress::for_week(week, &config);
+        assert_eq!(
+            progress,
+            vec![
+                GoalProgress {
+                    prefix: "rtimelog".to_string(),
+                    goal: Duration::hours(5),
+                    done: Duration::minutes(200),
+                },
+                GoalProgress {
+                    prefix: "reading".to_string(),
+                    goal: Duration::hours(2),
+                    done: Duration::minutes(0),
+                },
+            ]
+        );
+        assert_eq!(
+            progress[0].to_string(),
+            "rtimelog: 3 h 20 min of 5 h 0 min (66%)"
+        );
+        assert_eq!(
+            progress[0].to_plain_string(),
+            "rtimelog: 3 hours 20 minutes of 5 hours, 66 percent"
+        );
+
+        assert_eq!(
+            GoalProgress::find(&progress, "rtimelog: review"),
+            Some(&progress[0])
+        );
+        assert_eq!(GoalProgress::find(&progress, "email"), None);
+        assert_eq!(GoalProgress::find(&progress, "rtimelogger: code"), None);
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+
+use crate::activity;
+use crate::config::Config;
+use crate::report::short;
+use crate::store::Entry;
+
+// from nothing to the busiest hour
+const SHADES: [&str; 5] = ["  ", "░░", "▒▒", "▓▓", "██"];
+
+/**
+ * Work per weekday and hour of the day over the days from `first` to `last`, from the intervals
+ * of the entries, for seeing when the work actually happens
+ *
+ * Unlike the reports, this goes by the calendar: work after midnight is on the next weekday.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Heatmap {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    // the first row, see Config::week_start
+    pub week_start: Weekday,
+    // work per row and hour
+    pub work: [[Duration; 24]; 7],
+    pub total: Duration,
+    // before work_start, after work_end, or not on work_days
+    pub outside: Duration,
+}
+
+impl Heatmap {
+    pub fn new(entries: &[Entry], first: NaiveDate, last: NaiveDate, config: &Config) -> Heatmap {
+        let sentinels = &config.sentinels;
+        let day_of = |e: &Entry| sentinels.day_of(e.stop);
+        let begin = entries.partition_point(|e| day_of(e) < first);
+        let end = entries.partition_point(|e| day_of(e) <= last);
+        let entries = &entries[begin..end.max(begin)];
+        let mut heatmap = Heatmap {
+            first,
+            last,
+            week_start: config.week_start,
+            work: [[Duration::zero(); 24]; 7],
+            total: Duration::zero(),
+            outside: Duration::zero(),
+        };
+        for (e, duration) in activity::durations(entries, sentinels) {
+            if e.is_slack() || duration <= Duration::zero() {
+                continue;
+            }
+            // the interval in pieces within one hour each
+            let mut start = e.stop - duration;
+            while start < e.stop {
+                let hour = start.date().and_hms_opt(start.hour(), 0, 0).unwrap();
+                let stop = (hour + Duration::hours(1)).min(e.stop);
+                heatmap.add(start, stop, config);
+                start = stop;
+            }
+        }
+        heatmap
+    }
+
+    // work from `start` to `stop`, within the same hour
+    fn add(&mut self, start: NaiveDateTime, stop: NaiveDateTime, config: &Config) {
+        let d = stop - start;
+        let row = start.weekday().days_since(self.week_start) as usize;
+        self.work[row][start.hour() as usize] += d;
+        self.total += d;
+        // as durations since midnight, as `stop` may be the next one
+        let midnight = start.date().and_time(NaiveTime::MIN);
+        let since = |t: NaiveTime| t - NaiveTime::MIN;
+        let inside = match config.work_days.contains(&start.weekday()) {
+            true => {
+                (stop - midnight).min(since(config.work_end))
+                    - (start - midnight).max(since(config.work_start))
+            }
+            false => Duration::zero(),
+        };
+        self.outside += d - inside.max(Duration::zero());
+    }
+
+    // weekday rows with a column for each hour, which is the darker the more work it has
+    pub fn format(&self, config: &Config) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Work by hour from {} to {}\n",
+            config.date_format.format(&self.first),
+            config.date_format.format(&self.last)
+        )
+        .unwrap();
+        if self.total <= Duration::zero() {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        // any date of that weekday, for its localized name
+        let weekday = |row: usize| {
+            let monday = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+            let day = monday
+                + Duration::days(
+                    (self.week_start.num_days_from_monday() as usize + row) as i64 % 7,
+                );
+            config.date_format.format_with(&day, "%a")
+        };
+        if config.plain {
+            for (row, hours) in self.work.iter().enumerate() {
+                let hours: Vec<String> = (0..24)
+                    .filter(|h| hours[*h] > Duration::zero())
+                    .map(|h| {
+                        let time = NaiveTime::from_hms_opt(h as u32, 0, 0).unwrap();
+                        format!(
+                            "{} {}",
+                            config.date_format.time(time),
+                            activity::duration_in_words(hours[h])
+                        )
+                    })
+                    .collect();
+                if !hours.is_empty() {
+                    writeln!(out, "{}: {}", weekday(row), hours.join(", ")).unwrap();
+                }
+            }
+        } else {
+            let max = self
+                .work
+                .iter()
+                .flatten()
+                .max()
+                .copied()
+                .unwrap_or_default();
+            let width = (0..7)
+                .map(|r| weekday(r).chars().count())
+                .max()
+                .unwrap_or(0);
+            let header: String = (0..24).step_by(3).map(|h| format!("{h:<6}")).collect();
+            writeln!(out, "{:width$}  {}", "", header.trim_end()).unwrap();
+            for (row, hours) in self.work.iter().enumerate() {
+                let cells: String = hours
+                    .iter()
+                    .map(|d| {
+                        // any work at all gets the lightest shade
+                        let level = (d.num_seconds() * 4 + max.num_seconds() - 1)
+                            / max.num_seconds().max(1);
+                        SHADES[level.clamp(0, 4) as usize]
+                    })
+                    .collect();
+                let day: Duration = hours.iter().copied().sum();
+                let line = format!("{:<width$}  {cells}  {}", weekday(row), short(day));
+                writeln!(out, "{}", line.trim_end()).unwrap();
+            }
+            writeln!(
+                out,
+                "\n{} is the busiest hour, with {}",
+                SHADES[4],
+                short(max)
+            )
+            .unwrap();
+        }
+        let duration = if config.plain {
+            activity::duration_in_words
+        } else {
+            short
+        };
+        writeln!(
+            out,
+            "\nTotal work done: {}, {} of it outside of the work hours ({}%)",
+            duration(self.total),
+            duration(self.outside),
+            self.outside.num_seconds() * 100 / self.total.num_seconds()
+        )
+        .unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_heatmap() {
+        let tl = Timelog::new_from_string(
+            "2024-01-29 09:00: arrived
+2024-01-29 10:30: code
+2024-01-29 12:00: ** lunch
+2024-01-29 13:00: code
+2024-01-30 16:00: arrived
+2024-01-30 18:30: code
+2024-02-03 10:00: arrived
+2024-02-03 11:00: code
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let config = Config::default();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let h = Heatmap::new(entries, day(1, 29), day(2, 4), &config);
+        let m = Duration::minutes;
+        assert_eq!(h.work[0][9], m(60));
+        assert_eq!(h.work[0][10], m(30));
+        assert_eq!(h.work[0][11], m(0));
+        assert_eq!(h.work[1][17], m(60));
+        assert_eq!(h.work[5][10], m(60));
+        assert_eq!(h.total, m(360));
+        assert_eq!(h.outside, m(150));
+        assert_eq!(
+            h.format(&config),
+            "Work by hour from Monday, 2024-01-29 to Sunday, 2024-02-04
+
+     0     3     6     9     12    15    18    21
+Mon                    ██▒▒  ██                        2h 30m
+Tue                                  ████▒▒            2h 30m
+Wed                                                    0m
+Thu                                                    0m
+Fri                                                    0m
+Sat                      ██                            1h
+Sun                                                    0m
+
+██ is the busiest hour, with 1h
+
+Total work done: 6h, 2h 30m of it outside of the work hours (41%)
+"
+        );
+        let plain = Config {
+            plain: true,
+            week_start: Weekday::Sun,
+            ..Config::default()
+        };
+        let h = Heatmap::new(entries, day(1, 28), day(2, 3), &plain);
+        assert_eq!(
+            h.format(&plain),
+            "Work by hour from Sunday, 2024-01-28 to Saturday, 2024-02-03
+
+Mon: 09:00 1 hour, 10:00 30 minutes, 12:00 1 hour
+Tue: 16:00 1 hour, 17:00 1 hour, 18:00 30 minutes
+Sat: 10:00 1 hour
+
+Total work done: 6 hours, 2 hours 30 minutes of it outside of the work hours (41%)
+"
+        );
+        let h = Heatmap::new(entries, day(3, 4), day(3, 10), &config);
+        assert!(h.format(&config).ends_with("\n\nNo work logged\n"));
+    }
+}
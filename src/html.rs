@@ -0,0 +1,271 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{Duration, NaiveDate};
+
+use crate::config::Config;
+use crate::report::{short, PeriodReport, Timesheet};
+use crate::store::Entry;
+
+// height of the tallest day bar, and width of the longest project bar
+const BAR_HEIGHT: i64 = 120;
+const BAR_WIDTH: i64 = 360;
+// room for the project names left of their bars
+const LABEL_WIDTH: i64 = 220;
+
+const STYLE: &str =
+    "body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0 2em; }
+th, td { padding: 0.2em 0.6em; border-bottom: 1px solid #ddd; text-align: left; }
+.hours { text-align: right; font-variant-numeric: tabular-nums; }
+tr.total { font-weight: bold; }
+svg text { font-size: 12px; fill: #222; }
+svg rect { fill: #4a90d9; }";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// with a "…" after `max` characters
+fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}…", &s[..i]),
+        None => s.to_string(),
+    }
+}
+
+// `d` of `max` as a share of `length` pixels
+fn scale(d: Duration, max: Duration, length: i64) -> i64 {
+    match max.num_minutes() {
+        0 => 0,
+        m => d.num_minutes() * length / m,
+    }
+}
+
+// horizontal bars of the work of each project
+fn project_chart(projects: &[(String, Duration)]) -> String {
+    let max = projects.first().map_or(Duration::zero(), |(_, d)| *d);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" role="img" aria-label="Work per project">"#,
+        LABEL_WIDTH + BAR_WIDTH + 80,
+        projects.len() * 24
+    );
+    for (i, (project, d)) in projects.iter().enumerate() {
+        let y = i * 24;
+        let width = scale(*d, max, BAR_WIDTH);
+        write!(
+            svg,
+            r#"<text x="0" y="{}">{}</text><rect x="{LABEL_WIDTH}" y="{}" width="{width}" height="16"/><text x="{}" y="{}">{}</text>"#,
+            y + 16,
+            escape(&truncate(project, 30)),
+            y + 4,
+            LABEL_WIDTH + width + 6,
+            y + 16,
+            short(*d)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// vertical bars of the work of each day, with the same `max` in all weeks
+fn day_chart(days: &[(NaiveDate, Duration)], max: Duration) -> String {
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" role="img" aria-label="Work per day">"#,
+        days.len() * 70,
+        BAR_HEIGHT + 40
+    );
+    for (i, (day, d)) in days.iter().enumerate() {
+        let x = i as i64 * 70;
+        let height = scale(*d, max, BAR_HEIGHT);
+        let top = 20 + BAR_HEIGHT - height;
+        write!(
+            svg,
+            r#"<rect x="{}" y="{top}" width="50" height="{height}"/><text x="{}" y="{}">{}</text><text x="{}" y="{}">{}</text>"#,
+            x + 10,
+            x + 10,
+            top - 4,
+            // like the empty cells of the table
+            match d.is_zero() {
+                true => String::new(),
+                false => short(*d),
+            },
+            x + 10,
+            BAR_HEIGHT + 36,
+            day.format("%a %d")
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// the projects of a week with their work per day, like Timesheet
+fn week_table(week: &Timesheet) -> String {
+    let mut html = String::from("<table>\n<tr><th>Project</th>");
+    for (day, _) in &week.days {
+        write!(
+            html,
+            r#"<th class="hours">{}</th>"#,
+            day.format("%a %Y-%m-%d")
+        )
+        .unwrap();
+    }
+    html.push_str("<th class=\"hours\">Total</th></tr>\n");
+    let cell = |d: &Duration| match d.is_zero() {
+        true => r#"<td class="hours"></td>"#.to_string(),
+        false => format!(r#"<td class="hours">{}</td>"#, short(*d)),
+    };
+    for (project, work, total) in &week.projects {
+        write!(html, "<tr><td>{}</td>", escape(project)).unwrap();
+        for d in work {
+            html.push_str(&cell(d));
+        }
+        writeln!(html, "{}</tr>", cell(total)).unwrap();
+    }
+    html.push_str(r#"<tr class="total"><td>Total</td>"#);
+    for (_, d) in &week.days {
+        html.push_str(&cell(d));
+    }
+    writeln!(html, "{}</tr>\n</table>", cell(&week.total_work)).unwrap();
+    html
+}
+
+// Self-contained page of the days from `first` to `last`, for people who don't use rtimelog: the
+// work per project of all days, then of each week a chart of the work per day and a table of the
+// work per project and day. It needs no scripts, fonts, or images from elsewhere.
+pub fn dashboard(entries: &[Entry], first: NaiveDate, last: NaiveDate, config: &Config) -> String {
+    let date = |d: &NaiveDate| config.date_format.format(d);
+    let report = PeriodReport::new(entries, first, last, config);
+    let mut title = format!("Work from {} to {}", date(&first), date(&last));
+    if let Some(name) = &config.name {
+        title = format!("{title} of {name}");
+    }
+    let title = escape(&title);
+    let mut html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+{STYLE}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p>Total work: {}, slacking: {}</p>
+"#,
+        short(report.total_work),
+        short(report.total_slack)
+    );
+    if !report.projects.is_empty() {
+        writeln!(
+            html,
+            "<h2>Projects</h2>\n{}",
+            project_chart(&report.projects)
+        )
+        .unwrap();
+    }
+    let weeks = Timesheet::weeks(entries, first, last, config);
+    let max = weeks
+        .iter()
+        .flat_map(|w| w.days.iter().map(|(_, d)| *d))
+        .max()
+        .unwrap_or_default()
+        .max(config.hours);
+    for week in weeks.iter().filter(|w| !w.total_work.is_zero()) {
+        writeln!(
+            html,
+            "<h2>Week from {}</h2>\n{}\n{}",
+            date(&week.days[0].0),
+            day_chart(&week.days, max),
+            week_table(week)
+        )
+        .unwrap();
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("customer joe", 30), "customer joe");
+        assert_eq!(truncate("äöü customer", 3), "äöü…");
+    }
+
+    #[test]
+    fn test_dashboard() {
+        let tl = Timelog::new_from_string(
+            "2024-02-02 09:00: arrived
+2024-02-02 10:30: R&D: <prototype>
+2024-02-02 11:00: ** tea
+
+2024-02-05 09:00: arrived
+2024-02-05 10:00: customer joe: support
+2024-02-05 12:00: R&D: docs
+",
+        );
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let config = Config {
+            name: Some("Joe".to_string()),
+            ..Config::default()
+        };
+        let weeks = Timesheet::weeks(&entries, day(2), day(5), &config);
+        assert_eq!(
+            week_table(&weeks[1]),
+            r#"<table>
+<tr><th>Project</th><th class="hours">Mon 2024-02-05</th><th class="hours">Total</th></tr>
+<tr><td>R&amp;D</td><td class="hours">2h</td><td class="hours">2h</td></tr>
+<tr><td>customer joe</td><td class="hours">1h</td><td class="hours">1h</td></tr>
+<tr class="total"><td>Total</td><td class="hours">3h</td><td class="hours">3h</td></tr>
+</table>
+"#
+        );
+        // 3h of the 8h target
+        assert_eq!(
+            day_chart(&weeks[1].days, config.hours),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="70" height="160" role="img" aria-label="Work per day"><rect x="10" y="95" width="50" height="45"/><text x="10" y="91">3h</text><text x="10" y="156">Mon 05</text></svg>"#
+        );
+
+        let html = dashboard(&entries, day(2), day(5), &config);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(
+            "<h1>Work from Friday, 2024-02-02 to Monday, 2024-02-05 of Joe</h1>\n\
+             <p>Total work: 4h 30m, slacking: 30m</p>"
+        ));
+        assert!(
+            html.contains(r#"<text x="0" y="16">R&amp;D</text><rect x="220" y="4" width="360""#)
+        );
+        assert!(html.contains("<h2>Week from Friday, 2024-02-02</h2>"));
+        assert!(html.contains("<h2>Week from Monday, 2024-02-05</h2>"));
+        assert!(!html.contains("<prototype>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
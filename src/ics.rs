@@ -0,0 +1,354 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/**
+ * Single VEVENT from an iCalendar file
+ *
+ * Times are local. Recurrence rules are not supported.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub summary: String,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    pub all_day: bool,
+}
+
+impl Event {
+    pub fn is_on(&self, day: NaiveDate) -> bool {
+        let end = self.end.unwrap_or(self.start);
+        if self.all_day {
+            // DTEND of all-day events is exclusive
+            self.start.date() <= day && (day < end.date() || day == self.start.date())
+        } else {
+            self.start.date() <= day && day <= end.date()
+        }
+    }
+}
+
+pub fn read(path: &PathBuf) -> Vec<Event> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse(&contents),
+        Err(e) => {
+            eprintln!("WARNING: Could not read {}: {:?}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+// join folded lines (continuation lines start with a space or tab)
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => (),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// split lines longer than 75 bytes, the reverse of unfold()
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // the space counts
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+// parse DTSTART/DTEND value; returns time and whether it is a date only
+fn parse_time(value: &str) -> Option<(NaiveDateTime, bool)> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let dt = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((
+            Utc.from_utc_datetime(&dt)
+                .with_timezone(&Local)
+                .naive_local(),
+            false,
+        ));
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        // floating time or TZID; treat as local time
+        return Some((dt, false));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some((date.and_hms_opt(0, 0, 0).unwrap(), true))
+}
+
+// VEVENT while parsing it; DTSTART is mandatory
+#[derive(Default)]
+struct PartialEvent {
+    summary: String,
+    start: Option<(NaiveDateTime, bool)>,
+    end: Option<NaiveDateTime>,
+}
+
+pub fn parse(contents: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<PartialEvent> = None;
+
+    for line in unfold(contents) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // strip parameters like DTSTART;TZID=Europe/Berlin
+        let name = name.split(';').next().unwrap().to_uppercase();
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => current = Some(PartialEvent::default()),
+            ("END", "VEVENT") => {
+                if let Some(PartialEvent {
+                    summary,
+                    start: Some((start, all_day)),
+                    end,
+                }) = current.take()
+                {
+                    events.push(Event {
+                        summary,
+                        start,
+                        end,
+                        all_day,
+                    });
+                }
+            }
+            ("SUMMARY", _) => {
+                if let Some(c) = current.as_mut() {
+                    c.summary = unescape(value);
+                }
+            }
+            ("DTSTART", _) => {
+                if let Some(c) = current.as_mut() {
+                    c.start = parse_time(value);
+                }
+            }
+            ("DTEND", _) => {
+                if let Some(c) = current.as_mut() {
+                    c.end = parse_time(value).map(|t| t.0);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    events.sort_by_key(|e| e.start);
+    events
+}
+
+// iCalendar file with `events`, for calendar apps; `stamp` is when it got made
+pub fn format(events: &[Event], stamp: DateTime<Utc>) -> String {
+    let time = |t: &NaiveDateTime, all_day: bool| match all_day {
+        true => format!(";VALUE=DATE:{}", t.format("%Y%m%d")),
+        false => format!(":{}", t.format("%Y%m%dT%H%M%S")),
+    };
+    let mut out = String::new();
+    let mut line = |l: String| write!(out, "{}\r\n", fold(&l)).unwrap();
+    line("BEGIN:VCALENDAR".to_string());
+    line("VERSION:2.0".to_string());
+    line("PRODID:-//rtimelog//rtimelog//EN".to_string());
+    for e in events {
+        line("BEGIN:VEVENT".to_string());
+        // an end time is unique in a timelog, and stays the same when exporting again
+        let id = e.end.unwrap_or(e.start).format("%Y%m%dT%H%M%S");
+        line(format!("UID:{id}@rtimelog"));
+        line(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        // floating local times, like the timelog has them
+        line(format!("DTSTART{}", time(&e.start, e.all_day)));
+        if let Some(end) = &e.end {
+            line(format!("DTEND{}", time(end, e.all_day)));
+        }
+        line(format!("SUMMARY:{}", escape(&e.summary)));
+        line("END:VEVENT".to_string());
+    }
+    line("END:VCALENDAR".to_string());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Berlin:20220610T140000\r
+DTEND;TZID=Europe/Berlin:20220610T150000\r
+SUMMARY:customer joe\\, call\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20220610T093000\r
+DTEND:20220610T094500\r
+SUMMARY:team standup with a very long\r
+  title\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20220609\r
+DTEND;VALUE=DATE:20220611\r
+SUMMARY:conference\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:no start time\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn time(d: u32, h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 6, d)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), vec![]);
+        assert_eq!(
+            parse(CALENDAR),
+            vec![
+                Event {
+                    summary: "conference".to_string(),
+                    start: time(9, 0, 0),
+                    end: Some(time(11, 0, 0)),
+                    all_day: true,
+                },
+                Event {
+                    summary: "team standup with a very long title".to_string(),
+                    start: time(10, 9, 30),
+                    end: Some(time(10, 9, 45)),
+                    all_day: false,
+                },
+                Event {
+                    summary: "customer joe, call".to_string(),
+                    start: time(10, 14, 0),
+                    end: Some(time(10, 15, 0)),
+                    all_day: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let long = "customer joe: emergency; fix the login, then write a long report about it";
+        let events = vec![
+            Event {
+                summary: long.to_string(),
+                start: time(10, 9, 0),
+                end: Some(time(10, 11, 0)),
+                all_day: false,
+            },
+            Event {
+                summary: "conference".to_string(),
+                start: time(9, 0, 0),
+                end: Some(time(11, 0, 0)),
+                all_day: true,
+            },
+        ];
+        let stamp = Utc.with_ymd_and_hms(2022, 6, 12, 8, 0, 0).unwrap();
+        let out = format(&events, stamp);
+        assert_eq!(
+            out,
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//rtimelog//rtimelog//EN\r
+BEGIN:VEVENT\r
+UID:20220610T110000@rtimelog\r
+DTSTAMP:20220612T080000Z\r
+DTSTART:20220610T090000\r
+DTEND:20220610T110000\r
+SUMMARY:customer joe: emergency\\; fix the login\\, then write a long report \r
+ about it\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:20220611T000000@rtimelog\r
+DTSTAMP:20220612T080000Z\r
+DTSTART;VALUE=DATE:20220609\r
+DTEND;VALUE=DATE:20220611\r
+SUMMARY:conference\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+        );
+        // reads back
+        let mut sorted = events.clone();
+        sorted.reverse();
+        assert_eq!(parse(&out), sorted);
+        assert!(out.lines().all(|l| l.len() <= 76));
+    }
+
+    #[test]
+    fn test_parse_utc() {
+        let events = parse("BEGIN:VEVENT\nDTSTART:20220610T120000Z\nSUMMARY:x\nEND:VEVENT\n");
+        assert_eq!(events.len(), 1);
+        let expected = Utc
+            .from_utc_datetime(&time(10, 12, 0))
+            .with_timezone(&Local)
+            .naive_local();
+        assert_eq!(events[0].start, expected);
+    }
+
+    #[test]
+    fn test_is_on() {
+        let events = parse(CALENDAR);
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        // all-day event, end is exclusive
+        assert!(!events[0].is_on(day(8)));
+        assert!(events[0].is_on(day(9)));
+        assert!(events[0].is_on(day(10)));
+        assert!(!events[0].is_on(day(11)));
+        assert!(events[1].is_on(day(10)));
+        assert!(!events[1].is_on(day(9)));
+    }
+}
@@ -0,0 +1,757 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::env;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::process::{self, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone};
+
+/// How often the idle time gets probed
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(15);
+
+/// Difference between wall clock and monotonic clock which counts as suspend
+const CLOCK_JUMP_TOLERANCE_SECONDS: i64 = 60;
+
+/**
+ * Why the user was away; ordered by how certain that is
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AwayReason {
+    Idle,
+    Locked,
+    Suspended,
+}
+
+impl fmt::Display for AwayReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AwayReason::Idle => write!(f, "idle"),
+            AwayReason::Locked => write!(f, "screen locked"),
+            AwayReason::Suspended => write!(f, "suspended"),
+        }
+    }
+}
+
+/**
+ * Time span in which the user was away from the computer
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwaySpan {
+    pub begin: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub reason: AwayReason,
+}
+
+impl AwaySpan {
+    pub fn duration(&self) -> Duration {
+        self.end.signed_duration_since(self.begin)
+    }
+
+    // parse a HH:MM time strictly within the span, to split it into two
+    pub fn split_at(&self, input: &str) -> Option<(AwaySpan, AwaySpan)> {
+        let time = NaiveTime::parse_from_str(input.trim(), "%H:%M").ok()?;
+        let mut at = self.begin.date().and_time(time);
+        // spans through midnight
+        if at <= self.begin {
+            at += Duration::days(1);
+        }
+        if at >= self.end {
+            return None;
+        }
+        Some((
+            AwaySpan {
+                end: at,
+                ..self.clone()
+            },
+            AwaySpan {
+                begin: at,
+                ..self.clone()
+            },
+        ))
+    }
+}
+
+/**
+ * How to log an AwaySpan, as answered by the user
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum Assignment {
+    Break,
+    // the task which is being logged
+    Work,
+    OtherTask,
+    Split,
+    Error(String),
+}
+
+impl Assignment {
+    pub fn parse(answer: &str) -> Assignment {
+        match answer.trim().to_lowercase().as_str() {
+            "" | "b" | "break" => Assignment::Break,
+            "w" | "work" => Assignment::Work,
+            "t" | "task" => Assignment::OtherTask,
+            "s" | "split" => Assignment::Split,
+            other => Assignment::Error(format!("Unknown answer {other}")),
+        }
+    }
+}
+
+/**
+ * Screen lock and suspend notifications from the session
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Lock,
+    Unlock,
+    Sleep,
+    Resume,
+}
+
+/**
+ * Turn a series of idle time samples and session events into AwaySpans
+ */
+#[derive(Debug, Clone)]
+pub struct IdleTracker {
+    threshold: Duration,
+    // start of the currently ongoing idle span
+    current: Option<NaiveDateTime>,
+    // start of the currently ongoing lock/suspend span
+    session: Option<(NaiveDateTime, AwayReason)>,
+    locked: bool,
+    spans: Vec<AwaySpan>,
+}
+
+impl IdleTracker {
+    pub fn new(threshold: Duration) -> IdleTracker {
+        IdleTracker {
+            threshold,
+            current: None,
+            session: None,
+            locked: false,
+            spans: Vec::new(),
+        }
+    }
+
+    // feed the system idle time sampled at `now`
+    pub fn update(&mut self, now: NaiveDateTime, idle: Duration) {
+        let idle_start = now - idle;
+        if idle >= self.threshold {
+            if self.current.is_none() {
+                self.current = Some(idle_start);
+            }
+        } else if let Some(begin) = self.current.take() {
+            self.spans.push(AwaySpan {
+                begin,
+                end: idle_start,
+                reason: AwayReason::Idle,
+            });
+        }
+    }
+
+    // feed a screen lock or suspend notification received at `now`
+    pub fn session_event(&mut self, now: NaiveDateTime, event: SessionEvent) {
+        match event {
+            SessionEvent::Lock => {
+                self.locked = true;
+                self.session.get_or_insert((now, AwayReason::Locked));
+            }
+            SessionEvent::Sleep => {
+                self.session.get_or_insert((now, AwayReason::Suspended)).1 = AwayReason::Suspended;
+            }
+            SessionEvent::Unlock | SessionEvent::Resume => {
+                // after resuming, the user is only back once they unlocked the screen
+                if event == SessionEvent::Unlock {
+                    self.locked = false;
+                }
+                if !self.locked {
+                    if let Some((begin, reason)) = self.session.take() {
+                        self.spans.push(AwaySpan {
+                            begin,
+                            end: now,
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // The wall clock advanced from `last` to `now` much more than the monotonic clock, which
+    // stops during suspend; this also catches suspends that did not go through logind. The wall
+    // clock times are compared with their UTC offsets, so changing to daylight saving time is
+    // no jump.
+    pub fn clock_jump<Tz: TimeZone>(
+        &mut self,
+        last: DateTime<Tz>,
+        now: DateTime<Tz>,
+        monotonic: Duration,
+    ) {
+        let gap = now.naive_utc() - last.naive_utc() - monotonic;
+        if gap > Duration::seconds(CLOCK_JUMP_TOLERANCE_SECONDS) {
+            self.spans.push(AwaySpan {
+                begin: (last + monotonic).naive_local(),
+                end: now.naive_local(),
+                reason: AwayReason::Suspended,
+            });
+        }
+    }
+
+    // Return all away spans which ended after `since` and forget about them. The user is active
+    // again at `now`, so ongoing spans get closed, the poller may just not have noticed yet.
+    // Overlapping spans (e.g. idle time before locking the screen) get merged.
+    pub fn take_since(&mut self, since: NaiveDateTime, now: NaiveDateTime) -> Vec<AwaySpan> {
+        if let Some(begin) = self.current.take() {
+            self.spans.push(AwaySpan {
+                begin,
+                end: now,
+                reason: AwayReason::Idle,
+            });
+        }
+        if let Some((begin, reason)) = self.session.take() {
+            self.spans.push(AwaySpan {
+                begin,
+                end: now,
+                reason,
+            });
+            self.locked = false;
+        }
+
+        let mut spans: Vec<AwaySpan> = self
+            .spans
+            .drain(..)
+            .filter(|s| s.end > since)
+            .map(|s| AwaySpan {
+                begin: s.begin.max(since),
+                ..s
+            })
+            .collect();
+        spans.sort_by_key(|s| s.begin);
+
+        let mut merged: Vec<AwaySpan> = Vec::new();
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.begin <= last.end => {
+                    last.end = last.end.max(span.end);
+                    last.reason = last.reason.max(span.reason);
+                }
+                _ => merged.push(span),
+            }
+        }
+
+        merged.retain(|s| s.duration() >= self.threshold);
+        merged
+    }
+}
+
+// parse a line of `gdbus monitor` output
+fn parse_gdbus_signal(line: &str) -> Option<SessionEvent> {
+    let (_, signal) = line.split_once(": ")?;
+    let (name, args) = signal.split_once(' ').unwrap_or((signal, ""));
+    let active = args.starts_with("(true");
+    match name.rsplit('.').next()? {
+        "Lock" => Some(SessionEvent::Lock),
+        "Unlock" => Some(SessionEvent::Unlock),
+        "PrepareForSleep" if active => Some(SessionEvent::Sleep),
+        "PrepareForSleep" => Some(SessionEvent::Resume),
+        "ActiveChanged" if active => Some(SessionEvent::Lock),
+        "ActiveChanged" => Some(SessionEvent::Unlock),
+        _ => None,
+    }
+}
+
+// listen to D-Bus signals and feed them into the tracker, until gdbus goes away
+fn watch_bus(args: &[&str], tracker: &Mutex<IdleTracker>) -> Option<()> {
+    let mut child = process::Command::new("gdbus")
+        .arg("monitor")
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    for line in BufReader::new(child.stdout.take()?).lines() {
+        if let Some(event) = parse_gdbus_signal(&line.ok()?) {
+            tracker
+                .lock()
+                .unwrap()
+                .session_event(Local::now().naive_local(), event);
+        }
+    }
+    child.wait().ok()?;
+    Some(())
+}
+
+// parse the output of `loginctl show-session -p IdleHint -p IdleSinceHint`
+fn parse_loginctl(output: &str, now_usec: i64) -> Option<Duration> {
+    let mut idle_hint = None;
+    let mut idle_since = None;
+    for line in output.lines() {
+        match line.split_once('=') {
+            Some(("IdleHint", v)) => idle_hint = Some(v == "yes"),
+            Some(("IdleSinceHint", v)) => idle_since = v.parse::<i64>().ok(),
+            _ => (),
+        }
+    }
+
+    match (idle_hint?, idle_since?) {
+        (false, _) => Some(Duration::zero()),
+        // the hint is set, but the time is unknown
+        (true, 0) => None,
+        (true, since) => Some(Duration::microseconds((now_usec - since).max(0))),
+    }
+}
+
+fn run_probe(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = process::Command::new(cmd).args(args).output().ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+/// Ask the desktop for the time since the last user input
+///
+/// This uses the X11 screensaver extension (via xprintidle) if available, and otherwise logind's
+/// idle hint, which Wayland desktops set from their idle notification.
+pub fn system_idle_time() -> Option<Duration> {
+    if env::var_os("DISPLAY").is_some() {
+        if let Some(ms) = run_probe("xprintidle", &[]).and_then(|o| o.trim().parse::<i64>().ok()) {
+            return Some(Duration::milliseconds(ms));
+        }
+    }
+
+    let session = env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+    let output = run_probe(
+        "loginctl",
+        &[
+            "show-session",
+            &session,
+            "-p",
+            "IdleHint",
+            "-p",
+            "IdleSinceHint",
+        ],
+    )?;
+    parse_loginctl(&output, Local::now().timestamp_micros())
+}
+
+/**
+ * Background threads which watch the system idle time, screen lock, and suspend
+ */
+pub struct IdleMonitor {
+    tracker: Arc<Mutex<IdleTracker>>,
+}
+
+impl IdleMonitor {
+    // start watching; the threads end by themselves if their data source is not available
+    pub fn start(threshold: Duration) -> IdleMonitor {
+        let tracker = Arc::new(Mutex::new(IdleTracker::new(threshold)));
+
+        let thread_tracker = Arc::clone(&tracker);
+        thread::spawn(move || {
+            while let Some(idle) = system_idle_time() {
+                thread_tracker
+                    .lock()
+                    .unwrap()
+                    .update(Local::now().naive_local(), idle);
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        let thread_tracker = Arc::clone(&tracker);
+        thread::spawn(move || {
+            let mut last = (time::Instant::now(), Local::now());
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let now = (time::Instant::now(), Local::now());
+                let monotonic =
+                    Duration::from_std(now.0 - last.0).unwrap_or_else(|_| Duration::zero());
+                thread_tracker
+                    .lock()
+                    .unwrap()
+                    .clock_jump(last.1, now.1, monotonic);
+                last = now;
+            }
+        });
+
+        // logind's Lock/Unlock and PrepareForSleep signals
+        let thread_tracker = Arc::clone(&tracker);
+        thread::spawn(move || {
+            watch_bus(
+                &["--system", "--dest", "org.freedesktop.login1"],
+                &thread_tracker,
+            )
+        });
+
+        // desktop screen savers don't necessarily go through logind
+        for dest in ["org.gnome.ScreenSaver", "org.freedesktop.ScreenSaver"] {
+            let thread_tracker = Arc::clone(&tracker);
+            thread::spawn(move || watch_bus(&["--session", "--dest", dest], &thread_tracker));
+        }
+
+        IdleMonitor { tracker }
+    }
+
+    pub fn take_since(&self, since: NaiveDateTime) -> Vec<AwaySpan> {
+        self.tracker
+            .lock()
+            .unwrap()
+            .take_since(since, Local::now().naive_local())
+    }
+
+    // like take_since(), but keep the spans
+    pub fn peek_since(&self, since: NaiveDateTime) -> Vec<AwaySpan> {
+        self.tracker
+            .lock()
+            .unwrap()
+            .clone()
+            .take_since(since, Local::now().naive_local())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDate};
+    use pretty_assertions::assert_eq;
+
+    fn time(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn span(begin: NaiveDateTime, end: NaiveDateTime) -> AwaySpan {
+        AwaySpan {
+            begin,
+            end,
+            reason: AwayReason::Idle,
+        }
+    }
+
+    fn session_span(begin: NaiveDateTime, end: NaiveDateTime, reason: AwayReason) -> AwaySpan {
+        AwaySpan { begin, end, reason }
+    }
+
+    #[test]
+    fn test_tracker_no_idle() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        t.update(time(9, 0), Duration::seconds(3));
+        t.update(time(9, 1), Duration::minutes(9));
+        t.update(time(9, 2), Duration::seconds(1));
+        assert_eq!(t.take_since(time(8, 0), time(9, 3)), vec![]);
+    }
+
+    #[test]
+    fn test_tracker_spans() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        t.update(time(9, 0), Duration::seconds(3));
+        // away since 9:05
+        t.update(time(9, 15), Duration::minutes(10));
+        t.update(time(9, 30), Duration::minutes(25));
+        // back at 9:40
+        t.update(time(9, 45), Duration::minutes(5));
+        // away again from 10:00 to 10:20
+        t.update(time(10, 15), Duration::minutes(15));
+        t.update(time(10, 25), Duration::minutes(5));
+
+        assert_eq!(
+            t.take_since(time(8, 0), time(10, 30)),
+            vec![
+                span(time(9, 5), time(9, 40)),
+                span(time(10, 0), time(10, 20))
+            ]
+        );
+        // spans were consumed
+        assert_eq!(t.take_since(time(8, 0), time(10, 30)), vec![]);
+    }
+
+    #[test]
+    fn test_tracker_since() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        t.update(time(9, 30), Duration::minutes(30));
+        t.update(time(9, 45), Duration::minutes(1));
+        t.update(time(10, 30), Duration::minutes(20));
+        t.update(time(10, 45), Duration::minutes(1));
+        // first span is entirely before the last entry, the second gets clipped
+        assert_eq!(
+            t.take_since(time(10, 30), time(11, 0)),
+            vec![span(time(10, 30), time(10, 44))]
+        );
+
+        // clipped spans below the threshold are dropped
+        t.update(time(11, 30), Duration::minutes(20));
+        t.update(time(11, 45), Duration::minutes(1));
+        assert_eq!(t.take_since(time(11, 40), time(12, 0)), vec![]);
+    }
+
+    #[test]
+    fn test_tracker_ongoing() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        t.update(time(9, 30), Duration::minutes(30));
+        // user came back before the next poll
+        assert_eq!(
+            t.take_since(time(8, 0), time(9, 40)),
+            vec![span(time(9, 0), time(9, 40))]
+        );
+        assert!(t.current.is_none());
+    }
+
+    #[test]
+    fn test_tracker_lock() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        t.session_event(time(12, 0), SessionEvent::Lock);
+        t.session_event(time(12, 55), SessionEvent::Unlock);
+        // short lock is ignored
+        t.session_event(time(13, 0), SessionEvent::Lock);
+        t.session_event(time(13, 2), SessionEvent::Unlock);
+        assert_eq!(
+            t.take_since(time(8, 0), time(14, 0)),
+            vec![session_span(time(12, 0), time(12, 55), AwayReason::Locked)]
+        );
+    }
+
+    #[test]
+    fn test_tracker_suspend() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        // suspend without locking
+        t.session_event(time(10, 0), SessionEvent::Sleep);
+        t.session_event(time(11, 0), SessionEvent::Resume);
+        // lock, then suspend; the user is only back after unlocking
+        t.session_event(time(12, 0), SessionEvent::Lock);
+        t.session_event(time(12, 1), SessionEvent::Sleep);
+        t.session_event(time(13, 0), SessionEvent::Resume);
+        t.session_event(time(13, 5), SessionEvent::Unlock);
+        assert_eq!(
+            t.take_since(time(8, 0), time(14, 0)),
+            vec![
+                session_span(time(10, 0), time(11, 0), AwayReason::Suspended),
+                session_span(time(12, 0), time(13, 5), AwayReason::Suspended)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tracker_clock_jump() {
+        // in Central European Summer Time
+        let summer = |time: NaiveDateTime| {
+            FixedOffset::east_opt(2 * 3600)
+                .unwrap()
+                .from_local_datetime(&time)
+                .unwrap()
+        };
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        // no suspend, just some scheduling jitter
+        t.clock_jump(
+            summer(time(9, 0) - Duration::seconds(16)),
+            summer(time(9, 0)),
+            Duration::seconds(15),
+        );
+        // suspended for an hour
+        t.clock_jump(
+            summer(time(10, 0) - Duration::seconds(15)),
+            summer(time(11, 0)),
+            Duration::seconds(15),
+        );
+        // also reported by logind; gets merged
+        t.session_event(time(10, 0), SessionEvent::Sleep);
+        t.session_event(time(11, 0), SessionEvent::Resume);
+        assert_eq!(
+            t.take_since(time(8, 0), time(12, 0)),
+            vec![session_span(
+                time(10, 0),
+                time(11, 0),
+                AwayReason::Suspended
+            )]
+        );
+    }
+
+    #[test]
+    fn test_tracker_clock_jump_dst() {
+        let local = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+        let at = |offset: i32, time: &str| {
+            FixedOffset::east_opt(offset * 3600)
+                .unwrap()
+                .from_local_datetime(&local(time))
+                .unwrap()
+        };
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        // the local time jumps an hour ahead when daylight saving time starts, and back when it
+        // ends, but only 15 seconds passed both times
+        t.clock_jump(
+            at(1, "2024-03-31 01:59:50"),
+            at(2, "2024-03-31 03:00:05"),
+            Duration::seconds(15),
+        );
+        t.clock_jump(
+            at(2, "2024-10-27 02:59:50"),
+            at(1, "2024-10-27 02:00:05"),
+            Duration::seconds(15),
+        );
+        // suspended for two hours over the change
+        t.clock_jump(
+            at(1, "2024-03-31 00:59:45"),
+            at(2, "2024-03-31 04:00:00"),
+            Duration::seconds(15),
+        );
+        assert_eq!(
+            t.take_since(local("2024-03-30 00:00:00"), local("2024-10-28 00:00:00")),
+            vec![session_span(
+                local("2024-03-31 01:00:00"),
+                local("2024-03-31 04:00:00"),
+                AwayReason::Suspended
+            )]
+        );
+    }
+
+    #[test]
+    fn test_assignment_parse() {
+        assert_eq!(Assignment::parse(""), Assignment::Break);
+        assert_eq!(Assignment::parse("B"), Assignment::Break);
+        assert_eq!(Assignment::parse("w"), Assignment::Work);
+        assert_eq!(Assignment::parse(" task "), Assignment::OtherTask);
+        assert_eq!(Assignment::parse("s"), Assignment::Split);
+        assert_eq!(
+            Assignment::parse("x"),
+            Assignment::Error("Unknown answer x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_at() {
+        let s = session_span(time(10, 0), time(11, 0), AwayReason::Suspended);
+        assert_eq!(
+            s.split_at("10:20"),
+            Some((
+                session_span(time(10, 0), time(10, 20), AwayReason::Suspended),
+                session_span(time(10, 20), time(11, 0), AwayReason::Suspended)
+            ))
+        );
+        assert_eq!(s.split_at("10:00"), None);
+        assert_eq!(s.split_at("11:00"), None);
+        assert_eq!(s.split_at("9:30"), None);
+        assert_eq!(s.split_at("noon"), None);
+
+        // through midnight
+        let night = session_span(
+            time(23, 0),
+            time(23, 0) + Duration::hours(8),
+            AwayReason::Idle,
+        );
+        assert_eq!(
+            night.split_at("01:00").map(|(a, _)| a.end),
+            Some(time(1, 0) + Duration::days(1))
+        );
+    }
+
+    #[test]
+    fn test_tracker_merge() {
+        let mut t = IdleTracker::new(Duration::minutes(10));
+        // idle since 11:50, then the screen saver locks
+        t.update(time(12, 0), Duration::minutes(10));
+        t.session_event(time(12, 5), SessionEvent::Lock);
+        t.update(time(12, 30), Duration::minutes(40));
+        t.session_event(time(12, 45), SessionEvent::Unlock);
+        t.update(time(12, 46), Duration::minutes(1));
+        assert_eq!(
+            t.take_since(time(8, 0), time(13, 0)),
+            vec![session_span(time(11, 50), time(12, 45), AwayReason::Locked)]
+        );
+
+        // still locked when the user enters something
+        t.session_event(time(14, 0), SessionEvent::Lock);
+        assert_eq!(
+            t.take_since(time(13, 0), time(14, 30)),
+            vec![session_span(time(14, 0), time(14, 30), AwayReason::Locked)]
+        );
+        assert!(!t.locked);
+    }
+
+    #[test]
+    fn test_parse_gdbus_signal() {
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"
+            ),
+            Some(SessionEvent::Sleep)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)"
+            ),
+            Some(SessionEvent::Resume)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Lock ()"
+            ),
+            Some(SessionEvent::Lock)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Unlock ()"
+            ),
+            Some(SessionEvent::Unlock)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/gnome/ScreenSaver: org.gnome.ScreenSaver.ActiveChanged (true,)"
+            ),
+            Some(SessionEvent::Lock)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/gnome/ScreenSaver: org.gnome.ScreenSaver.ActiveChanged (false,)"
+            ),
+            Some(SessionEvent::Unlock)
+        );
+        assert_eq!(
+            parse_gdbus_signal(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', '/org/freedesktop/login1/session/_33')"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_gdbus_signal("Monitoring signals from all objects"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_loginctl() {
+        assert_eq!(
+            parse_loginctl("IdleHint=no\nIdleSinceHint=1000000\n", 5000000),
+            Some(Duration::zero())
+        );
+        assert_eq!(
+            parse_loginctl("IdleHint=yes\nIdleSinceHint=1000000\n", 5000000),
+            Some(Duration::seconds(4))
+        );
+        assert_eq!(
+            parse_loginctl("IdleHint=yes\nIdleSinceHint=0\n", 5000000),
+            None
+        );
+        assert_eq!(parse_loginctl("", 5000000), None);
+        assert_eq!(parse_loginctl("IdleHint=yes\n", 5000000), None);
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveDate;
+
+use crate::store::parse_entry_line;
+
+/**
+ * Where each day starts in a timelog file, and how many entries it has
+ *
+ * This is only a cache for finding the recent entries without a binary search over the file.
+ * It names the size and modification time of the file it belongs to, and gets ignored as soon
+ * as these differ, e.g. after gtimelog or an editor changed the file.
+ */
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DayIndex {
+    size: u64,
+    modified: Option<SystemTime>,
+    // date, byte offset of its first entry line, number of entries; sorted by date
+    days: Vec<(NaiveDate, u64, usize)>,
+}
+
+impl DayIndex {
+    pub fn new(contents: &str) -> DayIndex {
+        let mut index = DayIndex::default();
+        index.add_lines(contents);
+        index
+    }
+
+    pub fn parse(raw: &str) -> Option<DayIndex> {
+        let mut lines = raw.lines();
+        let mut header = lines.next()?.split(' ');
+        let size = header.next()?.parse().ok()?;
+        let modified = match header.next()? {
+            "-" => None,
+            nanos => Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos.parse().ok()?)),
+        };
+        let days = lines
+            .map(|line| {
+                let mut fields = line.split(' ');
+                let day = fields.next()?.parse().ok()?;
+                let offset = fields.next()?.parse().ok()?;
+                let count = fields.next()?.parse().ok()?;
+                Some((day, offset, count))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(DayIndex {
+            size,
+            modified,
+            days,
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let modified = self
+            .modified
+            .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map_or("-".to_string(), |d| d.as_nanos().to_string());
+        let mut output = format!("{} {modified}\n", self.size);
+        for (day, offset, count) in &self.days {
+            output.push_str(&format!("{day} {offset} {count}\n"));
+        }
+        output
+    }
+
+    // whether this is the index of a file with that size and modification time
+    pub fn is_current(&self, size: u64, modified: Option<SystemTime>) -> bool {
+        self.size == size && modified.is_some() && self.modified == modified
+    }
+
+    // the state after appending `appended` to the file
+    pub fn append(&mut self, appended: &str) {
+        self.add_lines(appended);
+    }
+
+    // Byte offset of the first entry of `day` or a later one; the end of the file if there is
+    // none.
+    pub fn offset_of(&self, day: NaiveDate) -> u64 {
+        let i = self.days.partition_point(|d| d.0 < day);
+        self.days.get(i).map_or(self.size, |d| d.1)
+    }
+
+    // number of entries on `day` and after it
+    pub fn count_since(&self, day: NaiveDate) -> usize {
+        let i = self.days.partition_point(|d| d.0 < day);
+        self.days[i..].iter().map(|d| d.2).sum()
+    }
+
+    fn add_lines(&mut self, contents: &str) {
+        for line in contents.split_inclusive('\n') {
+            if let Some((time, _)) = parse_entry_line(line) {
+                match self.days.last_mut() {
+                    // also counts entries out of order to their day before, they can't start one
+                    Some(last) if last.0 >= time.date() => last.2 += 1,
+                    _ => self.days.push((time.date(), self.size, 1)),
+                }
+            }
+            self.size += line.len() as u64;
+        }
+    }
+}
+
+// the cache next to the timelog, e.g. .timelog.idx for timelog.txt
+pub fn index_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_stem().unwrap_or_default());
+    name.push(".idx");
+    path.with_file_name(name)
+}
+
+// the index of `path`, if it is up to date
+pub fn load(path: &Path) -> Option<DayIndex> {
+    let metadata = fs::metadata(path).ok()?;
+    let index = DayIndex::parse(&fs::read_to_string(index_path(path)).ok()?)?;
+    index
+        .is_current(metadata.len(), metadata.modified().ok())
+        .then_some(index)
+}
+
+// index `contents`, which just got written to `path`
+pub fn write(path: &Path, contents: &str) -> Result<(), io::Error> {
+    let mut index = DayIndex::new(contents);
+    index.modified = fs::metadata(path)?.modified().ok();
+    fs::write(index_path(path), index.format())
+}
+
+// Record that `appended` got added to `path`, which had `old_size` and `old_modified` before.
+// Without an index to continue, index the whole file.
+pub fn append(
+    path: &Path,
+    old_size: u64,
+    old_modified: Option<SystemTime>,
+    appended: &str,
+) -> Result<(), io::Error> {
+    let index = fs::read_to_string(index_path(path))
+        .ok()
+        .and_then(|raw| DayIndex::parse(&raw));
+    let mut index = match index {
+        Some(mut index) if index.is_current(old_size, old_modified) => {
+            index.append(appended);
+            index
+        }
+        _ => DayIndex::new(&String::from_utf8_lossy(&fs::read(path)?)),
+    };
+    index.modified = fs::metadata(path)?.modified().ok();
+    fs::write(index_path(path), index.format())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const LOG: &str = "2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+  with a note
+
+2022-06-10 08:00: arrived
+2022-06-10 09:00: code
+";
+
+    fn day(d: &str) -> NaiveDate {
+        d.parse().unwrap()
+    }
+
+    #[test]
+    fn test_index_new() {
+        let index = DayIndex::new(LOG);
+        assert_eq!(
+            index.days,
+            vec![(day("2022-06-09"), 0, 2), (day("2022-06-10"), 65, 2)]
+        );
+        assert_eq!(index.size, LOG.len() as u64);
+        assert!(LOG[65..].starts_with("2022-06-10 08:00: arrived"));
+
+        assert_eq!(index.offset_of(day("2022-01-01")), 0);
+        assert_eq!(index.offset_of(day("2022-06-10")), 65);
+        assert_eq!(index.offset_of(day("2022-06-11")), LOG.len() as u64);
+        assert_eq!(index.count_since(day("2022-06-09")), 4);
+        assert_eq!(index.count_since(day("2022-06-10")), 2);
+        assert_eq!(index.count_since(day("2022-06-11")), 0);
+
+        assert_eq!(DayIndex::new(""), DayIndex::default());
+    }
+
+    #[test]
+    fn test_index_append() {
+        let mut index = DayIndex::new(LOG);
+        index.append("2022-06-10 10:00: review\n\n2022-06-13 08:00: arrived\n");
+        assert_eq!(
+            index.days,
+            vec![
+                (day("2022-06-09"), 0, 2),
+                (day("2022-06-10"), 65, 3),
+                (day("2022-06-13"), LOG.len() as u64 + 26, 1)
+            ]
+        );
+        assert_eq!(
+            index,
+            DayIndex::new(&format!(
+                "{LOG}2022-06-10 10:00: review\n\n2022-06-13 08:00: arrived\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_index_format() {
+        let mut index = DayIndex::new(LOG);
+        assert_eq!(DayIndex::parse(&index.format()), Some(DayIndex::new(LOG)));
+        assert!(!index.is_current(LOG.len() as u64, None));
+
+        index.modified = Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(1_654_844_400_123));
+        assert_eq!(
+            index.format(),
+            "114 1654844400123\n2022-06-09 0 2\n2022-06-10 65 2\n"
+        );
+        let parsed = DayIndex::parse(&index.format()).unwrap();
+        assert!(parsed.is_current(114, index.modified));
+        assert!(!parsed.is_current(115, index.modified));
+        assert!(!parsed.is_current(114, Some(SystemTime::UNIX_EPOCH)));
+
+        assert_eq!(DayIndex::parse(""), None);
+        assert_eq!(DayIndex::parse("114 -\n2022-06-09 0\n"), None);
+        assert_eq!(DayIndex::parse("114 x\n"), None);
+    }
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(
+            index_path(Path::new("/home/me/.local/share/gtimelog/timelog.txt")),
+            Path::new("/home/me/.local/share/gtimelog/.timelog.idx")
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, prelude::*, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::store::is_valid_line;
+
+// that many lines at the end of the file get kept in the journal
+const TAIL_LINES: usize = 50;
+
+// 64 bit FNV-1a; just for noticing a damaged journal, not for security
+pub(crate) fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/**
+ * Last known good state of a timelog file: its length and its last lines
+ *
+ * Programs like gtimelog append to the file, so that only ever grows. If the journal tail is
+ * not at the recorded position any more, the file got truncated or damaged.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Journal {
+    length: u64,
+    tail: String,
+}
+
+/**
+ * Difference between a damaged timelog and its journal
+ */
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Damage {
+    // entries from the journal which are not in the file any more
+    pub missing: Vec<String>,
+    // lines in the damaged part of the file which are not valid entries
+    pub garbled: Vec<String>,
+}
+
+impl Journal {
+    pub fn new(contents: &str) -> Journal {
+        let mut start = contents.len();
+        for _ in 0..TAIL_LINES {
+            match contents[..start.saturating_sub(1)].rfind('\n') {
+                Some(i) => start = i + 1,
+                None => {
+                    start = 0;
+                    break;
+                }
+            }
+        }
+        Journal {
+            length: contents.len() as u64,
+            tail: contents[start..].to_string(),
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Journal> {
+        let (header, tail) = raw.split_once('\n')?;
+        let mut fields = header.split(' ');
+        let length = fields.next()?.parse().ok()?;
+        let sum = u64::from_str_radix(fields.next()?, 16).ok()?;
+        if sum != checksum(tail.as_bytes()) {
+            return None;
+        }
+        Some(Journal {
+            length,
+            tail: tail.to_string(),
+        })
+    }
+
+    pub fn format(&self) -> String {
+        format!(
+            "{} {:016x}\n{}",
+            self.length,
+            checksum(self.tail.as_bytes()),
+            self.tail
+        )
+    }
+
+    // compare with the region of the file from the start of the journal tail
+    pub fn check(&self, region: &str) -> Option<Damage> {
+        let intact =
+            region.starts_with(&self.tail) && region[self.tail.len()..].lines().all(is_valid_line);
+        if intact {
+            return None;
+        }
+        let lines: Vec<&str> = region.lines().collect();
+        Some(Damage {
+            missing: self
+                .tail
+                .lines()
+                .filter(|l| !l.trim().is_empty() && is_valid_line(l) && !lines.contains(l))
+                .map(|l| l.to_string())
+                .collect(),
+            garbled: lines
+                .iter()
+                .filter(|l| !is_valid_line(l))
+                .map(|l| l.to_string())
+                .collect(),
+        })
+    }
+
+    // the state after appending `appended` to the file
+    pub fn append(&self, appended: &str) -> Journal {
+        let mut journal = Journal::new(&format!("{}{appended}", self.tail));
+        journal.length = self.length + appended.len() as u64;
+        journal
+    }
+
+    fn tail_start(&self) -> u64 {
+        self.length.saturating_sub(self.tail.len() as u64)
+    }
+}
+
+pub fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".journal");
+    path.with_file_name(name)
+}
+
+// damaged lines get moved there, instead of being dropped
+pub fn damaged_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".damaged");
+    path.with_file_name(name)
+}
+
+// record `contents` as last known good state of `path`
+pub fn write(path: &Path, contents: &str) -> Result<(), io::Error> {
+    fs::write(journal_path(path), Journal::new(contents).format())
+}
+
+// record that `appended` got added to `path`, which was `old_length` bytes long before
+pub fn append(path: &Path, old_length: u64, appended: &str) -> Result<(), io::Error> {
+    let journal = fs::read_to_string(journal_path(path))
+        .ok()
+        .and_then(|raw| Journal::parse(&raw));
+    match journal {
+        Some(j) if j.length == old_length => {
+            fs::write(journal_path(path), j.append(appended).format())
+        }
+        // nothing to continue, so take the whole file
+        _ => accept(path),
+    }
+}
+
+// Check `path` against its journal. Missing or damaged journals are not an error, there is
+// nothing to compare to then.
+pub fn check(path: &Path) -> Result<Option<Damage>, io::Error> {
+    let journal = match fs::read_to_string(journal_path(path)) {
+        Ok(raw) => match Journal::parse(&raw) {
+            Some(j) => j,
+            None => {
+                eprintln!("WARNING: ignoring damaged {}", journal_path(path).display());
+                return Ok(None);
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut f = match fs::File::open(path) {
+        Ok(f) => f,
+        // deleted on purpose
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let size = f.metadata()?.len();
+    // the file got truncated before the tail, compare the tail to what is left of the file
+    let start = if size < journal.tail_start() {
+        size.saturating_sub(journal.tail.len() as u64)
+    } else {
+        journal.tail_start()
+    };
+    f.seek(SeekFrom::Start(start))?;
+    let mut region = Vec::new();
+    f.read_to_end(&mut region)?;
+    Ok(journal.check(&String::from_utf8_lossy(&region)))
+}
+
+// take the current state of `path` as good, e.g. after declining recovery
+pub fn accept(path: &Path) -> Result<(), io::Error> {
+    let contents = fs::read(path)?;
+    write(path, &String::from_utf8_lossy(&contents))
+}
+
+// keep the garbled lines around for manual inspection
+pub fn save_damaged(path: &Path, lines: &[String]) -> Result<PathBuf, io::Error> {
+    let damaged = damaged_path(path);
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&damaged)?;
+    for line in lines {
+        writeln!(f, "{line}")?;
+    }
+    Ok(damaged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const LOG: &str = "2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+
+2022-06-10 08:00: arrived
+2022-06-10 09:00: code
+";
+
+    #[test]
+    fn test_journal_new() {
+        assert_eq!(
+            Journal::new(LOG),
+            Journal {
+                length: LOG.len() as u64,
+                tail: LOG.to_string()
+            }
+        );
+        assert_eq!(Journal::new("").tail, "");
+
+        let long: String = (0..100)
+            .map(|i| format!("2022-06-10 08:00: task {i}\n"))
+            .collect();
+        let j = Journal::new(&long);
+        assert_eq!(j.tail.lines().count(), TAIL_LINES);
+        assert!(j.tail.starts_with("2022-06-10 08:00: task 50\n"));
+        assert!(long.ends_with(&j.tail));
+        assert_eq!(j.tail_start() as usize, long.len() - j.tail.len());
+    }
+
+    #[test]
+    fn test_journal_format() {
+        let j = Journal::new(LOG);
+        assert_eq!(Journal::parse(&j.format()), Some(j));
+        assert_eq!(Journal::parse(""), None);
+        assert_eq!(Journal::parse("12 0000000000000000\nx\n"), None);
+        let damaged = Journal::new(LOG).format().replace("code", "cod");
+        assert_eq!(Journal::parse(&damaged), None);
+    }
+
+    #[test]
+    fn test_journal_check() {
+        let j = Journal::new(LOG);
+        assert_eq!(j.check(LOG), None);
+        // appended by gtimelog
+        assert_eq!(j.check(&format!("{LOG}2022-06-10 10:00: review\n")), None);
+
+        // truncated
+        assert_eq!(
+            j.check("2022-06-09 06:02: arrived\n2022-06-09 06:27: email\n2022-06-1"),
+            Some(Damage {
+                missing: vec![
+                    "2022-06-10 08:00: arrived".to_string(),
+                    "2022-06-10 09:00: code".to_string(),
+                ],
+                garbled: vec!["2022-06-1".to_string()],
+            })
+        );
+
+        // garbage at the end
+        assert_eq!(
+            j.check(&format!("{LOG}\0\0\0\0")),
+            Some(Damage {
+                missing: vec![],
+                garbled: vec!["\0\0\0\0".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = std::env::temp_dir().join(format!("rtimelog-journal-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timelog.txt");
+
+        // no journal yet
+        fs::write(&path, LOG).unwrap();
+        assert_eq!(check(&path).unwrap(), None);
+
+        write(&path, LOG).unwrap();
+        assert_eq!(check(&path).unwrap(), None);
+
+        fs::write(&path, &LOG[..30]).unwrap();
+        let damage = check(&path).unwrap().unwrap();
+        assert_eq!(damage.missing.len(), 3);
+
+        let damaged = save_damaged(&path, &damage.garbled).unwrap();
+        assert_eq!(fs::read_to_string(&damaged).unwrap(), "2022\n");
+
+        accept(&path).unwrap();
+        assert_eq!(check(&path).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
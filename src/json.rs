@@ -0,0 +1,195 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::activity::{self, Activities};
+use crate::config::Config;
+use crate::store::Entry;
+use crate::table::{json_string, Table};
+
+// Version of the documents of --format json. It only changes when fields go away or change
+// their meaning; new fields may appear without it.
+pub const SCHEMA: u32 = 1;
+
+const TIME_FMT: &str = "%Y-%m-%dT%H:%M";
+
+fn json_time(t: NaiveDateTime) -> String {
+    json_string(&t.format(TIME_FMT).to_string())
+}
+
+// one entry of a day; "start" is null for the ones which just start the clock
+fn entry_object(prev: Option<&Entry>, entry: &Entry, config: &Config) -> String {
+    let duration = activity::interval(prev, entry, &config.sentinels);
+    let start = match duration {
+        Some(_) => json_time(prev.unwrap().stop),
+        None => "null".to_string(),
+    };
+    let tags: Vec<String> = entry.tags().iter().map(|t| json_string(t)).collect();
+    format!(
+        "{{\"start\": {start}, \"stop\": {}, \"task\": {}, \"project\": {}, \"tags\": [{}], \"minutes\": {}, \"slack\": {}}}",
+        json_time(entry.stop),
+        json_string(&entry.task),
+        json_string(entry.project()),
+        tags.join(", "),
+        duration.map_or(0, |d| d.num_minutes()),
+        entry.is_slack(),
+    )
+}
+
+// The versioned document which --format json prints from the reports: the days from `first` to
+// `last` which have entries, with their entries, and the `rows` of the report. Work and slack of
+// the days are like in the reports, so with the rounding and the filter of `config`; the
+// minutes of the entries are the plain ones.
+pub fn document(
+    entries: &[Entry],
+    first: NaiveDate,
+    last: NaiveDate,
+    config: &Config,
+    rows: &Table,
+) -> String {
+    let day_of = |e: &Entry| config.sentinels.day_of(e.stop);
+    let mut days = Vec::new();
+    for day in entries.chunk_by(|a, b| day_of(a) == day_of(b)) {
+        let date = day_of(&day[0]);
+        if date < first || date > last {
+            continue;
+        }
+        let a = Activities::new_for_report(day, config);
+        let objects: Vec<String> = day
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| config.filter.matches(&e.task))
+            .map(|(i, e)| format!("        {}", entry_object(day[..i].last(), e, config)))
+            .collect();
+        let mut out = String::new();
+        writeln!(out, "    {{").unwrap();
+        writeln!(out, "      \"date\": {},", json_string(&date.to_string())).unwrap();
+        writeln!(out, "      \"work\": {},", a.total_work().num_minutes()).unwrap();
+        writeln!(out, "      \"slack\": {},", a.total_slack().num_minutes()).unwrap();
+        match objects.is_empty() {
+            true => writeln!(out, "      \"entries\": []").unwrap(),
+            false => writeln!(
+                out,
+                "      \"entries\": [\n{}\n      ]",
+                objects.join(",\n")
+            )
+            .unwrap(),
+        }
+        write!(out, "    }}").unwrap();
+        days.push(out);
+    }
+    let rows: Vec<String> = rows
+        .json_rows()
+        .iter()
+        .map(|r| format!("    {r}"))
+        .collect();
+    let list = |items: &[String]| match items.is_empty() {
+        true => "[]".to_string(),
+        false => format!("[\n{}\n  ]", items.join(",\n")),
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "  \"schema\": {SCHEMA},").unwrap();
+    writeln!(out, "  \"first\": {},", json_string(&first.to_string())).unwrap();
+    writeln!(out, "  \"last\": {},", json_string(&last.to_string())).unwrap();
+    writeln!(out, "  \"days\": {},", list(&days)).unwrap();
+    writeln!(out, "  \"rows\": {}", list(&rows)).unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TaskFilter;
+    use crate::store::Timelog;
+    use crate::table::Cell;
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_document() {
+        let tl = Timelog::new_from_string(
+            "2024-02-01 09:00: arrived
+2024-02-01 10:30: customer \"joe\": support #urgent
+2024-02-01 11:00: ** tea
+
+2024-02-05 09:00: arrived
+2024-02-05 10:00: email
+",
+        );
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let mut rows = Table::new(&["task", "minutes"]);
+        rows.push(vec![Cell::text("email"), Cell::Time(Duration::minutes(60))]);
+        let config = Config::default();
+        assert_eq!(
+            document(&entries, day(1), day(4), &config, &rows),
+            r#"{
+  "schema": 1,
+  "first": "2024-02-01",
+  "last": "2024-02-04",
+  "days": [
+    {
+      "date": "2024-02-01",
+      "work": 90,
+      "slack": 30,
+      "entries": [
+        {"start": null, "stop": "2024-02-01T09:00", "task": "arrived", "project": "arrived", "tags": [], "minutes": 0, "slack": false},
+        {"start": "2024-02-01T09:00", "stop": "2024-02-01T10:30", "task": "customer \"joe\": support #urgent", "project": "customer \"joe\"", "tags": ["urgent"], "minutes": 90, "slack": false},
+        {"start": "2024-02-01T10:30", "stop": "2024-02-01T11:00", "task": "** tea", "project": "** tea", "tags": [], "minutes": 30, "slack": true}
+      ]
+    }
+  ],
+  "rows": [
+    {"task": "email", "minutes": 60}
+  ]
+}
+"#
+        );
+
+        // the filter applies to the entries and the totals
+        let config = Config {
+            filter: TaskFilter {
+                tag: Some("urgent".to_string()),
+                ..TaskFilter::default()
+            },
+            ..Config::default()
+        };
+        let doc = document(&entries, day(5), day(5), &config, &Table::new(&["task"]));
+        assert_eq!(
+            doc,
+            r#"{
+  "schema": 1,
+  "first": "2024-02-05",
+  "last": "2024-02-05",
+  "days": [
+    {
+      "date": "2024-02-05",
+      "work": 0,
+      "slack": 0,
+      "entries": []
+    }
+  ],
+  "rows": []
+}
+"#
+        );
+    }
+}
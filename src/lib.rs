@@ -1,3 +1,46 @@
 pub mod activity;
+pub mod agent;
+pub mod archive;
+#[cfg(feature = "cli")]
+pub mod backup;
+pub mod billing;
+pub mod budget;
+pub mod chart;
+pub mod cli;
+pub mod clock;
 pub mod commands;
+pub mod config;
+pub mod daemon;
+pub mod dedupe;
+pub mod doctor;
+pub mod estimate;
+pub mod export;
+pub mod goals;
+pub mod heatmap;
+pub mod html;
+pub mod ics;
+pub mod idle;
+pub mod index;
+pub mod journal;
+pub mod json;
+pub mod mail;
+pub mod migrate;
+pub mod morning;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod notify;
+pub mod pomodoro;
+pub mod projects;
+pub mod prompt;
+pub mod reminder;
+pub mod report;
+pub mod search;
+pub mod shared;
+pub mod standup;
+pub mod stats;
+pub mod storage;
 pub mod store;
+pub mod table;
+pub mod template;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
@@ -0,0 +1,279 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime};
+
+use crate::cli::Period;
+use crate::config::{Config, Smtp};
+
+/**
+ * A report as an email to the team, like gtimelog's "Report" button writes them
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReportMail {
+    // from the name and sender settings
+    pub name: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub subject: String,
+    pub body: String,
+    // gtimelog's kind and id of the report in sentreports.log, like ("weekly", "2024/6")
+    pub kind: &'static str,
+    pub id: String,
+}
+
+impl ReportMail {
+    // the report of the days from `first` to `last`, with gtimelog's subject lines
+    pub fn new(
+        period: Period,
+        first: NaiveDate,
+        last: NaiveDate,
+        body: String,
+        config: &Config,
+    ) -> ReportMail {
+        // gtimelog's default name
+        let name = config.name.as_deref().unwrap_or("Anonymous");
+        // the ISO week of the fourth day has most of the days, for any week_start
+        let middle = first + Duration::days(3);
+        let week = middle.format("%V");
+        let subject = match period {
+            Period::Day(_) => format!(
+                "{} report for {name} ({}, week {})",
+                first.format("%Y-%m-%d"),
+                first.format("%a"),
+                first.format("%V")
+            ),
+            Period::Week(_) => format!("Weekly report for {name} (week {week})"),
+            Period::Month(_) => format!("Monthly report for {name} ({})", first.format("%Y/%m")),
+            Period::Range(..) => format!(
+                "Report for {name} from {} to {}",
+                first.format("%Y-%m-%d"),
+                last.format("%Y-%m-%d")
+            ),
+        };
+        let (kind, id) = match period {
+            Period::Day(_) => ("daily", first.format("%Y-%m-%d").to_string()),
+            Period::Week(_) => {
+                let week = middle.iso_week();
+                ("weekly", format!("{}/{}", week.year(), week.week()))
+            }
+            Period::Month(_) => ("monthly", first.format("%Y/%m").to_string()),
+            Period::Range(..) => ("range", format!("{first}..{last}")),
+        };
+        ReportMail {
+            name: config.name.clone(),
+            from: config.email.clone(),
+            to: config.report_to.clone(),
+            subject,
+            body,
+            kind,
+            id,
+        }
+    }
+
+    // the whole message, for a .eml file or a mailer
+    pub fn format(&self, date: DateTime<FixedOffset>) -> String {
+        let mut out = String::new();
+        match (&self.name, &self.from) {
+            (Some(name), Some(from)) => {
+                writeln!(out, "From: {} <{from}>", encode_header(name)).unwrap()
+            }
+            (None, Some(from)) => writeln!(out, "From: {from}").unwrap(),
+            (_, None) => (),
+        }
+        if let Some(to) = &self.to {
+            writeln!(out, "To: {to}").unwrap();
+        }
+        writeln!(out, "Subject: {}", encode_header(&self.subject)).unwrap();
+        writeln!(out, "Date: {}", date.to_rfc2822()).unwrap();
+        writeln!(out, "MIME-Version: 1.0").unwrap();
+        writeln!(out, "Content-Type: text/plain; charset=utf-8").unwrap();
+        writeln!(out, "Content-Transfer-Encoding: 8bit\n").unwrap();
+        out.push_str(&self.body);
+        out
+    }
+
+    // like gtimelog's line in sentreports.log, after sending it at `now`
+    pub fn sent_record(&self, now: NaiveDateTime) -> String {
+        format!(
+            "{},{},{},{}\n",
+            now.format("%Y-%m-%d %H:%M:%S"),
+            self.kind,
+            self.id,
+            self.to.as_deref().unwrap_or_default()
+        )
+    }
+
+    // to the list-email setting, over the [rtimelog] smtp_* server
+    #[cfg(feature = "smtp")]
+    pub fn send(&self, smtp: &Smtp) -> Result<(), io::Error> {
+        use lettre::message::{header::ContentType, Mailbox};
+        use lettre::transport::smtp::authentication::Credentials;
+        use lettre::{Message, SmtpTransport, Transport};
+
+        let missing = |setting: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Sending reports needs the {setting} setting"),
+            )
+        };
+        let invalid =
+            |e: &dyn std::fmt::Display| io::Error::new(io::ErrorKind::InvalidInput, e.to_string());
+        let from = self.from.as_deref().ok_or_else(|| missing("sender"))?;
+        let to = self.to.as_deref().ok_or_else(|| missing("list-email"))?;
+        let from = Mailbox::new(self.name.clone(), from.parse().map_err(|e| invalid(&e))?);
+        let message = Message::builder()
+            .from(from)
+            .to(to.parse().map_err(|e| invalid(&e))?)
+            .subject(&self.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(self.body.clone())
+            .map_err(|e| invalid(&e))?;
+
+        let failed = |e: lettre::transport::smtp::Error| {
+            io::Error::other(format!("Failed to send the report: {e}"))
+        };
+        let mut transport = match (smtp.server.as_deref(), smtp.port) {
+            // gtimelog's default
+            (None, port) => SmtpTransport::builder_dangerous("localhost").port(port.unwrap_or(25)),
+            (Some(server), Some(465)) => SmtpTransport::relay(server).map_err(failed)?,
+            (Some(server), port) => SmtpTransport::starttls_relay(server)
+                .map_err(failed)?
+                .port(port.unwrap_or(587)),
+        };
+        if let Some(username) = &smtp.username {
+            let password = smtp.password.clone().unwrap_or_default();
+            transport = transport.credentials(Credentials::new(username.clone(), password));
+        }
+        transport.build().send(&message).map_err(failed)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "smtp"))]
+    pub fn send(&self, _smtp: &Smtp) -> Result<(), io::Error> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Sending reports needs rtimelog with the smtp feature",
+        ))
+    }
+}
+
+// like gtimelog, next to the timelog
+pub fn sent_reports_file(timelog: &Path) -> PathBuf {
+    timelog.with_file_name("sentreports.log")
+}
+
+// headers are ASCII; anything else becomes an RFC 2047 encoded word, like "=?utf-8?q?J=C3=B6rg?="
+fn encode_header(s: &str) -> String {
+    if s.is_ascii() {
+        return s.to_string();
+    }
+    let mut out = String::from("=?utf-8?q?");
+    for b in s.bytes() {
+        match b {
+            b' ' => out.push('_'),
+            b if b.is_ascii_alphanumeric() || b"!*+-/".contains(&b) => out.push(b as char),
+            b => write!(out, "={b:02X}").unwrap(),
+        }
+    }
+    out.push_str("?=");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_report_mail() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let config = Config::new_from_string(
+            "[gtimelog]\nname = Jörg Müller\nsender = joerg@example.com\nlist-email = team@example.com\n",
+        );
+        let mail = ReportMail::new(
+            Period::Week(None),
+            day(5),
+            day(11),
+            "Weekly report\n".to_string(),
+            &config,
+        );
+        assert_eq!(mail.subject, "Weekly report for Jörg Müller (week 06)");
+        let date = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 2, 9, 17, 0, 0)
+            .unwrap();
+        assert_eq!(
+            mail.format(date),
+            "From: =?utf-8?q?J=C3=B6rg_M=C3=BCller?= <joerg@example.com>
+To: team@example.com
+Subject: =?utf-8?q?Weekly_report_for_J=C3=B6rg_M=C3=BCller_=28week_06=29?=
+Date: Fri, 9 Feb 2024 17:00:00 +0100
+MIME-Version: 1.0
+Content-Type: text/plain; charset=utf-8
+Content-Transfer-Encoding: 8bit
+
+Weekly report
+"
+        );
+        let now = NaiveDate::from_ymd_opt(2024, 2, 9)
+            .unwrap()
+            .and_hms_opt(17, 0, 1)
+            .unwrap();
+        assert_eq!(
+            mail.sent_record(now),
+            "2024-02-09 17:00:01,weekly,2024/6,team@example.com\n"
+        );
+        assert_eq!(
+            sent_reports_file(Path::new("/home/joe/timelog.txt")),
+            PathBuf::from("/home/joe/sentreports.log")
+        );
+
+        let mail = |period, first, last, config: &Config| {
+            ReportMail::new(period, first, last, String::new(), config).subject
+        };
+        let config = Config::default();
+        // a week from Sunday
+        assert_eq!(
+            mail(Period::Week(None), day(4), day(10), &config),
+            "Weekly report for Anonymous (week 06)"
+        );
+        assert_eq!(
+            mail(Period::Day(None), day(9), day(9), &config),
+            "2024-02-09 report for Anonymous (Fri, week 06)"
+        );
+        assert_eq!(
+            mail(Period::Month(None), day(1), day(29), &config),
+            "Monthly report for Anonymous (2024/02)"
+        );
+        assert_eq!(
+            mail(Period::Range(day(1), day(9)), day(1), day(9), &config),
+            "Report for Anonymous from 2024-02-01 to 2024-02-09"
+        );
+        // without sender nor list-email, the mailer fills them in
+        assert_eq!(
+            ReportMail::new(Period::Day(None), day(9), day(9), String::new(), &config)
+                .format(date)
+                .lines()
+                .next(),
+            Some("Subject: 2024-02-09 report for Anonymous (Fri, week 06)")
+        );
+    }
+}
@@ -15,18 +15,62 @@
 
 use std::env;
 use std::error::Error;
-use std::io;
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, IsTerminal as _, Write as _};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
 
-use chrono::prelude::*;
+use chrono::{prelude::*, Duration, Months};
+use regex::Regex;
 use rustyline::{error::ReadlineError, DefaultEditor};
 
+use rtimelog::activity::duration_in_words;
+use rtimelog::agent;
+use rtimelog::archive::{self, ArchiveStorage};
+use rtimelog::backup;
+use rtimelog::billing::{Earnings, Invoice};
+use rtimelog::budget::BudgetUse;
+use rtimelog::chart;
+use rtimelog::cli::{self, GlobalOptions, Mail, Period, ReportOptions, Subcommand};
 use rtimelog::commands::{Command, TimeMode};
-use rtimelog::store::Timelog;
+use rtimelog::config::{Config, Rate};
+use rtimelog::daemon::{self, Request, Response};
+use rtimelog::dedupe;
+use rtimelog::doctor;
+use rtimelog::export::{self, ExportFormat};
+use rtimelog::goals::GoalProgress;
+use rtimelog::heatmap::Heatmap;
+use rtimelog::ics;
+use rtimelog::idle::{Assignment, AwaySpan, IdleMonitor};
+use rtimelog::journal;
+use rtimelog::json;
+use rtimelog::mail::{self, ReportMail};
+use rtimelog::migrate;
+use rtimelog::morning;
+use rtimelog::notify::notify;
+use rtimelog::pomodoro::{self, PomodoroStats};
+use rtimelog::prompt::{PromptFormat, Segment};
+use rtimelog::reminder::Reminder;
+use rtimelog::report::{GroupBy, PeriodReport, TeamReport, YearReview};
+use rtimelog::search;
+use rtimelog::shared::SharedTimelog;
+use rtimelog::standup;
+use rtimelog::stats::Stats;
+use rtimelog::storage::{FileStorage, Storage};
+use rtimelog::store::{Entry, Timelog, TimelogError};
+use rtimelog::table::{OutputFormat, Table};
+use rtimelog::template;
+
+// away time after which we ask whether it was a break
+const IDLE_THRESHOLD_MINUTES: i64 = 10;
+
+// the interactive mode loads that many weeks first
+const RECENT_WEEKS: i64 = 4;
 
 fn clear_screen() {
-    print!("{esc}c", esc = 27 as char);
+    // clear and move home instead of a full reset, which Windows consoles don't understand
+    print!("{esc}[2J{esc}[H", esc = 27 as char);
 }
 
 fn get_input(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
@@ -43,6 +87,15 @@ fn get_input(rl: &mut DefaultEditor) -> Result<String, ReadlineError> {
     }
 }
 
+// ask a yes/no question, with "yes" being the default
+fn confirm(rl: &mut DefaultEditor, question: &str) -> Result<bool, ReadlineError> {
+    match rl.readline(&format!("{question} [Y/n] ")) {
+        Ok(answer) => Ok(!answer.trim().to_lowercase().starts_with('n')),
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
 fn show_help() {
     println!(
         "
@@ -53,51 +106,166 @@ fn show_help() {
 :q      - quit
 :h      - show this help
 :e      - open timelog.txt in $EDITOR
+:l      - list the shown entries with their notes
+:n TEXT - add TEXT to the note of the last entry
+:u      - remove the last entry
+:a HH:MM TASK - add a forgotten TASK which you finished at HH:MM today
 ^r      - history search (like in bash) through currently shown activities
 
 Any other input is the description of a task that you just finished."
     );
 }
 
-fn show(timelog: &Timelog, mode: &TimeMode, rl_editor: &mut DefaultEditor) {
-    clear_screen();
-    let today = Local::now().date_naive();
+// the individual entries with their notes, as a detail view of show()
+fn show_log(timelog: &Timelog, config: &Config, mode: &TimeMode) {
+    let s = &config.sentinels;
+    let today = s.day_of(Local::now().naive_local());
+    let entries = match mode {
+        TimeMode::Day(n) => timelog.get_n_days(&today, *n, s.virtual_midnight),
+        TimeMode::Week(n) => timelog.get_n_weeks(&today, *n, s.virtual_midnight, config.week_start),
+    };
+    let mut day = None;
+    for entry in entries {
+        if day != Some(s.day_of(entry.stop)) {
+            day = Some(s.day_of(entry.stop));
+            println!("\n{}:", config.date_format.format(&s.day_of(entry.stop)));
+        }
+        println!(
+            "{} {}",
+            config.date_format.time(entry.stop.time()),
+            entry.task
+        );
+        for line in timelog.note(entry).into_iter().flat_map(str::lines) {
+            println!("      {line}");
+        }
+    }
+    println!();
+}
+
+fn show(timelog: &mut Timelog, config: &Config, mode: &TimeMode, rl_editor: &mut DefaultEditor) {
+    // screen readers lose their place when clearing the screen
+    if !config.plain {
+        clear_screen();
+    }
+    let s = &config.sentinels;
+    let today = s.day_of(Local::now().naive_local());
+    let begin = match mode {
+        TimeMode::Day(n) => today - Duration::days(*n as i64),
+        TimeMode::Week(n) => today - Duration::weeks(*n as i64 + 1),
+    };
+    if let Err(e) = timelog.ensure_loaded(begin.and_hms_opt(0, 0, 0).unwrap()) {
+        eprintln!("WARNING: Failed to load older entries: {e}");
+    }
     let entries = match mode {
         TimeMode::Day(n) => {
             if *n == 1 {
-                println!("Work done today {}:", timelog.get_today_as_string());
+                println!(
+                    "Work done today {}:",
+                    timelog.get_today_as_string(&today, &config.date_format, config.week_start)
+                );
             } else {
                 println!("Work done in the last {n} days:");
             }
-            timelog.get_n_days(&today, *n)
+            timelog.get_n_days(&today, *n, s.virtual_midnight)
         }
         TimeMode::Week(n) => {
             if *n == 1 {
-                println!("Work done this week {}:", timelog.get_this_week_as_string());
+                println!(
+                    "Work done this week {}:",
+                    timelog.get_this_week_as_string(&today, &config.date_format, config.week_start)
+                );
             } else {
                 println!("Work done in the last {n} weeks:");
             }
-            timelog.get_n_weeks(&today, *n)
+            timelog.get_n_weeks(&today, *n, s.virtual_midnight, config.week_start)
         }
     };
 
-    let a = rtimelog::activity::Activities::new_from_entries(entries);
-    println!("{a}");
+    let a = rtimelog::activity::Activities::new_from_entries(entries, &config.sentinels);
+    if config.plain {
+        println!("{}", a.to_plain_string());
+    } else {
+        println!("{a}");
+    }
 
     rl_editor.clear_history().unwrap();
     for a in Timelog::get_history(entries) {
         rl_editor.add_history_entry(a).unwrap();
     }
+
+    let goals = GoalProgress::for_week(
+        timelog.get_n_weeks(&today, 1, s.virtual_midnight, config.week_start),
+        config,
+    );
+    if !goals.is_empty() {
+        println!("Weekly goals:");
+        for goal in goals {
+            if config.plain {
+                println!("{}", goal.to_plain_string());
+            } else {
+                println!("{goal}");
+            }
+        }
+        println!();
+    }
+}
+
+// this month's use of the [budgets]
+fn month_budgets(timelog: &mut Timelog, config: &Config) -> Vec<BudgetUse> {
+    if config.budgets.is_empty() {
+        return Vec::new();
+    }
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    let (first, last) = PeriodReport::month(today);
+    let Some((begin, end)) = PeriodReport::range(first, last, config) else {
+        return Vec::new();
+    };
+    if let Err(e) = timelog.ensure_loaded(begin) {
+        eprintln!("WARNING: Failed to load older entries: {e}");
+    }
+    BudgetUse::for_entries(timelog.get_time_range(begin, end), config)
 }
 
-fn show_prompt(timelog: &Timelog) -> Result<(), io::Error> {
+// warnings about the budgets which were not used up `before` adding entries, but are now
+fn budget_warnings(before: &[BudgetUse], timelog: &mut Timelog, config: &Config) -> Vec<String> {
+    let after = month_budgets(timelog, config);
+    BudgetUse::crossed(before, &after)
+        .into_iter()
+        .map(|b| {
+            let budget = match config.plain {
+                true => b.to_plain_string(),
+                false => b.to_string(),
+            };
+            format!("WARNING: over this month's budget of {budget}")
+        })
+        .collect()
+}
+
+// warning if the interval since `since` is longer than `threshold`
+fn threshold_warning(threshold: Option<Duration>, since: NaiveDateTime) -> Option<String> {
+    let threshold = threshold?;
+    let pending = Local::now().naive_local().signed_duration_since(since);
+    if pending <= threshold {
+        return None;
+    }
+    Some(format!(
+        "WARNING: {} h {} min since the last entry is longer than the {} h {} min threshold; did you forget to log something?",
+        pending.num_hours(),
+        pending.num_minutes() % 60,
+        threshold.num_hours(),
+        threshold.num_minutes() % 60
+    ))
+}
+
+fn show_prompt(timelog: &Timelog, config: &Config) -> Result<(), io::Error> {
     let since_last = timelog
-        .get_n_days(&Local::now().date_naive(), 1)
+        .get_today(Local::now().naive_local(), &config.sentinels)
         .last()
         .map(|e| Local::now().naive_local().signed_duration_since(e.stop));
 
     let since_str = match since_last {
         None => "no entries yet today".to_string(),
+        Some(d) if config.plain => format!("{} since last entry", duration_in_words(d)),
         Some(d) => format!(
             "{} h {} min since last entry",
             d.num_hours(),
@@ -106,58 +274,1378 @@ fn show_prompt(timelog: &Timelog) -> Result<(), io::Error> {
     };
 
     println!("\n{since_str}; type command (:h for help) or entry");
+
+    // the task of the running interval is not known yet, so only the global threshold applies
+    if let Some(last) = timelog
+        .get_today(Local::now().naive_local(), &config.sentinels)
+        .last()
+    {
+        if let Some(warning) = threshold_warning(config.max_pending, last.stop) {
+            println!("{warning}");
+        }
+    }
     Ok(())
 }
 
-// return the default editor on linux
-#[cfg(target_os = "linux")]
+// return the default editor on linux and macOS
+#[cfg(not(target_os = "windows"))]
 fn default_editor() -> &'static str {
     "vi"
 }
 
-// return the default editor on windows
+// return the default editor on windows; "start" is a cmd builtin, not a program
 #[cfg(target_os = "windows")]
 fn default_editor() -> &'static str {
-    "start"
+    "notepad"
 }
 
-fn run_editor(fname: &PathBuf) {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| default_editor().to_string());
+fn run_editor(fname: &PathBuf, config: &Config) {
+    let editor = config
+        .editor
+        .clone()
+        .or_else(|| env::var("EDITOR").ok())
+        .unwrap_or_else(|| default_editor().to_string());
     println!("Running {}", &editor);
     if let Err(e) = process::Command::new(&editor).arg(fname).status() {
         println!("Failed to run {} on {:?}: {:?}", &editor, fname, e);
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut timelog = Timelog::new_from_default_file();
+// ask how to log an away span; returns the entries to add before the task being logged, with
+// None standing for that task
+fn reconcile_span(
+    rl: &mut DefaultEditor,
+    span: &AwaySpan,
+) -> Result<Vec<(NaiveDateTime, Option<String>)>, ReadlineError> {
+    let question = format!(
+        "You were away ({}) from {} to {} ({} min). Log it as [b]reak, [w]ork on this task, another [t]ask, or [s]plit it? [B/w/t/s] ",
+        span.reason,
+        span.begin.format("%H:%M"),
+        span.end.format("%H:%M"),
+        span.duration().num_minutes()
+    );
+    loop {
+        let answer = match rl.readline(&question) {
+            Ok(answer) => answer,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        match Assignment::parse(&answer) {
+            Assignment::Break => {
+                return Ok(vec![
+                    (span.begin, None),
+                    (span.end, Some("** away".to_string())),
+                ])
+            }
+            Assignment::Work => return Ok(Vec::new()),
+            Assignment::OtherTask => {
+                let task = rl.readline("Task: ")?;
+                if !task.trim().is_empty() {
+                    return Ok(vec![
+                        (span.begin, None),
+                        (span.end, Some(task.trim().to_string())),
+                    ]);
+                }
+            }
+            Assignment::Split => {
+                let at = rl.readline("Split at (HH:MM): ")?;
+                match span.split_at(&at) {
+                    Some((first, second)) => {
+                        let mut entries = reconcile_span(rl, &first)?;
+                        entries.append(&mut reconcile_span(rl, &second)?);
+                        return Ok(entries);
+                    }
+                    None => println!(
+                        "Error: the split time must be between {} and {}",
+                        span.begin.format("%H:%M"),
+                        span.end.format("%H:%M")
+                    ),
+                }
+            }
+            Assignment::Error(e) => println!("Error: {e}"),
+        }
+    }
+}
+
+// ask how to log the away times (idle, screen lock, suspend) since the last entry
+fn reconcile_away(
+    timelog: &SharedTimelog,
+    config: &Config,
+    idle: &IdleMonitor,
+    rl: &mut DefaultEditor,
+) -> Result<Vec<(NaiveDateTime, Option<String>)>, ReadlineError> {
+    // the first entry of the day just marks the start, idle time before it does not matter
+    let last = timelog
+        .read()
+        .get_today(Local::now().naive_local(), &config.sentinels)
+        .last()
+        .map(|e| e.stop);
+    let since = match last {
+        Some(stop) => stop,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut entries = Vec::new();
+    for span in idle.take_since(since) {
+        entries.append(&mut reconcile_span(rl, &span)?);
+    }
+    Ok(entries)
+}
+
+// show the morning summary, and log the arrival if configured
+fn start_day(timelog: &SharedTimelog, config: &Config, today: NaiveDate) -> Result<(), io::Error> {
+    let events = config.calendar.as_ref().map(ics::read).unwrap_or_default();
+    let mut tl = timelog.write();
+    let summary = morning::summary(&tl, config, today, &events);
+    println!("\n{summary}");
+    notify("rtimelog", &summary, &[]);
+    if config.auto_arrive {
+        let now = Local::now().naive_local();
+        tl.add_snapped(
+            config.sentinels.arrival_task().to_string(),
+            now,
+            &config.snap,
+        )?;
+        tl.save()?;
+    }
+    Ok(())
+}
+
+fn run_pomodoro(
+    options: &GlobalOptions,
+    task: Option<String>,
+    cycles: u32,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let mut timelog = Timelog::new_from_file(&options.timelog_file())?;
+    let task = task.unwrap_or_else(|| "pomodoro".to_string());
+
+    // close the interval before the first pomodoro
+    let now = Local::now().naive_local();
+    match timelog.get_today(now, &config.sentinels).last() {
+        None => {
+            timelog.add(config.sentinels.arrival_task().to_string())?;
+            timelog.save()?;
+        }
+        Some(last) if now.signed_duration_since(last.stop) >= Duration::minutes(1) => {
+            let mut rl = DefaultEditor::new()?;
+            let prompt = format!(
+                "What did you do since {}? (empty to add it to the first pomodoro) ",
+                config.date_format.time(last.stop.time())
+            );
+            let done = rl.readline(&prompt)?;
+            if !done.trim().is_empty() {
+                timelog.add(done.trim().to_string())?;
+                timelog.save()?;
+            }
+        }
+        Some(_) => (),
+    }
+
+    pomodoro::run(&mut timelog, &config, &task, cycles)?;
+    Ok(())
+}
+
+// keep the log in memory and answer add/status requests
+#[cfg(unix)]
+fn run_daemon(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    if !options.is_default_file() {
+        return Err("The daemon only keeps the default timelog, see --help".into());
+    }
+    let config = load_config(options);
+    let timelog = SharedTimelog::new(Timelog::new_from_file(&options.timelog_file())?);
+    let idle = IdleMonitor::start(Duration::minutes(IDLE_THRESHOLD_MINUTES));
+    if let Some(reminder) = Reminder::new_from_config(&config) {
+        reminder.start(timelog.clone());
+    }
+    daemon::serve(&timelog, &config, &idle)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn run_daemon(_options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    eprintln!("Error: the daemon is only supported on Unix");
+    process::exit(1);
+}
+
+// None if there is no daemon running, or if it is not for our timelog
+#[cfg(unix)]
+fn send_to_daemon(
+    options: &GlobalOptions,
+    request: &Request,
+) -> Result<Option<Response>, io::Error> {
+    if !options.is_default_file() {
+        return Ok(None);
+    }
+    match daemon::send(request) {
+        Ok(response) => Ok(Some(response)),
+        Err(e)
+            if e.kind() == io::ErrorKind::NotFound
+                || e.kind() == io::ErrorKind::ConnectionRefused =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn send_to_daemon(
+    _options: &GlobalOptions,
+    _request: &Request,
+) -> Result<Option<Response>, io::Error> {
+    Ok(None)
+}
+
+// midnight of the first day of this week, which is before its first entry even with a virtual
+// midnight
+fn week_start(config: &Config, now: NaiveDateTime) -> NaiveDateTime {
+    let today = config.sentinels.day_of(now);
+    let first = today.week(config.week_start).first_day();
+    first.and_hms_opt(0, 0, 0).unwrap()
+}
+
+// let the daemon handle the request, or do it directly if it does not run
+fn run_request(options: &GlobalOptions, request: Request) -> Result<(), Box<dyn Error>> {
+    let response = match send_to_daemon(options, &request)? {
+        Some(response) => response,
+        None => {
+            let config = load_config(options);
+            let now = Local::now().naive_local();
+            // today and this week's goals are all that add and status need; saving an added
+            // entry appends it, so the years before don't even get parsed
+            let mut timelog = Timelog::new_from_storage_recent(
+                Box::new(FileStorage::new(&options.timelog_file())),
+                week_start(&config, now),
+            )?;
+            daemon::handle(&mut timelog, &config, request, &[], now)
+        }
+    };
+    if response.ok {
+        print!("{}", response.text);
+    } else {
+        eprint!("Error: {}", response.text);
+        process::exit(1);
+    }
+    Ok(())
+}
+
+// shell prompt segment; this runs on every prompt, so it has to be fast and must never print errors
+fn run_prompt(options: &GlobalOptions, format: PromptFormat) -> Result<(), Box<dyn Error>> {
+    if let Ok(Some(response)) = send_to_daemon(options, &Request::Prompt(format)) {
+        if response.ok {
+            print!("{}", response.text);
+        }
+        return Ok(());
+    }
+
+    let path = options.timelog_file();
+    if !path.exists() {
+        return Ok(());
+    }
+    let now = Local::now().naive_local();
+    let config = load_config(options);
+    // only this week matters for the goals, so don't parse the whole file
+    let Ok(timelog) = Timelog::new_from_storage_recent(
+        Box::new(FileStorage::new(&path)),
+        week_start(&config, now),
+    ) else {
+        return Ok(());
+    };
+    if let Some(segment) = Segment::new(
+        timelog.get_this_week(now, &config.sentinels, config.week_start),
+        &config,
+        now,
+    ) {
+        print!("{}", segment.format(format, config.plain));
+    }
+    Ok(())
+}
+
+// only run the reminders, e.g. from a launchd agent
+fn run_remind(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let Some(reminder) = Reminder::new_from_config(&config) else {
+        return Err(
+            "No reminders configured, see remind_after, break_after, and progress_at".into(),
+        );
+    };
+    reminder.run(SharedTimelog::new(Timelog::new_from_file(
+        &options.timelog_file(),
+    )?));
+    Ok(())
+}
+
+// the rows of a report for other programs; JSON gets the versioned document with the days of
+// `entries` from `first` to `last`, see json::document()
+fn format_table(
+    table: &Table,
+    format: OutputFormat,
+    entries: &[Entry],
+    (first, last): (NaiveDate, NaiveDate),
+    config: &Config,
+) -> String {
+    match format {
+        OutputFormat::Json => json::document(entries, first, last, config, table),
+        _ => table.format(format),
+    }
+}
+
+// stream through the file, so that only the entries of that year are in memory
+fn run_report(
+    options: &GlobalOptions,
+    year: Option<i32>,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let format = report_options.format;
+    let year = year.unwrap_or_else(|| config.sentinels.day_of(Local::now().naive_local()).year());
+    let (begin, end) = YearReview::range(year, &config).ok_or("Invalid year")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let entries = timelog.get_time_range(begin, end);
+    let review = YearReview::new(entries, year, &config);
+    match format {
+        OutputFormat::Text => print!("{}", review.format(&config)),
+        _ => {
+            let days = (begin.date(), end.date());
+            print!(
+                "{}",
+                format_table(&review.table(), format, entries, days, &config)
+            );
+        }
+    }
+    Ok(())
+}
+
+// like gtimelog's daily and weekly reports, or per month or any days; they may be in an archive
+#[cfg(feature = "xlsx")]
+fn write_xlsx(
+    path: &Path,
+    entries: &[Entry],
+    first: NaiveDate,
+    last: NaiveDate,
+    config: &Config,
+) -> Result<(), io::Error> {
+    rtimelog::xlsx::timesheet(entries, first, last, config).save(path)
+}
+
+#[cfg(not(feature = "xlsx"))]
+fn write_xlsx(
+    _path: &Path,
+    _entries: &[Entry],
+    _first: NaiveDate,
+    _last: NaiveDate,
+    _config: &Config,
+) -> Result<(), io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Excel timesheets need rtimelog with the xlsx feature",
+    ))
+}
+
+fn run_period_report(
+    options: &GlobalOptions,
+    period: Period,
+    group_by: Option<GroupBy>,
+    report_options: &ReportOptions,
+    mail: Option<Mail>,
+    compare: bool,
+    xlsx: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let format = report_options.format;
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    let (first, last) = period.days(today, &config);
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    // load the period before as well, for --compare
+    let previous = match compare {
+        true => {
+            let (first, last) = PeriodReport::previous(first, last).ok_or("Invalid date")?;
+            let range = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+            Some((first, last, range))
+        }
+        false => None,
+    };
+    let load_begin = previous.map_or(begin, |(_, _, (begin, _))| begin);
+    let storage = ArchiveStorage::new(&options.timelog_file(), load_begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, load_begin, end)?;
+    if let Some(path) = xlsx {
+        write_xlsx(
+            &path,
+            timelog.get_time_range(begin, end),
+            first,
+            last,
+            &config,
+        )
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+        println!("Saved the timesheet to {}", path.display());
+        return Ok(());
+    }
+    let entries = timelog.get_time_range(begin, end);
+    let report = PeriodReport::new(entries, first, last, &config);
+    let previous = previous.map(|(first, last, (begin, end))| {
+        PeriodReport::new(timelog.get_time_range(begin, end), first, last, &config)
+    });
+    let template_name = match period {
+        Period::Day(_) => "daily",
+        Period::Week(_) => "weekly",
+        Period::Month(_) => "monthly",
+        Period::Range(..) => "report",
+    };
+    let template_path = template::path(template_name);
+    let template = fs::read_to_string(&template_path)
+        .ok()
+        .map(|text| (template_path, text));
+    let mut out = match (period, group_by, &template) {
+        // the rows of the report, or of the comparison; a month is about its projects
+        _ if format != OutputFormat::Text => {
+            let table = match &previous {
+                Some(previous) => report.comparison_table(previous),
+                None => {
+                    let group_by = group_by.unwrap_or(match period {
+                        Period::Month(_) => GroupBy::Project { depth: 1 },
+                        _ => GroupBy::Task,
+                    });
+                    report.table(group_by, &config)
+                }
+            };
+            format_table(&table, format, entries, (first, last), &config)
+        }
+        (_, Some(group_by), _) => report.format_grouped(group_by, &config),
+        // the user's layout instead, if there is one
+        (_, None, Some((path, text))) => template::render(text, &report, &config)
+            .map_err(|e| format!("{}: {e}", path.display()))?,
+        (Period::Day(_), None, None) => report.format_daily(&config),
+        (Period::Week(_), None, None) => report.format_weekly(&config),
+        (Period::Month(_), None, None) => report.format_monthly(&config),
+        (Period::Range(..), None, None) => report.format_grouped(GroupBy::Task, &config),
+    };
+    if let (Some(previous), OutputFormat::Text) = (&previous, format) {
+        out = format!("{out}\n{}", report.format_comparison(previous, &config));
+    }
+    let Some(mail) = mail else {
+        print!("{out}");
+        return Ok(());
+    };
+    let report_mail = ReportMail::new(period, first, last, out, &config);
+    let message = report_mail.format(Local::now().fixed_offset());
+    match mail {
+        Mail::Stdout => print!("{message}"),
+        Mail::File(path) => {
+            fs::write(&path, message)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+            println!("Saved the report email to {}", path.display());
+        }
+        Mail::Send => {
+            report_mail.send(&config.smtp).map_err(|e| e.to_string())?;
+            // so that gtimelog also knows that it was sent
+            let log = mail::sent_reports_file(&options.timelog_file());
+            let record = report_mail.sent_record(Local::now().naive_local());
+            if let Err(e) = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log)
+                .and_then(|mut f| f.write_all(record.as_bytes()))
+            {
+                eprintln!("WARNING: Failed to write {}: {e}", log.display());
+            }
+            println!("Sent the report to {}", report_mail.to.unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+// this week of other people's timelogs, e.g. collected by a team lead
+fn run_team_report(
+    options: &GlobalOptions,
+    files: &[PathBuf],
+    label_by_file: bool,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let format = report_options.format;
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    let first = today.week(config.week_start).first_day();
+    let (begin, end) = TeamReport::range(first, &config).ok_or("Invalid week")?;
+    let mut timelogs = Vec::new();
+    for file in files {
+        let timelog = Timelog::new_from_storage_range(&FileStorage::new(file), begin, end)
+            .map_err(|e| format!("{}: {e}", file.display()))?;
+        // everybody's file may be called timelog.txt, in a directory per person
+        let name = match file.file_stem() {
+            Some(stem) if label_by_file => stem.to_string_lossy().to_string(),
+            _ => file.display().to_string(),
+        };
+        timelogs.push((name, timelog));
+    }
+    let logs: Vec<_> = timelogs
+        .iter()
+        .map(|(name, tl)| (name.clone(), tl.get_time_range(begin, end)))
+        .collect();
+    let report = TeamReport::new(&logs, first, &config);
+    match format {
+        OutputFormat::Text => print!("{}", report.format(&config)),
+        // the rows have everybody's projects; there is no own timelog for the days
+        _ => {
+            let week = (first, first + Duration::days(6));
+            print!(
+                "{}",
+                format_table(&report.table(), format, &[], week, &config)
+            );
+        }
+    }
+    Ok(())
+}
+
+// overtime per week and in total, for flexitime at work; from the start of the year of `to`
+fn run_flexitime(
+    options: &GlobalOptions,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let last = to.unwrap_or_else(|| config.sentinels.day_of(Local::now().naive_local()));
+    let first = from.unwrap_or_else(|| last.with_ordinal(1).unwrap());
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let report = PeriodReport::new(timelog.get_time_range(begin, end), first, last, &config);
+    print!("{}", report.format_flexitime(&config));
+    Ok(())
+}
+
+// (first, last) of the last `days` until today
+fn last_days(days: i64, config: &Config) -> Result<(NaiveDate, NaiveDate), Box<dyn Error>> {
+    let last = config.sentinels.day_of(Local::now().naive_local());
+    let first = Duration::try_days(days - 1)
+        .and_then(|d| last.checked_sub_signed(d))
+        .ok_or("Invalid number of days")?;
+    Ok((first, last))
+}
+
+fn run_stats(
+    options: &GlobalOptions,
+    days: i64,
+    pomodoro: bool,
+    heatmap: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let (first, last) = last_days(days, &config)?;
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let entries = timelog.get_time_range(begin, end);
+    if pomodoro {
+        let stats = PomodoroStats::new(entries, first, last, &config);
+        print!("{}", stats.format(&config));
+        return Ok(());
+    }
+    if heatmap {
+        let heatmap = Heatmap::new(entries, first, last, &config);
+        print!("{}", heatmap.format(&config));
+        return Ok(());
+    }
+    let stats = Stats::new(entries, first, last, &config);
+    print!("{}", stats.format(&config));
+    Ok(())
+}
+
+fn run_top(
+    options: &GlobalOptions,
+    days: i64,
+    count: usize,
+    projects: bool,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let (first, last) = last_days(days, &config)?;
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let entries = timelog.get_time_range(begin, end);
+    let report = PeriodReport::new(entries, first, last, &config);
+    match report_options.format {
+        OutputFormat::Text => print!("{}", report.format_top(count, projects, &config)),
+        format => {
+            let table = report.top_table(count, projects);
+            print!(
+                "{}",
+                format_table(&table, format, entries, (first, last), &config)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_chart(
+    options: &GlobalOptions,
+    period: Period,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    let (first, last) = period.days(today, &config);
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let report = PeriodReport::new(timelog.get_time_range(begin, end), first, last, &config);
+    // see https://no-color.org
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    print!("{}", chart::format(&report, &config, color));
+    Ok(())
+}
+
+// the entries from the day `from` to `to`, or the first or last ones without
+fn load_days(
+    options: &GlobalOptions,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    config: &Config,
+) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let range = |day| PeriodReport::range(day, day, config).ok_or("Invalid date");
+    let begin = match from {
+        Some(day) => range(day)?.0,
+        None => NaiveDateTime::MIN,
+    };
+    let end = match to {
+        Some(day) => range(day)?.1,
+        None => NaiveDateTime::MAX,
+    };
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    Ok(timelog.get_time_range(begin, end).to_vec())
+}
+
+fn run_export(
+    options: &GlobalOptions,
+    format: ExportFormat,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let entries = load_days(options, from, to, &config)?;
+    print!("{}", export::export(&entries, format, &config, Utc::now()));
+    Ok(())
+}
+
+fn run_grep(
+    options: &GlobalOptions,
+    pattern: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let entries = load_days(options, from, to, &config)?;
+    let matches = search::grep(&entries, &Regex::new(pattern)?, &config.sentinels);
+    print!("{}", search::format(&matches, &config));
+    Ok(())
+}
+
+// the first day of `month`, of this month by default, and its entries
+fn load_month(
+    options: &GlobalOptions,
+    month: Option<NaiveDate>,
+    config: &Config,
+) -> Result<(NaiveDate, Vec<Entry>), Box<dyn Error>> {
+    let month = month.unwrap_or_else(|| {
+        let today = config.sentinels.day_of(Local::now().naive_local());
+        today.with_day(1).unwrap()
+    });
+    let (first, last) = month_days(month)?;
+    let (begin, end) = PeriodReport::range(first, last, config).ok_or("Invalid month")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    Ok((month, timelog.get_time_range(begin, end).to_vec()))
+}
+
+// (first, last) day of the month which starts on `month`
+fn month_days(month: NaiveDate) -> Result<(NaiveDate, NaiveDate), Box<dyn Error>> {
+    let last = month
+        .checked_add_months(Months::new(1))
+        .and_then(|d| d.pred_opt())
+        .ok_or("Invalid month")?;
+    Ok((month, last))
+}
+
+fn run_earnings(
+    options: &GlobalOptions,
+    month: Option<NaiveDate>,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let format = report_options.format;
+    let (month, entries) = load_month(options, month, &config)?;
+    let earnings = Earnings::new(&entries, &config);
+    if format != OutputFormat::Text {
+        let days = month_days(month)?;
+        print!(
+            "{}",
+            format_table(&earnings.table(), format, &entries, days, &config)
+        );
+        return Ok(());
+    }
+    println!(
+        "Earnings in {}:\n",
+        config.date_format.format_with(&month, "%B %Y")
+    );
+    print!("{}", earnings.format(&config));
+    Ok(())
+}
+
+fn run_invoice(
+    options: &GlobalOptions,
+    month: Option<NaiveDate>,
+    rate: Option<&Rate>,
+    report_options: &ReportOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut config = load_config(options);
+    report_options.apply(&mut config);
+    let format = report_options.format;
+    let (month, entries) = load_month(options, month, &config)?;
+    let invoice = Invoice::new(&entries, rate, &config);
+    if format != OutputFormat::Text {
+        let days = month_days(month)?;
+        print!(
+            "{}",
+            format_table(&invoice.table(), format, &entries, days, &config)
+        );
+        return Ok(());
+    }
+    println!(
+        "Invoice for {}:\n",
+        config.date_format.format_with(&month, "%B %Y")
+    );
+    print!("{}", invoice.format(&config));
+    Ok(())
+}
+
+// move the old years out of the timelog, which keeps it quick to load
+fn run_archive(options: &GlobalOptions, before: Option<i32>) -> Result<(), Box<dyn Error>> {
+    let before = before.unwrap_or_else(|| Local::now().year());
+    let archived = archive::split(&options.timelog_file(), before)?;
+    if archived.is_empty() {
+        println!("No entries before {before}");
+    }
+    for (path, count) in archived {
+        println!("Moved {count} entries to {}", path.display());
+    }
+    Ok(())
+}
+
+fn run_merge(options: &GlobalOptions, file: &Path) -> Result<(), Box<dyn Error>> {
+    // rather than merging nothing
+    if !file.is_file() {
+        return Err(format!("{} does not exist", file.display()).into());
+    }
+    let other = Timelog::new_from_file(file)?;
+    let mut timelog = Timelog::new_from_file(&options.timelog_file())?;
+    let merged = timelog.merge(&other)?;
+    for (stop, existing, added) in &merged.conflicts {
+        println!(
+            "Both \"{existing}\" and \"{added}\" stopped at {}, kept both",
+            stop.format("%Y-%m-%d %H:%M")
+        );
+    }
+    if merged.added > 0 {
+        timelog.save()?;
+    }
+    println!(
+        "Added {} entries from {}, {} were there already",
+        merged.added,
+        file.display(),
+        merged.duplicates
+    );
+    if !merged.conflicts.is_empty() {
+        println!("Check the conflicts with :e in the interactive mode");
+    }
+    Ok(())
+}
+
+// timestamped archive in the current directory, unless given
+fn run_backup(options: &GlobalOptions, out: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let out = out.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "rtimelog-backup-{}.tar",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+    let files = backup::create(
+        &out,
+        &backup::default_files(&options.timelog_file(), &load_config(options)),
+    )?;
+    println!("Saved to {}:", out.display());
+    for file in files {
+        println!("  {}", file.path.display());
+    }
+    Ok(())
+}
+
+fn run_restore(
+    options: &GlobalOptions,
+    archive: PathBuf,
+    check: bool,
+) -> Result<(), Box<dyn Error>> {
+    let files = backup::verify(&archive)?;
+    if check {
+        println!("{} is complete:", archive.display());
+        for (file, data) in files {
+            println!("  {} ({} bytes)", file.path.display(), data.len());
+        }
+        return Ok(());
+    }
+
+    // so that restoring the wrong backup can be undone
+    let current = PathBuf::from(format!(
+        "rtimelog-before-restore-{}.tar",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    backup::create(
+        &current,
+        &backup::default_files(&options.timelog_file(), &load_config(options)),
+    )?;
+    println!("Saved the current files to {}", current.display());
+    println!("Restored from {}:", archive.display());
+    for file in backup::restore(&archive)? {
+        println!("  {}", file.path.display());
+    }
+    Ok(())
+}
+
+// list implausible entries, and fail if there are any, e.g. for a cron job
+fn run_standup(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let timelog = Timelog::new_from_file(&options.timelog_file())?;
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    print!("{}", standup::summary(&timelog, &config, today));
+    Ok(())
+}
+
+fn run_doctor(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let storage = FileStorage::new(&options.timelog_file());
+    let checkup = doctor::check(storage.reader()?, &config)?;
+    if checkup.is_empty() {
+        println!("No implausible entries");
+        return Ok(());
+    }
+    let findings = &checkup.findings;
+    for f in findings {
+        let problem = if config.plain {
+            f.problem.to_plain_string()
+        } else {
+            f.problem.to_string()
+        };
+        println!("line {}: {}\n  {problem}", f.line, f.entry);
+    }
+    let entries = if findings.len() == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    if !findings.is_empty() {
+        println!(
+            "\n{} implausible {entries}; fix them with :e in the interactive mode",
+            findings.len()
+        );
+    }
+    if !checkup.missing_days.is_empty() {
+        if !findings.is_empty() {
+            println!();
+        }
+        print_missing_days(&checkup.missing_days);
+    }
+    process::exit(1);
+}
+
+// list the duplicates, and remove them if that's fine
+fn run_dedupe(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let mut timelog = Timelog::new_from_file(&options.timelog_file())?;
+    let duplicates = dedupe::find(&timelog, &config.sentinels);
+    if duplicates.is_empty() {
+        println!("No duplicate entries");
+        return Ok(());
+    }
+    for d in &duplicates {
+        println!(
+            "{}: {}\n  {}",
+            d.entry.stop.format("%Y-%m-%d %H:%M"),
+            d.entry.task,
+            d.reason
+        );
+    }
+    let mut rl = DefaultEditor::new()?;
+    if !confirm(
+        &mut rl,
+        &format!("Remove these {} entries?", duplicates.len()),
+    )? {
+        return Ok(());
+    }
+    // the indexes would not fit any more
+    if timelog.reload_if_changed() {
+        return Err("The timelog changed in the meantime; run dedupe again".into());
+    }
+    dedupe::remove(&mut timelog, &duplicates)?;
+    timelog.save_atomic()?;
+    println!("Removed {} entries", duplicates.len());
+    Ok(())
+}
+
+fn print_missing_days(days: &[NaiveDate]) {
+    println!("No entries on these work days:");
+    for day in days {
+        println!("  {}", day.format("%A, %Y-%m-%d"));
+    }
+}
+
+fn run_missing(
+    options: &GlobalOptions,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    fill: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    let first = from.unwrap_or_else(|| today.with_day(1).unwrap());
+    let last = to.unwrap_or(today);
+    let (begin, end) = PeriodReport::range(first, last, &config).ok_or("Invalid date")?;
+    let storage = ArchiveStorage::new(&options.timelog_file(), begin, end);
+    let timelog = Timelog::new_from_storage_range(&storage, begin, end)?;
+    let missing = doctor::missing_days(timelog.get_time_range(begin, end), first, last, &config);
+    if missing.is_empty() {
+        println!("No work days without entries");
+        return Ok(());
+    }
+    print_missing_days(&missing);
+    if fill {
+        backfill(&options.timelog_file(), &missing, &config)?;
+    }
+    Ok(())
+}
+
+// Ask for the entries of each of `days`, with the tasks of the weeks before in the history for
+// ^r and the arrow keys. Each day gets saved when going on to the next one.
+fn backfill(path: &Path, days: &[NaiveDate], config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut timelog = Timelog::new_from_file(path)?;
+    let mut rl = DefaultEditor::new()?;
+    let vm = config.sentinels.virtual_midnight;
+    if let Some(last) = days.last() {
+        for task in Timelog::get_history(timelog.get_n_weeks(last, 4, vm, config.week_start)) {
+            rl.add_history_entry(task)?;
+        }
+    }
+    println!(
+        "\nLog each day as HH:MM TASK lines, starting with the arrival; an empty line goes on to the next day, ^D stops"
+    );
+    for day in days {
+        println!("\n{}:", config.date_format.format(day));
+        loop {
+            let line = match rl.readline("> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                    timelog.save()?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if line.trim().is_empty() {
+                break;
+            }
+            match Command::parse_add_at(&line) {
+                Command::AddAt(time, task) => {
+                    let stop = config.sentinels.time_on(*day, time);
+                    rl.add_history_entry(&task)?;
+                    if let Err(e) = timelog.add_at(task, stop) {
+                        println!("Error: {e}");
+                    }
+                }
+                Command::Error(e) => println!("Error: {e}"),
+                _ => unreachable!(),
+            }
+        }
+        timelog.save()?;
+    }
+    Ok(())
+}
+
+// from ~/.gtimelog to the XDG directories, where gtimelog also looks without it
+fn run_migrate(options: &GlobalOptions, check: bool) -> Result<(), Box<dyn Error>> {
+    let legacy = migrate::legacy_dir();
+    if !legacy.is_dir() {
+        println!("Nothing to migrate, there is no {}", legacy.display());
+        return Ok(());
+    }
+    if send_to_daemon(options, &Request::Status)?.is_some() {
+        return Err("Stop the daemon first, it uses the files there".into());
+    }
+    let moves = migrate::plan(&legacy, &Timelog::get_data_dir(), &Config::get_config_dir())?;
+    for (from, to) in &moves {
+        println!("{} -> {}", from.display(), to.display());
+    }
+    if !check {
+        migrate::migrate(&legacy, &moves)?;
+        println!(
+            "Moved {} files, and removed {}",
+            moves.len(),
+            legacy.display()
+        );
+    }
+    Ok(())
+}
+
+fn install_agent() -> Result<(), Box<dyn Error>> {
+    if !cfg!(target_os = "macos") {
+        eprintln!("Error: install-agent is only supported on macOS");
+        process::exit(1);
+    }
+    let path = agent::install(&env::current_exe()?)?;
+    println!(
+        "Installed {}; it starts on the next login, or now with:\n  launchctl load {}",
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+// configuration file settings, overridden by command line options
+fn load_config(options: &GlobalOptions) -> Config {
+    let mut config = Config::new_from_default_file();
+    config.plain |= options.plain;
+    config
+}
+
+// errors as their message, not their Debug form
+fn main() {
+    let (options, args) = GlobalOptions::parse(env::args().skip(1).collect());
+    if let Err(e) = run(&options, Subcommand::parse(args)) {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(options: &GlobalOptions, subcommand: Subcommand) -> Result<(), Box<dyn Error>> {
+    match subcommand {
+        Subcommand::Interactive => interactive(options),
+        Subcommand::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Subcommand::Pomodoro { task, cycles } => run_pomodoro(options, task, cycles),
+        Subcommand::Add(task) => run_request(options, Request::Add(task)),
+        Subcommand::AddAt(time, task) => run_request(options, Request::AddAt(time, task)),
+        Subcommand::Status => run_request(options, Request::Status),
+        Subcommand::Standup => run_standup(options),
+        Subcommand::Prompt(format) => run_prompt(options, format),
+        Subcommand::Daemon => run_daemon(options),
+        Subcommand::Remind => run_remind(options),
+        Subcommand::InstallAgent => install_agent(),
+        Subcommand::Report {
+            year,
+            options: report,
+        } => run_report(options, year, &report),
+        Subcommand::PeriodReport {
+            period,
+            group_by,
+            options: report,
+            mail,
+            compare,
+            xlsx,
+        } => run_period_report(options, period, group_by, &report, mail, compare, xlsx),
+        Subcommand::TeamReport {
+            files,
+            label_by_file,
+            options: report,
+        } => run_team_report(options, &files, label_by_file, &report),
+        Subcommand::Flexitime { from, to } => run_flexitime(options, from, to),
+        Subcommand::Chart {
+            period,
+            options: report,
+        } => run_chart(options, period, &report),
+        Subcommand::Stats {
+            days,
+            pomodoro,
+            heatmap,
+        } => run_stats(options, days, pomodoro, heatmap),
+        Subcommand::Top {
+            days,
+            count,
+            projects,
+            options: report,
+        } => run_top(options, days, count, projects, &report),
+        Subcommand::Export { format, from, to } => run_export(options, format, from, to),
+        Subcommand::Grep { pattern, from, to } => run_grep(options, &pattern, from, to),
+        Subcommand::Earnings {
+            month,
+            options: report,
+        } => run_earnings(options, month, &report),
+        Subcommand::Invoice {
+            month,
+            rate,
+            options: report,
+        } => run_invoice(options, month, rate.as_ref(), &report),
+        Subcommand::Archive { before } => run_archive(options, before),
+        Subcommand::Merge(file) => run_merge(options, &file),
+        Subcommand::Backup(out) => run_backup(options, out),
+        Subcommand::Restore { archive, check } => run_restore(options, archive, check),
+        Subcommand::Doctor => run_doctor(options),
+        Subcommand::Dedupe => run_dedupe(options),
+        Subcommand::Missing { from, to, fill } => run_missing(options, from, to, fill),
+        Subcommand::Migrate { check } => run_migrate(options, check),
+        Subcommand::Error(e) => {
+            eprintln!("Error: {e}\n\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+// offer to repair the timelog if it got damaged since it was last saved
+fn check_damage(
+    timelog: &SharedTimelog,
+    path: &Path,
+    rl: &mut DefaultEditor,
+) -> Result<(), Box<dyn Error>> {
+    let damage = match journal::check(path) {
+        Ok(Some(damage)) => damage,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            eprintln!(
+                "WARNING: Failed to check {} for damage: {e}",
+                path.display()
+            );
+            return Ok(());
+        }
+    };
+
+    println!(
+        "WARNING: {} got damaged since it was last saved.",
+        path.display()
+    );
+    if !damage.garbled.is_empty() {
+        let damaged = journal::save_damaged(path, &damage.garbled)?;
+        println!("These invalid lines got copied to {}:", damaged.display());
+        for line in &damage.garbled {
+            println!("  {line}");
+        }
+    }
+    if !damage.missing.is_empty() {
+        println!("These entries are missing:");
+        for line in &damage.missing {
+            println!("  {line}");
+        }
+        if confirm(rl, "Recover them?")? {
+            let mut tl = timelog.write();
+            let count = tl.recover(&damage.missing)?;
+            tl.save()?;
+            println!("Recovered {count} entries.");
+            return Ok(());
+        }
+    }
+    // don't ask again
+    journal::accept(path)?;
+    Ok(())
+}
+
+// load the recent entries right away, and the older ones in the background
+fn load_timelog(path: PathBuf) -> Result<SharedTimelog, TimelogError> {
+    let since = (Local::now().date_naive() - Duration::weeks(RECENT_WEEKS))
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let timelog = SharedTimelog::new(Timelog::new_from_storage_recent(
+        Box::new(FileStorage::new(&path)),
+        since,
+    )?);
+
+    let thread_timelog = timelog.clone();
+    thread::spawn(
+        move || match Timelog::load_older(&FileStorage::new(&path), since) {
+            Ok(older) => thread_timelog.write().prepend_older(older, since),
+            Err(e) => eprintln!("WARNING: Failed to load older entries: {e}"),
+        },
+    );
+    Ok(timelog)
+}
+
+// Save a change of the interactive mode; returns whether it did. A change that another program
+// holds the lock against is kept to get saved on the next entry, or on Enter.
+fn save_or_keep(tl: &mut Timelog, path: &Path) -> bool {
+    match tl.save() {
+        Ok(()) => true,
+        Err(TimelogError::Changed) => {
+            println!("Error: {}", TimelogError::Changed);
+            if tl.reload_if_changed() {
+                println!("Reloaded {}, the change is not saved", path.display());
+            }
+            false
+        }
+        Err(e) => {
+            println!("Error: {e}");
+            println!("The change is kept, press Enter to save it again");
+            false
+        }
+    }
+}
+
+fn interactive(options: &GlobalOptions) -> Result<(), Box<dyn Error>> {
+    let config = load_config(options);
+    let path = options.timelog_file();
+    let timelog = load_timelog(path.clone())?;
     let mut running = true;
     let mut time_mode = TimeMode::Day(1);
     let mut readline = DefaultEditor::new()?;
+    check_damage(&timelog, &path, &mut readline)?;
     let mut do_show = true;
+    // shown below the next show(), which clears the screen
+    let mut warnings: Vec<String> = Vec::new();
+    let mut greeted = None;
+    let idle = IdleMonitor::start(Duration::minutes(IDLE_THRESHOLD_MINUTES));
+    if let Some(reminder) = Reminder::new_from_config(&config) {
+        reminder.start(timelog.clone());
+    }
 
     while running {
         if do_show {
-            show(&timelog, &time_mode, &mut readline);
+            show(&mut timelog.write(), &config, &time_mode, &mut readline);
+        }
+        for w in warnings.drain(..) {
+            println!("{w}");
         }
         do_show = true;
-        show_prompt(&timelog)?;
 
-        match Command::parse(get_input(&mut readline)?) {
-            Command::Nothing => (),
-            Command::Quit => running = false,
+        let today = Local::now().date_naive();
+        if greeted != Some(today) && morning::is_day_start(&timelog.read(), &config, today) {
+            greeted = Some(today);
+            start_day(&timelog, &config, today)?;
+        }
+
+        show_prompt(&timelog.read(), &config)?;
+
+        let input = get_input(&mut readline)?;
+        // pick up edits from an editor or gtimelog, instead of overwriting them with stale entries
+        if timelog.write().reload_if_changed() {
+            println!(
+                "{} was changed by another program, reloaded it",
+                path.display()
+            );
+        }
+        match Command::parse(input) {
+            // try again to save what failed to
+            Command::Nothing => {
+                let mut tl = timelog.write();
+                if tl.has_unsaved() {
+                    save_or_keep(&mut tl, &path);
+                }
+            }
+            Command::Quit => {
+                let mut tl = timelog.write();
+                running = tl.has_unsaved()
+                    && !save_or_keep(&mut tl, &path)
+                    && !confirm(&mut readline, "Quit without saving?")?;
+            }
             Command::Help => {
                 show_help();
                 do_show = false;
             }
             Command::Edit => {
-                run_editor(&timelog.filename.unwrap());
-                timelog = Timelog::new_from_default_file();
+                run_editor(&path, &config);
+                match Timelog::new_from_file(&path) {
+                    Ok(tl) => {
+                        timelog.replace(tl);
+                    }
+                    Err(e) => {
+                        println!("Error: {e}");
+                        do_show = false;
+                    }
+                }
+            }
+            Command::Log => {
+                show_log(&timelog.read(), &config, &time_mode);
+                do_show = false;
+            }
+            Command::Note(note) => {
+                let mut tl = timelog.write();
+                if tl.append_note(&note) {
+                    save_or_keep(&mut tl, &path);
+                } else {
+                    println!("Error: No entry to add a note to");
+                    do_show = false;
+                }
+            }
+            Command::Undo => {
+                let last = timelog.read().last_added().cloned();
+                match last {
+                    Some(last) => {
+                        if confirm(&mut readline, &format!("Remove \"{last}\"?"))? {
+                            let mut tl = timelog.write();
+                            tl.pop_last();
+                            save_or_keep(&mut tl, &path);
+                        }
+                    }
+                    None => {
+                        println!("Error: No entry to remove");
+                        do_show = false;
+                    }
+                }
             }
             Command::SwitchMode(m) => time_mode = m,
             Command::Add(a) => {
-                timelog.add(a);
-                timelog.save()?;
+                let away = reconcile_away(&timelog, &config, &idle, &mut readline)?;
+                let since = away.last().map(|e| e.0).or_else(|| {
+                    timelog
+                        .read()
+                        .get_today(Local::now().naive_local(), &config.sentinels)
+                        .last()
+                        .map(|e| e.stop)
+                });
+                if let Some(warning) =
+                    since.and_then(|s| threshold_warning(config.threshold_for(&a), s))
+                {
+                    if !confirm(&mut readline, &format!("{warning}\nLog \"{a}\" anyway?"))? {
+                        continue;
+                    }
+                }
+
+                let mut tl = timelog.write();
+                let before = month_budgets(&mut tl, &config);
+                for (stop, task) in away {
+                    tl.add_at(task.unwrap_or_else(|| a.clone()), stop)?;
+                }
+                if let Err(e) = tl.add_snapped(a, Local::now().naive_local(), &config.snap) {
+                    println!("Error: {e}");
+                    do_show = false;
+                    continue;
+                }
+                if save_or_keep(&mut tl, &path) {
+                    warnings.extend(budget_warnings(&before, &mut tl, &config));
+                }
+            }
+            Command::AddAt(time, task) => {
+                let now = Local::now().naive_local();
+                let stop = config.sentinels.time_on(config.sentinels.day_of(now), time);
+                if stop > now {
+                    println!("Error: {} is in the future", config.date_format.time(time));
+                    do_show = false;
+                    continue;
+                }
+                let mut tl = timelog.write();
+                let before = month_budgets(&mut tl, &config);
+                match tl.add_at(task, stop) {
+                    Ok(()) => {
+                        if save_or_keep(&mut tl, &path) {
+                            warnings.extend(budget_warnings(&before, &mut tl, &config));
+                        }
+                    }
+                    Err(e) => {
+                        println!("Error: {e}");
+                        do_show = false;
+                    }
+                }
             }
             Command::Error(e) => {
                 println!("Error: {e}");
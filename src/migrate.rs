@@ -0,0 +1,140 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// gtimelog and rtimelog prefer it over the XDG directories as long as it exists
+pub fn legacy_dir() -> PathBuf {
+    dirs::home_dir().unwrap().join(".gtimelog")
+}
+
+// Where each file of the `legacy` directory goes: the configuration to `config_dir`, everything
+// else (the timelog with its journal and archives, gtimelog's tasks.txt) to `data_dir`. That's
+// where gtimelog looks as well once `legacy` is gone.
+pub fn plan(
+    legacy: &Path,
+    data_dir: &Path,
+    config_dir: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>, io::Error> {
+    // e.g. a synced directory; moving the files out of it would break that
+    if fs::symlink_metadata(legacy)?.file_type().is_symlink() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is a symlink, keeping it", legacy.display()),
+        ));
+    }
+    let mut moves = Vec::new();
+    for file in fs::read_dir(legacy)? {
+        let name = file?.file_name();
+        let to = if name == "gtimelogrc" {
+            config_dir.join(&name)
+        } else {
+            data_dir.join(&name)
+        };
+        // never overwrite anything
+        if to.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+        moves.push((legacy.join(&name), to));
+    }
+    moves.sort();
+    Ok(moves)
+}
+
+// move the files of a plan(), and remove the then empty `legacy` directory
+pub fn migrate(legacy: &Path, moves: &[(PathBuf, PathBuf)]) -> Result<(), io::Error> {
+    for (from, to) in moves {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(from, to).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to move {} to {}: {e}", from.display(), to.display()),
+            )
+        })?;
+    }
+    fs::remove_dir(legacy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    #[test]
+    fn test_migrate() {
+        let dir = env::temp_dir().join(format!("rtimelog-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let legacy = dir.join(".gtimelog");
+        let data = dir.join("data/gtimelog");
+        let config = dir.join("config/gtimelog");
+        fs::create_dir_all(&legacy).unwrap();
+        for file in [
+            "timelog.txt",
+            "timelog.txt.journal",
+            "tasks.txt",
+            "gtimelogrc",
+        ] {
+            fs::write(legacy.join(file), file).unwrap();
+        }
+
+        let moves = plan(&legacy, &data, &config).unwrap();
+        assert_eq!(
+            moves,
+            vec![
+                (legacy.join("gtimelogrc"), config.join("gtimelogrc")),
+                (legacy.join("tasks.txt"), data.join("tasks.txt")),
+                (legacy.join("timelog.txt"), data.join("timelog.txt")),
+                (
+                    legacy.join("timelog.txt.journal"),
+                    data.join("timelog.txt.journal")
+                ),
+            ]
+        );
+
+        // refuses to overwrite
+        fs::create_dir_all(&data).unwrap();
+        fs::write(data.join("timelog.txt"), "other").unwrap();
+        let e = plan(&legacy, &data, &config).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        fs::remove_file(data.join("timelog.txt")).unwrap();
+
+        migrate(&legacy, &moves).unwrap();
+        assert!(!legacy.exists());
+        assert_eq!(
+            fs::read_to_string(data.join("timelog.txt")).unwrap(),
+            "timelog.txt"
+        );
+        assert_eq!(
+            fs::read_to_string(config.join("gtimelogrc")).unwrap(),
+            "gtimelogrc"
+        );
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&data, &legacy).unwrap();
+            let e = plan(&legacy, &data, &config).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
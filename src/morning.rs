@@ -0,0 +1,184 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::activity;
+use crate::config::Config;
+use crate::ics::Event;
+use crate::store::Timelog;
+
+// first start on a working day without any entries yet
+pub fn is_day_start(timelog: &Timelog, config: &Config, today: NaiveDate) -> bool {
+    config.is_work_day(today)
+        && timelog
+            .get_n_days(&today, 1, config.sentinels.virtual_midnight)
+            .is_empty()
+}
+
+/**
+ * Summary for starting into the working day
+ */
+pub fn summary(timelog: &Timelog, config: &Config, today: NaiveDate, events: &[Event]) -> String {
+    let mut output = String::new();
+    writeln!(
+        output,
+        "Good morning! Today is {}.",
+        config.date_format.format(&today)
+    )
+    .unwrap();
+
+    let midnight = today.and_hms_opt(0, 0, 0).unwrap();
+    if let Some(last) = timelog.get_time_range(NaiveDateTime::MIN, midnight).last() {
+        writeln!(output, "Last entry: {last}").unwrap();
+    }
+
+    if config.plain {
+        writeln!(
+            output,
+            "Target for today: {}",
+            activity::duration_in_words(config.hours)
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            output,
+            "Target for today: {} h {} min",
+            config.hours.num_hours(),
+            config.hours.num_minutes() % 60
+        )
+        .unwrap();
+    }
+
+    let meetings: Vec<&Event> = events.iter().filter(|e| e.is_on(today)).collect();
+    if !meetings.is_empty() {
+        writeln!(output, "Meetings:").unwrap();
+        let clock = |t: NaiveDateTime| config.date_format.time(t.time());
+        for m in meetings {
+            if config.plain {
+                let time = match (m.all_day, m.end) {
+                    (true, _) => "all day".to_string(),
+                    (false, Some(end)) => format!("from {} to {}", clock(m.start), clock(end)),
+                    (false, None) => format!("at {}", clock(m.start)),
+                };
+                writeln!(output, "{}, {time}", m.summary).unwrap();
+                continue;
+            }
+            // as wide as a time range, to line up the summaries
+            let width = 2 * clock(m.start).len() + 1;
+            let time = match (m.all_day, m.end) {
+                (true, _) => format!("{:<width$}", "all day"),
+                (false, Some(end)) => format!("{}-{}", clock(m.start), clock(end)),
+                (false, None) => format!("{:<width$}", clock(m.start)),
+            };
+            writeln!(output, "  {time} {}", m.summary).unwrap();
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ics;
+    use pretty_assertions::assert_eq;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2022, 6, d).unwrap()
+    }
+
+    #[test]
+    fn test_is_day_start() {
+        let config = Config::default();
+        let tl = Timelog::new_from_string("2022-06-09 17:30: customer joe: support\n");
+        // Friday
+        assert!(is_day_start(&tl, &config, day(10)));
+        // Saturday
+        assert!(!is_day_start(&tl, &config, day(11)));
+        // already has an entry
+        assert!(!is_day_start(&tl, &config, day(9)));
+    }
+
+    #[test]
+    fn test_summary_empty() {
+        let tl = Timelog::new_from_string("");
+        assert_eq!(
+            summary(&tl, &Config::default(), day(10), &[]),
+            "Good morning! Today is Friday, 2022-06-10.
+Target for today: 8 h 0 min
+"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-08 07:00: arrived
+2022-06-08 16:00: work
+
+2022-06-09 07:00: arrived
+2022-06-09 17:30: customer joe: support
+",
+        );
+        let config = Config::new_from_string("[gtimelog]\nhours = 7.5\n");
+        let events = ics::parse(
+            "BEGIN:VEVENT
+DTSTART:20220610T093000
+DTEND:20220610T094500
+SUMMARY:standup
+END:VEVENT
+BEGIN:VEVENT
+DTSTART;VALUE=DATE:20220610
+SUMMARY:release day
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20220613T093000
+SUMMARY:next week
+END:VEVENT
+",
+        );
+        assert_eq!(
+            summary(&tl, &config, day(10), &events),
+            "Good morning! Today is Friday, 2022-06-10.
+Last entry: 2022-06-09 17:30: customer joe: support
+Target for today: 7 h 30 min
+Meetings:
+  all day     release day
+  09:30-09:45 standup
+"
+        );
+
+        let config = Config {
+            plain: true,
+            ..config
+        };
+        assert_eq!(
+            summary(&tl, &config, day(10), &events),
+            "Good morning! Today is Friday, 2022-06-10.
+Last entry: 2022-06-09 17:30: customer joe: support
+Target for today: 7 hours 30 minutes
+Meetings:
+release day, all day
+standup, from 09:30 to 09:45
+"
+        );
+    }
+}
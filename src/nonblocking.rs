@@ -0,0 +1,216 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use tokio::{fs, sync::Mutex, task};
+
+use crate::activity::Activities;
+use crate::config::Sentinels;
+use crate::journal;
+use crate::shared::SharedTimelog;
+use crate::storage::{write_atomic, FileStorage, Storage};
+use crate::store::Timelog;
+
+/**
+ * Timelog for async programs like an HTTP server or a chat bot
+ *
+ * The file I/O uses tokio::fs, and parsing, formatting, and reports run on tokio's blocking thread
+ * pool, so that big logs don't stall the runtime. Needs a tokio runtime.
+ */
+#[derive(Debug)]
+pub struct AsyncTimelog {
+    timelog: SharedTimelog,
+    // serializes saving, so that an older state never overwrites a newer one
+    saving: Mutex<()>,
+}
+
+impl AsyncTimelog {
+    pub fn new(timelog: SharedTimelog) -> AsyncTimelog {
+        AsyncTimelog {
+            timelog,
+            saving: Mutex::new(()),
+        }
+    }
+
+    pub async fn load(path: &Path) -> Result<AsyncTimelog, io::Error> {
+        let contents = match fs::read(path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {e}", path.display()))),
+        };
+        let storage = FileStorage::new(path);
+        let timelog =
+            unblock(move || Timelog::new_from_contents(&contents, Box::new(storage))).await?;
+        Ok(AsyncTimelog::new(SharedTimelog::new(timelog)))
+    }
+
+    // for synchronous access, e.g. by reminder threads; don't hold its guards across an .await
+    pub fn shared(&self) -> &SharedTimelog {
+        &self.timelog
+    }
+
+    pub async fn save(&self) -> Result<(), io::Error> {
+        let _saving = self.saving.lock().await;
+        let timelog = self.timelog.clone();
+        let (path, contents) = unblock(move || {
+            let mut tl = timelog.write();
+            let path = tl.filename().map(Path::to_path_buf);
+            if path.is_none() {
+                // not a file, so there is no async I/O for it
+                tl.save()?;
+                return Ok((None, String::new()));
+            }
+            Ok((path, tl.contents_to_save()?))
+        })
+        .await?;
+        let Some(path) = path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let write_path = path.clone();
+        unblock(move || {
+            let _lock = FileStorage::new(&write_path).lock()?;
+            write_atomic(&write_path, contents.as_bytes())?;
+            if let Err(e) = journal::write(&write_path, &contents) {
+                eprintln!("WARNING: Failed to write journal: {e}");
+            }
+            Ok(())
+        })
+        .await?;
+        let modified = fs::metadata(&path).await?.modified().ok();
+        self.timelog.write().set_modified(modified);
+        Ok(())
+    }
+
+    // add an entry which stops now, and save it
+    pub async fn append(&self, task: String) -> Result<(), io::Error> {
+        self.timelog.write().add(task)?;
+        self.save().await
+    }
+
+    // activities between `begin` and `end`, loading older entries if necessary
+    pub async fn activities(
+        &self,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+        sentinels: &Sentinels,
+    ) -> Result<Activities, io::Error> {
+        let timelog = self.timelog.clone();
+        let sentinels = sentinels.clone();
+        unblock(move || {
+            let mut tl = timelog.write();
+            tl.ensure_loaded(begin)?;
+            Ok(Activities::new_from_entries(
+                tl.get_time_range(begin, end),
+                &sentinels,
+            ))
+        })
+        .await
+    }
+}
+
+// run `f` on the blocking thread pool
+async fn unblock<T, F>(f: F) -> Result<T, io::Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, io::Error> + Send + 'static,
+{
+    task::spawn_blocking(f).await.map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use chrono::{Duration, NaiveDate};
+    use pretty_assertions::assert_eq;
+    use std::env;
+    use std::fs as stdfs;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    #[test]
+    fn test_async_file() {
+        let dir = env::temp_dir().join(format!("rtimelog-async-{}", std::process::id()));
+        let path = dir.join("timelog.txt");
+        let _ = stdfs::remove_dir_all(&dir);
+
+        block_on(async {
+            let tl = AsyncTimelog::load(&path).await.unwrap();
+            tl.append("arrived".to_string()).await.unwrap();
+            tl.append("work".to_string()).await.unwrap();
+            assert!(journal::check(&path).unwrap().is_none());
+
+            let contents = stdfs::read_to_string(&path).unwrap();
+            assert_eq!(contents.lines().count(), 2);
+            assert!(contents.ends_with(": work\n"));
+
+            let reloaded = AsyncTimelog::load(&path).await.unwrap();
+            let now = chrono::Local::now().naive_local();
+            let a = reloaded
+                .activities(
+                    now - Duration::days(1),
+                    now + Duration::minutes(1),
+                    &Sentinels::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(a.total_slack(), Duration::minutes(0));
+            // a save does not make the sync side reload its own change
+            assert!(!reloaded.shared().write().reload_if_changed());
+        });
+        stdfs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_async_storage() {
+        let storage = MemoryStorage::new(
+            "2022-06-09 09:00: arrived\n2022-06-09 10:00: work\n2022-06-09 10:15: ** tea\n",
+        );
+        let since = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        // the older entries get loaded for the report
+        let shared = SharedTimelog::new(
+            Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap(),
+        );
+        let tl = AsyncTimelog::new(shared);
+
+        block_on(async {
+            let a = tl
+                .activities(since - Duration::days(1), since, &Sentinels::default())
+                .await
+                .unwrap();
+            assert_eq!(a.total_work(), Duration::hours(1));
+            assert_eq!(a.total_slack(), Duration::minutes(15));
+
+            tl.append("work".to_string()).await.unwrap();
+        });
+        assert_eq!(storage.contents().lines().count(), 5);
+        assert!(storage.contents().ends_with(": work\n"));
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Show a desktop notification
+///
+/// If `actions` are given, this shows them as buttons and waits until the user picks one or
+/// dismisses the notification; then it returns the picked action.
+/// Without the "notifications" feature this does nothing.
+#[cfg(feature = "notifications")]
+pub fn notify(summary: &str, body: &str, actions: &[String]) -> Option<String> {
+    extern crate notify_rust;
+
+    // show them in the Notification Center as coming from the terminal
+    #[cfg(target_os = "macos")]
+    {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            let bundle = notify_rust::get_bundle_identifier_or_default("Terminal");
+            if let Err(e) = notify_rust::set_application(&bundle) {
+                eprintln!("WARNING: Failed to set notification application: {e}");
+            }
+        });
+    }
+
+    let mut notification = notify_rust::Notification::new();
+    notification.appname("rtimelog").summary(summary).body(body);
+    // macOS can only wait for actions while running an application main loop
+    let actions = if cfg!(target_os = "macos") {
+        &[]
+    } else {
+        actions
+    };
+    for (i, action) in actions.iter().enumerate() {
+        notification.action(&i.to_string(), action);
+    }
+
+    let handle = match notification.show() {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("WARNING: Failed to show notification: {e}");
+            return None;
+        }
+    };
+
+    if actions.is_empty() {
+        return None;
+    }
+    let mut picked = None;
+    handle.wait_for_action(|id| picked = id.parse::<usize>().ok());
+    picked.and_then(|i| actions.get(i)).cloned()
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn notify(_summary: &str, _body: &str, _actions: &[String]) -> Option<String> {
+    None
+}
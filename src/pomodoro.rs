@@ -0,0 +1,320 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::cmp::Reverse;
+use std::fmt::Write as _;
+use std::thread;
+
+use chrono::{Duration, Local, NaiveDate};
+
+use crate::activity::{self, duration_in_words};
+use crate::config::{Config, Sentinels};
+use crate::notify::notify;
+use crate::report::short;
+use crate::store::{Entry, Timelog, TimelogError};
+
+pub const BREAK_TASK: &str = "** break";
+
+// stored times only have minute precision
+fn is_pomodoro(duration: Duration, work: Duration) -> bool {
+    (duration - work).num_seconds().abs() < 60
+}
+
+/**
+ * Number of pomodoros per day: work intervals of the configured length
+ */
+pub fn count_per_day(
+    entries: &[Entry],
+    work: Duration,
+    sentinels: &Sentinels,
+) -> Vec<(NaiveDate, u32)> {
+    let mut counts: Vec<(NaiveDate, u32)> = Vec::new();
+    for (e, duration) in activity::durations(entries, sentinels) {
+        if e.is_slack() {
+            continue;
+        }
+        if is_pomodoro(duration, work) {
+            match counts.last_mut() {
+                Some((day, n)) if *day == sentinels.day_of(e.stop) => *n += 1,
+                _ => counts.push((sentinels.day_of(e.stop), 1)),
+            }
+        }
+    }
+    counts
+}
+
+/**
+ * Pomodoros of the days from `first` to `last` per day and per project, next to the length of
+ * the work blocks in general, for seeing how well the work fits into pomodoros
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct PomodoroStats {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    // see count_per_day()
+    pub days: Vec<(NaiveDate, u32)>,
+    // (project, pomodoros), most first; see activity::project()
+    pub projects: Vec<(String, u32)>,
+    // work intervals of any length
+    pub blocks: u32,
+    pub average_block: Duration,
+}
+
+impl PomodoroStats {
+    pub fn new(
+        entries: &[Entry],
+        first: NaiveDate,
+        last: NaiveDate,
+        config: &Config,
+    ) -> PomodoroStats {
+        let sentinels = &config.sentinels;
+        let day_of = |e: &Entry| sentinels.day_of(e.stop);
+        let begin = entries.partition_point(|e| day_of(e) < first);
+        let end = entries.partition_point(|e| day_of(e) <= last);
+        let entries = &entries[begin..end.max(begin)];
+
+        let mut projects: Vec<(String, u32)> = Vec::new();
+        let (mut blocks, mut work) = (0, Duration::zero());
+        for (e, duration) in activity::durations(entries, sentinels) {
+            // the ones which just start the clock are no blocks
+            if e.is_slack() || duration <= Duration::zero() {
+                continue;
+            }
+            blocks += 1;
+            work += duration;
+            if !is_pomodoro(duration, config.pomodoro_work) {
+                continue;
+            }
+            match projects.iter_mut().find(|(p, _)| p == e.project()) {
+                Some((_, n)) => *n += 1,
+                None => projects.push((e.project().to_string(), 1)),
+            }
+        }
+        projects.sort_by_key(|(_, n)| Reverse(*n));
+        PomodoroStats {
+            first,
+            last,
+            days: count_per_day(entries, config.pomodoro_work, sentinels),
+            projects,
+            blocks,
+            average_block: work / blocks.max(1) as i32,
+        }
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = if plain { duration_in_words } else { short };
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Pomodoros from {} to {}\n",
+            config.date_format.format(&self.first),
+            config.date_format.format(&self.last)
+        )
+        .unwrap();
+        if self.blocks == 0 {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        let total: u32 = self.days.iter().map(|(_, n)| n).sum();
+        writeln!(
+            out,
+            "Completed pomodoros: {total} of {}",
+            duration(config.pomodoro_work)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "Work blocks: {}, on average {}",
+            self.blocks,
+            duration(self.average_block)
+        )
+        .unwrap();
+        if total == 0 {
+            return out;
+        }
+
+        writeln!(out, "\nDays:").unwrap();
+        for (day, n) in &self.days {
+            let day = config.date_format.format(day);
+            match plain {
+                true => writeln!(out, "{day}: {n}"),
+                false => writeln!(out, "{n:>4}: {day}"),
+            }
+            .unwrap();
+        }
+        writeln!(out, "\nProjects:").unwrap();
+        for (project, n) in &self.projects {
+            match plain {
+                true => writeln!(out, "{project}: {n}"),
+                false => writeln!(out, "{n:>4}: {project}"),
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+fn sleep(d: Duration) {
+    thread::sleep(d.to_std().unwrap_or_default());
+}
+
+fn log(timelog: &mut Timelog, task: &str) -> Result<(), TimelogError> {
+    timelog.add(task.to_string())?;
+    timelog.save()
+}
+
+/**
+ * Run `cycles` pomodoros on `task`, with breaks in between
+ */
+pub fn run(
+    timelog: &mut Timelog,
+    config: &Config,
+    task: &str,
+    cycles: u32,
+) -> Result<(), TimelogError> {
+    let work_min = config.pomodoro_work.num_minutes();
+    let break_min = config.pomodoro_break.num_minutes();
+
+    for i in 1..=cycles {
+        let msg = format!("Pomodoro {i}/{cycles}: {work_min} min of {task}");
+        println!("{} {msg}", config.date_format.time(Local::now().time()));
+        notify("Pomodoro started", &msg, &[]);
+        sleep(config.pomodoro_work);
+        log(timelog, task)?;
+
+        if i < cycles {
+            let msg = format!("Take a {break_min} min break");
+            println!("{} {msg}", config.date_format.time(Local::now().time()));
+            notify("Pomodoro done", &msg, &[]);
+            sleep(config.pomodoro_break);
+            log(timelog, BREAK_TASK)?;
+        }
+    }
+
+    notify(
+        "Pomodoros done",
+        &format!("Finished {cycles} pomodoros"),
+        &[],
+    );
+    println!("\nCompleted pomodoros:");
+    let today = config.sentinels.day_of(Local::now().naive_local());
+    for (day, n) in count_per_day(
+        timelog.get_n_days(&today, 7, config.sentinels.virtual_midnight),
+        config.pomodoro_work,
+        &config.sentinels,
+    ) {
+        println!("{}: {n}", config.date_format.format(&day));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_count_per_day() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 09:00: arrived
+2022-06-09 09:25: code
+2022-06-09 09:30: ** break
+2022-06-09 09:55: code
+2022-06-09 10:30: meeting
+
+2022-06-10 08:35: arrived
+2022-06-10 09:00: review
+2022-06-10 09:25: ** long tea
+2022-06-10 09:26: code
+",
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        let entries = tl.get_n_days(&day(10), 2, NaiveTime::MIN);
+        let s = Sentinels::default();
+        assert_eq!(
+            count_per_day(entries, Duration::minutes(25), &s),
+            // slack intervals don't count
+            vec![(day(9), 2), (day(10), 1)]
+        );
+        assert_eq!(
+            count_per_day(entries, Duration::minutes(35), &s),
+            vec![(day(9), 1)]
+        );
+        assert_eq!(count_per_day(&[], Duration::minutes(25), &s), vec![]);
+    }
+
+    #[test]
+    fn test_pomodoro_stats() {
+        let tl = Timelog::new_from_string(
+            "2022-06-09 09:00: arrived
+2022-06-09 09:25: rtimelog: code
+2022-06-09 09:30: ** break
+2022-06-09 09:55: rtimelog: review
+2022-06-09 10:30: customer joe: meeting
+
+2022-06-10 08:35: arrived
+2022-06-10 09:00: customer joe: support
+2022-06-10 09:25: ** long tea
+2022-06-10 09:29: rtimelog: code
+",
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        let config = Config::default();
+        let stats = PomodoroStats::new(&entries, day(9), day(10), &config);
+        assert_eq!(
+            stats,
+            PomodoroStats {
+                first: day(9),
+                last: day(10),
+                days: vec![(day(9), 2), (day(10), 1)],
+                projects: vec![("rtimelog".to_string(), 2), ("customer joe".to_string(), 1)],
+                blocks: 5,
+                average_block: Duration::seconds(1368),
+            }
+        );
+        assert_eq!(
+            stats.format(&config),
+            "Pomodoros from Thursday, 2022-06-09 to Friday, 2022-06-10
+
+Completed pomodoros: 3 of 25m
+Work blocks: 5, on average 22m
+
+Days:
+   2: Thursday, 2022-06-09
+   1: Friday, 2022-06-10
+
+Projects:
+   2: rtimelog
+   1: customer joe
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(stats.format(&plain).contains(
+            "\nWork blocks: 5, on average 22 minutes\n\nDays:\nThursday, 2022-06-09: 2\n"
+        ));
+
+        let stats = PomodoroStats::new(&entries, day(11), day(12), &config);
+        assert!(stats.format(&config).ends_with("\nNo work logged\n"));
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/**
+ * A project of the registry in projects.toml: the client it is for, and what else is known
+ * about it, like a contact or a contract number
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    // task prefix, like the ones of [rates]
+    pub prefix: String,
+    pub client: String,
+    // the other keys, in the order of the file
+    pub metadata: Vec<(String, String)>,
+}
+
+// rtimelog's own file, next to the templates
+pub fn path() -> PathBuf {
+    Config::get_config_dir()
+        .with_file_name("rtimelog")
+        .join("projects.toml")
+}
+
+// A table per project, named by its prefix, with at least the client:
+//
+// ["customer joe"]
+// client = "Joe's Garage"
+// contact = "joe@example.com"
+pub fn parse(contents: &str) -> Result<Vec<Project>, String> {
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|e: toml::de::Error| e.to_string())?;
+    let mut projects = Vec::new();
+    for (prefix, value) in table {
+        let toml::Value::Table(keys) = value else {
+            return Err(format!("{prefix} is not a table of a project"));
+        };
+        let mut client = None;
+        let mut metadata = Vec::new();
+        for (key, value) in keys {
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Table(_) | toml::Value::Array(_) => {
+                    return Err(format!("{prefix}: {key} needs a plain value"))
+                }
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                toml::Value::Datetime(d) => d.to_string(),
+            };
+            match key.as_str() {
+                "client" => client = Some(value),
+                _ => metadata.push((key, value)),
+            }
+        }
+        let Some(client) = client else {
+            return Err(format!("{prefix} has no client"));
+        };
+        projects.push(Project {
+            prefix,
+            client,
+            metadata,
+        });
+    }
+    Ok(projects)
+}
+
+// the registry in `path`; none if there is no such file, and none with a warning if it is broken
+pub fn load(path: &Path) -> Vec<Project> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                eprintln!("WARNING: Could not read {}: {e}", path.display());
+            }
+            return Vec::new();
+        }
+    };
+    parse(&contents).unwrap_or_else(|e| {
+        eprintln!("WARNING: Ignoring {}: {e}", path.display());
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() {
+        let projects = parse(
+            r#"
+["customer joe"]
+client = "Joe's Garage"
+contract = 42
+
+[joe-portal]
+client = "Joe's Garage"
+contact = "joe@example.com"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            projects,
+            vec![
+                Project {
+                    prefix: "customer joe".to_string(),
+                    client: "Joe's Garage".to_string(),
+                    metadata: vec![("contract".to_string(), "42".to_string())],
+                },
+                Project {
+                    prefix: "joe-portal".to_string(),
+                    client: "Joe's Garage".to_string(),
+                    metadata: vec![("contact".to_string(), "joe@example.com".to_string())],
+                },
+            ]
+        );
+        assert_eq!(parse(""), Ok(Vec::new()));
+
+        assert_eq!(
+            parse("[rtimelog]\ncontact = \"me\"\n"),
+            Err("rtimelog has no client".to_string())
+        );
+        assert_eq!(
+            parse("client = \"me\"\n"),
+            Err("client is not a table of a project".to_string())
+        );
+        assert_eq!(
+            parse("[rtimelog]\nclient = \"me\"\ntags = [\"a\"]\n"),
+            Err("rtimelog: tags needs a plain value".to_string())
+        );
+        assert!(parse("[rtimelog\n").is_err());
+    }
+}
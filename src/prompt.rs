@@ -0,0 +1,226 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::activity::project;
+use crate::config::Config;
+use crate::goals::GoalProgress;
+use crate::store::Entry;
+
+// longer labels get cut off, to keep the prompt short
+const MAX_LABEL_CHARS: usize = 20;
+
+/**
+ * Output format of the shell prompt segment
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptFormat {
+    // with ANSI colors, for e.g. bash's PROMPT_COMMAND
+    Raw,
+    // plain text for a starship custom module, which does the styling itself
+    Starship,
+    // plain text for `p10k segment -t`, with zsh's prompt escapes
+    P10k,
+}
+
+impl PromptFormat {
+    pub fn parse(name: &str) -> Option<PromptFormat> {
+        match name {
+            "raw" => Some(PromptFormat::Raw),
+            "starship" => Some(PromptFormat::Starship),
+            "p10k" => Some(PromptFormat::P10k),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PromptFormat::Raw => "raw",
+            PromptFormat::Starship => "starship",
+            PromptFormat::P10k => "p10k",
+        }
+    }
+}
+
+/**
+ * Shell prompt segment: what the last entry was about, and how long ago it was
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Segment {
+    // the part of the task before the first ": ", like the customer or project
+    pub label: String,
+    pub slack: bool,
+    pub since: Duration,
+    // the running interval is longer than max_pending
+    pub overdue: bool,
+    // progress of the weekly goal of the last task
+    pub goal: Option<GoalProgress>,
+}
+
+impl Segment {
+    // from the entries of the current week; None if there are no entries today
+    pub fn new(week: &[Entry], config: &Config, now: NaiveDateTime) -> Option<Segment> {
+        let s = &config.sentinels;
+        let last = week.last().filter(|e| s.day_of(e.stop) == s.day_of(now))?;
+        let task = last.task.trim();
+        let slack = last.is_slack();
+        let label = project(task);
+        let label = if label.chars().count() > MAX_LABEL_CHARS {
+            let mut cut: String = label.chars().take(MAX_LABEL_CHARS - 1).collect();
+            cut.push('…');
+            cut
+        } else {
+            label.to_string()
+        };
+        let since = now.signed_duration_since(last.stop);
+        let goal = GoalProgress::find(&GoalProgress::for_week(week, config), task).cloned();
+        Some(Segment {
+            label,
+            slack,
+            since,
+            overdue: config.max_pending.is_some_and(|max| since > max),
+            goal,
+        })
+    }
+
+    // `plain` avoids the emoji for breaks, for screen readers
+    pub fn format(&self, format: PromptFormat, plain: bool) -> String {
+        let since = short_duration(self.since);
+        let mut text = match (self.slack, plain) {
+            (true, false) => format!("☕ {since}"),
+            (true, true) => format!("break {since}"),
+            (false, _) => format!("{} {since}", self.label),
+        };
+        if let Some(goal) = &self.goal {
+            text.push_str(&format!(
+                " {}/{}",
+                short_duration(goal.done),
+                short_duration(goal.goal)
+            ));
+        }
+
+        match format {
+            PromptFormat::Raw => {
+                let color = if self.overdue {
+                    31
+                } else if self.slack {
+                    33
+                } else {
+                    36
+                };
+                format!("\x1b[{color}m{text}\x1b[0m")
+            }
+            PromptFormat::Starship => text,
+            PromptFormat::P10k => text.replace('%', "%%"),
+        }
+    }
+}
+
+// like "12m", "2h5m", or "5h"
+fn short_duration(d: Duration) -> String {
+    let minutes = d.num_minutes().max(0);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+    use pretty_assertions::assert_eq;
+
+    fn time(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn segment(log: &str, now: NaiveDateTime) -> Option<Segment> {
+        let tl = Timelog::new_from_string(log);
+        let config =
+            Config::new_from_string("[rtimelog]\nmax_pending = 2h\n[goals]\nrtimelog = 5h\n");
+        Segment::new(
+            tl.get_n_weeks(&now.date(), 1, NaiveTime::MIN, Weekday::Mon),
+            &config,
+            now,
+        )
+    }
+
+    #[test]
+    fn test_prompt_format() {
+        assert_eq!(PromptFormat::parse("p10k"), Some(PromptFormat::P10k));
+        assert_eq!(PromptFormat::parse("fish"), None);
+        for f in [
+            PromptFormat::Raw,
+            PromptFormat::Starship,
+            PromptFormat::P10k,
+        ] {
+            assert_eq!(PromptFormat::parse(f.name()), Some(f));
+        }
+    }
+
+    #[test]
+    fn test_segment() {
+        assert_eq!(segment("", time(9, 0)), None);
+        assert_eq!(segment("2022-06-09 17:00: code\n", time(9, 0)), None);
+
+        let s = segment("2022-06-10 09:00: customer joe: support\n", time(9, 47)).unwrap();
+        assert_eq!(s.label, "customer joe");
+        assert!(!s.slack);
+        assert_eq!(s.format(PromptFormat::Starship, false), "customer joe 47m");
+        assert_eq!(
+            s.format(PromptFormat::Raw, false),
+            "\x1b[36mcustomer joe 47m\x1b[0m"
+        );
+
+        let s = segment("2022-06-10 09:00: ** tea\n", time(9, 12)).unwrap();
+        assert_eq!(s.format(PromptFormat::Starship, false), "☕ 12m");
+        assert_eq!(s.format(PromptFormat::Starship, true), "break 12m");
+        assert_eq!(s.format(PromptFormat::Raw, false), "\x1b[33m☕ 12m\x1b[0m");
+
+        let s = segment("2022-06-10 09:00: 100% done\n", time(11, 5)).unwrap();
+        assert!(s.overdue);
+        assert_eq!(s.format(PromptFormat::P10k, false), "100%% done 2h5m");
+        assert_eq!(
+            s.format(PromptFormat::Raw, false),
+            "\x1b[31m100% done 2h5m\x1b[0m"
+        );
+
+        let s = segment(
+            "2022-06-10 09:00: a really long project name: code\n",
+            time(9, 0),
+        )
+        .unwrap();
+        assert_eq!(s.label, "a really long proje…");
+
+        // a task with a weekly goal
+        let s = segment(
+            "2022-06-09 09:00: arrived\n2022-06-09 11:00: rtimelog: code\n\n\
+             2022-06-10 09:00: arrived\n2022-06-10 10:30: rtimelog: docs\n",
+            time(10, 40),
+        )
+        .unwrap();
+        assert_eq!(
+            s.format(PromptFormat::Starship, false),
+            "rtimelog 10m 3h30m/5h"
+        );
+    }
+}
@@ -0,0 +1,460 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::thread;
+use std::time;
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::activity::{self, Activities};
+use crate::config::{Config, Sentinels, Snap};
+use crate::notify::notify;
+use crate::shared::SharedTimelog;
+use crate::store::Entry;
+
+/// How often to check whether a reminder is due
+const CHECK_INTERVAL: time::Duration = time::Duration::from_secs(60);
+
+/// Repeat the break suggestion after that many minutes, as long as there is no break
+const BREAK_REPEAT_MINUTES: i64 = 30;
+
+/**
+ * Nag with a notification if nothing got logged for a while during working hours,
+ * suggest a break after working for a long stretch, and notify about the progress
+ * towards the daily target
+ */
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    after: Option<Duration>,
+    break_after: Option<Duration>,
+    progress_at: Vec<NaiveTime>,
+    hours: Duration,
+    actions: usize,
+    work_start: NaiveTime,
+    work_end: NaiveTime,
+    work_days: Vec<Weekday>,
+    holidays: Vec<NaiveDate>,
+    sentinels: Sentinels,
+    snap: Snap,
+}
+
+impl Reminder {
+    // None if reminders are not enabled
+    pub fn new_from_config(config: &Config) -> Option<Reminder> {
+        if config.remind_after.is_none()
+            && config.break_after.is_none()
+            && config.progress_at.is_empty()
+        {
+            return None;
+        }
+        Some(Reminder {
+            after: config.remind_after,
+            break_after: config.break_after,
+            progress_at: config.progress_at.clone(),
+            hours: config.hours,
+            actions: config.remind_actions,
+            work_start: config.work_start,
+            work_end: config.work_end,
+            work_days: config.work_days.clone(),
+            holidays: config.holidays.clone(),
+            sentinels: config.sentinels.clone(),
+            snap: config.snap.clone(),
+        })
+    }
+
+    // like Config::is_work_day()
+    fn is_work_day(&self, now: NaiveDateTime) -> bool {
+        self.work_days.contains(&now.weekday()) && !self.holidays.contains(&now.date())
+    }
+
+    pub fn is_working_time(&self, now: NaiveDateTime) -> bool {
+        self.is_work_day(now) && now.time() >= self.work_start && now.time() < self.work_end
+    }
+
+    pub fn is_due(
+        &self,
+        now: NaiveDateTime,
+        last_entry: Option<NaiveDateTime>,
+        last_reminder: Option<NaiveDateTime>,
+    ) -> bool {
+        let after = match self.after {
+            Some(a) => a,
+            None => return false,
+        };
+        if !self.is_working_time(now) {
+            return false;
+        }
+        // don't count the time before the working day started
+        let since = [
+            Some(now.date().and_time(self.work_start)),
+            last_entry,
+            last_reminder,
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap();
+        now.signed_duration_since(since) >= after
+    }
+
+    // `stretch` is the time worked without a break
+    pub fn is_break_due(
+        &self,
+        now: NaiveDateTime,
+        stretch: Duration,
+        last_reminder: Option<NaiveDateTime>,
+    ) -> bool {
+        let break_after = match self.break_after {
+            Some(b) => b,
+            None => return false,
+        };
+        // the reminder is still from the current stretch
+        let recently_reminded = last_reminder.is_some_and(|r| {
+            now.signed_duration_since(r) < stretch
+                && now.signed_duration_since(r) < Duration::minutes(BREAK_REPEAT_MINUTES)
+        });
+        stretch >= break_after && !recently_reminded
+    }
+
+    // whether one of the progress notification times is in (last_check, now]
+    pub fn is_progress_due(&self, now: NaiveDateTime, last_check: NaiveDateTime) -> bool {
+        self.is_work_day(now)
+            && self.progress_at.iter().any(|t| {
+                let at = now.date().and_time(*t);
+                last_check < at && at <= now
+            })
+    }
+
+    // check for due reminders in a background thread
+    pub fn start(self, timelog: SharedTimelog) {
+        thread::spawn(move || self.run(timelog));
+    }
+
+    // check for due reminders forever; picked actions get logged right away
+    pub fn run(self, timelog: SharedTimelog) {
+        let mut last_reminder = None;
+        let mut last_break_reminder = None;
+        let mut last_check = Local::now().naive_local();
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            let now = Local::now().naive_local();
+
+            // pick up entries from other rtimelog or gtimelog instances
+            timelog.write().reload_if_changed();
+
+            let (last_entry, left, stretch, progress, actions) = {
+                let tl = timelog.read();
+                let today = tl.get_today(now, &self.sentinels);
+                let last_entry = today.last().map(|e| e.stop);
+                (
+                    last_entry,
+                    today
+                        .last()
+                        .is_some_and(|e| self.sentinels.is_departure(&e.task)),
+                    activity::continuous_work(today, now, &self.sentinels),
+                    progress_message(today, self.hours, now, &self.sentinels),
+                    recent_tasks(
+                        tl.get_n_days(
+                            &self.sentinels.day_of(now),
+                            7,
+                            self.sentinels.virtual_midnight,
+                        ),
+                        self.actions,
+                    ),
+                )
+            };
+
+            if self.is_progress_due(now, last_check) {
+                notify("Daily progress", &progress, &[]);
+            }
+            last_check = now;
+
+            if self.is_break_due(now, stretch, last_break_reminder) {
+                last_break_reminder = Some(now);
+                let body = format!(
+                    "You have been working for {} h {} min without a break.",
+                    stretch.num_hours(),
+                    stretch.num_minutes() % 60
+                );
+                notify("Time for a break", &body, &[]);
+            }
+
+            // nothing to log after leaving
+            if left || !self.is_due(now, last_entry, last_reminder) {
+                continue;
+            }
+            last_reminder = Some(now);
+
+            let body = match last_entry {
+                Some(stop) => format!(
+                    "No entry for {} min. What did you just finish?",
+                    now.signed_duration_since(stop).num_minutes()
+                ),
+                None => "Nothing logged yet today.".to_string(),
+            };
+            if let Some(task) = notify("Time to log your work", &body, &actions) {
+                let mut tl = timelog.write();
+                if let Err(e) = tl.add_snapped(task, Local::now().naive_local(), &self.snap) {
+                    eprintln!("Failed to log: {e}");
+                } else if let Err(e) = tl.save() {
+                    eprintln!("Failed to save timelog: {e}");
+                }
+            }
+        }
+    }
+}
+
+// work done today (counting the running interval as work), and when the target will be reached
+pub fn progress_message(
+    today: &[Entry],
+    target: Duration,
+    now: NaiveDateTime,
+    sentinels: &Sentinels,
+) -> String {
+    let done = match today.last() {
+        Some(last) if sentinels.is_departure(&last.task) => {
+            Activities::new_from_entries(today, sentinels).total_work()
+        }
+        Some(last) => {
+            Activities::new_from_entries(today, sentinels).total_work()
+                + now.signed_duration_since(last.stop)
+        }
+        None => Duration::zero(),
+    };
+    let mut message = format!(
+        "{} h {} min of {} h {} min done",
+        done.num_hours(),
+        done.num_minutes() % 60,
+        target.num_hours(),
+        target.num_minutes() % 60
+    );
+    if target > Duration::zero() {
+        message.push_str(&format!(
+            " ({}%)",
+            done.num_minutes() * 100 / target.num_minutes().max(1)
+        ));
+    }
+
+    let remaining = target - done;
+    if remaining > Duration::zero() {
+        message.push_str(&format!(
+            ". Projected leave time: {}",
+            (now + remaining).format("%H:%M")
+        ));
+    } else {
+        let over = -remaining;
+        message.push_str(&format!(
+            ". Target reached, {} h {} min overtime",
+            over.num_hours(),
+            over.num_minutes() % 60
+        ));
+    }
+    message
+}
+
+// distinct tasks, most recently used first
+fn recent_tasks(entries: &[Entry], n: usize) -> Vec<String> {
+    let mut tasks: Vec<String> = Vec::new();
+    for e in entries.iter().rev() {
+        if tasks.len() >= n {
+            break;
+        }
+        if !tasks.iter().any(|t| **t == *e.task) {
+            tasks.push(e.task.to_string());
+        }
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    // 2022-06-10 is a Friday
+    fn time(day: u32, h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 6, day)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn reminder() -> Reminder {
+        Reminder::new_from_config(&Config::new_from_string("[rtimelog]\nremind_after = 30m\n"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(Reminder::new_from_config(&Config::default()).is_none());
+    }
+
+    #[test]
+    fn test_working_time() {
+        let r = reminder();
+        assert!(r.is_working_time(time(10, 9, 0)));
+        assert!(r.is_working_time(time(10, 16, 59)));
+        assert!(!r.is_working_time(time(10, 8, 59)));
+        assert!(!r.is_working_time(time(10, 17, 0)));
+        // Saturday
+        assert!(!r.is_working_time(time(11, 10, 0)));
+        let r = Reminder::new_from_config(&Config::new_from_string(
+            "[rtimelog]\nremind_after = 30m\nholidays = 2022-06-10\n",
+        ))
+        .unwrap();
+        assert!(!r.is_working_time(time(10, 10, 0)));
+        assert!(r.is_working_time(time(13, 10, 0)));
+    }
+
+    #[test]
+    fn test_is_due() {
+        let r = reminder();
+        // nothing logged yet, but the day has just started
+        assert!(!r.is_due(time(10, 9, 10), None, None));
+        assert!(r.is_due(time(10, 9, 30), None, None));
+        // recent entry
+        assert!(!r.is_due(time(10, 11, 0), Some(time(10, 10, 45)), None));
+        assert!(r.is_due(time(10, 11, 15), Some(time(10, 10, 45)), None));
+        // entry from yesterday evening does not count
+        assert!(r.is_due(time(10, 9, 30), Some(time(9, 18, 0)), None));
+        // don't nag again right away
+        assert!(!r.is_due(
+            time(10, 11, 20),
+            Some(time(10, 10, 45)),
+            Some(time(10, 11, 15))
+        ));
+        assert!(r.is_due(
+            time(10, 11, 45),
+            Some(time(10, 10, 45)),
+            Some(time(10, 11, 15))
+        ));
+        // outside of working hours
+        assert!(!r.is_due(time(10, 20, 0), Some(time(10, 10, 45)), None));
+    }
+
+    #[test]
+    fn test_is_break_due() {
+        let r =
+            Reminder::new_from_config(&Config::new_from_string("[rtimelog]\nbreak_after = 2h\n"))
+                .unwrap();
+        // log reminders are off
+        assert!(!r.is_due(time(10, 11, 0), None, None));
+
+        assert!(!r.is_break_due(time(10, 11, 0), Duration::minutes(119), None));
+        assert!(r.is_break_due(time(10, 11, 0), Duration::hours(2), None));
+        // just reminded
+        assert!(!r.is_break_due(
+            time(10, 11, 10),
+            Duration::minutes(130),
+            Some(time(10, 11, 0))
+        ));
+        // still no break half an hour later
+        assert!(r.is_break_due(
+            time(10, 11, 30),
+            Duration::minutes(150),
+            Some(time(10, 11, 0))
+        ));
+        // reminder was from an earlier stretch
+        assert!(r.is_break_due(time(10, 14, 0), Duration::hours(2), Some(time(10, 11, 50))));
+        // also outside of working hours
+        assert!(r.is_break_due(time(10, 20, 0), Duration::hours(3), None));
+
+        assert!(!reminder().is_break_due(time(10, 11, 0), Duration::hours(5), None));
+    }
+
+    #[test]
+    fn test_is_progress_due() {
+        let r = Reminder::new_from_config(&Config::new_from_string(
+            "[rtimelog]\nprogress_at = 12:00, 16:00\n",
+        ))
+        .unwrap();
+        assert!(!r.is_progress_due(time(10, 11, 0), time(10, 10, 59)));
+        assert!(r.is_progress_due(time(10, 12, 0), time(10, 11, 59)));
+        assert!(r.is_progress_due(time(10, 16, 0), time(10, 15, 59)));
+        // missed it while suspended
+        assert!(r.is_progress_due(time(10, 16, 30), time(10, 15, 0)));
+        assert!(!r.is_progress_due(time(10, 16, 1), time(10, 16, 0)));
+        // Saturday
+        assert!(!r.is_progress_due(time(11, 12, 0), time(11, 11, 59)));
+    }
+
+    #[test]
+    fn test_progress_message() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 08:00: arrived
+2022-06-10 12:00: code
+2022-06-10 12:30: ** lunch
+2022-06-10 14:00: code
+",
+        );
+        let entries = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            1,
+            NaiveTime::MIN,
+        );
+        let s = Sentinels::default();
+        assert_eq!(
+            progress_message(entries, Duration::hours(8), time(10, 16, 0), &s),
+            "7 h 30 min of 8 h 0 min done (93%). Projected leave time: 16:30"
+        );
+        assert_eq!(
+            progress_message(entries, Duration::hours(7), time(10, 16, 0), &s),
+            "7 h 30 min of 7 h 0 min done (107%). Target reached, 0 h 30 min overtime"
+        );
+        assert_eq!(
+            progress_message(&[], Duration::hours(8), time(10, 8, 0), &s),
+            "0 h 0 min of 8 h 0 min done (0%). Projected leave time: 16:00"
+        );
+
+        // the time after leaving does not count
+        let s = Sentinels {
+            departure: vec!["code".to_string()],
+            ..Sentinels::default()
+        };
+        assert_eq!(
+            progress_message(entries, Duration::hours(8), time(10, 16, 0), &s),
+            "5 h 30 min of 8 h 0 min done (68%). Projected leave time: 18:30"
+        );
+    }
+
+    #[test]
+    fn test_recent_tasks() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+2022-06-10 12:05: gtimelog: code
+2022-06-10 12:35: customer joe: inquiry
+",
+        );
+        let entries = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            1,
+            NaiveTime::MIN,
+        );
+        assert_eq!(
+            recent_tasks(entries, 3),
+            vec!["customer joe: inquiry", "gtimelog: code", "** tea"]
+        );
+        assert_eq!(recent_tasks(entries, 0), Vec::<String>::new());
+        assert_eq!(recent_tasks(&[], 3), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,2538 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime};
+
+use crate::activity::{self, duration_in_words, is_slack, project, tags, Activities};
+use crate::billing::Earnings;
+use crate::budget::BudgetUse;
+use crate::config::{Absence, Config};
+use crate::estimate::Estimate;
+use crate::store::Entry;
+use crate::table::{Cell, Table};
+
+// width of the longest month bar
+const BAR_WIDTH: i64 = 30;
+
+fn hours_min(d: Duration) -> String {
+    format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+}
+
+// like "6h 15m", "35m", or "2h", where space is short, e.g. in charts and lists of days
+pub(crate) fn short(d: Duration) -> String {
+    match (d.num_hours(), d.num_minutes() % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+// like "+1h 20m" or "-35m", for differences to the target
+pub(crate) fn signed(d: Duration, plain: bool) -> String {
+    signed_with(d, if plain { duration_in_words } else { short })
+}
+
+// like signed(), with another format of the duration
+fn signed_with(d: Duration, duration: fn(Duration) -> String) -> String {
+    match d < Duration::zero() {
+        true => format!("-{}", duration(-d)),
+        false => format!("+{}", duration(d)),
+    }
+}
+
+/**
+ * Annual summary: totals, projects, and how the work was spread over the year
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct YearReview {
+    pub year: i32,
+    pub total_work: Duration,
+    pub total_slack: Duration,
+    // (project, work), most work first; see activity::project()
+    pub projects: Vec<(String, Duration)>,
+    // (tag, work), most work first; see activity::tags()
+    pub tags: Vec<(String, Duration)>,
+    // days with any work
+    pub days: usize,
+    // (first day, work) of the week with the most work, see Config::week_start
+    pub busiest_week: Option<(NaiveDate, Duration)>,
+    // (day, work) of the day with the most work
+    pub longest_day: Option<(NaiveDate, Duration)>,
+    // working days (see `work_days`) without work between the first and the last day with work,
+    // like holidays which are not in the `holidays` setting; without the `vacation` and `sick` days
+    pub days_off: Vec<NaiveDate>,
+    // the days of vacation and sick leave, see Config::absence()
+    pub vacation: Vec<NaiveDate>,
+    pub sick: Vec<NaiveDate>,
+    // work logged on these days anyway, which is not part of the `days` with work
+    pub absent_work: Duration,
+    // (first, last day) of the longest run of working days (see `work_days`) with entries
+    pub longest_streak: Option<(NaiveDate, NaiveDate)>,
+    pub streak_days: usize,
+    pub months: [Duration; 12],
+}
+
+impl YearReview {
+    // time range of `year`, for loading its entries; like PeriodReport::range(), it ends at the
+    // virtual midnight
+    pub fn range(year: i32, config: &Config) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        PeriodReport::range(
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year, 12, 31)?,
+            config,
+        )
+    }
+
+    pub fn new(entries: &[Entry], year: i32, config: &Config) -> YearReview {
+        let entries: Vec<Entry> = entries
+            .iter()
+            .filter(|e| config.sentinels.day_of(e.stop).year() == year)
+            .cloned()
+            .collect();
+        let a = Activities::new_for_report(&entries, config);
+
+        let mut review = YearReview {
+            year,
+            total_work: a.total_work(),
+            total_slack: a.total_slack(),
+            projects: project_totals(&a),
+            tags: tag_totals(&a),
+            days: 0,
+            busiest_week: None,
+            longest_day: None,
+            days_off: Vec::new(),
+            vacation: Vec::new(),
+            sick: Vec::new(),
+            absent_work: Duration::zero(),
+            longest_streak: None,
+            streak_days: 0,
+            months: [Duration::minutes(0); 12],
+        };
+
+        let mut weeks: Vec<(NaiveDate, Duration)> = Vec::new();
+        let mut worked: Vec<NaiveDate> = Vec::new();
+        let mut streak: Option<(NaiveDate, NaiveDate, usize)> = None;
+        for (day, absence) in activity::absences(&entries, config) {
+            match absence {
+                Absence::Vacation => review.vacation.push(day),
+                Absence::Sick => review.sick.push(day),
+            }
+        }
+        let absent = |day: &NaiveDate| review.vacation.contains(day) || review.sick.contains(day);
+        let day_of = |e: &Entry| config.sentinels.day_of(e.stop);
+        for day in entries.chunk_by(|a, b| day_of(a) == day_of(b)) {
+            let date = day_of(&day[0]);
+            let work = Activities::new_for_report(day, config).total_work();
+            if work <= Duration::minutes(0) {
+                continue;
+            }
+            review.months[date.month0() as usize] += work;
+            if absent(&date) {
+                review.absent_work += work;
+                continue;
+            }
+            review.days += 1;
+            worked.push(date);
+            // the first one of the longest days
+            if review.longest_day.is_none_or(|(_, d)| work > d) {
+                review.longest_day = Some((date, work));
+            }
+
+            let week = date.week(config.week_start).first_day();
+            match weeks.last_mut() {
+                Some((w, sum)) if *w == week => *sum += work,
+                _ => weeks.push((week, work)),
+            }
+
+            streak = match streak {
+                Some((first, last, n)) if next_work_day(last, config) == date => {
+                    Some((first, date, n + 1))
+                }
+                _ => Some((date, date, 1)),
+            };
+            if let Some((first, last, n)) = streak {
+                if n > review.streak_days {
+                    review.streak_days = n;
+                    review.longest_streak = Some((first, last));
+                }
+            }
+        }
+        // the first one of the busiest weeks
+        review.busiest_week = weeks
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, d)| *d)
+            .filter(|(_, d)| *d > Duration::minutes(0));
+        if let (Some(first), Some(last)) = (worked.first(), worked.last()) {
+            review.days_off = first
+                .iter_days()
+                .take_while(|d| d <= last)
+                .filter(|d| config.is_work_day(*d) && !worked.contains(d) && !absent(d))
+                .collect();
+        }
+        review
+    }
+
+    pub fn average_day(&self) -> Duration {
+        match self.days {
+            0 => Duration::minutes(0),
+            n => (self.total_work - self.absent_work) / n as i32,
+        }
+    }
+
+    // the projects, tags, months, the longest day, and the days off, for the other output
+    // formats; days in ISO format
+    pub fn table(&self) -> Table {
+        let mut t = Table::new(&["section", "name", "minutes"]);
+        for (section, totals) in [("project", &self.projects), ("tag", &self.tags)] {
+            for (name, d) in totals {
+                t.push(vec![Cell::text(section), Cell::text(name), Cell::Time(*d)]);
+            }
+        }
+        for (i, d) in self.months.iter().enumerate() {
+            let month = format!("{}-{:02}", self.year, i + 1);
+            t.push(vec![Cell::text("month"), Cell::Text(month), Cell::Time(*d)]);
+        }
+        let iso = |d: &NaiveDate| Cell::Text(d.format("%F").to_string());
+        if let Some((day, d)) = &self.longest_day {
+            t.push(vec![Cell::text("longest day"), iso(day), Cell::Time(*d)]);
+        }
+        for (section, days) in [
+            ("day off", &self.days_off),
+            ("vacation", &self.vacation),
+            ("sick", &self.sick),
+        ] {
+            for day in days {
+                t.push(vec![
+                    Cell::text(section),
+                    iso(day),
+                    Cell::Time(Duration::zero()),
+                ]);
+            }
+        }
+        t
+    }
+
+    fn percent(&self, d: Duration) -> i64 {
+        d.num_minutes() * 100 / self.total_work.num_minutes().max(1)
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = |d: Duration| {
+            if plain {
+                duration_in_words(d)
+            } else {
+                hours_min(d)
+            }
+        };
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        let mut out = String::new();
+
+        match (&config.name, &config.email) {
+            (Some(name), Some(email)) => {
+                writeln!(out, "Year in review {} of {name} <{email}>\n", self.year)
+            }
+            (Some(name), None) => writeln!(out, "Year in review {} of {name}\n", self.year),
+            _ => writeln!(out, "Year in review {}\n", self.year),
+        }
+        .unwrap();
+        writeln!(out, "Total work done: {}", duration(self.total_work)).unwrap();
+        writeln!(out, "Total slacking: {}", duration(self.total_slack)).unwrap();
+        if self.days == 0 {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        writeln!(
+            out,
+            "Days with work: {}, on average {}",
+            self.days,
+            duration(self.average_day())
+        )
+        .unwrap();
+        if let Some((first, work)) = &self.busiest_week {
+            writeln!(
+                out,
+                "Busiest week: from {} with {}",
+                date(first),
+                duration(*work)
+            )
+            .unwrap();
+        }
+        if let Some((day, work)) = &self.longest_day {
+            writeln!(out, "Longest day: {} with {}", date(day), duration(*work)).unwrap();
+        }
+        if let Some((first, last)) = &self.longest_streak {
+            writeln!(
+                out,
+                "Longest streak: {} working days, {} to {}",
+                self.streak_days,
+                date(first),
+                date(last)
+            )
+            .unwrap();
+        }
+        let days_off = match self.days_off.len() {
+            1 => "1 working day".to_string(),
+            n => format!("{n} working days"),
+        };
+        writeln!(out, "Days off: {days_off} without work").unwrap();
+        match (self.vacation.len(), config.vacation_days) {
+            (0, None) => (),
+            (n, None) => writeln!(out, "Vacation: {n} days").unwrap(),
+            (n, Some(allowance)) => writeln!(
+                out,
+                "Vacation: {n} of {allowance} days, {} left",
+                i64::from(allowance) - n as i64
+            )
+            .unwrap(),
+        }
+        if !self.sick.is_empty() {
+            writeln!(out, "Sick days: {}", self.sick.len()).unwrap();
+        }
+
+        for (heading, totals) in [("Projects", &self.projects), ("Tags", &self.tags)] {
+            // most logs have no tags
+            if totals.is_empty() && heading == "Tags" {
+                continue;
+            }
+            writeln!(out, "\n{heading}:").unwrap();
+            for (name, d) in totals {
+                if plain {
+                    writeln!(
+                        out,
+                        "{name}: {}, {} percent",
+                        duration(*d),
+                        self.percent(*d)
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "{:>4} h {:>2} min ({:>3}%): {name}",
+                        d.num_hours(),
+                        d.num_minutes() % 60,
+                        self.percent(*d)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        writeln!(out, "\nMonths:").unwrap();
+        let max = self.months.iter().max().unwrap().num_minutes().max(1);
+        for (i, d) in self.months.iter().enumerate() {
+            let name = config.date_format.format_with(
+                &NaiveDate::from_ymd_opt(self.year, i as u32 + 1, 1).unwrap(),
+                "%B",
+            );
+            if plain {
+                writeln!(out, "{name}: {}", duration(*d)).unwrap();
+            } else {
+                let bar = "#".repeat((d.num_minutes() * BAR_WIDTH / max) as usize);
+                let line = format!(
+                    "{name:<10} {:>4} h {:>2} min {bar}",
+                    d.num_hours(),
+                    d.num_minutes() % 60
+                );
+                writeln!(out, "{}", line.trim_end()).unwrap();
+            }
+        }
+        out
+    }
+}
+
+// (project, work) of the activities, most work first; see activity::project()
+fn project_totals(a: &Activities) -> Vec<(String, Duration)> {
+    let mut projects: Vec<(String, Duration)> = Vec::new();
+    for (task, d) in a.iter().filter(|(task, _)| !is_slack(task)) {
+        match projects.iter_mut().find(|(p, _)| p == project(task)) {
+            Some((_, sum)) => *sum += d,
+            None => projects.push((project(task).to_string(), d)),
+        }
+    }
+    // stable, so that projects with the same time stay in the order of their first entry
+    projects.sort_by_key(|(_, d)| -*d);
+    projects
+}
+
+// same for the #tags; a task with several tags counts for each of them
+fn tag_totals(a: &Activities) -> Vec<(String, Duration)> {
+    let mut totals: Vec<(String, Duration)> = Vec::new();
+    for (task, d) in a.iter().filter(|(task, _)| !is_slack(task)) {
+        for tag in tags(task) {
+            match totals.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, sum)) => *sum += d,
+                None => totals.push((tag.to_string(), d)),
+            }
+        }
+    }
+    totals.sort_by_key(|(_, d)| -*d);
+    totals
+}
+
+/**
+ * Work of one team member in a TeamReport
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct PersonWork {
+    pub name: String,
+    pub work: Duration,
+    // (project, work), most work first
+    pub projects: Vec<(String, Duration)>,
+}
+
+/**
+ * Work of one category in gtimelog's "Entries by category", see PeriodReport::categories(), or
+ * of one client, see PeriodReport::clients()
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Category {
+    pub name: String,
+    pub work: Duration,
+    // (detail, work), by name
+    pub details: Vec<(String, Duration)>,
+}
+
+/**
+ * Weekly report over the timelogs of several people
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct TeamReport {
+    // first day of the week, see Config::week_start
+    pub first: NaiveDate,
+    pub total_work: Duration,
+    // (project, work) of everybody together, most work first
+    pub projects: Vec<(String, Duration)>,
+    // in the order of the logs
+    pub people: Vec<PersonWork>,
+}
+
+impl TeamReport {
+    // time range of the week starting at `first`, for loading its entries; it ends at the
+    // virtual midnight, like PeriodReport::range()
+    pub fn range(first: NaiveDate, config: &Config) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        PeriodReport::range(first, first + Duration::days(6), config)
+    }
+
+    // `logs` are (name, entries of the week) of each person
+    pub fn new(logs: &[(String, &[Entry])], first: NaiveDate, config: &Config) -> TeamReport {
+        let mut report = TeamReport {
+            first,
+            total_work: Duration::minutes(0),
+            projects: Vec::new(),
+            people: Vec::new(),
+        };
+        for (name, entries) in logs {
+            let a = Activities::new_for_report(entries, config);
+            let projects = project_totals(&a);
+            for (p, d) in &projects {
+                match report.projects.iter_mut().find(|(q, _)| q == p) {
+                    Some((_, sum)) => *sum += *d,
+                    None => report.projects.push((p.clone(), *d)),
+                }
+            }
+            report.total_work += a.total_work();
+            report.people.push(PersonWork {
+                name: name.clone(),
+                work: a.total_work(),
+                projects,
+            });
+        }
+        report.projects.sort_by_key(|(_, d)| -*d);
+        report
+    }
+
+    // the projects of each person, for the other output formats
+    pub fn table(&self) -> Table {
+        let mut t = Table::new(&["person", "project", "minutes"]);
+        for person in &self.people {
+            for (project, d) in &person.projects {
+                t.push(vec![
+                    Cell::text(&person.name),
+                    Cell::text(project),
+                    Cell::Time(*d),
+                ]);
+            }
+        }
+        t
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = |d: Duration| {
+            if plain {
+                duration_in_words(d)
+            } else {
+                hours_min(d)
+            }
+        };
+        let percent = |d: Duration| d.num_minutes() * 100 / self.total_work.num_minutes().max(1);
+        let mut out = String::new();
+
+        writeln!(
+            out,
+            "Team report for the week from {}\n",
+            config.date_format.format(&self.first)
+        )
+        .unwrap();
+        writeln!(out, "Total work done: {}", duration(self.total_work)).unwrap();
+
+        writeln!(out, "\nProjects:").unwrap();
+        for (name, d) in &self.projects {
+            if plain {
+                writeln!(out, "{name}: {}, {} percent", duration(*d), percent(*d)).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>4} h {:>2} min ({:>3}%): {name}",
+                    d.num_hours(),
+                    d.num_minutes() % 60,
+                    percent(*d)
+                )
+                .unwrap();
+            }
+        }
+
+        for person in &self.people {
+            writeln!(out, "\n{}: {}", person.name, duration(person.work)).unwrap();
+            for (name, d) in &person.projects {
+                if plain {
+                    writeln!(out, "{name}: {}", duration(*d)).unwrap();
+                } else {
+                    writeln!(
+                        out,
+                        "{:>4} h {:>2} min: {name}",
+                        d.num_hours(),
+                        d.num_minutes() % 60
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        out
+    }
+}
+
+/**
+ * Rows of a PeriodReport of any days, see PeriodReport::format_grouped()
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Task,
+    Day,
+    // see Config::week_start
+    Week,
+    // the ": " separated parts of the tasks, up to `depth` levels, see PeriodReport::hierarchy()
+    Project { depth: usize },
+    // each day with its first and last entry, see PeriodReport::presence
+    Presence,
+    // everything before the last ": " of the tasks, like gtimelog's categorized view, see
+    // PeriodReport::categories()
+    Category,
+    // the clients of projects.toml, see PeriodReport::clients()
+    Client,
+}
+
+/**
+ * When somebody was at work on a day: from the first to the last entry, with the work in between
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Presence {
+    pub day: NaiveDate,
+    // None without entries
+    pub first: Option<NaiveDateTime>,
+    pub last: Option<NaiveDateTime>,
+    pub work: Duration,
+    pub slack: Duration,
+    // work minus the target of the day (see Config::target()), negative if short of it
+    pub gap: Duration,
+}
+
+/**
+ * Time spent on each task over some days, with slacking kept apart, like gtimelog's reports
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct PeriodReport {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    pub total_work: Duration,
+    pub total_slack: Duration,
+    // (task, work), in the order of their first entry
+    pub work: Vec<(String, Duration)>,
+    // same for the "**" tasks
+    pub slack: Vec<(String, Duration)>,
+    // (day, work) of each day from `first` to `last`
+    pub days: Vec<(NaiveDate, Duration)>,
+    // (project, work), most work first; see activity::project()
+    pub projects: Vec<(String, Duration)>,
+    // of each day from `first` to `last`
+    pub presence: Vec<Presence>,
+    // with the [rates], for the weekly and monthly reports
+    pub earnings: Earnings,
+    // the days of vacation or sick leave, which have no target
+    pub absences: Vec<(NaiveDate, Absence)>,
+    // of the [budgets], for the monthly report
+    pub budgets: Vec<BudgetUse>,
+    // of the tasks with an "[est:...]" marker, for the weekly report
+    pub estimates: Vec<Estimate>,
+}
+
+impl PeriodReport {
+    // time range of the days from `first` to `last`, for loading their entries; like the days of
+    // Timelog::get_n_days(), they end at the virtual midnight
+    pub fn range(
+        first: NaiveDate,
+        last: NaiveDate,
+        config: &Config,
+    ) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let midnight = config.sentinels.virtual_midnight;
+        Some((
+            first.and_time(midnight),
+            last.succ_opt()?.and_time(midnight) - Duration::seconds(1),
+        ))
+    }
+
+    // (first, last) day of the week with `day`, which starts at `week_start`
+    pub fn week(day: NaiveDate, config: &Config) -> (NaiveDate, NaiveDate) {
+        let first = day.week(config.week_start).first_day();
+        (first, first + Duration::days(6))
+    }
+
+    pub fn new(
+        entries: &[Entry],
+        first: NaiveDate,
+        last: NaiveDate,
+        config: &Config,
+    ) -> PeriodReport {
+        let day_of = |e: &Entry| config.sentinels.day_of(e.stop);
+        let begin = entries.partition_point(|e| day_of(e) < first);
+        let end = entries.partition_point(|e| day_of(e) <= last);
+        let entries = &entries[begin..end.max(begin)];
+        let a = Activities::new_for_report(entries, config);
+        let (slack, work) = a
+            .iter()
+            .map(|(task, d)| (task.to_string(), d))
+            .partition(|(task, _)| is_slack(task));
+        let mut days: Vec<(NaiveDate, Duration)> = first
+            .iter_days()
+            .take_while(|d| *d <= last)
+            .map(|d| (d, Duration::minutes(0)))
+            .collect();
+        let absences = activity::absences(entries, config);
+        let mut presence: Vec<Presence> = days
+            .iter()
+            .map(|(day, _)| Presence {
+                day: *day,
+                first: None,
+                last: None,
+                work: Duration::minutes(0),
+                slack: Duration::minutes(0),
+                gap: match absences.iter().any(|(d, _)| d == day) {
+                    true => Duration::zero(),
+                    false => -config.day_target(*day),
+                },
+            })
+            .collect();
+        for day in entries.chunk_by(|a, b| day_of(a) == day_of(b)) {
+            let i = (day_of(&day[0]) - first).num_days() as usize;
+            let a = Activities::new_for_report(day, config);
+            days[i].1 = a.total_work();
+            let p = &mut presence[i];
+            p.first = Some(day[0].stop);
+            p.last = day.last().map(|e| e.stop);
+            p.work = a.total_work();
+            p.slack = a.total_slack();
+            p.gap += a.total_work();
+        }
+        PeriodReport {
+            first,
+            last,
+            total_work: a.total_work(),
+            total_slack: a.total_slack(),
+            slack,
+            days,
+            projects: project_totals(&a),
+            presence,
+            earnings: Earnings::new(entries, config),
+            absences,
+            budgets: BudgetUse::for_entries(entries, config),
+            estimates: Estimate::for_tasks(&work),
+            work,
+        }
+    }
+
+    // (level, path, work) of the ": " separated parts of the tasks, like "rtimelog" (level 0)
+    // with the work of all its tasks, then "rtimelog: code" (level 1) and so on, up to `depth`
+    // levels; each level has the most work first, and is followed by its parts
+    pub fn hierarchy(&self, depth: usize) -> Vec<(usize, String, Duration)> {
+        let tasks: Vec<(Vec<&str>, Duration)> = self
+            .work
+            .iter()
+            .map(|(task, d)| (task.split(": ").map(str::trim).collect(), *d))
+            .collect();
+        let mut rows = Vec::new();
+        add_level(&mut rows, &tasks, 0, depth);
+        rows
+    }
+
+    // gtimelog's "Entries by category": the category is everything before the last ": " of a
+    // task, the detail the rest; tasks without one are in "(none)" at the end. Like gtimelog,
+    // categories and details go alphabetically.
+    pub fn categories(&self) -> Vec<Category> {
+        let mut categories: BTreeMap<&str, Vec<(String, Duration)>> = BTreeMap::new();
+        let mut none = Vec::new();
+        for (task, d) in &self.work {
+            let (details, detail) = match task.rsplit_once(": ") {
+                Some((category, detail)) => (
+                    categories.entry(category.trim()).or_default(),
+                    detail.trim(),
+                ),
+                None => (&mut none, task.as_str()),
+            };
+            match details.iter_mut().find(|(t, _)| t == detail) {
+                Some((_, sum)) => *sum += *d,
+                None => details.push((detail.to_string(), *d)),
+            }
+        }
+        let none = (!none.is_empty()).then_some(("(none)", none));
+        categories
+            .into_iter()
+            .chain(none)
+            .map(|(name, mut details)| {
+                details.sort();
+                Category {
+                    name: name.to_string(),
+                    work: details.iter().map(|(_, d)| *d).sum(),
+                    details,
+                }
+            })
+            .collect()
+    }
+
+    // the work per client of the projects.toml registry, most work first, with the work per
+    // project prefix of the registry as details; tasks of no client are in "(none)" at the end,
+    // by their project
+    pub fn clients(&self, config: &Config) -> Vec<Category> {
+        let mut clients: Vec<Category> = Vec::new();
+        let mut none = Vec::new();
+        for (task, d) in &self.work {
+            let (details, detail) = match config.client_for(task) {
+                Some(p) => {
+                    let i = match clients.iter().position(|c| c.name == p.client) {
+                        Some(i) => i,
+                        None => {
+                            clients.push(Category {
+                                name: p.client.clone(),
+                                work: Duration::zero(),
+                                details: Vec::new(),
+                            });
+                            clients.len() - 1
+                        }
+                    };
+                    (&mut clients[i].details, p.prefix.as_str())
+                }
+                None => (&mut none, project(task)),
+            };
+            match details.iter_mut().find(|(t, _)| t == detail) {
+                Some((_, sum)) => *sum += *d,
+                None => details.push((detail.to_string(), *d)),
+            }
+        }
+        if !none.is_empty() {
+            clients.push(Category {
+                name: "(none)".to_string(),
+                work: Duration::zero(),
+                details: none,
+            });
+        }
+        for client in &mut clients {
+            client
+                .details
+                .sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            client.work = client.details.iter().map(|(_, d)| *d).sum();
+        }
+        clients.sort_by(|a, b| {
+            let none = |c: &Category| c.name == "(none)";
+            (none(a), b.work, &a.name).cmp(&(none(b), a.work, &b.name))
+        });
+        clients
+    }
+
+    // (first, last) day of the month with `day`
+    pub fn month(day: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let first = day.with_day(1).unwrap();
+        let next = first
+            .checked_add_months(Months::new(1))
+            .unwrap_or(NaiveDate::MAX);
+        (first, next.pred_opt().unwrap_or(next))
+    }
+
+    // (first, last) day of the period before the one from `first` to `last`: the month before a
+    // whole month, otherwise as many days just before
+    pub fn previous(first: NaiveDate, last: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let before = first.pred_opt()?;
+        if PeriodReport::month(first) == (first, last) {
+            return Some(PeriodReport::month(before));
+        }
+        Some((before - last.signed_duration_since(first), before))
+    }
+
+    // (project, work, work in `previous`) of both reports; this report's projects first, then
+    // the ones which only `previous` has
+    pub fn compare(&self, previous: &PeriodReport) -> Vec<(String, Duration, Duration)> {
+        let work = |projects: &[(String, Duration)], name: &str| {
+            projects
+                .iter()
+                .find(|(p, _)| p == name)
+                .map_or(Duration::zero(), |(_, d)| *d)
+        };
+        let mut rows: Vec<(String, Duration, Duration)> = self
+            .projects
+            .iter()
+            .map(|(name, d)| (name.clone(), *d, work(&previous.projects, name)))
+            .collect();
+        for (name, d) in &previous.projects {
+            if !rows.iter().any(|(p, _, _)| p == name) {
+                rows.push((name.clone(), Duration::zero(), *d));
+            }
+        }
+        rows
+    }
+
+    // the work per project next to its change since `previous`, for report --compare
+    pub fn format_comparison(&self, previous: &PeriodReport, config: &Config) -> String {
+        let plain = config.plain;
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        let mut out = String::new();
+        match previous.first == previous.last {
+            true => writeln!(out, "Compared to {}:", date(&previous.first)),
+            false => writeln!(
+                out,
+                "Compared to {} to {}:",
+                date(&previous.first),
+                date(&previous.last)
+            ),
+        }
+        .unwrap();
+        let rows = self.compare(previous);
+        if rows.is_empty() {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        let percent = |now: Duration, before: Duration| match before.num_minutes() {
+            0 => "new".to_string(),
+            b => format!("{:+}%", (now - before).num_minutes() * 100 / b),
+        };
+        let line = |name: &str, now: Duration, before: Duration| {
+            let change = signed(now - before, plain);
+            let percent = percent(now, before);
+            match plain {
+                true => format!(
+                    "{name}: {}, {change}, {}",
+                    duration_in_words(now),
+                    percent.replace('%', " percent")
+                ),
+                false => format!(
+                    "{:>4} h {:>2} min {change:>8} {percent:>6}: {name}",
+                    now.num_hours(),
+                    now.num_minutes() % 60
+                ),
+            }
+        };
+        for (name, now, before) in &rows {
+            writeln!(out, "{}", line(name, *now, *before)).unwrap();
+        }
+        writeln!(
+            out,
+            "\n{}",
+            line("total work", self.total_work, previous.total_work)
+        )
+        .unwrap();
+        out
+    }
+
+    // share of all work, rounded to whole percent
+    fn percent(&self, d: Duration) -> i64 {
+        let total = self.total_work.num_minutes().max(1);
+        (d.num_minutes() * 200 + total) / (2 * total)
+    }
+
+    // the `count` tasks (or projects) with the most work
+    pub fn top(&self, count: usize, projects: bool) -> Vec<(String, Duration)> {
+        let mut rows = match projects {
+            true => self.projects.clone(),
+            false => self.work.clone(),
+        };
+        // stable, so equal ones stay in the order of their first entry
+        rows.sort_by_key(|(_, d)| -*d);
+        rows.truncate(count);
+        rows
+    }
+
+    // top() with the share of all work
+    pub fn format_top(&self, count: usize, projects: bool, config: &Config) -> String {
+        let plain = config.plain;
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        let kind = if projects { "projects" } else { "tasks" };
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Top {kind} from {} to {}\n",
+            date(&self.first),
+            date(&self.last)
+        )
+        .unwrap();
+        let rows = self.top(count, projects);
+        if rows.is_empty() {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        for (name, d) in &rows {
+            if plain {
+                writeln!(
+                    out,
+                    "{name}: {}, {} percent",
+                    duration_in_words(*d),
+                    self.percent(*d)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>4} h {:>2} min ({:>3}%): {name}",
+                    d.num_hours(),
+                    d.num_minutes() % 60,
+                    self.percent(*d)
+                )
+                .unwrap();
+            }
+        }
+        let total = match plain {
+            true => duration_in_words(self.total_work),
+            false => hours_min(self.total_work),
+        };
+        writeln!(out, "\nTotal work done: {total}").unwrap();
+        out
+    }
+
+    // the rows of format_top(), for the other output formats
+    pub fn top_table(&self, count: usize, projects: bool) -> Table {
+        let mut t = Table::new(&[
+            if projects { "project" } else { "task" },
+            "minutes",
+            "percent",
+        ]);
+        for (name, d) in self.top(count, projects) {
+            let percent = self.percent(d);
+            t.push(vec![Cell::Text(name), Cell::Time(d), Cell::Number(percent)]);
+        }
+        t
+    }
+
+    // the rows of format_comparison(), for the other output formats
+    pub fn comparison_table(&self, previous: &PeriodReport) -> Table {
+        let mut t = Table::new(&["project", "minutes", "previous", "change"]);
+        for (name, now, before) in self.compare(previous) {
+            t.push(vec![
+                Cell::Text(name),
+                Cell::Time(now),
+                Cell::Time(before),
+                Cell::Number((now - before).num_minutes()),
+            ]);
+        }
+        t
+    }
+
+    // (first day, work) of the weeks, according to `week_start`; the first one starts at
+    // `first`, even in the middle of a week
+    pub fn weeks(&self, config: &Config) -> Vec<(NaiveDate, Duration)> {
+        let mut weeks: Vec<(NaiveDate, Duration)> = Vec::new();
+        for (day, d) in &self.days {
+            match weeks.last_mut() {
+                Some((_, sum)) if day.weekday() != config.week_start => *sum += *d,
+                _ => weeks.push((*day, *d)),
+            }
+        }
+        weeks
+    }
+
+    fn format_tasks(out: &mut String, tasks: &[(String, Duration)], plain: bool) {
+        for (task, d) in tasks {
+            if plain {
+                writeln!(out, "{task}: {}", duration_in_words(*d)).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>2} h {:>2} min: {task}",
+                    d.num_hours(),
+                    d.num_minutes() % 60
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    // like "6h 15m work, 35m slacking"
+    fn summary(&self, plain: bool) -> String {
+        let duration = if plain { duration_in_words } else { hours_min };
+        format!(
+            "{} work, {} slacking",
+            duration(self.total_work),
+            duration(self.total_slack)
+        )
+    }
+
+    // Config::target() of the days from `first` to `last`, without the absences
+    pub fn target(&self, first: NaiveDate, last: NaiveDate, config: &Config) -> Duration {
+        self.absences
+            .iter()
+            .filter(|(day, _)| (first..=last).contains(day))
+            .fold(config.target(first, last), |sum, (day, _)| {
+                sum - config.day_target(*day)
+            })
+    }
+
+    // work beyond the target of the days, negative if short of it
+    pub fn overtime(&self, config: &Config) -> Duration {
+        self.total_work - self.target(self.first, self.last, config)
+    }
+
+    // like "+1h 20m overtime this week (target 40h)"; not for some of the tasks (see
+    // TaskFilter), the target is for all work
+    fn format_overtime(&self, out: &mut String, when: &str, config: &Config) {
+        if !config.filter.is_empty() {
+            return;
+        }
+        let duration = if config.plain {
+            duration_in_words
+        } else {
+            hours_min
+        };
+        writeln!(
+            out,
+            "{} overtime{when} (target {})",
+            signed_with(self.overtime(config), duration),
+            duration(self.target(self.first, self.last, config))
+        )
+        .unwrap();
+    }
+
+    // (first day, work, target) of each week, like weeks()
+    pub fn flexitime(&self, config: &Config) -> Vec<(NaiveDate, Duration, Duration)> {
+        self.weeks(config)
+            .into_iter()
+            .map(|(first, work)| {
+                let last = first.week(config.week_start).last_day().min(self.last);
+                (first, work, work - self.target(first, last, config))
+            })
+            .collect()
+    }
+
+    // the overtime of each week, and the balance up to it
+    pub fn format_flexitime(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = if plain { duration_in_words } else { hours_min };
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Flexitime from {} to {}\n",
+            date(&self.first),
+            date(&self.last)
+        )
+        .unwrap();
+        let mut balance = Duration::zero();
+        for (first, work, overtime) in self.flexitime(config) {
+            balance += overtime;
+            writeln!(
+                out,
+                "Week from {}: {} work, {}, balance {}",
+                date(&first),
+                duration(work),
+                signed_with(overtime, duration),
+                signed_with(balance, duration)
+            )
+            .unwrap();
+        }
+        writeln!(out, "\nBalance: {}", signed_with(balance, duration)).unwrap();
+        out
+    }
+
+    // the tasks, then the slacking; false if there are neither
+    fn format_all_tasks(&self, out: &mut String, plain: bool) -> bool {
+        if self.work.is_empty() && self.slack.is_empty() {
+            writeln!(out, "No work logged").unwrap();
+            return false;
+        }
+        PeriodReport::format_tasks(out, &self.work, plain);
+        if !self.slack.is_empty() {
+            writeln!(out, "\nSlacking:").unwrap();
+            PeriodReport::format_tasks(out, &self.slack, plain);
+        }
+        true
+    }
+
+    // the tasks of the first day, then the slacking, then the totals
+    pub fn format_daily(&self, config: &Config) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Daily report for {}\n",
+            config.date_format.format(&self.first)
+        )
+        .unwrap();
+        if self.format_all_tasks(&mut out, config.plain) {
+            writeln!(out, "\n{}", self.summary(config.plain)).unwrap();
+            self.format_overtime(&mut out, "", config);
+        }
+        out
+    }
+
+    // like the daily report, with the work of each day before the totals
+    pub fn format_weekly(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Weekly report for the week from {} (week {})\n",
+            config.date_format.format(&self.first),
+            // the ISO week of the fourth day has most of the days, for any week_start
+            (self.first + Duration::days(3)).format("%V")
+        )
+        .unwrap();
+        if !self.format_all_tasks(&mut out, plain) {
+            return out;
+        }
+        let days: Vec<String> = self
+            .days
+            .iter()
+            .map(|(day, d)| {
+                let name = config.date_format.format_with(day, "%a");
+                match plain {
+                    true => format!("{name}: {}", duration_in_words(*d)),
+                    false => format!("{name} {}", short(*d)),
+                }
+            })
+            .collect();
+        writeln!(out, "\n{}", days.join(", ")).unwrap();
+        writeln!(out, "{}", self.summary(plain)).unwrap();
+        self.format_overtime(&mut out, " this week", config);
+        self.format_earnings(&mut out, config);
+        self.format_estimates(&mut out, config);
+        out
+    }
+
+    // the earnings per currency and project, if there are [rates]
+    fn format_earnings(&self, out: &mut String, config: &Config) {
+        if self.earnings.currencies.is_empty() {
+            return;
+        }
+        writeln!(
+            out,
+            "\nEarnings:\n{}",
+            self.earnings.format(config).trim_end()
+        )
+        .unwrap();
+    }
+
+    // the work on each task with an estimate, against the estimate
+    fn format_estimates(&self, out: &mut String, config: &Config) {
+        if self.estimates.is_empty() {
+            return;
+        }
+        writeln!(out, "\nEstimates:").unwrap();
+        for e in &self.estimates {
+            match config.plain {
+                true => writeln!(out, "{}", e.to_plain_string()).unwrap(),
+                false => writeln!(out, "{e}").unwrap(),
+            }
+        }
+    }
+
+    // the use of each of the [budgets]
+    fn format_budgets(&self, out: &mut String, config: &Config) {
+        if self.budgets.is_empty() {
+            return;
+        }
+        writeln!(out, "\nBudgets:").unwrap();
+        for b in &self.budgets {
+            match config.plain {
+                true => writeln!(out, "{}", b.to_plain_string()).unwrap(),
+                false => writeln!(out, "{b}").unwrap(),
+            }
+        }
+    }
+
+    // each day with its first and last entry, the work and slacking, and the gap to the target
+    fn format_presence(&self, out: &mut String, config: &Config) {
+        let plain = config.plain;
+        let duration = if plain { duration_in_words } else { hours_min };
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        for p in &self.presence {
+            let gap = signed_with(p.gap, duration);
+            match (p.first, p.last) {
+                (Some(first), Some(last)) => writeln!(
+                    out,
+                    "{}: {} to {}, {} work, {} slacking, {gap}",
+                    date(&p.day),
+                    config.date_format.time(first.time()),
+                    config.date_format.time(last.time()),
+                    duration(p.work),
+                    duration(p.slack)
+                ),
+                _ => writeln!(out, "{}: no entries, {gap}", date(&p.day)),
+            }
+            .unwrap();
+        }
+        writeln!(out, "\n{}", self.summary(plain)).unwrap();
+        self.format_overtime(out, "", config);
+    }
+
+    // any days, with the work per task (and the slacking), per day with work, or per week
+    pub fn format_grouped(&self, group_by: GroupBy, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = |d: Duration| match plain {
+            true => duration_in_words(d),
+            false => hours_min(d),
+        };
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Report from {} to {}\n",
+            date(&self.first),
+            date(&self.last)
+        )
+        .unwrap();
+        let rows = match group_by {
+            GroupBy::Task => {
+                if !self.format_all_tasks(&mut out, plain) {
+                    return out;
+                }
+                Vec::new()
+            }
+            GroupBy::Presence => {
+                self.format_presence(&mut out, config);
+                return out;
+            }
+            GroupBy::Day => self
+                .days
+                .iter()
+                .filter(|(_, d)| *d > Duration::minutes(0))
+                .map(|(day, d)| (date(day), *d))
+                .collect(),
+            GroupBy::Week => self
+                .weeks(config)
+                .into_iter()
+                .map(|(first, d)| (format!("from {}", date(&first)), d))
+                .collect(),
+            // the whole path for plain output, which can't indent
+            GroupBy::Project { depth } => self
+                .hierarchy(depth)
+                .into_iter()
+                .map(|(level, path, d)| match plain {
+                    true => (path, d),
+                    false => {
+                        let name = path.rsplit(": ").next().unwrap_or_default();
+                        (format!("{}{name}", "  ".repeat(level)), d)
+                    }
+                })
+                .collect(),
+            // likewise
+            GroupBy::Category | GroupBy::Client => {
+                let categories = match group_by {
+                    GroupBy::Client => self.clients(config),
+                    _ => self.categories(),
+                };
+                let mut rows = Vec::new();
+                for category in categories {
+                    rows.push((category.name.clone(), category.work));
+                    for (detail, d) in category.details {
+                        match plain {
+                            true => rows.push((format!("{}: {detail}", category.name), d)),
+                            false => rows.push((format!("  {detail}"), d)),
+                        }
+                    }
+                }
+                rows
+            }
+        };
+        if group_by != GroupBy::Task && self.total_work <= Duration::minutes(0) {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        for (name, d) in &rows {
+            if plain {
+                writeln!(out, "{name}: {}", duration(*d)).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>4} h {:>2} min: {name}",
+                    d.num_hours(),
+                    d.num_minutes() % 60
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "\nTotal work done: {}", duration(self.total_work)).unwrap();
+        writeln!(out, "{}", self.summary(plain)).unwrap();
+        out
+    }
+
+    // the rows of format_grouped(), for the other output formats; days and weeks by their first
+    // day in ISO format
+    pub fn table(&self, group_by: GroupBy, config: &Config) -> Table {
+        let iso = |d: &NaiveDate| Cell::Text(d.format("%F").to_string());
+        match group_by {
+            GroupBy::Task => {
+                let mut t = Table::new(&["task", "minutes", "slack"]);
+                for (slack, tasks) in [(false, &self.work), (true, &self.slack)] {
+                    for (task, d) in tasks {
+                        t.push(vec![Cell::text(task), Cell::Time(*d), Cell::Bool(slack)]);
+                    }
+                }
+                t
+            }
+            GroupBy::Day => {
+                let mut t = Table::new(&["day", "minutes"]);
+                for (day, d) in &self.days {
+                    t.push(vec![iso(day), Cell::Time(*d)]);
+                }
+                t
+            }
+            GroupBy::Week => {
+                let mut t = Table::new(&["week", "minutes"]);
+                for (first, d) in self.weeks(config) {
+                    t.push(vec![iso(&first), Cell::Time(d)]);
+                }
+                t
+            }
+            GroupBy::Project { depth } => {
+                let mut t = Table::new(&["project", "level", "minutes"]);
+                for (level, path, d) in self.hierarchy(depth) {
+                    t.push(vec![
+                        Cell::Text(path),
+                        Cell::Number(level as i64),
+                        Cell::Time(d),
+                    ]);
+                }
+                t
+            }
+            GroupBy::Category | GroupBy::Client => {
+                let (categories, columns) = match group_by {
+                    GroupBy::Client => (self.clients(config), ["client", "project", "minutes"]),
+                    _ => (self.categories(), ["category", "detail", "minutes"]),
+                };
+                let mut t = Table::new(&columns);
+                for category in categories {
+                    for (detail, d) in category.details {
+                        t.push(vec![
+                            Cell::text(&category.name),
+                            Cell::Text(detail),
+                            Cell::Time(d),
+                        ]);
+                    }
+                }
+                t
+            }
+            // the gap in minutes, as it may be negative
+            GroupBy::Presence => {
+                let mut t = Table::new(&["day", "first", "last", "minutes", "slack", "gap"]);
+                let time = |t: Option<NaiveDateTime>| {
+                    Cell::Text(t.map(|t| t.format("%H:%M").to_string()).unwrap_or_default())
+                };
+                for p in &self.presence {
+                    t.push(vec![
+                        iso(&p.day),
+                        time(p.first),
+                        time(p.last),
+                        Cell::Time(p.work),
+                        Cell::Time(p.slack),
+                        Cell::Number(p.gap.num_minutes()),
+                    ]);
+                }
+                t
+            }
+        }
+    }
+
+    // the projects instead of the tasks, with the work of each week
+    pub fn format_monthly(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = |d: Duration| match plain {
+            true => duration_in_words(d),
+            false => hours_min(d),
+        };
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Monthly report for {}\n",
+            config.date_format.format_with(&self.first, "%B %Y")
+        )
+        .unwrap();
+        if self.projects.is_empty() {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        writeln!(out, "Projects:").unwrap();
+        for (name, d) in &self.projects {
+            if plain {
+                writeln!(
+                    out,
+                    "{name}: {}, {} percent",
+                    duration(*d),
+                    self.percent(*d)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>4} h {:>2} min ({:>3}%): {name}",
+                    d.num_hours(),
+                    d.num_minutes() % 60,
+                    self.percent(*d)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "\nWeeks:").unwrap();
+        for (first, d) in self.weeks(config) {
+            let from = config.date_format.format(&first);
+            if plain {
+                writeln!(out, "from {from}: {}", duration(d)).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{:>4} h {:>2} min: from {from}",
+                    d.num_hours(),
+                    d.num_minutes() % 60
+                )
+                .unwrap();
+            }
+        }
+        writeln!(
+            out,
+            "\nTotal work done this month: {}",
+            duration(self.total_work)
+        )
+        .unwrap();
+        writeln!(out, "{}", self.summary(plain)).unwrap();
+        self.format_earnings(&mut out, config);
+        self.format_budgets(&mut out, config);
+        out
+    }
+}
+
+// the rows of PeriodReport::hierarchy() for `tasks`, which share their first `level` parts
+fn add_level(
+    rows: &mut Vec<(usize, String, Duration)>,
+    tasks: &[(Vec<&str>, Duration)],
+    level: usize,
+    depth: usize,
+) {
+    if level >= depth {
+        return;
+    }
+    let mut parts: Vec<(&str, Duration)> = Vec::new();
+    for (task, d) in tasks {
+        let Some(part) = task.get(level) else {
+            continue;
+        };
+        match parts.iter_mut().find(|(p, _)| p == part) {
+            Some((_, sum)) => *sum += *d,
+            None => parts.push((part, *d)),
+        }
+    }
+    parts.sort_by_key(|(_, d)| -*d);
+    for (part, d) in parts {
+        let below: Vec<(Vec<&str>, Duration)> = tasks
+            .iter()
+            .filter(|(task, _)| task.get(level) == Some(&part))
+            .cloned()
+            .collect();
+        rows.push((level, below[0].0[..=level].join(": "), d));
+        add_level(rows, &below, level + 1, depth);
+    }
+}
+
+// the working day after `day`, according to `work_days`
+fn next_work_day(day: NaiveDate, config: &Config) -> NaiveDate {
+    let mut next = day.succ_opt().unwrap_or(day);
+    // with no working days configured, every day counts
+    for _ in 0..7 {
+        if config.work_days.is_empty() || config.is_work_day(next) {
+            break;
+        }
+        next = next.succ_opt().unwrap_or(next);
+    }
+    next
+}
+
+/**
+ * Work per project and day of one week, or the part of it in a report, like on a timesheet
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Timesheet {
+    // (day, work) from the first to the last day
+    pub days: Vec<(NaiveDate, Duration)>,
+    // (project, work per day, total), most work first
+    pub projects: Vec<(String, Vec<Duration>, Duration)>,
+    pub total_work: Duration,
+}
+
+impl Timesheet {
+    // one per week (see PeriodReport::week()) of the days from `first` to `last`
+    pub fn weeks(
+        entries: &[Entry],
+        first: NaiveDate,
+        last: NaiveDate,
+        config: &Config,
+    ) -> Vec<Timesheet> {
+        let mut sheets = Vec::new();
+        let mut week = PeriodReport::week(first, config).0;
+        while week <= last {
+            let begin = week.max(first);
+            let end = (week + Duration::days(6)).min(last);
+            let report = PeriodReport::new(entries, begin, end, config);
+            let days: Vec<PeriodReport> = begin
+                .iter_days()
+                .take_while(|d| *d <= end)
+                .map(|d| PeriodReport::new(entries, d, d, config))
+                .collect();
+            let projects = report
+                .projects
+                .iter()
+                .map(|(project, total)| {
+                    let work = days
+                        .iter()
+                        .map(|d| {
+                            d.projects
+                                .iter()
+                                .find(|(p, _)| p == project)
+                                .map_or(Duration::zero(), |(_, work)| *work)
+                        })
+                        .collect();
+                    (project.clone(), work, *total)
+                })
+                .collect();
+            sheets.push(Timesheet {
+                days: days.iter().map(|d| (d.first, d.total_work)).collect(),
+                projects,
+                total_work: report.total_work,
+            });
+            week += Duration::days(7);
+        }
+        sheets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projects;
+    use crate::store::Timelog;
+    use crate::table::OutputFormat;
+    use chrono::NaiveTime;
+    use pretty_assertions::assert_eq;
+
+    const LOG: &str = "
+2023-12-29 09:00: arrived
+2023-12-29 17:00: old year
+
+2024-01-05 09:00: arrived
+2024-01-05 11:00: rtimelog: code #release
+2024-01-05 11:30: ** tea #break
+
+2024-02-01 09:00: arrived
+2024-02-01 12:00: customer joe: support
+2024-02-01 13:00: rtimelog: docs #release
+
+2024-02-02 09:00: arrived
+2024-02-02 10:00: customer joe: support #urgent
+
+2024-02-05 09:00: arrived
+2024-02-05 10:00: email
+
+2024-02-07 09:00: arrived
+2024-02-07 10:00: email
+";
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_year_review() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let (begin, end) = YearReview::range(2024, &config).unwrap();
+        let r = YearReview::new(tl.get_time_range(begin, end), 2024, &config);
+
+        assert_eq!(r.total_work, Duration::hours(9));
+        assert_eq!(r.total_slack, Duration::minutes(30));
+        assert_eq!(
+            r.projects,
+            vec![
+                ("customer joe".to_string(), Duration::hours(4)),
+                ("rtimelog".to_string(), Duration::hours(3)),
+                ("email".to_string(), Duration::hours(2)),
+            ]
+        );
+        // slacking does not count for tags
+        assert_eq!(
+            r.tags,
+            vec![
+                ("release".to_string(), Duration::hours(3)),
+                ("urgent".to_string(), Duration::hours(1)),
+            ]
+        );
+        assert_eq!(r.days, 5);
+        assert_eq!(r.average_day(), Duration::minutes(108));
+        assert_eq!(r.busiest_week, Some((date(1, 29), Duration::hours(5))));
+        // Thursday, Friday, and Monday; Wednesday is after a gap
+        assert_eq!(r.longest_streak, Some((date(2, 1), date(2, 5))));
+        assert_eq!(r.streak_days, 3);
+        assert_eq!(r.longest_day, Some((date(2, 1), Duration::hours(4))));
+        // all of January after the 5th, and the Tuesday between
+        assert_eq!(r.days_off.len(), 19);
+        assert_eq!(r.days_off[0], date(1, 8));
+        assert_eq!(r.days_off[18], date(2, 6));
+        assert_eq!(r.months[0], Duration::hours(2));
+        assert_eq!(r.months[1], Duration::hours(7));
+        assert_eq!(r.months[2], Duration::minutes(0));
+
+        let out = r.format(&config);
+        assert!(out.starts_with(
+            "Year in review 2024
+
+Total work done: 9 h 0 min
+Total slacking: 0 h 30 min
+Days with work: 5, on average 1 h 48 min
+Busiest week: from Monday, 2024-01-29 with 5 h 0 min
+Longest day: Thursday, 2024-02-01 with 4 h 0 min
+Longest streak: 3 working days, Thursday, 2024-02-01 to Monday, 2024-02-05
+Days off: 19 working days without work
+
+Projects:
+   4 h  0 min ( 44%): customer joe
+   3 h  0 min ( 33%): rtimelog
+   2 h  0 min ( 22%): email
+
+Tags:
+   3 h  0 min ( 33%): release
+   1 h  0 min ( 11%): urgent
+
+Months:
+January       2 h  0 min ########
+February      7 h  0 min ##############################
+March         0 h  0 min
+"
+        ));
+
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        let out = r.format(&plain);
+        assert!(out.contains("\ncustomer joe: 4 hours, 44 percent\n"));
+        assert!(out.contains("\nFebruary: 7 hours\n"));
+
+        let named = Config::new_from_string("[gtimelog]\nname = Joe\nsender = joe@example.com\n");
+        assert!(r
+            .format(&named)
+            .starts_with("Year in review 2024 of Joe <joe@example.com>\n\n"));
+
+        let empty = YearReview::new(tl.get_time_range(begin, end), 2022, &config);
+        assert_eq!(
+            empty.format(&config),
+            "Year in review 2022\n\nTotal work done: 0 h 0 min\nTotal slacking: 0 h 0 min\nNo work logged\n"
+        );
+
+        // the night of New Year's Eve belongs to the old year, up to the virtual midnight
+        let tl = Timelog::new_from_string(
+            "2024-12-31 22:00: arrived\n2025-01-01 01:00: rtimelog: release\n",
+        );
+        let mut config = Config::default();
+        config.sentinels.virtual_midnight = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+        let (begin, end) = YearReview::range(2024, &config).unwrap();
+        let r = YearReview::new(tl.get_time_range(begin, end), 2024, &config);
+        assert_eq!(r.total_work, Duration::hours(3));
+        assert_eq!(r.months[11], Duration::hours(3));
+        let (begin, end) = YearReview::range(2025, &config).unwrap();
+        let r = YearReview::new(tl.get_time_range(begin, end), 2025, &config);
+        assert_eq!(r.total_work, Duration::zero());
+    }
+
+    #[test]
+    fn test_daily_report() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let (begin, end) = PeriodReport::range(date(1, 5), date(1, 5), &config).unwrap();
+        let r = PeriodReport::new(
+            tl.get_time_range(begin, end),
+            date(1, 5),
+            date(1, 5),
+            &config,
+        );
+        assert_eq!(r.total_work, Duration::hours(2));
+        assert_eq!(r.total_slack, Duration::minutes(30));
+        assert_eq!(
+            r.format_daily(&config),
+            "Daily report for Friday, 2024-01-05
+
+ 2 h  0 min: rtimelog: code #release
+
+Slacking:
+ 0 h 30 min: ** tea #break
+
+2 h 0 min work, 0 h 30 min slacking
+-6 h 0 min overtime (target 8 h 0 min)
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r
+            .format_daily(&plain)
+            .ends_with("\nrtimelog: code #release: 2 hours\n\nSlacking:\n** tea #break: 30 minutes\n\n2 hours work, 30 minutes slacking\n-6 hours overtime (target 8 hours)\n"));
+
+        // only the entries of that day count, also with more loaded
+        let r = PeriodReport::new(
+            &tl.get_all().cloned().collect::<Vec<_>>(),
+            date(2, 1),
+            date(2, 1),
+            &config,
+        );
+        assert_eq!(
+            r.work,
+            vec![
+                ("customer joe: support".to_string(), Duration::hours(3)),
+                ("rtimelog: docs #release".to_string(), Duration::hours(1)),
+            ]
+        );
+        assert_eq!(r.slack, Vec::new());
+        assert!(r
+            .format_daily(&config)
+            .ends_with(" 1 h  0 min: rtimelog: docs #release\n\n4 h 0 min work, 0 h 0 min slacking\n-4 h 0 min overtime (target 8 h 0 min)\n"));
+        // only one customer, which has no target
+        let mut joe = config.clone();
+        joe.filter.project = Some("customer joe".to_string());
+        let r = PeriodReport::new(
+            &tl.get_all().cloned().collect::<Vec<_>>(),
+            date(2, 1),
+            date(2, 1),
+            &joe,
+        );
+        assert!(r.format_daily(&joe).ends_with(
+            "\n 3 h  0 min: customer joe: support\n\n3 h 0 min work, 0 h 0 min slacking\n"
+        ));
+        let empty = PeriodReport::new(&[], date(3, 1), date(3, 1), &config);
+        assert_eq!(
+            empty.format_daily(&config),
+            "Daily report for Friday, 2024-03-01\n\nNo work logged\n"
+        );
+    }
+
+    #[test]
+    fn test_weekly_report() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let (first, last) = PeriodReport::week(date(2, 1), &config);
+        assert_eq!((first, last), (date(1, 29), date(2, 4)));
+        let (begin, end) = PeriodReport::range(first, last, &config).unwrap();
+        let r = PeriodReport::new(tl.get_time_range(begin, end), first, last, &config);
+        assert_eq!(r.days.len(), 7);
+        assert_eq!(r.days[3], (date(2, 1), Duration::hours(4)));
+        assert_eq!(
+            r.format_weekly(&config),
+            "Weekly report for the week from Monday, 2024-01-29 (week 05)
+
+ 3 h  0 min: customer joe: support
+ 1 h  0 min: rtimelog: docs #release
+ 1 h  0 min: customer joe: support #urgent
+
+Mon 0m, Tue 0m, Wed 0m, Thu 4h, Fri 1h, Sat 0m, Sun 0m
+5 h 0 min work, 0 h 0 min slacking
+-35 h 0 min overtime this week (target 40 h 0 min)
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r.format_weekly(&plain).contains(
+            "\nMon: 0 minutes, Tue: 0 minutes, Wed: 0 minutes, Thu: 4 hours, Fri: 1 hour, \
+             Sat: 0 minutes, Sun: 0 minutes\n5 hours work, 0 minutes slacking\n"
+        ));
+
+        // weeks from Sunday, with more loaded than that
+        let config = Config::new_from_string("[rtimelog]\nweek_start = sun\n");
+        let (first, last) = PeriodReport::week(date(2, 6), &config);
+        assert_eq!((first, last), (date(2, 4), date(2, 10)));
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, first, last, &config);
+        assert_eq!(r.total_work, Duration::hours(2));
+        assert!(r
+            .format_weekly(&config)
+            .contains("\nSun 0m, Mon 1h, Tue 0m, Wed 1h, "));
+        let r = PeriodReport::new(&all, date(3, 3), date(3, 9), &config);
+        assert!(r.format_weekly(&config).ends_with("\nNo work logged\n"));
+
+        let rates = Config::new_from_string("[rates]\nrtimelog = 80 USD\n");
+        let r = PeriodReport::new(&all, date(1, 29), date(2, 4), &rates);
+        assert!(r.format_weekly(&rates).ends_with(
+            "(target 40 h 0 min)\n\nEarnings:\nUSD:\n   1 h  0 min      80.00 USD: rtimelog\n\
+             Total: 1 h 0 min, 80.00 USD\n\nNot billed: 4 h 0 min\n"
+        ));
+
+        // tasks with an estimate, also when only one of the entries has it
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 12:00: rtimelog: parser rewrite [est:4h]
+2024-02-05 13:00: email
+2024-02-06 09:00: arrived
+2024-02-06 11:00: rtimelog: parser rewrite
+",
+        );
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 11), &config);
+        assert!(r.format_weekly(&config).ends_with(
+            "(target 40 h 0 min)\n\nEstimates:\n\
+             rtimelog: parser rewrite: 5 h 0 min of 4 h 0 min estimated (125%), 1 h 0 min over\n"
+        ));
+        assert!(r.format_weekly(&plain).ends_with(
+            "\nEstimates:\nrtimelog: parser rewrite: 5 hours of 4 hours estimated, 125 percent, \
+             1 hour over\n"
+        ));
+    }
+
+    #[test]
+    fn test_monthly_report() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let (first, last) = PeriodReport::month(date(2, 7));
+        assert_eq!((first, last), (date(2, 1), date(2, 29)));
+        assert_eq!(
+            PeriodReport::month(date(12, 31)),
+            (date(12, 1), date(12, 31))
+        );
+        let (begin, end) = PeriodReport::range(first, last, &config).unwrap();
+        let r = PeriodReport::new(tl.get_time_range(begin, end), first, last, &config);
+        assert_eq!(
+            r.weeks(&config),
+            vec![
+                (date(2, 1), Duration::hours(5)),
+                (date(2, 5), Duration::hours(2)),
+                (date(2, 12), Duration::minutes(0)),
+                (date(2, 19), Duration::minutes(0)),
+                (date(2, 26), Duration::minutes(0)),
+            ]
+        );
+        assert_eq!(
+            r.format_monthly(&config),
+            "Monthly report for February 2024
+
+Projects:
+   4 h  0 min ( 57%): customer joe
+   2 h  0 min ( 29%): email
+   1 h  0 min ( 14%): rtimelog
+
+Weeks:
+   5 h  0 min: from Thursday, 2024-02-01
+   2 h  0 min: from Monday, 2024-02-05
+   0 h  0 min: from Monday, 2024-02-12
+   0 h  0 min: from Monday, 2024-02-19
+   0 h  0 min: from Monday, 2024-02-26
+
+Total work done this month: 7 h 0 min
+7 h 0 min work, 0 h 0 min slacking
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        let out = r.format_monthly(&plain);
+        assert!(out.contains("\ncustomer joe: 4 hours, 57 percent\n"));
+        assert!(out.contains("\nfrom Monday, 2024-02-05: 2 hours\n"));
+
+        // with the earnings of the [rates]
+        let rates = Config::new_from_string("[rates]\ncustomer joe = 100\n");
+        let r = PeriodReport::new(tl.get_time_range(begin, end), first, last, &rates);
+        assert!(r.format_monthly(&rates).ends_with(
+            "7 h 0 min work, 0 h 0 min slacking
+
+Earnings:
+EUR:
+   4 h  0 min     400.00 EUR: customer joe
+Total: 4 h 0 min, 400.00 EUR
+
+Not billed: 3 h 0 min
+"
+        ));
+
+        // with the use of the [budgets]
+        let budgets = Config::new_from_string("[budgets]\ncustomer joe = 3h\nemail = 10h\n");
+        let r = PeriodReport::new(tl.get_time_range(begin, end), first, last, &budgets);
+        assert!(r.format_monthly(&budgets).ends_with(
+            "7 h 0 min work, 0 h 0 min slacking
+
+Budgets:
+customer joe: 4 h 0 min of 3 h 0 min (133%), 1 h 0 min over
+email: 2 h 0 min of 10 h 0 min (20%), 8 h 0 min left
+"
+        ));
+
+        let (first, last) = PeriodReport::month(date(3, 1));
+        let r = PeriodReport::new(tl.get_time_range(begin, end), first, last, &config);
+        assert_eq!(
+            r.format_monthly(&config),
+            "Monthly report for March 2024\n\nNo work logged\n"
+        );
+    }
+
+    #[test]
+    fn test_grouped_report() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(1, 1), date(3, 31), &config);
+        assert_eq!(r.total_work, Duration::hours(9));
+        assert_eq!(
+            r.format_grouped(GroupBy::Day, &config),
+            "Report from Monday, 2024-01-01 to Sunday, 2024-03-31
+
+   2 h  0 min: Friday, 2024-01-05
+   4 h  0 min: Thursday, 2024-02-01
+   1 h  0 min: Friday, 2024-02-02
+   1 h  0 min: Monday, 2024-02-05
+   1 h  0 min: Wednesday, 2024-02-07
+
+Total work done: 9 h 0 min
+9 h 0 min work, 0 h 30 min slacking
+"
+        );
+        let weeks = r.format_grouped(GroupBy::Week, &config);
+        assert!(weeks.contains(
+            "\n   2 h  0 min: from Monday, 2024-01-01\n   0 h  0 min: from Monday, 2024-01-08\n"
+        ));
+        assert!(weeks
+            .contains("\n   0 h  0 min: from Monday, 2024-03-25\n\nTotal work done: 9 h 0 min\n"));
+        let tasks = r.format_grouped(GroupBy::Task, &config);
+        assert!(tasks.contains("\n 2 h  0 min: rtimelog: code #release\n"));
+        assert!(tasks
+            .contains("\nSlacking:\n 0 h 30 min: ** tea #break\n\nTotal work done: 9 h 0 min\n"));
+
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r
+            .format_grouped(GroupBy::Day, &plain)
+            .contains("\nThursday, 2024-02-01: 4 hours\n"));
+
+        let r = PeriodReport::new(&all, date(4, 1), date(4, 30), &config);
+        for group_by in [GroupBy::Task, GroupBy::Day, GroupBy::Week] {
+            assert!(r
+                .format_grouped(group_by, &config)
+                .ends_with("2024-04-30\n\nNo work logged\n"));
+        }
+    }
+
+    #[test]
+    fn test_project_hierarchy() {
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 12:00: rtimelog: code: parser
+2024-02-05 13:00: rtimelog: review
+2024-02-05 14:00: rtimelog: code: tests
+2024-02-05 14:30: ** lunch
+2024-02-05 16:00: customer joe: support
+2024-02-05 17:00: email
+",
+        );
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 5), &config);
+        let rows = r.hierarchy(usize::MAX);
+        assert_eq!(
+            rows,
+            vec![
+                (0, "rtimelog".to_string(), Duration::hours(5)),
+                (1, "rtimelog: code".to_string(), Duration::hours(4)),
+                (2, "rtimelog: code: parser".to_string(), Duration::hours(3)),
+                (2, "rtimelog: code: tests".to_string(), Duration::hours(1)),
+                (1, "rtimelog: review".to_string(), Duration::hours(1)),
+                (0, "customer joe".to_string(), Duration::minutes(90)),
+                (
+                    1,
+                    "customer joe: support".to_string(),
+                    Duration::minutes(90)
+                ),
+                (0, "email".to_string(), Duration::hours(1)),
+            ]
+        );
+        assert_eq!(r.hierarchy(1).len(), 3);
+        assert_eq!(
+            r.format_grouped(GroupBy::Project { depth: 2 }, &config),
+            "Report from Monday, 2024-02-05 to Monday, 2024-02-05
+
+   5 h  0 min: rtimelog
+   4 h  0 min:   code
+   1 h  0 min:   review
+   1 h 30 min: customer joe
+   1 h 30 min:   support
+   1 h  0 min: email
+
+Total work done: 7 h 30 min
+7 h 30 min work, 0 h 30 min slacking
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r
+            .format_grouped(GroupBy::Project { depth: 3 }, &plain)
+            .contains("\nrtimelog: code: parser: 3 hours\n"));
+    }
+
+    #[test]
+    fn test_clients() {
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 11:00: customer joe: support
+2024-02-05 12:00: joe-portal: bugfix
+2024-02-05 13:00: customer joe: emergency: login
+2024-02-05 13:30: ** lunch
+2024-02-05 15:30: acme: website
+2024-02-05 16:30: rtimelog: code
+2024-02-05 17:00: email
+",
+        );
+        let config = Config {
+            projects: projects::parse(
+                r#"
+["customer joe"]
+client = "Joe's Garage"
+
+[joe-portal]
+client = "Joe's Garage"
+
+["customer joe: emergency"]
+client = "Joe's Garage"
+contract = "urgent"
+
+[acme]
+client = "ACME"
+"#,
+            )
+            .unwrap(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config
+                .client_for("customer joe: emergency: login")
+                .map(|p| &p.prefix[..]),
+            Some("customer joe: emergency")
+        );
+        assert_eq!(config.client_for("rtimelog: code"), None);
+        assert_eq!(config.client_for("acme-corp: website"), None);
+
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 5), &config);
+        let h = |min| Duration::minutes(min);
+        let client = |name: &str, work, details: &[(&str, Duration)]| Category {
+            name: name.to_string(),
+            work,
+            details: details.iter().map(|(t, d)| (t.to_string(), *d)).collect(),
+        };
+        assert_eq!(
+            r.clients(&config),
+            vec![
+                client(
+                    "Joe's Garage",
+                    h(240),
+                    &[
+                        ("customer joe", h(120)),
+                        ("customer joe: emergency", h(60)),
+                        ("joe-portal", h(60))
+                    ]
+                ),
+                client("ACME", h(120), &[("acme", h(120))]),
+                client("(none)", h(90), &[("rtimelog", h(60)), ("email", h(30))]),
+            ]
+        );
+        assert_eq!(
+            r.format_grouped(GroupBy::Client, &config),
+            "Report from Monday, 2024-02-05 to Monday, 2024-02-05
+
+   4 h  0 min: Joe's Garage
+   2 h  0 min:   customer joe
+   1 h  0 min:   customer joe: emergency
+   1 h  0 min:   joe-portal
+   2 h  0 min: ACME
+   2 h  0 min:   acme
+   1 h 30 min: (none)
+   1 h  0 min:   rtimelog
+   0 h 30 min:   email
+
+Total work done: 7 h 30 min
+7 h 30 min work, 0 h 30 min slacking
+"
+        );
+        assert_eq!(
+            r.table(GroupBy::Client, &config).format(OutputFormat::Csv),
+            "client,project,minutes
+Joe's Garage,customer joe,120
+Joe's Garage,customer joe: emergency,60
+Joe's Garage,joe-portal,60
+ACME,acme,120
+(none),rtimelog,60
+(none),email,30
+"
+        );
+    }
+
+    #[test]
+    fn test_categories() {
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 12:00: rtimelog: code: parser
+2024-02-05 13:00: rtimelog: review
+2024-02-05 14:00: rtimelog: code: tests
+2024-02-05 14:30: ** lunch
+2024-02-05 16:00: customer joe: support
+2024-02-05 17:00: email
+2024-02-05 17:30: rtimelog: code: parser
+",
+        );
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 5), &config);
+        let h = |min| Duration::minutes(min);
+        let category = |name: &str, work, details: &[(&str, Duration)]| Category {
+            name: name.to_string(),
+            work,
+            details: details.iter().map(|(t, d)| (t.to_string(), *d)).collect(),
+        };
+        assert_eq!(
+            r.categories(),
+            vec![
+                category("customer joe", h(90), &[("support", h(90))]),
+                category("rtimelog", h(60), &[("review", h(60))]),
+                category(
+                    "rtimelog: code",
+                    h(270),
+                    &[("parser", h(210)), ("tests", h(60))]
+                ),
+                category("(none)", h(60), &[("email", h(60))]),
+            ]
+        );
+        assert_eq!(
+            r.format_grouped(GroupBy::Category, &config),
+            "Report from Monday, 2024-02-05 to Monday, 2024-02-05
+
+   1 h 30 min: customer joe
+   1 h 30 min:   support
+   1 h  0 min: rtimelog
+   1 h  0 min:   review
+   4 h 30 min: rtimelog: code
+   3 h 30 min:   parser
+   1 h  0 min:   tests
+   1 h  0 min: (none)
+   1 h  0 min:   email
+
+Total work done: 8 h 0 min
+8 h 0 min work, 0 h 30 min slacking
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r
+            .format_grouped(GroupBy::Category, &plain)
+            .contains("\nrtimelog: code: parser: 3 hours 30 minutes\n"));
+        assert_eq!(
+            r.table(GroupBy::Category, &config)
+                .format(OutputFormat::Csv),
+            "category,detail,minutes
+customer joe,support,90
+rtimelog,review,60
+rtimelog: code,parser,210
+rtimelog: code,tests,60
+(none),email,60
+"
+        );
+    }
+
+    #[test]
+    fn test_presence() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 1), date(2, 3), &config);
+        let at = |d: u32, h: u32| date(2, d).and_hms_opt(h, 0, 0);
+        assert_eq!(
+            r.presence[0],
+            Presence {
+                day: date(2, 1),
+                first: at(1, 9),
+                last: at(1, 13),
+                work: Duration::hours(4),
+                slack: Duration::zero(),
+                gap: Duration::hours(-4),
+            }
+        );
+        assert_eq!(
+            r.format_grouped(GroupBy::Presence, &config),
+            "Report from Thursday, 2024-02-01 to Saturday, 2024-02-03
+
+Thursday, 2024-02-01: 09:00 to 13:00, 4 h 0 min work, 0 h 0 min slacking, -4 h 0 min
+Friday, 2024-02-02: 09:00 to 10:00, 1 h 0 min work, 0 h 0 min slacking, -7 h 0 min
+Saturday, 2024-02-03: no entries, +0 h 0 min
+
+5 h 0 min work, 0 h 0 min slacking
+-11 h 0 min overtime (target 16 h 0 min)
+"
+        );
+        assert_eq!(
+            r.table(GroupBy::Presence, &config)
+                .format(OutputFormat::Csv),
+            "day,first,last,minutes,slack,gap
+2024-02-01,09:00,13:00,240,0,-240
+2024-02-02,09:00,10:00,60,0,-420
+2024-02-03,,,0,0,0
+"
+        );
+    }
+
+    #[test]
+    fn test_absences() {
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 17:00: rtimelog: code
+
+2024-02-06 09:00: vacation **
+
+2024-02-07 09:00: arrived
+2024-02-07 17:00: sick
+
+2024-02-09 09:00: arrived
+2024-02-09 13:00: rtimelog: code
+",
+        );
+        let config = Config::new_from_string("[rtimelog]\nvacation_days = 30\n");
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 11), &config);
+        assert_eq!(
+            r.absences,
+            vec![(date(2, 6), Absence::Vacation), (date(2, 7), Absence::Sick)]
+        );
+        // the sick day was logged as work, and has no target either
+        assert_eq!(r.target(r.first, r.last, &config), Duration::hours(24));
+        assert_eq!(r.overtime(&config), Duration::hours(-4));
+        assert_eq!(r.presence[1].gap, Duration::zero());
+        assert_eq!(r.presence[3].gap, Duration::hours(-8));
+
+        let (begin, end) = YearReview::range(2024, &config).unwrap();
+        let y = YearReview::new(tl.get_time_range(begin, end), 2024, &config);
+        assert_eq!(
+            (y.vacation.clone(), y.sick.clone()),
+            (vec![date(2, 6)], vec![date(2, 7)])
+        );
+        assert_eq!(y.days, 2);
+        assert_eq!(y.average_day(), Duration::hours(6));
+        assert_eq!(y.days_off, vec![date(2, 8)]);
+        let out = y.format(&config);
+        assert!(out.contains(
+            "\nDays off: 1 working day without work\nVacation: 1 of 30 days, 29 left\nSick days: 1\n"
+        ));
+        assert!(!YearReview::new(&[], 2024, &Config::default())
+            .format(&config)
+            .contains("Vacation"));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            PeriodReport::previous(date(3, 1), date(3, 31)),
+            Some((date(2, 1), date(2, 29)))
+        );
+        assert_eq!(
+            PeriodReport::previous(date(3, 1), date(3, 1)),
+            Some((date(2, 29), date(2, 29)))
+        );
+        assert_eq!(
+            PeriodReport::previous(date(2, 5), date(2, 11)),
+            Some((date(1, 29), date(2, 4)))
+        );
+
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 11), &config);
+        let previous = PeriodReport::new(&all, date(1, 29), date(2, 4), &config);
+        assert_eq!(
+            r.format_comparison(&previous, &config),
+            "Compared to Monday, 2024-01-29 to Sunday, 2024-02-04:
+   2 h  0 min      +2h    new: email
+   0 h  0 min      -4h  -100%: customer joe
+   0 h  0 min      -1h  -100%: rtimelog
+
+   2 h  0 min      -3h   -60%: total work
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(r
+            .format_comparison(&previous, &plain)
+            .ends_with("\ntotal work: 2 hours, -3 hours, -60 percent\n"));
+        assert_eq!(
+            r.comparison_table(&previous).format(OutputFormat::Csv),
+            "project,minutes,previous,change
+email,120,0,120
+customer joe,0,240,-240
+rtimelog,0,60,-60
+"
+        );
+    }
+
+    #[test]
+    fn test_top() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(2, 1), date(2, 7), &config);
+        assert_eq!(
+            r.format_top(2, false, &config),
+            "Top tasks from Thursday, 2024-02-01 to Wednesday, 2024-02-07
+
+   3 h  0 min ( 43%): customer joe: support
+   2 h  0 min ( 29%): email
+
+Total work done: 7 h 0 min
+"
+        );
+        assert_eq!(
+            r.top_table(2, true).format(OutputFormat::Csv),
+            "project,minutes,percent
+customer joe,240,57
+email,120,29
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            r.format_top(1, true, &plain),
+            "Top projects from Thursday, 2024-02-01 to Wednesday, 2024-02-07
+
+customer joe: 4 hours, 57 percent
+
+Total work done: 7 hours
+"
+        );
+        let empty = PeriodReport::new(&all, date(3, 1), date(3, 7), &config);
+        assert!(empty.top(10, false).is_empty());
+        assert!(empty
+            .format_top(10, false, &config)
+            .ends_with("\nNo work logged\n"));
+    }
+
+    #[test]
+    fn test_flexitime() {
+        let tl = Timelog::new_from_string(
+            "
+2024-02-05 09:00: arrived
+2024-02-05 11:00: rtimelog: code
+2024-02-09 09:00: arrived
+2024-02-09 09:30: rtimelog: code
+
+2024-02-12 09:00: arrived
+2024-02-12 13:00: customer joe: support
+",
+        );
+        let config = Config::new_from_string("[gtimelog]\nhours = 1\n");
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        // the second week only until Tuesday
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 13), &config);
+        assert_eq!(r.overtime(&config), Duration::minutes(-30));
+        assert_eq!(
+            r.flexitime(&config),
+            vec![
+                (date(2, 5), Duration::minutes(150), Duration::minutes(-150)),
+                (date(2, 12), Duration::hours(4), Duration::hours(2)),
+            ]
+        );
+        assert_eq!(
+            r.format_flexitime(&config),
+            "Flexitime from Monday, 2024-02-05 to Tuesday, 2024-02-13
+
+Week from Monday, 2024-02-05: 2 h 30 min work, -2 h 30 min, balance -2 h 30 min
+Week from Monday, 2024-02-12: 4 h 0 min work, +2 h 0 min, balance -0 h 30 min
+
+Balance: -0 h 30 min
+"
+        );
+
+        // a holiday on Tuesday lowers the target
+        let config =
+            Config::new_from_string("[gtimelog]\nhours = 1\n[rtimelog]\nholidays = 2024-02-13\n");
+        let r = PeriodReport::new(&all, date(2, 5), date(2, 13), &config);
+        assert_eq!(
+            r.flexitime(&config)[1],
+            (date(2, 12), Duration::hours(4), Duration::hours(3))
+        );
+    }
+
+    #[test]
+    fn test_report_tables() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let r = PeriodReport::new(&all, date(1, 5), date(1, 5), &config);
+        assert_eq!(
+            r.table(GroupBy::Task, &config).format(OutputFormat::Csv),
+            "task,minutes,slack\nrtimelog: code #release,120,false\n** tea #break,30,true\n"
+        );
+        assert_eq!(
+            r.table(GroupBy::Day, &config).rows,
+            vec![vec![
+                Cell::text("2024-01-05"),
+                Cell::Time(Duration::hours(2))
+            ]]
+        );
+        let r = PeriodReport::new(&all, date(2, 1), date(2, 29), &config);
+        assert_eq!(
+            r.table(GroupBy::Project { depth: 1 }, &config)
+                .format(OutputFormat::Csv),
+            "project,level,minutes\ncustomer joe,0,240\nemail,0,120\nrtimelog,0,60\n"
+        );
+        assert_eq!(r.table(GroupBy::Week, &config).rows.len(), 5);
+
+        let (begin, end) = YearReview::range(2024, &config).unwrap();
+        let y = YearReview::new(tl.get_time_range(begin, end), 2024, &config).table();
+        assert_eq!(y.rows.len(), 3 + 2 + 12 + 1 + 19);
+        assert_eq!(
+            y.rows[3],
+            vec![
+                Cell::text("tag"),
+                Cell::text("release"),
+                Cell::Time(Duration::hours(3))
+            ]
+        );
+        assert_eq!(
+            y.rows[6],
+            vec![
+                Cell::text("month"),
+                Cell::text("2024-02"),
+                Cell::Time(Duration::hours(7))
+            ]
+        );
+        assert_eq!(
+            y.rows[17],
+            vec![
+                Cell::text("longest day"),
+                Cell::text("2024-02-01"),
+                Cell::Time(Duration::hours(4))
+            ]
+        );
+        assert_eq!(y.rows[18][0], Cell::text("day off"));
+    }
+
+    #[test]
+    fn test_team_report() {
+        let alice = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 12:00: rtimelog: code
+2024-02-05 12:30: ** lunch
+2024-02-05 14:30: customer joe: support
+
+2024-02-12 09:00: arrived
+2024-02-12 17:00: next week
+",
+        );
+        let bob = Timelog::new_from_string(
+            "2024-02-07 09:00: arrived
+2024-02-07 10:00: customer joe: support
+2024-02-07 11:00: email
+",
+        );
+        let mut config = Config::default();
+        config.sentinels.virtual_midnight = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+        let (begin, end) = TeamReport::range(date(2, 5), &config).unwrap();
+        // like the week of the other reports, up to the virtual midnight
+        assert_eq!(
+            (begin, end),
+            PeriodReport::range(date(2, 5), date(2, 11), &config).unwrap()
+        );
+        assert_eq!(end, date(2, 12).and_hms_opt(1, 59, 59).unwrap());
+        let report = TeamReport::new(
+            &[
+                ("alice".to_string(), alice.get_time_range(begin, end)),
+                ("bob".to_string(), bob.get_time_range(begin, end)),
+            ],
+            date(2, 5),
+            &config,
+        );
+        assert_eq!(report.total_work, Duration::hours(7));
+        assert_eq!(
+            report.projects,
+            vec![
+                ("rtimelog".to_string(), Duration::hours(3)),
+                ("customer joe".to_string(), Duration::hours(3)),
+                ("email".to_string(), Duration::hours(1)),
+            ]
+        );
+        assert_eq!(
+            report.format(&Config::default()),
+            "Team report for the week from Monday, 2024-02-05
+
+Total work done: 7 h 0 min
+
+Projects:
+   3 h  0 min ( 42%): rtimelog
+   3 h  0 min ( 42%): customer joe
+   1 h  0 min ( 14%): email
+
+alice: 5 h 0 min
+   3 h  0 min: rtimelog
+   2 h  0 min: customer joe
+
+bob: 2 h 0 min
+   1 h  0 min: customer joe
+   1 h  0 min: email
+"
+        );
+
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        let out = report.format(&plain);
+        assert!(out.contains("\ncustomer joe: 3 hours, 42 percent\n"));
+        assert!(out.contains("\nbob: 2 hours\ncustomer joe: 1 hour\n"));
+    }
+}
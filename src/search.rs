@@ -0,0 +1,105 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::Duration;
+use regex::Regex;
+
+use crate::activity::{self, duration_in_words};
+use crate::config::{Config, Sentinels};
+use crate::report::short;
+use crate::store::Entry;
+
+// the entries whose task matches `pattern`, with the time spent on each; see
+// activity::durations()
+pub fn grep<'a>(
+    entries: &'a [Entry],
+    pattern: &Regex,
+    sentinels: &'a Sentinels,
+) -> Vec<(&'a Entry, Duration)> {
+    activity::durations(entries, sentinels)
+        .filter(|(e, _)| pattern.is_match(&e.task))
+        .collect()
+}
+
+// one line per match, with the date, and their total
+pub fn format(matches: &[(&Entry, Duration)], config: &Config) -> String {
+    let duration = if config.plain {
+        duration_in_words
+    } else {
+        short
+    };
+    let mut out = String::new();
+    for (e, d) in matches {
+        writeln!(
+            out,
+            "{} {}: {} ({})",
+            config.date_format.format(&e.stop.date()),
+            config.date_format.time(e.stop.time()),
+            e.task,
+            duration(*d)
+        )
+        .unwrap();
+    }
+    let total = matches.iter().map(|(_, d)| *d).sum();
+    match matches.len() {
+        0 => writeln!(out, "No matching entries"),
+        1 => writeln!(out, "\n1 entry, {}", duration(total)),
+        n => writeln!(out, "\n{n} entries, {}", duration(total)),
+    }
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::NaiveDateTime;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_grep() {
+        let tl = Timelog::new_from_string(
+            "
+2024-05-06 09:00: arrived
+2024-05-06 10:00: customer joe: fix login
+2024-05-06 11:30: acme: Login page
+
+2024-05-07 09:00: customer joe: login again
+2024-05-07 09:45: customer joe: logout
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let sentinels = Sentinels::default();
+        let config = Config::default();
+        let matches = grep(entries, &Regex::new("(?i)login").unwrap(), &sentinels);
+        assert_eq!(
+            format(&matches, &config),
+            "Monday, 2024-05-06 10:00: customer joe: fix login (1h)
+Monday, 2024-05-06 11:30: acme: Login page (1h 30m)
+Tuesday, 2024-05-07 09:00: customer joe: login again (0m)
+
+3 entries, 2h 30m
+"
+        );
+        let matches = grep(entries, &Regex::new("joe: logout").unwrap(), &sentinels);
+        assert_eq!(matches.len(), 1);
+        assert!(format(&matches, &config).ends_with("\n\n1 entry, 45m\n"));
+        let matches = grep(entries, &Regex::new("meeting").unwrap(), &sentinels);
+        assert_eq!(format(&matches, &config), "No matching entries\n");
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::store::Timelog;
+
+/**
+ * Handle to a Timelog which is used by several threads: the prompt, the background loading of
+ * older entries, the reminders, and the daemon. Clones refer to the same Timelog.
+ *
+ * Reports only need read(), so they can run at the same time; adding, saving, and reloading need
+ * write(). Don't hold a guard while waiting for user input, otherwise the other threads stall.
+ */
+#[derive(Debug, Default, Clone)]
+pub struct SharedTimelog(Arc<RwLock<Timelog>>);
+
+impl SharedTimelog {
+    pub fn new(timelog: Timelog) -> SharedTimelog {
+        SharedTimelog(Arc::new(RwLock::new(timelog)))
+    }
+
+    // a thread which panicked while holding the lock cannot leave the entries half-sorted (all
+    // changes are single insertions), so keep going with them instead of propagating the panic
+    pub fn read(&self) -> RwLockReadGuard<'_, Timelog> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, Timelog> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // swap in a freshly loaded Timelog, e.g. after editing the file
+    pub fn replace(&self, timelog: Timelog) -> Timelog {
+        std::mem::replace(&mut *self.write(), timelog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use chrono::{Duration, NaiveDate, NaiveTime};
+    use pretty_assertions::assert_eq;
+    use std::thread;
+
+    #[test]
+    fn test_shared_timelog() {
+        let storage = MemoryStorage::new("2022-06-10 08:00: arrived\n");
+        let shared =
+            SharedTimelog::new(Timelog::new_from_storage(Box::new(storage.clone())).unwrap());
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        // looking at the last entry and adding after it is atomic
+                        let mut tl = shared.write();
+                        let stop = tl.get_n_days(&day, 1, NaiveTime::MIN).last().unwrap().stop;
+                        tl.add_at(format!("task {i}"), stop + Duration::minutes(1))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        // readers see consistent snapshots while the writers are busy
+        let reader = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let tl = shared.read();
+                    let entries = tl.get_n_days(&day, 1, NaiveTime::MIN);
+                    assert!(entries.windows(2).all(|w| w[0].stop <= w[1].stop));
+                }
+            })
+        };
+        for t in writers {
+            t.join().unwrap();
+        }
+        reader.join().unwrap();
+
+        assert_eq!(shared.read().get_n_days(&day, 1, NaiveTime::MIN).len(), 41);
+        shared.write().save().unwrap();
+        assert_eq!(storage.contents().lines().count(), 41);
+
+        let old = shared.replace(Timelog::new_from_string(""));
+        assert_eq!(old.get_n_days(&day, 1, NaiveTime::MIN).len(), 41);
+        assert_eq!(shared.read().get_n_days(&day, 1, NaiveTime::MIN).len(), 0);
+    }
+
+    #[test]
+    fn test_shared_timelog_poisoned() {
+        let shared = SharedTimelog::new(Timelog::new_from_string("2022-06-10 08:00: arrived\n"));
+        let poisoner = shared.clone();
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.write();
+            panic!("boom");
+        })
+        .join();
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        assert_eq!(shared.read().get_n_days(&day, 1, NaiveTime::MIN).len(), 1);
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::activity::{self, Activities};
+use crate::config::Config;
+use crate::store::Timelog;
+
+// the last day with entries before `today`, which usually is yesterday or the last Friday
+pub fn previous_day(timelog: &Timelog, config: &Config, today: NaiveDate) -> Option<NaiveDate> {
+    let begin = today.and_time(config.sentinels.virtual_midnight);
+    let last = timelog.get_time_range(NaiveDateTime::MIN, begin).last()?;
+    Some(config.sentinels.day_of(last.stop))
+}
+
+// the tasks of `day` without slacking, with their time
+fn tasks(timelog: &Timelog, config: &Config, day: NaiveDate) -> Vec<(String, Duration)> {
+    let entries = timelog.get_n_days(&day, 1, config.sentinels.virtual_midnight);
+    Activities::new_from_entries(entries, &config.sentinels)
+        .iter()
+        .filter(|(task, d)| !activity::is_slack(task) && *d > Duration::zero())
+        .map(|(task, d)| (task.to_string(), d))
+        .collect()
+}
+
+/**
+ * What got done on the previous working day and so far today, as a list for pasting into a chat
+ */
+pub fn summary(timelog: &Timelog, config: &Config, today: NaiveDate) -> String {
+    let mut output = String::new();
+    let mut section = |heading: &str, tasks: Vec<(String, Duration)>| {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        writeln!(output, "{heading}:").unwrap();
+        if tasks.is_empty() {
+            writeln!(output, "- nothing logged").unwrap();
+        }
+        for (task, d) in tasks {
+            let time = if config.plain {
+                activity::duration_in_words(d)
+            } else {
+                format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+            };
+            writeln!(output, "- {task} ({time})").unwrap();
+        }
+    };
+
+    if let Some(day) = previous_day(timelog, config, today) {
+        let heading = if today.pred_opt() == Some(day) {
+            "Yesterday".to_string()
+        } else if today - day < Duration::days(7) {
+            day.format("%A").to_string()
+        } else {
+            config.date_format.format(&day)
+        };
+        section(&heading, tasks(timelog, config, day));
+    }
+    section("Today (so far)", tasks(timelog, config, today));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 2, d).unwrap()
+    }
+
+    const LOG: &str = "
+2024-02-01 09:00: arrived
+2024-02-01 10:00: rtimelog: code
+
+2024-02-02 09:00: arrived
+2024-02-02 10:00: customer joe: support
+2024-02-02 10:30: rtimelog: docs
+2024-02-02 11:00: ** tea
+2024-02-02 12:00: customer joe: support
+
+2024-02-05 09:00: arrived
+2024-02-05 09:15: email
+";
+
+    #[test]
+    fn test_previous_day() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        assert_eq!(previous_day(&tl, &config, day(5)), Some(day(2)));
+        assert_eq!(previous_day(&tl, &config, day(2)), Some(day(1)));
+        assert_eq!(previous_day(&tl, &config, day(1)), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let tl = Timelog::new_from_string(LOG);
+        let config = Config::default();
+        assert_eq!(
+            summary(&tl, &config, day(5)),
+            "Friday:
+- customer joe: support (2 h 0 min)
+- rtimelog: docs (0 h 30 min)
+
+Today (so far):
+- email (0 h 15 min)
+"
+        );
+        assert_eq!(
+            summary(&tl, &config, day(2)),
+            "Yesterday:
+- rtimelog: code (1 h 0 min)
+
+Today (so far):
+- customer joe: support (2 h 0 min)
+- rtimelog: docs (0 h 30 min)
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            summary(&tl, &plain, day(20)),
+            "Monday, 2024-02-05:
+- email (15 minutes)
+
+Today (so far):
+- nothing logged
+"
+        );
+        assert_eq!(
+            summary(&Timelog::new_from_string(""), &config, day(5)),
+            "Today (so far):\n- nothing logged\n"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::{Duration, NaiveDate, NaiveTime, Timelike};
+
+use crate::activity::{self, duration_in_words};
+use crate::config::Config;
+use crate::report::short;
+use crate::store::Entry;
+
+// how many tasks the top list has
+const TOP_TASKS: usize = 10;
+
+// change of the work from one week to the next which still counts as the same
+const STEADY_PERCENT: i64 = 10;
+
+/**
+ * Averages and trends of the days from `first` to `last`
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+    // days with work
+    pub days: usize,
+    // of the days with work
+    pub average_work: Duration,
+    // from the first to the last entry of a day
+    pub average_workday: Duration,
+    // of the first entry of a day
+    pub average_arrival: Option<NaiveTime>,
+    // (task, work), most work first
+    pub top_tasks: Vec<(String, Duration)>,
+    // (first day, work) of each week, see Config::week_start
+    pub weeks: Vec<(NaiveDate, Duration)>,
+}
+
+impl Stats {
+    pub fn new(entries: &[Entry], first: NaiveDate, last: NaiveDate, config: &Config) -> Stats {
+        let sentinels = &config.sentinels;
+        let day_of = |e: &Entry| sentinels.day_of(e.stop);
+        let mut tasks: Vec<(String, Duration)> = Vec::new();
+        let mut weeks: Vec<(NaiveDate, Duration)> = Vec::new();
+        // a log shorter than the range did not have weeks without work before its first entry
+        let start = entries.first().map_or(first, |e| day_of(e).max(first));
+        let mut week = start.week(config.week_start).first_day();
+        while week <= last {
+            weeks.push((week, Duration::zero()));
+            week += Duration::weeks(1);
+        }
+        let (mut days, mut work, mut workday, mut arrival_seconds) =
+            (0, Duration::zero(), Duration::zero(), 0);
+
+        let begin = entries.partition_point(|e| day_of(e) < first);
+        let end = entries.partition_point(|e| day_of(e) <= last);
+        for day in entries[begin..end.max(begin)].chunk_by(|a, b| day_of(a) == day_of(b)) {
+            // vacation and sick days are no normal working days
+            if day.iter().any(|e| config.absence(&e.task).is_some()) {
+                continue;
+            }
+            let mut day_work = Duration::zero();
+            for (e, d) in activity::durations(day, sentinels) {
+                if e.is_slack() || d <= Duration::zero() {
+                    continue;
+                }
+                day_work += d;
+                match tasks.iter_mut().find(|(t, _)| *t == *e.task) {
+                    Some((_, sum)) => *sum += d,
+                    None => tasks.push((e.task.to_string(), d)),
+                }
+            }
+            if day_work <= Duration::zero() {
+                continue;
+            }
+            days += 1;
+            work += day_work;
+            let (start, stop) = (day[0].stop, day[day.len() - 1].stop);
+            workday += stop - start;
+            arrival_seconds += i64::from(start.num_seconds_from_midnight());
+            let i = (day_of(&day[0]) - weeks[0].0).num_weeks() as usize;
+            weeks[i].1 += day_work;
+        }
+        tasks.sort_by_key(|(_, d)| -*d);
+        tasks.truncate(TOP_TASKS);
+        let average = |d: Duration| d / (days.max(1) as i32);
+        Stats {
+            first,
+            last,
+            days,
+            average_work: average(work),
+            average_workday: average(workday),
+            average_arrival: (days > 0)
+                .then(|| {
+                    NaiveTime::from_num_seconds_from_midnight_opt(
+                        (arrival_seconds / days as i64) as u32,
+                        0,
+                    )
+                })
+                .flatten(),
+            top_tasks: tasks,
+            weeks,
+        }
+    }
+
+    pub fn format(&self, config: &Config) -> String {
+        let plain = config.plain;
+        let duration = if plain { duration_in_words } else { short };
+        let mut out = String::new();
+        writeln!(
+            out,
+            "Statistics from {} to {}\n",
+            config.date_format.format(&self.first),
+            config.date_format.format(&self.last)
+        )
+        .unwrap();
+        if self.days == 0 {
+            writeln!(out, "No work logged").unwrap();
+            return out;
+        }
+        writeln!(out, "Days with work: {}", self.days).unwrap();
+        writeln!(out, "Average work per day: {}", duration(self.average_work)).unwrap();
+        writeln!(
+            out,
+            "Average workday: {}, from the first to the last entry",
+            duration(self.average_workday)
+        )
+        .unwrap();
+        if let Some(arrival) = self.average_arrival {
+            let start = config.date_format.time(arrival);
+            writeln!(out, "Average start: {start}").unwrap();
+        }
+
+        writeln!(out, "\nTop tasks:").unwrap();
+        for (task, d) in &self.top_tasks {
+            match plain {
+                true => writeln!(out, "{task}: {}", duration(*d)),
+                false => writeln!(out, "{:>8}: {task}", duration(*d)),
+            }
+            .unwrap();
+        }
+
+        writeln!(out, "\nWeeks:").unwrap();
+        let mut previous = None;
+        for (first, d) in &self.weeks {
+            let trend = match previous.map(|p| trend(p, *d)) {
+                Some(t) if plain => format!(", {}", t.1),
+                Some(t) => format!(" {}", t.0),
+                None => String::new(),
+            };
+            let from = config.date_format.format(first);
+            match plain {
+                true => writeln!(out, "from {from}: {}{trend}", duration(*d)),
+                false => writeln!(out, "{:>8}: from {from}{trend}", duration(*d)),
+            }
+            .unwrap();
+            previous = Some(*d);
+        }
+        out
+    }
+}
+
+// (arrow, word) of the change from `before` to `after`
+fn trend(before: Duration, after: Duration) -> (&'static str, &'static str) {
+    let (before, after) = (before.num_minutes(), after.num_minutes());
+    let change = (after - before) * 100;
+    if change > STEADY_PERCENT * before {
+        ("↑", "more")
+    } else if change < -STEADY_PERCENT * before {
+        ("↓", "less")
+    } else {
+        ("→", "about the same")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use chrono::NaiveDateTime;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_stats() {
+        let tl = Timelog::new_from_string(
+            "
+2024-04-26 09:00: arrived
+2024-04-26 17:00: before the range
+
+2024-04-29 09:00: arrived
+2024-04-29 12:00: customer joe: support
+2024-04-29 12:30: ** lunch
+2024-04-29 17:00: rtimelog: code
+
+2024-04-30 08:00: arrived
+2024-04-30 16:00: customer joe: support
+
+2024-05-02 09:00: arrived
+2024-05-02 17:00: vacation
+
+2024-05-06 10:00: arrived
+2024-05-06 18:00: rtimelog: code
+",
+        );
+        let entries = tl.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX);
+        let config = Config::default();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let stats = Stats::new(entries, day(4, 29), day(5, 19), &config);
+        // not the vacation day
+        assert_eq!(stats.days, 3);
+        assert_eq!(stats.average_work, Duration::minutes(470));
+        assert_eq!(stats.average_workday, Duration::hours(8));
+        assert_eq!(stats.average_arrival, NaiveTime::from_hms_opt(9, 0, 0));
+        assert_eq!(
+            stats.format(&config),
+            "Statistics from Monday, 2024-04-29 to Sunday, 2024-05-19
+
+Days with work: 3
+Average work per day: 7h 50m
+Average workday: 8h, from the first to the last entry
+Average start: 09:00
+
+Top tasks:
+ 12h 30m: rtimelog: code
+     11h: customer joe: support
+
+Weeks:
+ 15h 30m: from Monday, 2024-04-29
+      8h: from Monday, 2024-05-06 ↓
+      0m: from Monday, 2024-05-13 ↓
+"
+        );
+        let plain = Config {
+            plain: true,
+            ..Config::default()
+        };
+        assert!(stats
+            .format(&plain)
+            .contains("\nfrom Monday, 2024-05-06: 8 hours, less\n"));
+
+        assert_eq!(trend(Duration::hours(40), Duration::hours(42)).0, "→");
+        assert_eq!(trend(Duration::hours(40), Duration::hours(45)).0, "↑");
+        assert_eq!(trend(Duration::zero(), Duration::zero()).0, "→");
+        // a log which starts within the range
+        let stats = Stats::new(&entries[10..], day(4, 15), day(5, 19), &config);
+        assert_eq!(stats.days, 1);
+        assert_eq!(stats.weeks[0], (day(5, 6), Duration::hours(8)));
+        assert!(stats.format(&config).ends_with(
+            "Weeks:\n      8h: from Monday, 2024-05-06\n      0m: from Monday, 2024-05-13 ↓\n"
+        ));
+
+        let empty = Stats::new(&[], day(4, 29), day(5, 19), &config);
+        assert_eq!(empty.average_arrival, None);
+        assert!(empty.format(&config).ends_with("\n\nNo work logged\n"));
+    }
+}
@@ -0,0 +1,556 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, prelude::*, BufReader, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::index;
+use crate::journal;
+use crate::store::{Entry, Timelog};
+
+// saving takes milliseconds, so a lock which is held longer belongs to a hanging program
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+
+// Text of a stored timelog: without a byte order mark, and with invalid UTF-8 (like a latin-1
+// byte from an old editor) replaced, so that the rest of the file stays readable.
+pub fn decode(bytes: Vec<u8>) -> String {
+    let contents = match String::from_utf8(bytes) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("WARNING: timelog is not valid UTF-8, replacing the invalid characters");
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    };
+    match contents.strip_prefix('\u{feff}') {
+        Some(rest) => rest.to_string(),
+        None => contents,
+    }
+}
+
+/**
+ * Exclusive access to a Storage, until this gets dropped
+ */
+#[derive(Debug)]
+pub struct StorageLock {
+    _file: Option<fs::File>,
+}
+
+/**
+ * Where a Timelog gets loaded from and saved to
+ *
+ * This keeps the parsing and reporting code free of file system access, so that it also works
+ * e.g. in a browser.
+ *
+ * Other backends, like a database or a remote service, only need load() and save(). The other
+ * methods default to these, and a backend can replace them with faster ones: append() for
+ * adding an entry, reader_at() for loading only the recent ones. The contents are always in the
+ * timelog.txt format, so that notes and comments survive any backend.
+ *
+ * Tools which only deal with entries can use load_entries(), append_entry() and rewrite() on
+ * top of that.
+ */
+pub trait Storage: fmt::Debug + Send + Sync {
+    // the stored contents; empty if there is nothing stored yet
+    fn load(&self) -> Result<String, io::Error>;
+
+    fn save(&self, contents: &str) -> Result<(), io::Error>;
+
+    // add `contents` to the end, on a line of its own
+    fn append(&self, contents: &str) -> Result<(), io::Error> {
+        let mut all = self.load()?;
+        if !all.is_empty() && !all.ends_with('\n') {
+            all.push('\n');
+        }
+        all.push_str(contents);
+        self.save(&all)
+    }
+
+    // read the contents line by line, without loading all of them into memory
+    fn reader(&self) -> Result<Box<dyn BufRead + '_>, io::Error> {
+        Ok(Box::new(io::Cursor::new(self.load()?)))
+    }
+
+    // size in bytes, if reader_at() can jump to an offset quickly
+    fn size(&self) -> Option<u64> {
+        None
+    }
+
+    // read from byte `offset` on
+    fn reader_at(&self, offset: u64) -> Result<Box<dyn BufRead + '_>, io::Error> {
+        let mut reader = self.reader()?;
+        io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+        Ok(reader)
+    }
+
+    // file name for editing, if the storage is a file
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    // time of the last change, to notice changes from other programs
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
+
+    // byte offset of the first entry on `day` or later, if the storage keeps an index of that
+    fn day_offset(&self, _day: NaiveDate) -> Option<u64> {
+        None
+    }
+
+    // keep other programs from changing the storage; fails with WouldBlock if another one
+    // holds the lock for too long
+    fn lock(&self) -> Result<StorageLock, io::Error> {
+        Ok(StorageLock { _file: None })
+    }
+
+    // the stored entries, without their notes
+    fn load_entries(&self) -> Result<Vec<Entry>, io::Error> {
+        let timelog = Timelog::new_from_string(&self.load()?);
+        Ok(timelog
+            .get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX)
+            .iter()
+            .map(|e| Entry {
+                note: None,
+                ..e.clone()
+            })
+            .collect())
+    }
+
+    // add `entry` at the end, after an empty line if it starts a new day
+    fn append_entry(&self, entry: &Entry) -> Result<(), io::Error> {
+        let last = self.load_entries()?.last().map(|e| e.stop.date());
+        let new_day = last.is_some_and(|day| day != entry.stop.date());
+        self.append(&format!("{}{entry}\n", if new_day { "\n" } else { "" }))
+    }
+
+    // replace the contents with `entries`, with an empty line between days; this drops all notes
+    // and comments
+    fn rewrite(&self, entries: &[Entry]) -> Result<(), io::Error> {
+        let mut contents = String::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 && entries[i - 1].stop.date() != entry.stop.date() {
+                contents.push('\n');
+            }
+            contents.push_str(&format!("{entry}\n"));
+        }
+        self.save(&contents)
+    }
+}
+
+/**
+ * Storage in a local file
+ */
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: &Path) -> FileStorage {
+        FileStorage {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self) -> Result<String, io::Error> {
+        match fs::read(&self.path) {
+            Ok(contents) => Ok(decode(contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("No existing {}, starting new log", self.path.display());
+                Ok(String::new())
+            }
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {e}", self.path.display()),
+            )),
+        }
+    }
+
+    fn reader(&self) -> Result<Box<dyn BufRead + '_>, io::Error> {
+        match fs::File::open(&self.path) {
+            Ok(f) => Ok(Box::new(BufReader::new(f))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("No existing {}, starting new log", self.path.display());
+                Ok(Box::new(io::empty()))
+            }
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {e}", self.path.display()),
+            )),
+        }
+    }
+
+    fn size(&self) -> Option<u64> {
+        fs::metadata(&self.path).map(|m| m.len()).ok()
+    }
+
+    fn reader_at(&self, offset: u64) -> Result<Box<dyn BufRead + '_>, io::Error> {
+        if offset == 0 {
+            // also handles a missing file
+            return self.reader();
+        }
+        let mut f = fs::File::open(&self.path)?;
+        f.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(BufReader::new(f)))
+    }
+
+    fn save(&self, contents: &str) -> Result<(), io::Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&self.path, contents.as_bytes())?;
+        if let Err(e) = journal::write(&self.path, contents) {
+            eprintln!("WARNING: Failed to write journal: {e}");
+        }
+        if let Err(e) = index::write(&self.path, contents) {
+            eprintln!("WARNING: Failed to write index: {e}");
+        }
+        Ok(())
+    }
+
+    // like gtimelog, don't rewrite years of history for every new entry
+    fn append(&self, contents: &str) -> Result<(), io::Error> {
+        let mut f = fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        let metadata = f.metadata()?;
+        let length = metadata.len();
+        let mut appended = String::new();
+        if length > 0 {
+            let mut last = [0];
+            f.seek(SeekFrom::Start(length - 1))?;
+            f.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                appended.push('\n');
+            }
+        }
+        appended.push_str(contents);
+        f.write_all(appended.as_bytes())?;
+        if let Err(e) = journal::append(&self.path, length, &appended) {
+            eprintln!("WARNING: Failed to write journal: {e}");
+        }
+        if let Err(e) = index::append(&self.path, length, metadata.modified().ok(), &appended) {
+            eprintln!("WARNING: Failed to write index: {e}");
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    // from .timelog.idx, which gets updated on saving; gtimelog and editors don't, but then the
+    // size or time of the file does not match any more
+    fn day_offset(&self, day: NaiveDate) -> Option<u64> {
+        index::load(&self.path).map(|index| index.offset_of(day))
+    }
+
+    // Advisory lock on timelog.txt.lock; not on the file itself, as saving replaces that.
+    // gtimelog does not lock, so this only protects against other rtimelog instances.
+    fn lock(&self) -> Result<StorageLock, io::Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut lock_path = self.path.clone().into_os_string();
+        lock_path.push(".lock");
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(PathBuf::from(lock_path))?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(StorageLock { _file: Some(file) }),
+                Err(fs::TryLockError::WouldBlock) if start.elapsed() < LOCK_WAIT => {
+                    thread::sleep(Duration::from_millis(20))
+                }
+                Err(fs::TryLockError::WouldBlock) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        format!("{} is locked by another process", self.path.display()),
+                    ))
+                }
+                // e.g. file systems without locking
+                Err(fs::TryLockError::Error(e)) if e.kind() == io::ErrorKind::Unsupported => {
+                    return Ok(StorageLock { _file: None })
+                }
+                Err(fs::TryLockError::Error(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+// Write to a temporary file next to `path` and rename it over `path`, so that a crash or a full
+// disk leaves either the old or the new contents behind, never a truncated file.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    // replace the file a symlink points to, not the symlink
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut f = fs::File::create(&tmp)?;
+    let written = f.write_all(contents).and_then(|()| f.sync_all());
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    if let Ok(meta) = fs::metadata(&path) {
+        fs::set_permissions(&tmp, meta.permissions())?;
+    }
+    fs::rename(&tmp, &path)?;
+    // make the rename itself durable
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/**
+ * Storage in memory, e.g. for an uploaded file; clones share the contents
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    contents: Arc<Mutex<String>>,
+}
+
+impl MemoryStorage {
+    pub fn new(contents: &str) -> MemoryStorage {
+        MemoryStorage {
+            contents: Arc::new(Mutex::new(contents.to_string())),
+        }
+    }
+
+    pub fn contents(&self) -> String {
+        self.contents.lock().unwrap().clone()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<String, io::Error> {
+        Ok(self.contents())
+    }
+
+    fn save(&self, contents: &str) -> Result<(), io::Error> {
+        *self.contents.lock().unwrap() = contents.to_string();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    // an empty directory of its own for each test, as saving also writes the journal and index
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rtimelog-storage-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_all(mut reader: Box<dyn BufRead + '_>) -> String {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = temp_dir("append");
+        let path = dir.join("timelog.txt");
+        let storage = FileStorage::new(&path);
+        // creates a missing file
+        storage.append("2022-06-09 06:02: arrived\n").unwrap();
+        assert_eq!(storage.load().unwrap(), "2022-06-09 06:02: arrived\n");
+
+        // the new contents go on a line of their own
+        fs::write(&path, "2022-06-09 06:02: arrived").unwrap();
+        storage.append("2022-06-09 06:27: email\n").unwrap();
+        assert_eq!(
+            storage.load().unwrap(),
+            "2022-06-09 06:02: arrived\n2022-06-09 06:27: email\n"
+        );
+
+        let memory = MemoryStorage::new("2022-06-09 06:02: arrived");
+        memory.append("2022-06-09 06:27: email\n").unwrap();
+        assert_eq!(
+            memory.contents(),
+            "2022-06-09 06:02: arrived\n2022-06-09 06:27: email\n"
+        );
+        let memory = MemoryStorage::default();
+        memory.append("2022-06-09 06:02: arrived\n").unwrap();
+        assert_eq!(memory.contents(), "2022-06-09 06:02: arrived\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reader_at() {
+        let log = "2022-06-09 06:02: arrived\n2022-06-09 06:27: email\n";
+        let dir = temp_dir("reader");
+        let path = dir.join("timelog.txt");
+        let storage = FileStorage::new(&path);
+        // a missing file is empty
+        assert_eq!(read_all(storage.reader().unwrap()), "");
+        assert_eq!(read_all(storage.reader_at(0).unwrap()), "");
+
+        storage.save(log).unwrap();
+        assert_eq!(storage.size(), Some(log.len() as u64));
+        assert_eq!(read_all(storage.reader_at(0).unwrap()), log);
+        assert_eq!(
+            read_all(storage.reader_at(26).unwrap()),
+            "2022-06-09 06:27: email\n"
+        );
+        assert_eq!(read_all(storage.reader_at(log.len() as u64).unwrap()), "");
+
+        // the default skips to the offset, without knowing the size
+        let memory = MemoryStorage::new(log);
+        assert_eq!(memory.size(), None);
+        assert_eq!(
+            read_all(memory.reader_at(26).unwrap()),
+            "2022-06-09 06:27: email\n"
+        );
+        assert_eq!(read_all(memory.reader_at(1000).unwrap()), "");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_save_symlink() {
+        let dir = temp_dir("symlink");
+        let target = dir.join("synced.txt");
+        let link = dir.join("timelog.txt");
+        fs::write(&target, "2022-06-09 06:02: arrived\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        // the file behind the link gets replaced, the link stays
+        let storage = FileStorage::new(&link);
+        storage.save("2022-06-09 06:27: email\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "2022-06-09 06:27: email\n"
+        );
+        assert_eq!(storage.load().unwrap(), "2022-06-09 06:27: email\n");
+        assert!(!dir.join("synced.txt.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock() {
+        let dir = temp_dir("lock");
+        let path = dir.join("timelog.txt");
+        let storage = FileStorage::new(&path);
+        let lock = storage.lock().unwrap();
+
+        // a second lock waits for LOCK_WAIT, then gives up
+        let start = Instant::now();
+        let e = FileStorage::new(&path).lock().unwrap_err();
+        assert!(start.elapsed() >= LOCK_WAIT);
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(
+            e.to_string(),
+            format!("{} is locked by another process", path.display())
+        );
+
+        drop(lock);
+        drop(storage.lock().unwrap());
+
+        // nothing to wait for in memory
+        let memory = MemoryStorage::default();
+        let _first = memory.lock().unwrap();
+        memory.lock().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_memory() {
+        let memory = MemoryStorage::new("2022-06-09 06:02: arrived\n");
+        // clones share the contents
+        let clone = memory.clone();
+        clone.save("2022-06-09 06:27: email\n").unwrap();
+        assert_eq!(memory.load().unwrap(), "2022-06-09 06:27: email\n");
+        assert_eq!(
+            read_all(memory.reader().unwrap()),
+            "2022-06-09 06:27: email\n"
+        );
+        assert_eq!(memory.path(), None);
+        assert_eq!(memory.modified(), None);
+        assert_eq!(memory.day_offset(NaiveDate::MIN), None);
+    }
+
+    #[test]
+    fn test_entries() {
+        let memory = MemoryStorage::new("2022-06-09 06:02: arrived\n  with a note\n");
+        let mut entries = memory.load_entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(&*entries[0].task, "arrived");
+        assert_eq!(entries[0].note, None);
+
+        let entry = |time: &str, task: &str| Entry {
+            stop: NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap(),
+            task: task.into(),
+            note: None,
+        };
+        memory
+            .append_entry(&entry("2022-06-09 06:27", "email"))
+            .unwrap();
+        memory
+            .append_entry(&entry("2022-06-10 08:00", "arrived"))
+            .unwrap();
+        assert_eq!(
+            memory.contents(),
+            "2022-06-09 06:02: arrived
+  with a note
+2022-06-09 06:27: email
+
+2022-06-10 08:00: arrived
+"
+        );
+
+        entries.push(entry("2022-06-10 08:00", "arrived"));
+        memory.rewrite(&entries).unwrap();
+        assert_eq!(
+            memory.contents(),
+            "2022-06-09 06:02: arrived\n\n2022-06-10 08:00: arrived\n"
+        );
+        assert_eq!(memory.load_entries().unwrap(), entries);
+
+        let empty = MemoryStorage::new("");
+        empty.append_entry(&entries[0]).unwrap();
+        assert_eq!(empty.contents(), "2022-06-09 06:02: arrived\n");
+        empty.rewrite(&[]).unwrap();
+        assert_eq!(empty.load_entries().unwrap(), vec![]);
+    }
+}
@@ -20,26 +20,403 @@ use std::collections::HashSet;
 use std::env;
 use std::fmt;
 use std::fmt::Write as _; // import without risk of name clashing
-use std::fs::{self, File};
-use std::io::{self, prelude::*};
-use std::path::PathBuf;
+use std::io::{self, BufRead};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
-use chrono::{prelude::*, Duration, Local, NaiveDate, NaiveDateTime, Weekday};
+use chrono::{prelude::*, Duration, IsoWeek, Local, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::activity;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{DateFormat, Sentinels, Snap};
+use crate::storage::{FileStorage, Storage};
 
 /**
  * Single timelog entry
  */
 const TIME_FMT: &str = "%Y-%m-%d %H:%M";
 
+// formats of hand edited or imported files, tried in this order after the fast path; saving
+// writes them like TIME_FMT
+const TIME_FORMATS: [&str; 6] = [
+    TIME_FMT,
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+];
+
+// environment variable which overrides the data directory
+#[cfg(not(windows))]
+const DATA_DIR_ENV: &str = "XDG_DATA_HOME";
+#[cfg(windows)]
+const DATA_DIR_ENV: &str = "APPDATA";
+
+// longer lines are certainly garbage, don't read them into memory
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+// dates outside of these are garbage as well, and would make date calculations overflow
+const VALID_YEARS: std::ops::RangeInclusive<i32> = 1..=9999;
+
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entry {
     pub stop: NaiveDateTime,
-    pub task: String,
+    // shared between all entries with the same task, see Timelog::intern()
+    pub task: Arc<str>,
+    // free-form text from the indented lines after the entry, see Timelog::note(); only
+    // meaningful within its Timelog, so serializing the Timelog includes the text instead
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub note: Option<NoteId>,
+}
+
+/**
+ * Reference to the note of an entry in its Timelog, and to the other lines that belong to it
+ *
+ * Few entries have notes, so keeping them out of Entry keeps that at 32 bytes.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteId(NonZeroU32);
+
+/**
+ * Text of an entry besides its time and task
+ */
+#[derive(Debug, Clone, Default)]
+struct Attached {
+    // from the indented lines after the entry
+    note: String,
+    // Lines before the entry which are neither entries nor notes, like comments, with the empty
+    // lines between them and the entry before; saving writes them verbatim.
+    leader: String,
+    // UTC offset written after the time, and the one of the first entry of that day; the stop
+    // time gets converted to the latter, so that durations stay right across offset changes
+    offset: Option<(FixedOffset, FixedOffset)>,
+}
+
+// The parts of the task, see activity::project() and the functions after it. They get parsed on
+// demand, to keep Entry small.
+impl Entry {
+    pub fn is_slack(&self) -> bool {
+        activity::is_slack(&self.task)
+    }
+
+    pub fn project(&self) -> &str {
+        activity::project(&self.task)
+    }
+
+    pub fn subproject(&self) -> Option<&str> {
+        activity::subproject(&self.task)
+    }
+
+    pub fn text(&self) -> &str {
+        activity::text(&self.task)
+    }
+
+    pub fn tags(&self) -> Vec<&str> {
+        activity::tags(&self.task)
+    }
 }
 
 impl fmt::Display for Entry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.stop.format(TIME_FMT), self.task)
+        write_time(f, self.stop)?;
+        write!(f, ": {}", self.task)
+    }
+}
+
+// Same as TIME_FMT, but much faster than parsing the format string each time. Times with seconds
+// (see Snap::seconds) get them as well, so that files with and without them stay the same.
+fn write_time(out: &mut impl fmt::Write, time: NaiveDateTime) -> fmt::Result {
+    write!(
+        out,
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute()
+    )?;
+    match time.second() {
+        0 => Ok(()),
+        s => write!(out, ":{s:02}"),
+    }
+}
+
+// `time` at UTC offset `from`, at UTC offset `to`
+fn convert(time: NaiveDateTime, from: FixedOffset, to: FixedOffset) -> NaiveDateTime {
+    time + Duration::seconds((to.local_minus_utc() - from.local_minus_utc()) as i64)
+}
+
+// fast path for the usual fixed width TIME_FMT, optionally with seconds
+fn parse_time(time: &str) -> Option<NaiveDateTime> {
+    let b = time.as_bytes();
+    if !(b.len() == 16 || b.len() == 19 && b[16] == b':')
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || b[10] != b' '
+        || b[13] != b':'
+    {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| -> Option<u32> {
+        b[range].iter().try_fold(0, |n, &c| {
+            c.is_ascii_digit().then(|| n * 10 + (c - b'0') as u32)
+        })
+    };
+    let seconds = if b.len() == 19 { num(17..19)? } else { 0 };
+    NaiveDate::from_ymd_opt(num(0..4)? as i32, num(5..7)?, num(8..10)?)?.and_hms_opt(
+        num(11..13)?,
+        num(14..16)?,
+        seconds,
+    )
+}
+
+// The time of an entry line, with the UTC offset which may follow it, like in
+// "2023-03-26 09:00 +0200". Without offset, that's the timelog.txt format of gtimelog.
+fn parse_stamp(stamp: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    let (time, offset) = match stamp.rsplit_once(' ') {
+        Some((time, offset)) if offset.starts_with(['+', '-']) => (time, Some(offset)),
+        _ => (stamp, None),
+    };
+    let offset = match offset {
+        Some(offset) => Some(parse_offset(offset)?),
+        None => None,
+    };
+    parse_time(time)
+        .or_else(|| {
+            TIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(time, f).ok())
+        })
+        .filter(|dt| VALID_YEARS.contains(&dt.year()))
+        .map(|dt| (dt, offset))
+}
+
+// like "+0200" or "-0430"
+fn parse_offset(offset: &str) -> Option<FixedOffset> {
+    let b = offset.as_bytes();
+    if b.len() != 5 || !b[1..].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let num = |i: usize| ((b[i] - b'0') * 10 + (b[i + 1] - b'0')) as i32;
+    let seconds = num(1) * 3600 + num(3) * 60;
+    match b[0] {
+        b'+' => FixedOffset::east_opt(seconds),
+        _ => FixedOffset::west_opt(seconds),
+    }
+}
+
+fn format_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{sign}{:02}{:02}", minutes / 60, minutes % 60)
+}
+
+// empty, an entry, or a note line; without warnings
+pub(crate) fn is_valid_line(line: &str) -> bool {
+    is_note_line(line) || line.trim().is_empty() || parse_entry_line(line).is_some()
+}
+
+// time and task of an entry line, without warnings; None for all other lines
+pub(crate) fn parse_entry_line(line: &str) -> Option<(NaiveDateTime, &str)> {
+    if is_note_line(line) {
+        return None;
+    }
+    let (time, task) = line.trim().split_once(": ")?;
+    parse_stamp(time).map(|(dt, _)| (dt, task))
+}
+
+// The ISO week of the week with `day`, which begins on `week_start`: the one of its fourth day,
+// which is the ISO week with most of its days. It is just the ISO week of `day` for weeks from
+// Monday.
+fn iso_week(day: &NaiveDate, week_start: Weekday) -> IsoWeek {
+    (day.week(week_start).first_day() + Duration::days(3)).iso_week()
+}
+
+// indented non-empty lines after an entry are its note
+fn is_note_line(line: &str) -> bool {
+    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+}
+
+// Read up to and including the next newline into `buf`, but keep at most MAX_LINE_BYTES of it,
+// so that a huge line in a broken file does not need huge amounts of memory. Returns the number
+// of bytes read (0 at the end), and whether the line was cut off.
+fn read_line<R: BufRead + ?Sized>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<(usize, bool), io::Error> {
+    let mut len = 0;
+    let mut too_long = false;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            break;
+        }
+        let (n, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (available.len(), false),
+        };
+        let room = MAX_LINE_BYTES - buf.len().min(MAX_LINE_BYTES);
+        too_long |= n > room;
+        buf.extend_from_slice(&available[..n.min(room)]);
+        reader.consume(n);
+        len += n;
+        if done {
+            break;
+        }
+    }
+    Ok((len, too_long))
+}
+
+/**
+ * What loading does with an entry which is earlier than the one before it, e.g. after a manual edit
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutOfOrder {
+    // keep it, at its place in time
+    #[default]
+    Sort,
+    // drop it with a warning, like a damaged line; saving everything removes it from the file
+    Skip,
+}
+
+/**
+ * How to treat questionable contents when loading a timelog
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub out_of_order: OutOfOrder,
+}
+
+/**
+ * Stable reference to an entry: its stop time, and which of the entries with that time it is
+ *
+ * Unlike an index it stays the same when other entries get added or removed, or when older ones
+ * get loaded; it only changes when the entry itself gets a different time.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntryId {
+    pub stop: NaiveDateTime,
+    // almost always 0; entries with the same time happen with typos or merge() conflicts
+    pub seq: u32,
+}
+
+impl fmt::Display for EntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.stop.format(TIME_FMT))?;
+        match self.seq {
+            0 => Ok(()),
+            seq => write!(f, " #{seq}"),
+        }
+    }
+}
+
+/**
+ * What Timelog::merge() did with the entries of the other timelog
+ */
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Merged {
+    pub added: usize,
+    // same time and task as an existing entry
+    pub duplicates: usize,
+    // (time, existing task, added task) of entries at the same time as an existing one with a
+    // different task; both get kept, as only the user knows which one is right
+    pub conflicts: Vec<(NaiveDateTime, String, String)>,
+}
+
+/**
+ * Why a Timelog could not be loaded or saved
+ */
+#[derive(Debug)]
+pub enum TimelogError {
+    Io(io::Error),
+    // damaged line, with its 1-based number; only in strict parsing, see new_from_string_strict()
+    Parse { line: usize, text: String },
+    // entry earlier than the one before it; only in strict parsing
+    Order { line: usize, text: String },
+    // save() on a Timelog without storage
+    NoStorage,
+    // another rtimelog is saving, and does not finish; the message of Storage::lock(), which
+    // says which file
+    Locked { message: String },
+    // another program changed the storage since it was loaded, and saving would have to write
+    // everything, which would drop that change
+    Changed,
+    // editing an entry index which does not exist
+    NoEntry(usize),
+    // editing an entry which does not exist (any more)
+    UnknownId(EntryId),
+    // editing would put this entry before an earlier one, or after a later one
+    Misplaced { text: String },
+    // empty, or more than one line
+    InvalidTask(String),
+}
+
+impl fmt::Display for TimelogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelogError::Io(e) => write!(f, "Could not access timelog: {e}"),
+            TimelogError::Parse { line, text } => write!(f, "Invalid line {line}: {text}"),
+            TimelogError::Order { line, text } => {
+                write!(f, "Line {line} goes back in time: {text}")
+            }
+            TimelogError::NoStorage => write!(f, "Timelog has no storage to save to"),
+            TimelogError::Locked { message } => write!(
+                f,
+                "{message}; try again once it is done, or end it if it hangs"
+            ),
+            TimelogError::Changed => write!(
+                f,
+                "The timelog was changed by another program since it was loaded; load it again, and redo the change"
+            ),
+            TimelogError::NoEntry(index) => write!(f, "There is no entry {index}"),
+            TimelogError::UnknownId(id) => write!(f, "There is no entry at {id}"),
+            TimelogError::Misplaced { text } => {
+                write!(f, "{text} would not be in chronological order")
+            }
+            TimelogError::InvalidTask(task) => write!(f, "Invalid task {task:?}"),
+        }
+    }
+}
+
+impl std::error::Error for TimelogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TimelogError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TimelogError {
+    fn from(e: io::Error) -> TimelogError {
+        match e.kind() {
+            // see Storage::lock()
+            io::ErrorKind::WouldBlock => TimelogError::Locked {
+                message: e.to_string(),
+            },
+            _ => TimelogError::Io(e),
+        }
+    }
+}
+
+// for code which deals with files anyway, like the async API
+impl From<TimelogError> for io::Error {
+    fn from(e: TimelogError) -> io::Error {
+        match e {
+            TimelogError::Io(e) => e,
+            TimelogError::NoStorage => io::Error::new(io::ErrorKind::Unsupported, e.to_string()),
+            TimelogError::Locked { .. } => io::Error::new(io::ErrorKind::WouldBlock, e.to_string()),
+            _ => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
     }
 }
 
@@ -49,28 +426,298 @@ impl fmt::Display for Entry {
 
 #[derive(Default, Debug)]
 pub struct Timelog {
+    // sorted by time, so that binary search finds days and weeks
     entries: Vec<Entry>,
-    pub filename: Option<PathBuf>,
+    // all distinct tasks; most logs have few tasks which repeat a lot
+    tasks: HashSet<Arc<str>>,
+    storage: Option<Box<dyn Storage>>,
+    // keep Windows line endings when the file already has them
+    crlf: bool,
+    // write the UTC offset of new entries, when the last one in the file has it
+    offsets: bool,
+    // modification time of the storage when it was last loaded or saved
+    modified: Option<SystemTime>,
+    // entries before that are not loaded yet, see new_from_storage_recent()
+    loaded_since: Option<NaiveDateTime>,
+    // entry notes, indexed by NoteId - 1
+    notes: Vec<Attached>,
+    // like Attached::leader, for the lines after the last entry
+    trailer: String,
+    // index of the first entry which is not in the storage yet, so that save() only needs to
+    // append; None if the stored entries changed, and save() has to write everything
+    unsaved_from: Option<usize>,
+    // index of the entry which add*() added last, for undoing it with pop_last(); None after
+    // other changes which took it away
+    last_added: Option<usize>,
+    // for loading more from the storage later on
+    options: ParseOptions,
+    // None for SystemClock
+    clock: Option<Box<dyn Clock>>,
 }
 
 impl Timelog {
-    pub fn new_from_default_file() -> Timelog {
+    pub fn new_from_default_file() -> Result<Timelog, TimelogError> {
         Timelog::new_from_file(&Timelog::get_default_file())
     }
 
-    pub fn new_from_file(path: &PathBuf) -> Timelog {
-        Timelog {
-            entries: Timelog::parse(&Timelog::read(path)),
-            filename: Some(path.clone()),
+    pub fn new_from_file(path: &Path) -> Result<Timelog, TimelogError> {
+        Timelog::new_from_storage(Box::new(FileStorage::new(path)))
+    }
+
+    pub fn new_from_storage(storage: Box<dyn Storage>) -> Result<Timelog, TimelogError> {
+        Timelog::new_from_storage_with(storage, ParseOptions::default())
+    }
+
+    pub fn new_from_storage_with(
+        storage: Box<dyn Storage>,
+        options: ParseOptions,
+    ) -> Result<Timelog, TimelogError> {
+        let mut timelog = Timelog::parse_reader(
+            storage.reader()?,
+            NaiveDateTime::MIN,
+            NaiveDateTime::MAX,
+            options,
+        )?;
+        timelog.modified = storage.modified();
+        timelog.storage = Some(storage);
+        timelog.unsaved_from = Some(timelog.entries.len());
+        Ok(timelog)
+    }
+
+    // Read-only view of the entries between `begin` and `end`; this keeps the memory usage
+    // bounded by the size of the range, not of the whole log.
+    pub fn new_from_storage_range(
+        storage: &dyn Storage,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Timelog, TimelogError> {
+        Ok(Timelog::parse_reader(
+            storage.reader()?,
+            begin,
+            end,
+            ParseOptions::default(),
+        )?)
+    }
+
+    // Only load the entries since `since`, for a quick start with big files. The others get
+    // loaded with load_older(), or when saving.
+    pub fn new_from_storage_recent(
+        storage: Box<dyn Storage>,
+        since: NaiveDateTime,
+    ) -> Result<Timelog, TimelogError> {
+        let offset = Timelog::find_offset(&*storage, since)?;
+        let mut reader = storage.reader_at(offset)?;
+        if offset > 0 {
+            // skip the partial line
+            read_line(&mut reader, &mut Vec::new())?;
         }
+        let mut timelog =
+            Timelog::parse_reader(reader, since, NaiveDateTime::MAX, ParseOptions::default())?;
+        timelog.modified = storage.modified();
+        timelog.storage = Some(storage);
+        timelog.loaded_since = Some(since);
+        timelog.unsaved_from = Some(timelog.entries.len());
+        Ok(timelog)
     }
 
-    #[cfg(test)]
+    // Byte offset before the first entry at `since`: from the index of the storage if it has an
+    // up to date one, otherwise with a binary search, or 0 if the storage can't seek.
+    // Entries are sorted, so the time of the next line after some offset tells on which side
+    // of it `since` is.
+    fn find_offset(storage: &dyn Storage, since: NaiveDateTime) -> Result<u64, io::Error> {
+        if let Some(offset) = storage.day_offset(since.date()) {
+            // the newline before, as the caller skips a partial line
+            return Ok(offset.saturating_sub(1));
+        }
+        let Some(size) = storage.size() else {
+            return Ok(0);
+        };
+        let (mut lo, mut hi) = (0, size);
+        let mut raw = Vec::new();
+        while hi - lo > 4096 {
+            let mid = lo + (hi - lo) / 2;
+            let mut reader = storage.reader_at(mid)?;
+            // skip the partial line
+            read_line(&mut reader, &mut raw)?;
+            let mut time = None;
+            while time.is_none() {
+                raw.clear();
+                if read_line(&mut reader, &mut raw)?.0 == 0 {
+                    break;
+                }
+                if is_note_line(&String::from_utf8_lossy(&raw)) {
+                    continue;
+                }
+                time = Timelog::split_line(&String::from_utf8_lossy(&raw)).map(|(t, _, _)| t);
+            }
+            match time {
+                Some(t) if t < since => lo = mid,
+                _ => hi = mid,
+            }
+        }
+        Ok(lo)
+    }
+
+    // start of the loaded entries, if new_from_storage_recent() did not load the older ones yet
+    pub fn loaded_since(&self) -> Option<NaiveDateTime> {
+        self.loaded_since
+    }
+
+    // read the entries before loaded_since(), without needing the Timelog; this can take a
+    // while, so do it without holding a lock
+    pub fn load_older(
+        storage: &dyn Storage,
+        since: NaiveDateTime,
+    ) -> Result<Timelog, TimelogError> {
+        Timelog::new_from_storage_range(
+            storage,
+            NaiveDateTime::MIN,
+            since - Duration::nanoseconds(1),
+        )
+    }
+
+    // make sure that the entries since `begin` are loaded
+    pub fn ensure_loaded(&mut self, begin: NaiveDateTime) -> Result<(), TimelogError> {
+        match (self.loaded_since, self.storage.as_deref()) {
+            (Some(since), Some(storage)) if since > begin => {
+                let older = Timelog::parse_reader(
+                    storage.reader()?,
+                    NaiveDateTime::MIN,
+                    since - Duration::nanoseconds(1),
+                    self.options,
+                )?;
+                self.prepend_older(older, since);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // complete a new_from_storage_recent() Timelog with the result of load_older()
+    pub fn prepend_older(&mut self, older: Timelog, since: NaiveDateTime) {
+        if self.loaded_since != Some(since) {
+            // got reloaded or completed in the meantime
+            return;
+        }
+        let mut entries = Vec::with_capacity(older.entries.len() + self.entries.len());
+        let mut older_notes = older.notes.into_iter().map(Some).collect::<Vec<_>>();
+        for e in older.entries {
+            let task = self.intern(&e.task);
+            let note = e
+                .note
+                .and_then(|id| older_notes[id.0.get() as usize - 1].take())
+                .map(|attached| self.push_attached(attached));
+            entries.push(Entry { task, note, ..e });
+        }
+        let older_len = entries.len();
+        entries.append(&mut self.entries);
+        self.entries = entries;
+        self.loaded_since = None;
+        self.unsaved_from = self.unsaved_from.map(|from| from + older_len);
+        self.last_added = self.last_added.map(|i| i + older_len);
+    }
+
+    // load the storage again if another program changed it; returns whether it did. New entries
+    // which failed to get saved are kept instead, the next save() merges them into the change.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if self.unsaved_from.is_some() && self.has_unsaved() {
+            return false;
+        }
+        let Some(storage) = self.storage.take() else {
+            return false;
+        };
+        let modified = storage.modified();
+        if modified.is_none() || modified == self.modified {
+            self.storage = Some(storage);
+            return false;
+        }
+        let reloaded = storage.reader().and_then(|r| {
+            Timelog::parse_reader(r, NaiveDateTime::MIN, NaiveDateTime::MAX, self.options)
+        });
+        match reloaded {
+            Ok(mut timelog) => {
+                timelog.modified = modified;
+                timelog.storage = Some(storage);
+                timelog.unsaved_from = Some(timelog.entries.len());
+                *self = timelog;
+                true
+            }
+            // keep the entries from before, and try again on the next change
+            Err(e) => {
+                eprintln!("WARNING: Failed to reload the timelog: {e}");
+                self.storage = Some(storage);
+                false
+            }
+        }
+    }
+
+    // parse already loaded contents of `storage`
+    #[cfg(feature = "async")]
+    pub(crate) fn new_from_contents(
+        contents: &[u8],
+        storage: Box<dyn Storage>,
+    ) -> Result<Timelog, io::Error> {
+        let mut timelog = Timelog::parse_reader(
+            contents,
+            NaiveDateTime::MIN,
+            NaiveDateTime::MAX,
+            ParseOptions::default(),
+        )?;
+        timelog.modified = storage.modified();
+        timelog.storage = Some(storage);
+        Ok(timelog)
+    }
+
+    // parse a timelog without any storage, e.g. for just reporting
     pub fn new_from_string(contents: &str) -> Timelog {
-        Timelog {
-            entries: Timelog::parse(contents),
-            filename: None,
+        Timelog::new_from_bytes(contents.as_bytes())
+    }
+
+    pub fn new_from_string_with(contents: &str, options: ParseOptions) -> Timelog {
+        Timelog::parse_reader(
+            contents.as_bytes(),
+            NaiveDateTime::MIN,
+            NaiveDateTime::MAX,
+            options,
+        )
+        .expect("reading from memory cannot fail")
+    }
+
+    // Parse a timelog which must be completely valid, e.g. one generated by another program; this
+    // fails on the first damaged line or entry that goes back in time, instead of skipping or
+    // sorting it with a warning.
+    pub fn new_from_string_strict(contents: &str) -> Result<Timelog, TimelogError> {
+        let mut prev = None;
+        // like parse_reader(), accept the byte order mark of Windows editors
+        for (i, line) in contents.trim_start_matches('\u{feff}').lines().enumerate() {
+            let text = line.trim().to_string();
+            if !is_valid_line(line) || line.len() > MAX_LINE_BYTES {
+                return Err(TimelogError::Parse { line: i + 1, text });
+            }
+            if let Some((stop, _)) = parse_entry_line(line) {
+                if prev.is_some_and(|p| stop < p) {
+                    return Err(TimelogError::Order { line: i + 1, text });
+                }
+                prev = Some(stop);
+            }
         }
+        Ok(Timelog::new_from_string(contents))
+    }
+
+    // same for contents which may not be valid UTF-8, e.g. an upload
+    pub fn new_from_bytes(contents: &[u8]) -> Timelog {
+        Timelog::parse_reader(
+            contents,
+            NaiveDateTime::MIN,
+            NaiveDateTime::MAX,
+            ParseOptions::default(),
+        )
+        .expect("reading from memory cannot fail")
+    }
+
+    // the timelog file, if it is stored in one
+    pub fn filename(&self) -> Option<&Path> {
+        self.storage.as_ref()?.path()
     }
 
     pub fn get_default_file() -> PathBuf {
@@ -79,66 +726,201 @@ impl Timelog {
         let mut log_path = if legacy_dir.is_dir() {
             legacy_dir
         } else {
-            let mut data_dir = match env::var_os("XDG_DATA_HOME") {
-                Some(val) => PathBuf::from(val.into_string().unwrap()),
-                None => dirs::data_dir().unwrap(),
-            };
-            data_dir.push("gtimelog");
-            data_dir
+            Timelog::get_data_dir()
         };
         log_path.push("timelog.txt");
         log_path
     }
 
-    fn read(path: &PathBuf) -> String {
-        match File::open(path) {
-            Ok(mut f) => {
-                let mut contents = String::new();
-                f.read_to_string(&mut contents)
-                    .unwrap_or_else(|e| panic!("Failed to read {}: {:?}", path.display(), e));
-                contents
-            }
+    // gtimelog's directory in the XDG data directory, for when there is no ~/.gtimelog
+    pub fn get_data_dir() -> PathBuf {
+        let mut data_dir = match env::var_os(DATA_DIR_ENV) {
+            Some(val) => PathBuf::from(val),
+            None => dirs::data_dir().unwrap(),
+        };
+        data_dir.push("gtimelog");
+        data_dir
+    }
 
-            Err(e) => {
-                if e.kind() == io::ErrorKind::NotFound {
-                    println!("No existing {}, starting new log", path.display());
-                    String::new()
-                } else {
-                    panic!("Could not open {}: {:?}", path.display(), e);
-                }
+    // share the task string with other entries of the same task
+    fn intern(&mut self, task: &str) -> Arc<str> {
+        match self.tasks.get(task) {
+            Some(t) => Arc::clone(t),
+            None => {
+                let t: Arc<str> = Arc::from(task);
+                self.tasks.insert(Arc::clone(&t));
+                t
             }
         }
     }
 
+    #[cfg(test)]
     fn parse(raw: &str) -> Vec<Entry> {
-        let mut entries = Vec::new();
-        let mut prev: Option<NaiveDateTime> = None;
+        Timelog::new_from_string(raw).entries
+    }
 
-        for line in raw.lines() {
-            if let Some(e) = Timelog::parse_line(line) {
-                // require a monotonously increasing file
-                if prev.is_some() && e.stop < prev.unwrap() {
-                    panic!("line {line} goes back in time");
+    // Parse line by line, without holding the whole file in memory. Only keep the entries
+    // between `begin` and `end`. This must not panic on any input, as files may come from sync
+    // services or uploads; damaged lines get skipped with a warning.
+    fn parse_reader(
+        mut reader: impl BufRead,
+        begin: NaiveDateTime,
+        end: NaiveDateTime,
+        options: ParseOptions,
+    ) -> Result<Timelog, io::Error> {
+        let mut timelog = Timelog {
+            options,
+            ..Timelog::default()
+        };
+        let mut prev: Option<NaiveDateTime> = None;
+        let mut sorted = true;
+        let mut raw = Vec::new();
+        let mut first = true;
+        // whether the previous line belongs to a kept (true) or skipped (false) entry, so that
+        // its note lines can follow; the start of the reader may be in the middle of a note
+        let mut note_of: Option<bool> = Some(false);
+        // the lines since the last entry or note, for Attached::leader
+        let mut pending = String::new();
+        let mut pending_unknown = false;
+        // date and UTC offset of the first entry with an offset of the current day
+        let mut day_offset: Option<(NaiveDate, FixedOffset)> = None;
+
+        loop {
+            raw.clear();
+            let (len, too_long) = read_line(&mut reader, &mut raw)?;
+            if len == 0 {
+                break;
+            }
+            // a damaged file must not prevent reading the intact entries
+            let mut line = String::from_utf8_lossy(&raw).into_owned();
+            if std::str::from_utf8(&raw).is_err() {
+                eprintln!(
+                    "WARNING: replacing invalid UTF-8 in timelog line: {}",
+                    line.trim()
+                );
+            }
+            if first {
+                timelog.crlf = line.ends_with("\r\n");
+                // Windows editors like to add a byte order mark
+                line = line.trim_start_matches('\u{feff}').to_string();
+                first = false;
+            }
+            if too_long {
+                eprintln!(
+                    "WARNING: ignoring line longer than {MAX_LINE_BYTES} bytes in timelog: {}...",
+                    line.chars().take(40).collect::<String>()
+                );
+                continue;
+            }
+            // crashes can leave NUL blocks behind
+            line.retain(|c| c != '\0');
+            if let (true, Some(kept)) = (is_note_line(&line), note_of) {
+                if kept {
+                    timelog.append_note(line.trim());
+                }
+                continue;
+            }
+            note_of = None;
+            let content = line.trim_end_matches(['\r', '\n']);
+            let parsed = if content.trim_start().starts_with('#') {
+                // comments are no damage, so don't warn about them
+                None
+            } else {
+                Timelog::split_line(&line)
+            };
+            let Some((mut stop, offset, task)) = parsed else {
+                pending_unknown |= !content.trim().is_empty();
+                pending.push_str(content);
+                pending.push('\n');
+                continue;
+            };
+            timelog.offsets = offset.is_some();
+            let offsets = offset.map(|own| {
+                let day = match day_offset {
+                    Some((date, day)) if date == stop.date() => day,
+                    _ => own,
+                };
+                day_offset = Some((stop.date(), day));
+                stop = convert(stop, own, day);
+                (own, day)
+            });
+            if prev.is_some_and(|p| stop < p) {
+                if options.out_of_order == OutOfOrder::Skip {
+                    eprintln!(
+                        "WARNING: ignoring line which goes back in time: {}",
+                        line.trim()
+                    );
+                    // with its notes
+                    note_of = Some(false);
+                    continue;
+                }
+                eprintln!(
+                    "WARNING: line {} goes back in time, sorting it in",
+                    line.trim()
+                );
+                sorted = false;
+            } else {
+                prev = Some(stop);
+                if stop > end {
+                    pending.clear();
+                    break;
+                }
+            }
+            note_of = Some(stop >= begin && stop <= end);
+            let leader = std::mem::take(&mut pending);
+            if stop >= begin && stop <= end {
+                // format_from() writes an empty line between days, so only other ones need
+                // keeping; not the ones at the start
+                let separator = match timelog.entries.last() {
+                    Some(prev) if prev.stop.date() != stop.date() => "\n",
+                    Some(_) => "",
+                    None => &leader,
+                };
+                let task = timelog.intern(task);
+                timelog.entries.push(Entry {
+                    stop,
+                    task,
+                    note: None,
+                });
+                if std::mem::take(&mut pending_unknown) || leader != separator {
+                    timelog.set_leader(timelog.entries.len() - 1, leader);
+                }
+                if offsets.is_some() {
+                    timelog.attached_mut(timelog.entries.len() - 1).offset = offsets;
                 }
-                prev = Some(e.stop);
-                entries.push(e);
             }
+            pending_unknown = false;
         }
-        entries
+        // also empty lines, which an append would keep before the new entries
+        timelog.trailer = pending;
+
+        if !sorted {
+            timelog.entries.sort_by_key(|e| e.stop);
+        }
+        timelog.entries.shrink_to_fit();
+        timelog.notes.shrink_to_fit();
+        Ok(timelog)
     }
 
+    #[cfg(test)]
     fn parse_line(line: &str) -> Option<Entry> {
+        Timelog::split_line(line).map(|(stop, _, task)| Entry {
+            stop,
+            task: Arc::from(task),
+            note: None,
+        })
+    }
+
+    // time, UTC offset if any, and task of an entry line
+    fn split_line(line: &str) -> Option<(NaiveDateTime, Option<FixedOffset>, &str)> {
         let line = line.trim();
         if line.is_empty() {
             return None;
         }
 
         if let Some((time, task)) = line.split_once(": ") {
-            if let Ok(dt) = NaiveDateTime::parse_from_str(time, TIME_FMT) {
-                Some(Entry {
-                    stop: dt,
-                    task: task.to_string(),
-                })
+            if let Some((dt, offset)) = parse_stamp(time) {
+                Some((dt, offset, task))
             } else {
                 eprintln!("WARNING: ignoring line with invalid date in timelog: {line}");
                 None
@@ -150,98 +932,303 @@ impl Timelog {
     }
 
     fn format_store(&self) -> String {
-        let mut output = String::new();
-        let mut prev: Option<NaiveDate> = None;
+        let mut output = self.format_from(0);
+        if self.crlf {
+            output.push_str(&self.trailer.replace('\n', "\r\n"));
+        } else {
+            output.push_str(&self.trailer);
+        }
+        output
+    }
 
-        for entry in &self.entries {
-            // leave an empty line between days
-            if prev.is_some() && prev.unwrap() != entry.stop.date() {
+    // the lines of the entries from index `start` on, like they continue the ones before
+    fn format_from(&self, start: usize) -> String {
+        let mut output = String::new();
+        let mut prev: Option<NaiveDate> = start
+            .checked_sub(1)
+            .and_then(|i| self.entries.get(i))
+            .map(|e| e.stop.date());
+
+        for entry in &self.entries[start..] {
+            let leader = self.leader(entry);
+            if !leader.is_empty() {
+                // that has its own empty lines
+                output.push_str(leader);
+            } else if prev.is_some() && prev.unwrap() != entry.stop.date() {
+                // leave an empty line between days
                 output.push('\n');
             }
             prev = Some(entry.stop.date());
-            writeln!(output, "{entry}").expect("failed to format entry");
+            match self.attached(entry).and_then(|a| a.offset) {
+                // the time as it was written
+                Some((own, day)) => write_time(&mut output, convert(entry.stop, day, own))
+                    .and_then(|_| writeln!(output, " {}: {}", format_offset(own), entry.task)),
+                None => writeln!(output, "{entry}"),
+            }
+            .expect("failed to format entry");
+            for line in self.note(entry).into_iter().flat_map(str::lines) {
+                writeln!(output, "  {line}").expect("failed to format note");
+            }
         }
 
+        if self.crlf {
+            output = output.replace('\n', "\r\n");
+        }
         output
     }
 
-    pub fn save(&self) -> Result<(), io::Error> {
-        assert!(self.filename.is_some());
-        let filename = self.filename.as_ref().unwrap();
-        if let Some(parent) = filename.parent() {
-            fs::create_dir_all(parent)?;
+    // Only append the new entries if nothing else changed, neither here nor in the storage;
+    // otherwise, write everything. If another program added entries in the meantime, keep them.
+    pub fn save(&mut self) -> Result<(), TimelogError> {
+        let Some(storage) = self.storage.as_ref() else {
+            return Err(TimelogError::NoStorage);
+        };
+        let _lock = storage.lock()?;
+        let changed = storage.modified() != self.modified;
+        // new entries can get merged into the other program's change, but edits can't
+        if changed && self.unsaved_from.is_none() {
+            return Err(TimelogError::Changed);
+        }
+        if let (Some(from), true) = (self.unsaved_from, changed) {
+            self.merge_stored(from)?;
+        }
+        match self.unsaved_from {
+            // an empty storage does not need the blank line before a new day
+            Some(from) if (from > 0 || self.loaded_since.is_some()) && !changed => {
+                if from < self.entries.len() {
+                    let storage = self.storage.as_ref().unwrap();
+                    let mut appended = self.format_from(from);
+                    // the stored entries before loaded_since are of earlier days
+                    if from == 0 && !storage.reader()?.fill_buf()?.is_empty() {
+                        appended.insert_str(0, if self.crlf { "\r\n" } else { "\n" });
+                    }
+                    storage.append(&appended)?;
+                    // the lines at the end of the storage now come before the new entries
+                    if !self.trailer.is_empty() {
+                        let mut leader = std::mem::take(&mut self.trailer);
+                        if appended.starts_with(['\r', '\n']) {
+                            leader.push('\n');
+                        }
+                        self.set_leader(from, leader);
+                    }
+                }
+            }
+            _ => {
+                let contents = self.contents_to_save()?;
+                self.storage.as_ref().unwrap().save(&contents)?;
+            }
+        }
+        self.modified = self.storage.as_ref().unwrap().modified();
+        self.unsaved_from = Some(self.entries.len());
+        Ok(())
+    }
+
+    // load the storage again, with the unsaved entries from index `from` on sorted in
+    fn merge_stored(&mut self, from: usize) -> Result<(), TimelogError> {
+        let unsaved: Vec<(NaiveDateTime, Arc<str>, Option<String>, _)> = self.entries[from..]
+            .iter()
+            .map(|e| {
+                let offset = self.attached(e).and_then(|a| a.offset);
+                (
+                    e.stop,
+                    e.task.clone(),
+                    self.note(e).map(str::to_string),
+                    offset,
+                )
+            })
+            .collect();
+        let storage = self.storage.as_deref().unwrap();
+        let mut stored = Timelog::parse_reader(
+            storage.reader()?,
+            NaiveDateTime::MIN,
+            NaiveDateTime::MAX,
+            self.options,
+        )?;
+        stored.storage = self.storage.take();
+        *self = stored;
+        for (stop, task, note, offset) in unsaved {
+            if self
+                .entries
+                .iter()
+                .any(|e| e.stop == stop && *e.task == *task)
+            {
+                continue;
+            }
+            let task = self.intern(&task);
+            let pos = self.entries.partition_point(|e| e.stop <= stop);
+            self.entries.insert(
+                pos,
+                Entry {
+                    stop,
+                    task,
+                    note: None,
+                },
+            );
+            if offset.is_some() {
+                self.attached_mut(pos).offset = offset;
+            }
+            for line in note.iter().flat_map(|n| n.lines()) {
+                self.append_note_at(pos, line);
+            }
         }
-        let mut f = File::create(filename)?;
-        write!(f, "{}", self.format_store())?;
+        // unsaved_from stays None, so that everything gets written
         Ok(())
     }
 
+    // Always write everything, e.g. after bulk changes; file storage replaces the file only once
+    // the new contents are completely on disk.
+    pub fn save_atomic(&mut self) -> Result<(), TimelogError> {
+        self.unsaved_from = None;
+        self.save()
+    }
+
+    // the complete log for saving; this does not lose the not yet loaded entries
+    pub(crate) fn contents_to_save(&mut self) -> Result<String, TimelogError> {
+        self.ensure_loaded(NaiveDateTime::MIN)?;
+        // saved elsewhere, so the next save() can't rely on what is in the storage
+        self.unsaved_from = None;
+        Ok(self.format_store())
+    }
+
+    // Move the entries before `time` with their notes into a new Timelog without storage, e.g.
+    // for archiving them; the next save() writes everything.
+    pub fn split_off_before(&mut self, time: NaiveDateTime) -> Result<Timelog, TimelogError> {
+        self.ensure_loaded(NaiveDateTime::MIN)?;
+        self.unsaved_from = None;
+        self.last_added = None;
+        let split = self.entries.partition_point(|e| e.stop < time);
+        let mut older = Timelog {
+            crlf: self.crlf,
+            ..Timelog::default()
+        };
+        for e in self.entries.drain(..split).collect::<Vec<_>>() {
+            let task = older.intern(&e.task);
+            let note = e
+                .note
+                .map(|id| older.push_attached(self.notes[id.0.get() as usize - 1].clone()));
+            older.entries.push(Entry { task, note, ..e });
+        }
+        Ok(older)
+    }
+
+    // after saving the contents_to_save() elsewhere, e.g. asynchronously
+    #[cfg(feature = "async")]
+    pub(crate) fn set_modified(&mut self, modified: Option<SystemTime>) {
+        self.modified = modified;
+    }
+
     #[cfg(test)]
     pub fn get_all(&self) -> impl Iterator<Item = &Entry> {
-        return self.entries.iter();
+        self.entries.iter()
     }
 
     pub fn get_time_range(&self, begin: NaiveDateTime, end: NaiveDateTime) -> &[Entry] {
-        let first = self
-            .entries
-            .iter()
-            .position(move |e| e.stop >= begin)
-            .unwrap_or(self.entries.len());
-        let last = self
-            .entries
-            .iter()
-            .position(move |e| e.stop > end)
-            .unwrap_or(self.entries.len());
+        // entries are sorted by time
+        let first = self.entries.partition_point(|e| e.stop < begin);
+        let last = self.entries.partition_point(|e| e.stop <= end).max(first);
 
         &self.entries[first..last]
     }
 
-    // get entries for n most recent days including given day
-    pub fn get_n_days(&self, day: &NaiveDate, n: u32) -> &[Entry] {
-        let eod = day.and_hms_opt(23, 59, 59).unwrap();
+    // get entries for n most recent days including given day; days end at `virtual_midnight`
+    // of the next calendar day, see Sentinels::day_of()
+    pub fn get_n_days(&self, day: &NaiveDate, n: u32, virtual_midnight: NaiveTime) -> &[Entry] {
+        let eod = day.succ_opt().unwrap().and_time(virtual_midnight) - Duration::seconds(1);
         self.get_time_range(eod - Duration::days(n as i64), eod)
     }
 
-    pub fn get_today_as_string(&self) -> String {
-        Local::now().format("%A, %F (week %W)").to_string()
+    // entries of the day of `now`, which is still the previous one before virtual midnight
+    pub fn get_today(&self, now: NaiveDateTime, sentinels: &Sentinels) -> &[Entry] {
+        self.get_n_days(&sentinels.day_of(now), 1, sentinels.virtual_midnight)
     }
 
-    // get entries for n most recent weeks including week of given day
-    pub fn get_n_weeks(&self, day: &NaiveDate, n: u32) -> &[Entry] {
-        let week_of_day = day.iso_week().week();
-        let eow = NaiveDate::from_isoywd_opt(day.year(), week_of_day + 1, Weekday::Mon)
-            .unwrap()
-            .and_hms_opt(0, 0, 0)
-            .unwrap();
+    // the entries of `day` with the time spent on each, see activity::durations()
+    pub fn durations_for_day<'a>(
+        &'a self,
+        day: &NaiveDate,
+        sentinels: &'a Sentinels,
+    ) -> impl Iterator<Item = (&'a Entry, Duration)> + 'a {
+        let entries = self.get_n_days(day, 1, sentinels.virtual_midnight);
+        activity::durations(entries, sentinels)
+    }
+
+    // get_today() for the time of the clock, see set_clock()
+    pub fn today(&self, sentinels: &Sentinels) -> &[Entry] {
+        self.get_today(self.now(), sentinels)
+    }
+
+    pub fn get_this_week(
+        &self,
+        now: NaiveDateTime,
+        sentinels: &Sentinels,
+        week_start: Weekday,
+    ) -> &[Entry] {
+        self.get_n_weeks(
+            &sentinels.day_of(now),
+            1,
+            sentinels.virtual_midnight,
+            week_start,
+        )
+    }
+
+    pub fn get_today_as_string(
+        &self,
+        today: &NaiveDate,
+        dates: &DateFormat,
+        week_start: Weekday,
+    ) -> String {
+        format!(
+            "{} (week {})",
+            dates.format(today),
+            iso_week(today, week_start).week()
+        )
+    }
+
+    // get entries for n most recent weeks including week of given day; weeks begin on
+    // `week_start`, see Config::week_start
+    pub fn get_n_weeks(
+        &self,
+        day: &NaiveDate,
+        n: u32,
+        virtual_midnight: NaiveTime,
+        week_start: Weekday,
+    ) -> &[Entry] {
+        let eow =
+            (day.week(week_start).first_day() + Duration::weeks(1)).and_time(virtual_midnight);
         self.get_time_range(eow - Duration::weeks(n as i64), eow)
     }
 
-    pub fn get_this_week_as_string(&self) -> String {
-        let now_local = Local::now();
-        let week_begin = now_local
-            .checked_sub_signed(Duration::days(
-                now_local.weekday().num_days_from_monday().into(),
-            ))
-            .unwrap();
-        let week_end = week_begin.checked_add_signed(Duration::days(6)).unwrap();
-        let this_week = if week_begin.month() == now_local.month() {
+    pub fn get_this_week_as_string(
+        &self,
+        today: &NaiveDate,
+        dates: &DateFormat,
+        week_start: Weekday,
+    ) -> String {
+        let week_begin = today.week(week_start).first_day();
+        let week_end = week_begin + Duration::days(6);
+        let this_week = if week_begin.month() == today.month() {
             format!(
                 "{} {}-{}",
-                now_local.format("%B"),
+                dates.format_with(today, "%B"),
                 week_begin.day(),
                 week_end.day()
             )
         } else {
-            format!("{}-{}", week_begin.format("%B %e"), week_end.day())
+            format!(
+                "{}-{}",
+                dates.format_with(&week_begin, "%B %e"),
+                week_end.day()
+            )
         };
-        format!("{} ({})", now_local.format("%Y, week %W"), this_week)
+        let week = iso_week(today, week_start);
+        format!("{}, week {} ({this_week})", week.year(), week.week())
     }
 
-    pub fn get_history(entries: &[Entry]) -> Vec<&String> {
+    pub fn get_history(entries: &[Entry]) -> Vec<&str> {
         let mut seen = HashSet::new();
         entries
             .iter()
-            .map(|e| &e.task)
+            .map(|e| &*e.task)
             .filter(|&t| {
                 if seen.contains(&t) {
                     false
@@ -253,27 +1240,598 @@ impl Timelog {
             .collect()
     }
 
-    pub fn add(&mut self, task: String) {
-        let now = Local::now();
-        let naivenow = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day())
-            .unwrap()
-            .and_hms_opt(now.hour(), now.minute(), now.second())
-            .unwrap();
-        self.entries.push(Entry {
-            task,
-            stop: naivenow,
-        });
+    // Add entry lines which got lost from the file, e.g. from a journal; returns how many were
+    // valid and not present yet
+    pub fn recover(&mut self, lines: &[String]) -> Result<usize, TimelogError> {
+        self.ensure_loaded(NaiveDateTime::MIN)?;
+        self.unsaved_from = None;
+        self.last_added = None;
+        let mut count = 0;
+        // position of the last recovered entry, for its note lines
+        let mut recovered = None;
+        for line in lines {
+            if is_note_line(line) {
+                if let Some(pos) = recovered {
+                    self.append_note_at(pos, line.trim());
+                }
+                continue;
+            }
+            recovered = None;
+            let Some((stop, offset, task)) = Timelog::split_line(line) else {
+                continue;
+            };
+            if self
+                .entries
+                .iter()
+                .any(|e| e.stop == stop && *e.task == *task)
+            {
+                continue;
+            }
+            let task = self.intern(task);
+            let pos = self.entries.partition_point(|e| e.stop <= stop);
+            self.entries.insert(
+                pos,
+                Entry {
+                    stop,
+                    task,
+                    note: None,
+                },
+            );
+            if let Some(offset) = offset {
+                self.attached_mut(pos).offset = Some((offset, offset));
+            }
+            recovered = Some(pos);
+            count += 1;
+        }
+        Ok(count)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Duration;
-    use pretty_assertions::assert_eq;
 
-    const TWO_DAYS: &'static str = "
-2022-06-09 06:02: arrived
+    // Add the entries of `other` which are not present yet, e.g. from the timelog of another
+    // computer, with their notes. Comments and other unknown lines of `other` are not taken.
+    pub fn merge(&mut self, other: &Timelog) -> Result<Merged, TimelogError> {
+        self.ensure_loaded(NaiveDateTime::MIN)?;
+        self.unsaved_from = None;
+        let mut merged = Merged::default();
+        for entry in &other.entries {
+            let first = self.entries.partition_point(|e| e.stop < entry.stop);
+            let same_time = self.entries[first..]
+                .iter()
+                .take_while(|e| e.stop == entry.stop);
+            let mut conflict = None;
+            let mut duplicate = None;
+            for (i, e) in same_time.enumerate() {
+                if e.task == entry.task {
+                    duplicate = Some(first + i);
+                    break;
+                }
+                conflict.get_or_insert_with(|| e.task.to_string());
+            }
+            let note = other.note(entry);
+            if let Some(pos) = duplicate {
+                merged.duplicates += 1;
+                // a note which only got written on the other computer
+                if let (Some(note), None) = (note, self.note(&self.entries[pos])) {
+                    self.append_note_at(pos, note);
+                }
+                continue;
+            }
+            if let Some(existing) = conflict {
+                merged
+                    .conflicts
+                    .push((entry.stop, existing, entry.task.to_string()));
+            }
+            // already converted to the offset of their day
+            let offsets = other.attached(entry).and_then(|a| a.offset);
+            self.insert_at_offset(entry.task.to_string(), entry.stop, None);
+            let pos = self.entries.partition_point(|e| e.stop <= entry.stop) - 1;
+            if offsets.is_some() {
+                self.attached_mut(pos).offset = offsets;
+            }
+            if let Some(note) = note {
+                self.append_note_at(pos, note);
+            }
+            merged.added += 1;
+        }
+        Ok(merged)
+    }
+
+    // the current local time, from the clock of set_clock()
+    pub fn now(&self) -> NaiveDateTime {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => SystemClock.now(),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    // add an entry which stops now, in minutes like gtimelog
+    pub fn add(&mut self, task: String) -> Result<(), TimelogError> {
+        let now = self.now();
+        let naivenow = now.date().and_hms_opt(now.hour(), now.minute(), 0).unwrap();
+        self.insert_sorted(task, naivenow)
+    }
+
+    // Add an entry with an explicit stop time, e.g. one which got forgotten; it goes after the
+    // entries with the same or an earlier time. It can't go before the not yet loaded entries.
+    pub fn add_at(&mut self, task: String, stop: NaiveDateTime) -> Result<(), TimelogError> {
+        Timelog::check_task(&task)?;
+        if self.loaded_since.is_some_and(|since| stop < since) {
+            return Err(TimelogError::Misplaced {
+                text: Entry {
+                    stop,
+                    task: Arc::from(task),
+                    note: None,
+                }
+                .to_string(),
+            });
+        }
+        self.insert_sorted(task, stop)
+    }
+
+    // a task with a line break would write more than one line, like a forged entry
+    fn insert_sorted(&mut self, task: String, stop: NaiveDateTime) -> Result<(), TimelogError> {
+        let task = Timelog::check_task(&task)?.to_string();
+        // the local time zone knows the offset at that time, also across a DST change
+        let offset = self
+            .offsets
+            .then(|| Local.offset_from_local_datetime(&stop).earliest())
+            .flatten();
+        self.last_added = Some(self.insert_at_offset(task, stop, offset));
+        Ok(())
+    }
+
+    // Insert an entry which stopped at local time `stop` with UTC `offset`; within a day, it gets
+    // converted to the offset of the entry before it, see Attached::offset. Returns its index.
+    fn insert_at_offset(
+        &mut self,
+        task: String,
+        stop: NaiveDateTime,
+        offset: Option<FixedOffset>,
+    ) -> usize {
+        let mut stop = stop;
+        let mut offsets = None;
+        if let Some(own) = offset {
+            let before = self.entries.partition_point(|e| e.stop <= stop);
+            let day = before
+                .checked_sub(1)
+                .and_then(|i| {
+                    let prev = &self.entries[i];
+                    let (prev_own, day) = self.attached(prev)?.offset?;
+                    (convert(prev.stop, day, prev_own).date() == stop.date()).then_some(day)
+                })
+                .unwrap_or(own);
+            stop = convert(stop, own, day);
+            offsets = Some((own, day));
+        }
+        let pos = self.entries.partition_point(|e| e.stop <= stop);
+        if self.unsaved_from.is_some_and(|from| pos < from) {
+            self.unsaved_from = None;
+        }
+        self.last_added = self.last_added.map(|i| if pos <= i { i + 1 } else { i });
+        let task = self.intern(&task);
+        self.entries.insert(
+            pos,
+            Entry {
+                task,
+                stop,
+                note: None,
+            },
+        );
+        if offsets.is_some() {
+            self.attached_mut(pos).offset = offsets;
+        }
+        pos
+    }
+
+    // add an entry which stops at `now`, rounded according to `snap`, but not before the last
+    // entry; with `keep_raw`, the actual time goes into its note
+    pub fn add_snapped(
+        &mut self,
+        task: String,
+        now: NaiveDateTime,
+        snap: &Snap,
+    ) -> Result<(), TimelogError> {
+        let mut stop = snap.apply(now).with_nanosecond(0).unwrap();
+        if !snap.seconds {
+            stop = stop.with_second(0).unwrap();
+        }
+        if let Some(last) = self.entries.last() {
+            stop = stop.max(last.stop);
+        }
+        if let Some(since) = self.loaded_since {
+            stop = stop.max(since);
+        }
+        self.insert_sorted(task, stop)?;
+        let same_minute =
+            stop.date() == now.date() && (stop.hour(), stop.minute()) == (now.hour(), now.minute());
+        if snap.keep_raw && !same_minute {
+            self.append_note(&format!("logged at {}", now.format("%H:%M")));
+        }
+        Ok(())
+    }
+
+    // the note of `entry`, if it has one
+    pub fn note(&self, entry: &Entry) -> Option<&str> {
+        self.attached(entry)
+            .map(|a| a.note.as_str())
+            .filter(|note| !note.is_empty())
+    }
+
+    // the unknown lines before `entry`, see Attached::leader
+    fn leader(&self, entry: &Entry) -> &str {
+        self.attached(entry).map_or("", |a| a.leader.as_str())
+    }
+
+    fn attached(&self, entry: &Entry) -> Option<&Attached> {
+        entry
+            .note
+            .and_then(|id| self.notes.get(id.0.get() as usize - 1))
+    }
+
+    // add a line to the note of the last entry; returns false if there is none
+    pub fn append_note(&mut self, line: &str) -> bool {
+        match self.entries.len() {
+            0 => false,
+            n => {
+                self.append_note_at(n - 1, line);
+                true
+            }
+        }
+    }
+
+    fn append_note_at(&mut self, pos: usize, line: &str) {
+        if self.unsaved_from.is_some_and(|from| pos < from) {
+            self.unsaved_from = None;
+        }
+        match self.entries[pos].note {
+            Some(id) => {
+                let note = &mut self.notes[id.0.get() as usize - 1].note;
+                if !note.is_empty() {
+                    note.push('\n');
+                }
+                note.push_str(line);
+            }
+            None => {
+                let id = self.push_attached(Attached {
+                    note: line.to_string(),
+                    ..Attached::default()
+                });
+                self.entries[pos].note = Some(id);
+            }
+        }
+    }
+
+    fn set_leader(&mut self, pos: usize, leader: String) {
+        self.attached_mut(pos).leader = leader;
+    }
+
+    fn attached_mut(&mut self, pos: usize) -> &mut Attached {
+        let id = match self.entries[pos].note {
+            Some(id) => id,
+            None => {
+                let id = self.push_attached(Attached::default());
+                self.entries[pos].note = Some(id);
+                id
+            }
+        };
+        &mut self.notes[id.0.get() as usize - 1]
+    }
+
+    // keep the unknown lines before the entry at `pos` when removing it
+    fn keep_leader(&mut self, pos: usize) {
+        let leader = self.leader(&self.entries[pos]).to_string();
+        if leader.is_empty() {
+            return;
+        }
+        match self.entries.get(pos + 1) {
+            Some(next) => {
+                let joined = leader + self.leader(next);
+                self.set_leader(pos + 1, joined);
+            }
+            None => self.trailer.insert_str(0, &leader),
+        }
+    }
+
+    fn push_attached(&mut self, attached: Attached) -> NoteId {
+        self.notes.push(attached);
+        let id = u32::try_from(self.notes.len()).expect("too many notes");
+        NoteId(NonZeroU32::new(id).unwrap())
+    }
+
+    // Editing works on indexes into all loaded entries, e.g. from position(). Entries must stay
+    // in chronological order, so this fails instead of moving an entry past its neighbours. Any
+    // edit makes the next save() write everything.
+
+    // index of the entry with `stop` and `task`
+    pub fn position(&self, stop: NaiveDateTime, task: &str) -> Option<usize> {
+        let first = self.entries.partition_point(|e| e.stop < stop);
+        self.entries[first..]
+            .iter()
+            .take_while(|e| e.stop == stop)
+            .position(|e| *e.task == *task)
+            .map(|i| first + i)
+    }
+
+    // The id of an entry of this Timelog, like from get_time_range() or durations_for_day(); a
+    // clone of one works as well, as long as the stop and task are still the same.
+    pub fn id(&self, entry: &Entry) -> Option<EntryId> {
+        let first = self.entries.partition_point(|e| e.stop < entry.stop);
+        let same = self.entries[first..]
+            .iter()
+            .take_while(|e| e.stop == entry.stop);
+        let seq = match same.clone().position(|e| std::ptr::eq(e, entry)) {
+            Some(seq) => seq,
+            None => same.clone().position(|e| e.task == entry.task)?,
+        };
+        Some(EntryId {
+            stop: entry.stop,
+            seq: seq as u32,
+        })
+    }
+
+    // the entries of a query, with their ids
+    pub fn with_ids<'a>(
+        &'a self,
+        entries: &'a [Entry],
+    ) -> impl Iterator<Item = (EntryId, &'a Entry)> + 'a {
+        entries.iter().filter_map(move |e| Some((self.id(e)?, e)))
+    }
+
+    pub fn get(&self, id: EntryId) -> Option<&Entry> {
+        self.index_of(id).map(|i| &self.entries[i])
+    }
+
+    // for the index-based functions below
+    pub fn index_of(&self, id: EntryId) -> Option<usize> {
+        let index = self.entries.partition_point(|e| e.stop < id.stop) + id.seq as usize;
+        (self.entries.get(index)?.stop == id.stop).then_some(index)
+    }
+
+    fn id_at(&self, index: usize) -> EntryId {
+        let stop = self.entries[index].stop;
+        EntryId {
+            stop,
+            seq: (index - self.entries.partition_point(|e| e.stop < stop)) as u32,
+        }
+    }
+
+    // Change the time and task of an entry at once, and return its new id. Like set_stop(), this
+    // does not move the entry, so the new time has to be between the ones before and after it.
+    pub fn edit(
+        &mut self,
+        id: EntryId,
+        stop: NaiveDateTime,
+        task: &str,
+    ) -> Result<EntryId, TimelogError> {
+        let index = self.index_of(id).ok_or(TimelogError::UnknownId(id))?;
+        let task = Timelog::check_task(task)?;
+        self.check_place(stop, index.checked_sub(1), index + 1, task)?;
+        let task = self.intern(task);
+        let entry = &mut self.entries[index];
+        entry.stop = stop;
+        entry.task = task;
+        self.unsaved_from = None;
+        Ok(self.id_at(index))
+    }
+
+    // the deleted entry; its note stays valid for note()
+    pub fn delete(&mut self, id: EntryId) -> Result<Entry, TimelogError> {
+        let index = self.index_of(id).ok_or(TimelogError::UnknownId(id))?;
+        self.remove(index)
+    }
+
+    pub fn set_task(&mut self, index: usize, task: &str) -> Result<(), TimelogError> {
+        self.check_index(index)?;
+        let task = Timelog::check_task(task)?;
+        self.entries[index].task = self.intern(task);
+        self.unsaved_from = None;
+        Ok(())
+    }
+
+    pub fn set_stop(&mut self, index: usize, stop: NaiveDateTime) -> Result<(), TimelogError> {
+        self.check_index(index)?;
+        self.check_place(
+            stop,
+            index.checked_sub(1),
+            index + 1,
+            &self.entries[index].task,
+        )?;
+        self.entries[index].stop = stop;
+        self.unsaved_from = None;
+        Ok(())
+    }
+
+    // the removed entry; its note stays valid for note()
+    pub fn remove(&mut self, index: usize) -> Result<Entry, TimelogError> {
+        self.check_index(index)?;
+        self.unsaved_from = None;
+        self.last_added = self.last_added.and_then(|i| match i.cmp(&index) {
+            std::cmp::Ordering::Less => Some(i),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(i - 1),
+        });
+        self.keep_leader(index);
+        Ok(self.entries.remove(index))
+    }
+
+    // insert before the entry at `index`, or at the end for len()
+    pub fn insert(
+        &mut self,
+        index: usize,
+        stop: NaiveDateTime,
+        task: &str,
+    ) -> Result<(), TimelogError> {
+        if index > self.entries.len() {
+            return Err(TimelogError::NoEntry(index));
+        }
+        let task = Timelog::check_task(task)?;
+        self.check_place(stop, index.checked_sub(1), index, task)?;
+        let task = self.intern(task);
+        self.entries.insert(
+            index,
+            Entry {
+                stop,
+                task,
+                note: None,
+            },
+        );
+        self.unsaved_from = None;
+        self.last_added = self.last_added.map(|i| if index <= i { i + 1 } else { i });
+        Ok(())
+    }
+
+    // Remove the entry which got added last, e.g. one with a typo, also when it went before
+    // later ones; without one, since loading or after undoing it, the chronologically last entry.
+    pub fn pop_last(&mut self) -> Option<Entry> {
+        let index = self.last_added_index()?;
+        self.last_added = None;
+        self.keep_leader(index);
+        let entry = self.entries.remove(index);
+        // only appending the remaining new ones would keep it in the storage
+        if self.unsaved_from.is_some_and(|from| index < from) {
+            self.unsaved_from = None;
+        }
+        Some(entry)
+    }
+
+    // the entry which pop_last() removes
+    pub fn last_added(&self) -> Option<&Entry> {
+        self.entries.get(self.last_added_index()?)
+    }
+
+    fn last_added_index(&self) -> Option<usize> {
+        self.last_added
+            .or_else(|| self.entries.len().checked_sub(1))
+    }
+
+    pub fn last(&self) -> Option<&Entry> {
+        self.entries.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // whether there are changes which save() did not write yet
+    pub fn has_unsaved(&self) -> bool {
+        self.unsaved_from
+            .is_none_or(|from| from < self.entries.len())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), TimelogError> {
+        match index < self.entries.len() {
+            true => Ok(()),
+            false => Err(TimelogError::NoEntry(index)),
+        }
+    }
+
+    // the task as it gets stored; a line break would turn the rest into a note or garbage
+    fn check_task(task: &str) -> Result<&str, TimelogError> {
+        let trimmed = task.trim();
+        if trimmed.is_empty() || trimmed.contains(['\n', '\r']) {
+            return Err(TimelogError::InvalidTask(task.to_string()));
+        }
+        Ok(trimmed)
+    }
+
+    // whether an entry at `stop` fits after the one at `prev` and before the one at `next`; the
+    // not yet loaded entries are all earlier than loaded_since()
+    fn check_place(
+        &self,
+        stop: NaiveDateTime,
+        prev: Option<usize>,
+        next: usize,
+        task: &str,
+    ) -> Result<(), TimelogError> {
+        let after_prev = match prev {
+            Some(p) => self.entries[p].stop <= stop,
+            None => self.loaded_since.is_none_or(|since| since <= stop),
+        };
+        let before_next = self.entries.get(next).is_none_or(|e| stop <= e.stop);
+        if after_prev && before_next {
+            return Ok(());
+        }
+        Err(TimelogError::Misplaced {
+            text: Entry {
+                stop,
+                task: Arc::from(task),
+                note: None,
+            }
+            .to_string(),
+        })
+    }
+}
+
+/**
+ * Serialized form of an entry within its Timelog, with the text of its note
+ */
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerdeEntry<'a> {
+    stop: NaiveDateTime,
+    #[serde(borrow)]
+    task: std::borrow::Cow<'a, str>,
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    note: Option<std::borrow::Cow<'a, str>>,
+}
+
+// A list of the entries with their notes. Comments and other unknown lines are not part of it,
+// and neither is the storage.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Timelog {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.entries.iter().map(|e| SerdeEntry {
+            stop: e.stop,
+            task: std::borrow::Cow::Borrowed(&e.task),
+            note: self.note(e).map(std::borrow::Cow::Borrowed),
+        }))
+    }
+}
+
+// the entries may come in any order, but have to be valid like the ones in a file
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Timelog {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Timelog, D::Error> {
+        use serde::de::Error;
+
+        let mut timelog = Timelog::default();
+        for e in Vec::<SerdeEntry>::deserialize(deserializer)? {
+            if !VALID_YEARS.contains(&e.stop.year()) {
+                return Err(D::Error::custom(format!("Invalid time {}", e.stop)));
+            }
+            timelog
+                .insert_sorted(e.task.into_owned(), e.stop)
+                .map_err(D::Error::custom)?;
+            if let Some(note) = e.note.filter(|note| !note.is_empty()) {
+                let pos = timelog.entries.partition_point(|x| x.stop <= e.stop) - 1;
+                timelog.append_note_at(pos, &note);
+            }
+        }
+        Ok(timelog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::index;
+    use crate::journal;
+    use crate::storage::MemoryStorage;
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::io::Read;
+
+    const TWO_DAYS: &str = "
+2022-06-09 06:02: arrived
 2022-06-09 06:27: email
 2022-06-09 06:32: **tea
 2022-06-09 12:00: work
@@ -286,7 +1844,7 @@ mod tests {
 2022-06-10 16:00: customer joe: support
 ";
 
-    const TWO_WEEKS: &'static str = "
+    const TWO_WEEKS: &str = "
 2022-06-01 06:00: arrived
 2022-06-01 07:00: workw1
 2022-06-01 07:10: ** tea
@@ -309,16 +1867,29 @@ mod tests {
     #[test]
     fn test_parse_line_valid() {
         let e1 = Timelog::parse_line("2022-05-31 13:59: email").unwrap();
-        assert_eq!(e1.task, "email");
+        assert_eq!(&*e1.task, "email");
         assert_eq!(e1.stop.format(TIME_FMT).to_string(), "2022-05-31 13:59");
 
         let e2 = Timelog::parse_line("2022-05-31 14:07: read docs").unwrap();
-        assert_eq!(e2.task, "read docs");
+        assert_eq!(&*e2.task, "read docs");
         assert_eq!(e2.stop.format(TIME_FMT).to_string(), "2022-05-31 14:07");
 
         assert_eq!(e2.stop.signed_duration_since(e1.stop), Duration::minutes(8));
     }
 
+    #[test]
+    fn test_parse_time() {
+        let expected = NaiveDateTime::parse_from_str("2022-05-31 13:59", TIME_FMT).unwrap();
+        assert_eq!(parse_time("2022-05-31 13:59"), Some(expected));
+        assert_eq!(parse_time("2022-05-31 13:5x"), None);
+        assert_eq!(parse_time("2022-02-30 13:59"), None);
+        assert_eq!(parse_time("2022-05-31 24:00"), None);
+        assert_eq!(parse_time("2022-05-31T13:59"), None);
+        // falls back to chrono for non-padded times
+        let e = Timelog::parse_line("2022-5-31 9:05: email").unwrap();
+        assert_eq!(e.to_string(), "2022-05-31 09:05: email");
+    }
+
     #[test]
     fn test_parse_line_invalid() {
         assert_eq!(Timelog::parse_line(""), None);
@@ -347,15 +1918,61 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_parse_out_of_order() {
-        Timelog::parse(
+        let entries = Timelog::parse(
             "
 2022-06-09 06:02: arrived
 2022-06-09 06:10: ** tea
 2022-06-08 07:32: huh, previous day
+2022-06-09 06:30: work
 ",
         );
+        let tasks: Vec<&str> = entries.iter().map(|e| &*e.task).collect();
+        assert_eq!(tasks, ["huh, previous day", "arrived", "** tea", "work"]);
+
+        let skip = ParseOptions {
+            out_of_order: OutOfOrder::Skip,
+        };
+        let tl = Timelog::new_from_string_with(
+            "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+2022-06-08 07:32: huh, previous day
+  with a note
+2022-06-09 06:30: work
+",
+            skip,
+        );
+        assert_eq!(
+            tl.format_store(),
+            "2022-06-09 06:02: arrived\n2022-06-09 06:10: ** tea\n2022-06-09 06:30: work\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_garbage() {
+        // invalid UTF-8, NULs, absurd dates, and a huge line don't stop the parsing
+        let mut raw = b"2022-06-09 06:02: arrived\n\xff\xfe\x00garbage\n".to_vec();
+        raw.extend_from_slice(b"0000-01-01 00:00: year zero\n+262143-12-31 23:59: far away\n");
+        raw.extend_from_slice(b"2022-06-09 06:10: \x00\x00** tea\n\x00\x00\x00\n");
+        raw.extend(std::iter::repeat_n(b'x', 5 * MAX_LINE_BYTES));
+        raw.extend_from_slice(b"\n2022-06-09 07:\xc3\x28: broken\n2022-06-09 08:00: w\xc3\x28rk\n");
+        let tl = Timelog::new_from_bytes(&raw);
+        let tasks: Vec<&str> = tl.entries.iter().map(|e| &*e.task).collect();
+        assert_eq!(tasks, ["arrived", "** tea", "w\u{fffd}(rk"]);
+
+        // a huge line does not need huge buffers
+        let mut reader = io::BufReader::new(io::repeat(b'x').take(1 << 24).chain(&b"\nrest\n"[..]));
+        let mut buf = Vec::new();
+        assert_eq!(
+            read_line(&mut reader, &mut buf).unwrap(),
+            ((1 << 24) + 1, true)
+        );
+        assert_eq!(buf.len(), MAX_LINE_BYTES);
+        buf.clear();
+        assert_eq!(read_line(&mut reader, &mut buf).unwrap(), (5, false));
+        assert_eq!(buf, b"rest\n");
+        buf.clear();
+        assert_eq!(read_line(&mut reader, &mut buf).unwrap(), (0, false));
     }
 
     #[test]
@@ -363,7 +1980,7 @@ mod tests {
         let tl = Timelog::new_from_string("");
         assert_eq!(tl.get_all().next(), None);
 
-        let tl = Timelog::new_from_file(&PathBuf::from("/nonexisting"));
+        let tl = Timelog::new_from_file(&PathBuf::from("/nonexisting")).unwrap();
         assert_eq!(tl.get_all().next(), None);
 
         let tl = Timelog::new_from_string(TWO_DAYS);
@@ -380,30 +1997,201 @@ mod tests {
         assert_eq!(entries.next(), None);
     }
 
+    #[test]
+    fn test_errors() {
+        let e = Timelog::new_from_file(&env::temp_dir()).unwrap_err();
+        assert!(matches!(e, TimelogError::Io(_)));
+        assert!(e.to_string().starts_with("Could not access timelog: "));
+
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        assert!(matches!(tl.save(), Err(TimelogError::NoStorage)));
+
+        let tl = Timelog::new_from_string_strict(TWO_DAYS).unwrap();
+        assert_eq!(tl.get_all().count(), 10);
+        let e = Timelog::new_from_string_strict(
+            "2022-06-09 06:02: arrived\n  a note\n\n2022-06-09 07:00: email\ngarbage\n",
+        )
+        .unwrap_err();
+        assert_eq!(e.to_string(), "Invalid line 5: garbage");
+        let e = Timelog::new_from_string_strict(
+            "2022-06-09 06:02: arrived\n2022-06-09 07:00: email\n2022-06-09 06:30: code\n",
+        )
+        .unwrap_err();
+        assert!(matches!(e, TimelogError::Order { line: 3, .. }));
+        assert_eq!(io::Error::from(e).kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_edit() {
+        let storage = MemoryStorage::new(TWO_DAYS);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        assert_eq!(tl.len(), 10);
+        assert_eq!(
+            tl.position(time("2022-06-10 12:05"), "rtimelog: code"),
+            Some(5)
+        );
+        assert_eq!(
+            tl.position(time("2022-06-10 14:00"), "rtimelog: code"),
+            Some(7)
+        );
+        assert_eq!(tl.position(time("2022-06-10 12:05"), "email"), None);
+
+        tl.set_task(1, " email: inbox ").unwrap();
+        tl.set_stop(2, time("2022-06-09 06:40")).unwrap();
+        let removed = tl.remove(3).unwrap();
+        assert_eq!(&*removed.task, "work");
+        tl.insert(3, time("2022-06-09 11:00"), "review").unwrap();
+        tl.insert(tl.len(), time("2022-06-10 17:00"), "email")
+            .unwrap();
+        // failed edits do not change anything
+        assert!(matches!(
+            tl.set_stop(2, time("2022-06-09 06:00")),
+            Err(TimelogError::Misplaced { .. })
+        ));
+        assert_eq!(
+            tl.insert(5, time("2022-06-10 13:00"), "lunch")
+                .unwrap_err()
+                .to_string(),
+            "2022-06-10 13:00: lunch would not be in chronological order"
+        );
+        assert!(matches!(tl.remove(11), Err(TimelogError::NoEntry(11))));
+        assert!(matches!(
+            tl.insert(12, time("2022-06-11 08:00"), "x"),
+            Err(TimelogError::NoEntry(12))
+        ));
+        assert!(matches!(
+            tl.set_task(0, "two\nlines"),
+            Err(TimelogError::InvalidTask(_))
+        ));
+        assert!(matches!(
+            tl.set_task(0, "  "),
+            Err(TimelogError::InvalidTask(_))
+        ));
+
+        tl.save().unwrap();
+        assert_eq!(
+            storage.contents(),
+            "2022-06-09 06:02: arrived
+2022-06-09 06:27: email: inbox
+2022-06-09 06:40: **tea
+2022-06-09 11:00: review
+
+2022-06-10 07:00: arrived
+2022-06-10 12:05: rtimelog: code
+2022-06-10 12:30: **lunch
+2022-06-10 14:00: rtimelog: code
+2022-06-10 15:00: bug triage
+2022-06-10 16:00: customer joe: support
+2022-06-10 17:00: email
+"
+        );
+
+        // entries before the loaded ones are unknown, so nothing can go there
+        let since = time("2022-06-10 00:00");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage), since).unwrap();
+        assert!(tl.set_stop(0, time("2022-06-09 23:00")).is_err());
+        tl.set_stop(0, time("2022-06-10 06:00")).unwrap();
+    }
+
+    #[test]
+    fn test_entry_ids() {
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let day = tl
+            .get_n_days(
+                &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+                1,
+                NaiveTime::MIN,
+            )
+            .to_vec();
+        let ids: Vec<EntryId> = tl.with_ids(&day).map(|(id, _)| id).collect();
+        assert_eq!(ids.len(), 6);
+        let code = ids[3];
+        assert_eq!(code.to_string(), "2022-06-10 14:00");
+        assert_eq!(&*tl.get(code).unwrap().task, "rtimelog: code");
+
+        // ids of later entries stay valid when earlier ones change
+        tl.remove(0).unwrap();
+        tl.insert(0, time("2022-06-08 17:00"), "email").unwrap();
+        tl.insert(0, time("2022-06-08 09:00"), "arrived").unwrap();
+        assert_eq!(&*tl.get(code).unwrap().task, "rtimelog: code");
+
+        // a second entry at the same time
+        tl.add_at("typo".to_string(), time("2022-06-10 16:00"))
+            .unwrap();
+        let typo = tl.id(tl.get_all().last().unwrap()).unwrap();
+        assert_eq!(typo.to_string(), "2022-06-10 16:00 #1");
+        let clone = tl.get_all().last().unwrap().clone();
+        assert_eq!(tl.id(&clone), Some(typo));
+        assert_eq!(&*tl.delete(typo).unwrap().task, "typo");
+        assert!(matches!(
+            tl.delete(typo),
+            Err(TimelogError::UnknownId(id)) if id == typo
+        ));
+
+        let moved = tl
+            .edit(code, time("2022-06-10 14:30"), "rtimelog: review")
+            .unwrap();
+        assert_eq!(moved.stop, time("2022-06-10 14:30"));
+        assert_eq!(tl.get(code), None);
+        assert_eq!(&*tl.get(moved).unwrap().task, "rtimelog: review");
+        // stays before the next entry, and unchanged on failure
+        assert!(matches!(
+            tl.edit(moved, time("2022-06-10 15:30"), "x"),
+            Err(TimelogError::Misplaced { .. })
+        ));
+        assert!(matches!(
+            tl.edit(moved, time("2022-06-10 14:30"), ""),
+            Err(TimelogError::InvalidTask(_))
+        ));
+        assert_eq!(&*tl.get(moved).unwrap().task, "rtimelog: review");
+    }
+
     #[test]
     fn test_get_n_days() {
         let tl = Timelog::new_from_string("");
         assert_eq!(
-            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(), 1),
+            tl.get_n_days(
+                &NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(),
+                1,
+                NaiveTime::MIN
+            ),
             &[]
         );
 
         let tl = Timelog::new_from_string(TWO_DAYS);
         assert_eq!(
-            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(), 1),
+            tl.get_n_days(
+                &NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(),
+                1,
+                NaiveTime::MIN
+            ),
             &[]
         );
 
-        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(), 1);
+        let entries = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(),
+            1,
+            NaiveTime::MIN,
+        );
         assert_eq!(entries.len(), 4);
         assert_eq!(&format!("{}", entries[0]), "2022-06-09 06:02: arrived");
         assert_eq!(&format!("{}", entries[3]), "2022-06-09 12:00: work");
 
         // no earlier entries, same entries as above
-        let entries2 = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(), 2);
+        let entries2 = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(),
+            2,
+            NaiveTime::MIN,
+        );
         assert_eq!(entries2, entries);
 
-        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let entries = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            1,
+            NaiveTime::MIN,
+        );
         assert_eq!(entries.len(), 6);
         assert_eq!(&format!("{}", entries[0]), "2022-06-10 07:00: arrived");
         assert_eq!(
@@ -412,42 +2200,199 @@ mod tests {
         );
 
         // last two days includes 2022-06-09
-        let entries2 = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2);
+        let entries2 = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            2,
+            NaiveTime::MIN,
+        );
         assert_eq!(entries2.len(), 10);
         assert_eq!(entries2[4..], entries[..]);
         assert_eq!(&format!("{}", entries2[1]), "2022-06-09 06:27: email");
+
+        // working past midnight
+        let tl = Timelog::new_from_string(
+            "2022-06-09 20:00: arrived\n2022-06-10 01:30: code\n2022-06-10 09:00: arrived\n",
+        );
+        let day = NaiveDate::from_ymd_opt(2022, 6, 9).unwrap();
+        let midnight = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+        assert_eq!(tl.get_n_days(&day, 1, NaiveTime::MIN).len(), 1);
+        let entries = tl.get_n_days(&day, 1, midnight);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(&format!("{}", entries[1]), "2022-06-10 01:30: code");
+        let s = Sentinels {
+            virtual_midnight: midnight,
+            ..Sentinels::default()
+        };
+        let now = NaiveDateTime::new(
+            day.succ_opt().unwrap(),
+            NaiveTime::from_hms_opt(1, 45, 0).unwrap(),
+        );
+        assert_eq!(tl.get_today(now, &s), entries);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut tl = Timelog::new_from_string(
+            "# comments get lost\n2022-06-09 06:02: arrived\n2022-06-09 06:27: email\n  to joe\n",
+        );
+        let json = serde_json::to_string(&tl).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"stop":"2022-06-09T06:02:00","task":"arrived"},{"stop":"2022-06-09T06:27:00","task":"email","note":"to joe"}]"#
+        );
+        let entry = tl.get_all().next().unwrap();
+        assert_eq!(
+            serde_json::to_string(entry).unwrap(),
+            r#"{"stop":"2022-06-09T06:02:00","task":"arrived"}"#
+        );
+
+        // out of order
+        let mut back: Timelog = serde_json::from_str(
+            r#"[{"stop":"2022-06-09T06:27:00","task":"email","note":"to joe"},
+                {"stop":"2022-06-09T06:02:00","task":"arrived"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            back.contents_to_save().unwrap(),
+            tl.contents_to_save()
+                .unwrap()
+                .replace("# comments get lost\n", "")
+        );
+        let e = back.last().unwrap().clone();
+        assert_eq!(back.note(&e), Some("to joe"));
+
+        assert!(serde_json::from_str::<Timelog>(
+            r#"[{"stop":"2022-06-09T06:02:00","task":"two\nlines"}]"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Timelog>(r#"[{"task":"arrived"}]"#).is_err());
+    }
+
+    #[test]
+    fn test_durations_for_day() {
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let s = Sentinels::default();
+        let durations: Vec<(String, i64)> = tl
+            .durations_for_day(&day, &s)
+            .map(|(e, d)| (e.task.to_string(), d.num_minutes()))
+            .collect();
+        assert_eq!(
+            durations,
+            vec![
+                // the previous day does not count
+                ("arrived".to_string(), 0),
+                ("rtimelog: code".to_string(), 305),
+                ("**lunch".to_string(), 25),
+                ("rtimelog: code".to_string(), 90),
+                ("bug triage".to_string(), 60),
+                ("customer joe: support".to_string(), 60),
+            ]
+        );
+
+        // nothing gets done while away
+        let s = Sentinels {
+            departure: vec!["bug triage".to_string()],
+            ..Sentinels::default()
+        };
+        let after = tl.durations_for_day(&day, &s).last().unwrap();
+        assert_eq!(after.1, Duration::minutes(0));
+
+        let empty = NaiveDate::from_ymd_opt(2022, 6, 11).unwrap();
+        assert_eq!(tl.durations_for_day(&empty, &s).count(), 0);
     }
 
     #[test]
     fn test_get_n_weeks() {
         let tl = Timelog::new_from_string("");
         assert_eq!(
-            tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(), 1),
+            tl.get_n_weeks(
+                &NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(),
+                1,
+                NaiveTime::MIN,
+                Weekday::Mon,
+            ),
             &[]
         );
 
         let tl = Timelog::new_from_string(TWO_WEEKS);
         // select Wed, data has Tue and Thu
-        let entries_w1_1 = tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(), 1);
+        let entries_w1_1 = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(),
+            1,
+            NaiveTime::MIN,
+            Weekday::Mon,
+        );
         assert_eq!(entries_w1_1.len(), 6);
         assert_eq!(&format!("{}", entries_w1_1[0]), "2022-06-01 06:00: arrived");
         assert_eq!(&format!("{}", entries_w1_1[5]), "2022-06-03 07:10: ** tea");
 
         // previous week has no entries
-        let entries_w1_2 = tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(), 2);
+        let entries_w1_2 = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 2).unwrap(),
+            2,
+            NaiveTime::MIN,
+            Weekday::Mon,
+        );
         assert_eq!(entries_w1_2, entries_w1_1);
 
         // select Tue, data has Wed to Fri
-        let entries_w2_1 = tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(), 1);
+        let entries_w2_1 = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(),
+            1,
+            NaiveTime::MIN,
+            Weekday::Mon,
+        );
         assert_eq!(entries_w2_1.len(), 7);
         assert_eq!(&format!("{}", entries_w2_1[0]), "2022-06-08 06:00: arrived");
         assert_eq!(&format!("{}", entries_w2_1[6]), "2022-06-10 07:00: workw2");
 
         // previous week has entries
-        let entries_w2_2 = tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(), 2);
+        let entries_w2_2 = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(),
+            2,
+            NaiveTime::MIN,
+            Weekday::Mon,
+        );
         assert_eq!(entries_w2_2.len(), 13);
         assert_eq!(entries_w2_2[0..6], entries_w1_1[..]);
         assert_eq!(entries_w2_2[6..], entries_w2_1[..]);
+
+        // weeks from Thursday: the one of that Tue has the Fri before and the Wed after it
+        let entries = tl.get_n_weeks(
+            &NaiveDate::from_ymd_opt(2022, 6, 7).unwrap(),
+            1,
+            NaiveTime::MIN,
+            Weekday::Thu,
+        );
+        assert_eq!(entries.len(), 6);
+        assert_eq!(&format!("{}", entries[0]), "2022-06-03 06:00: arrived");
+    }
+
+    #[test]
+    fn test_week_as_string() {
+        let tl = Timelog::new_from_string("");
+        let dates = DateFormat::default();
+        let day = NaiveDate::from_ymd_opt(2022, 6, 7).unwrap();
+        assert_eq!(
+            tl.get_today_as_string(&day, &dates, Weekday::Mon),
+            "Tuesday, 2022-06-07 (week 23)"
+        );
+        assert_eq!(
+            tl.get_this_week_as_string(&day, &dates, Weekday::Mon),
+            "2022, week 23 (June 6-12)"
+        );
+        assert_eq!(
+            tl.get_this_week_as_string(&day, &dates, Weekday::Sun),
+            "2022, week 23 (June 5-11)"
+        );
+        // the week with the Thursday counts, also for the year
+        let day = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        assert_eq!(
+            tl.get_this_week_as_string(&day, &dates, Weekday::Mon),
+            "2025, week 1 (December 30-5)"
+        );
     }
 
     #[test]
@@ -457,16 +2402,731 @@ mod tests {
         assert_eq!(tl.format_store(), TWO_DAYS.trim_start());
     }
 
+    #[test]
+    fn test_storage() {
+        let storage = MemoryStorage::new(TWO_DAYS);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        assert_eq!(tl.get_all().count(), 10);
+        assert_eq!(tl.filename(), None);
+
+        tl.add_at(
+            "review".to_string(),
+            NaiveDateTime::parse_from_str("2022-06-10 17:00", TIME_FMT).unwrap(),
+        )
+        .unwrap();
+        tl.save().unwrap();
+        // only appended
+        assert_eq!(
+            storage.contents(),
+            format!("{TWO_DAYS}2022-06-10 17:00: review\n")
+        );
+
+        // everything gets written after changing a stored entry
+        tl.recover(&["2022-06-09 06:10: email".to_string()])
+            .unwrap();
+        tl.save().unwrap();
+        assert!(storage
+            .contents()
+            .starts_with("2022-06-09 06:02: arrived\n2022-06-09 06:10: email\n"));
+    }
+
+    #[test]
+    fn test_save_atomic() {
+        let dir = env::temp_dir().join(format!("rtimelog-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timelog.txt");
+        fs::write(&path, "2022-06-09 06:02: arrived\ngarbage\n").unwrap();
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        tl.save_atomic().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-09 06:02: arrived\ngarbage\n"
+        );
+        // the temporary file is gone
+        assert!(!dir.join("timelog.txt.tmp").exists());
+
+        // a symlink, e.g. into a synced directory, stays one
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.txt");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            let mut tl = Timelog::new_from_file(&link).unwrap();
+            let stop = tl.get_all().next().unwrap().stop;
+            tl.add_at("email".to_string(), stop).unwrap();
+            tl.save_atomic().unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+            assert_eq!(
+                fs::read_to_string(&path).unwrap(),
+                "2022-06-09 06:02: arrived\n2022-06-09 06:02: email\ngarbage\n"
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_append() {
+        let path = env::temp_dir().join(format!("rtimelog-append-{}.txt", std::process::id()));
+        // damaged lines stay in the file when only appending, and no final newline
+        fs::write(
+            &path,
+            "2022-06-09 06:02: arrived\ngarbage\n2022-06-09 12:00: work",
+        )
+        .unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        tl.add_at("arrived".to_string(), time("2022-06-10 07:00"))
+            .unwrap();
+        tl.save().unwrap();
+        tl.add_at("email".to_string(), time("2022-06-10 08:00"))
+            .unwrap();
+        tl.append_note("inbox zero");
+        tl.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-09 06:02: arrived\ngarbage\n2022-06-09 12:00: work\n\n\
+             2022-06-10 07:00: arrived\n2022-06-10 08:00: email\n  inbox zero\n"
+        );
+        assert_eq!(journal::check(&path).unwrap(), None);
+
+        // a note for a stored entry needs a rewrite, which keeps the damaged line as well
+        tl.append_note("and more");
+        tl.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-09 06:02: arrived\ngarbage\n2022-06-09 12:00: work\n\n\
+             2022-06-10 07:00: arrived\n2022-06-10 08:00: email\n  inbox zero\n  and more\n"
+        );
+
+        // so does a change by another program
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&path, "2022-06-10 07:00: arrived\ngarbage\n").unwrap();
+        tl.add_at("code".to_string(), time("2022-06-10 09:00"))
+            .unwrap();
+        tl.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-10 07:00: arrived\n2022-06-10 09:00: code\ngarbage\n"
+        );
+
+        fs::remove_file(journal::journal_path(&path)).unwrap();
+        fs::remove_file(index::index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let path = env::temp_dir().join(format!("rtimelog-reload-{}.txt", std::process::id()));
+        fs::write(&path, "2022-06-10 09:00: arrived\n").unwrap();
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        assert!(!tl.reload_if_changed());
+
+        // edited in an editor; set the time explicitly, as it may not have ticked yet
+        fs::write(&path, "2022-06-10 09:00: arrived\n2022-06-10 10:00: code\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert!(tl.reload_if_changed());
+        assert_eq!(tl.len(), 2);
+        assert!(!tl.reload_if_changed());
+
+        // changing the reloaded entries keeps the edit
+        assert!(tl.append_note("tests"));
+        tl.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-10 09:00: arrived\n2022-06-10 10:00: code\n  tests\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lock() {
+        let path = env::temp_dir().join(format!("rtimelog-lock-{}.txt", std::process::id()));
+        fs::write(&path, "2022-06-10 07:00: arrived\n").unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+
+        // two instances add entries concurrently; the later save keeps the other one's
+        let mut tl1 = Timelog::new_from_file(&path).unwrap();
+        let mut tl2 = Timelog::new_from_file(&path).unwrap();
+        tl1.add_at("code".to_string(), time("2022-06-10 09:00"))
+            .unwrap();
+        tl1.append_note("ticket 123");
+        tl1.save().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        tl2.add_at("email".to_string(), time("2022-06-10 08:00"))
+            .unwrap();
+        tl2.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-10 07:00: arrived\n2022-06-10 08:00: email\n\
+             2022-06-10 09:00: code\n  ticket 123\n"
+        );
+        assert_eq!(tl2.get_all().count(), 3);
+
+        // saving waits for the lock, and gives up eventually
+        let lock = FileStorage::new(&path).lock().unwrap();
+        tl2.add_at("review".to_string(), time("2022-06-10 10:00"))
+            .unwrap();
+        let e = tl2.save().unwrap_err();
+        assert!(matches!(e, TimelogError::Locked { .. }));
+        assert!(e.to_string().starts_with(&format!(
+            "{} is locked by another process; ",
+            path.display()
+        )));
+        drop(lock);
+        tl2.save().unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("2022-06-10 10:00: review\n"));
+
+        fs::remove_file(journal::journal_path(&path)).unwrap();
+        fs::remove_file(index::index_path(&path)).unwrap();
+        fs::remove_file(path.with_extension("txt.lock")).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_changed() {
+        let path = env::temp_dir().join(format!("rtimelog-changed-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "2022-06-10 07:00: arrived\n2022-06-10 08:00: email\n",
+        )
+        .unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let edit = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH)
+                .unwrap();
+        };
+
+        // an entry that failed to get saved survives a reload, and gets merged on saving
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        assert!(!tl.has_unsaved());
+        tl.add_at("code".to_string(), time("2022-06-10 09:00"))
+            .unwrap();
+        assert!(tl.has_unsaved());
+        edit("2022-06-10 07:00: arrived\n2022-06-10 08:00: mail\n");
+        assert!(!tl.reload_if_changed());
+        tl.save().unwrap();
+        assert!(!tl.has_unsaved());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "2022-06-10 07:00: arrived\n2022-06-10 08:00: mail\n2022-06-10 09:00: code\n"
+        );
+
+        // an edit would overwrite the other program's change
+        tl.set_task(1, "email").unwrap();
+        edit("2022-06-10 07:00: arrived\n2022-06-10 08:00: inbox\n2022-06-10 09:00: code\n");
+        assert!(matches!(tl.save(), Err(TimelogError::Changed)));
+        assert!(tl.reload_if_changed());
+        assert_eq!(tl.get_all().nth(1).unwrap().task.as_ref(), "inbox");
+
+        fs::remove_file(journal::journal_path(&path)).ok();
+        fs::remove_file(index::index_path(&path)).ok();
+        fs::remove_file(path.with_extension("txt.lock")).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_storage_range() {
+        let storage = MemoryStorage::new(TWO_WEEKS);
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let tl = Timelog::new_from_storage_range(
+            &storage,
+            time("2022-06-08 00:00"),
+            time("2022-06-09 23:59"),
+        )
+        .unwrap();
+        assert_eq!(
+            tl.get_all().map(|e| e.to_string()).collect::<Vec<_>>(),
+            vec![
+                "2022-06-08 06:00: arrived",
+                "2022-06-08 07:00: workw2",
+                "2022-06-08 07:10: ** tea",
+                "2022-06-09 06:00: arrived",
+                "2022-06-09 07:00: workw2",
+            ]
+        );
+        // same tasks share their string
+        let entries: Vec<&Entry> = tl.get_all().collect();
+        assert!(Arc::ptr_eq(&entries[1].task, &entries[4].task));
+        // read-only
+        assert_eq!(tl.filename(), None);
+    }
+
+    #[test]
+    fn test_storage_recent() {
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let since = time("2022-06-09 00:00");
+        let storage = MemoryStorage::new(TWO_WEEKS);
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        assert_eq!(tl.loaded_since(), Some(since));
+        assert_eq!(tl.get_all().count(), 4);
+        assert_eq!(
+            tl.get_all().next().unwrap().to_string(),
+            "2022-06-09 06:00: arrived"
+        );
+
+        // loading in the background
+        let older = Timelog::load_older(&storage, since).unwrap();
+        tl.add_at("code".to_string(), time("2022-06-10 08:00"))
+            .unwrap();
+        tl.prepend_older(older, since);
+        assert_eq!(tl.loaded_since(), None);
+        assert_eq!(tl.get_all().count(), Timelog::parse(TWO_WEEKS).len() + 1);
+
+        // appending does not need the older entries
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        tl.add_at("code".to_string(), time("2022-06-10 08:00"))
+            .unwrap();
+        tl.save().unwrap();
+        assert_eq!(tl.loaded_since(), Some(since));
+        assert_eq!(
+            storage.contents(),
+            format!("{TWO_WEEKS}2022-06-10 08:00: code\n")
+        );
+        // but writing everything does
+        tl.recover(&[]).unwrap();
+        tl.save().unwrap();
+        assert_eq!(tl.loaded_since(), None);
+        assert_eq!(
+            storage.contents(),
+            format!("{}2022-06-10 08:00: code\n", TWO_WEEKS.trim_start())
+        );
+
+        // nothing loaded yet, e.g. the first entry of the week
+        let since = time("2022-06-13 00:00");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        assert!(tl.is_empty());
+        tl.add_at("arrived".to_string(), time("2022-06-13 09:00"))
+            .unwrap();
+        tl.save().unwrap();
+        assert_eq!(tl.loaded_since(), Some(since));
+        assert_eq!(
+            storage.contents(),
+            format!(
+                "{}2022-06-10 08:00: code\n\n2022-06-13 09:00: arrived\n",
+                TWO_WEEKS.trim_start()
+            )
+        );
+        let empty = MemoryStorage::new("");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(empty.clone()), since).unwrap();
+        tl.add_at("arrived".to_string(), time("2022-06-13 09:00"))
+            .unwrap();
+        tl.save().unwrap();
+        assert_eq!(empty.contents(), "2022-06-13 09:00: arrived\n");
+
+        // no file yet
+        let missing = FileStorage::new(Path::new("/nonexisting/timelog.txt"));
+        let tl = Timelog::new_from_storage_recent(Box::new(missing), since).unwrap();
+        assert_eq!(tl.get_all().count(), 0);
+    }
+
+    #[test]
+    fn test_find_offset() {
+        let path = env::temp_dir().join(format!("rtimelog-offset-{}.txt", std::process::id()));
+        let mut log = String::new();
+        let start = NaiveDate::from_ymd_opt(2022, 1, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        for i in 0..5000 {
+            writeln!(
+                log,
+                "{}: task {i}",
+                (start + Duration::hours(i)).format(TIME_FMT)
+            )
+            .unwrap();
+        }
+        fs::write(&path, &log).unwrap();
+
+        let since = start + Duration::hours(4000);
+        let tl =
+            Timelog::new_from_storage_recent(Box::new(FileStorage::new(&path)), since).unwrap();
+        let offset = Timelog::find_offset(&FileStorage::new(&path), since).unwrap();
+
+        // it did seek, and did not miss anything
+        assert!(offset > 0);
+        assert_eq!(tl.get_all().count(), 1000);
+        assert_eq!(tl.get_all().next().unwrap().task.as_ref(), "task 4000");
+
+        // saving writes an index, which has the exact line start
+        let storage = FileStorage::new(&path);
+        storage.save(&log).unwrap();
+        storage.append("2022-07-30 09:00: appended\n").unwrap();
+        let line = log.find(&format!("{}: task 4000", since.format(TIME_FMT)));
+        assert_eq!(storage.day_offset(since.date()), line.map(|l| l as u64));
+        let tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        assert_eq!(tl.get_all().count(), 1001);
+        assert_eq!(tl.get_all().next().unwrap().task.as_ref(), "task 4000");
+
+        // changes from other programs make it stale
+        fs::write(&path, format!("2021-12-31 08:00: arrived\n{log}")).unwrap();
+        assert_eq!(storage.day_offset(since.date()), None);
+        let tl = Timelog::new_from_storage_recent(Box::new(storage), since).unwrap();
+        assert_eq!(tl.get_all().count(), 1000);
+
+        fs::remove_file(journal::journal_path(&path)).unwrap();
+        fs::remove_file(index::index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_recover() {
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let count = tl
+            .recover(&[
+                // already there
+                "2022-06-09 06:02: arrived".to_string(),
+                "2022-06-09 06:30: lost".to_string(),
+                "garbage".to_string(),
+                "2022-06-11 08:00: arrived".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(count, 2);
+        let entries: Vec<String> = tl.get_all().map(|e| e.to_string()).collect();
+        assert_eq!(entries.len(), 12);
+        assert_eq!(entries[2], "2022-06-09 06:30: lost");
+        assert_eq!(entries[11], "2022-06-11 08:00: arrived");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut desktop = Timelog::new_from_string(TWO_DAYS);
+        let laptop = Timelog::new_from_string(
+            "2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+  from the train
+2022-06-09 06:32: reading
+2022-06-09 08:00: conference
+
+2022-06-11 08:00: arrived
+",
+        );
+        let merged = desktop.merge(&laptop).unwrap();
+        assert_eq!(
+            merged,
+            Merged {
+                added: 3,
+                duplicates: 2,
+                conflicts: vec![(
+                    NaiveDate::from_ymd_opt(2022, 6, 9)
+                        .unwrap()
+                        .and_hms_opt(6, 32, 0)
+                        .unwrap(),
+                    "**tea".to_string(),
+                    "reading".to_string()
+                )],
+            }
+        );
+        let entries: Vec<String> = desktop.get_all().map(|e| e.to_string()).collect();
+        assert_eq!(entries.len(), 13);
+        assert_eq!(entries[2], "2022-06-09 06:32: **tea");
+        assert_eq!(entries[3], "2022-06-09 06:32: reading");
+        assert_eq!(entries[4], "2022-06-09 08:00: conference");
+        assert_eq!(entries[12], "2022-06-11 08:00: arrived");
+        let email = desktop.get_all().nth(1).unwrap();
+        assert_eq!(desktop.note(email), Some("from the train"));
+
+        // nothing new the second time
+        let again = desktop.merge(&laptop).unwrap();
+        assert_eq!((again.added, again.duplicates), (0, 5));
+        assert_eq!(desktop.len(), 13);
+    }
+
+    #[test]
+    fn test_time_formats() {
+        let mut tl = Timelog::new_from_string(
+            "2022/06/09 6:02: arrived
+2022-06-09T06:27: email
+2022-06-09T06:32:15: **tea
+2022-6-9 12:00: work
+2022.06.09 13:00: garbage
+",
+        );
+        assert_eq!(
+            tl.contents_to_save().unwrap(),
+            "2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+2022-06-09 06:32:15: **tea
+2022-06-09 12:00: work
+2022.06.09 13:00: garbage
+"
+        );
+    }
+
+    #[test]
+    fn test_utc_offsets() {
+        let log = "2023-03-26 01:00 +0100: arrived
+2023-03-26 03:30 +0200: code
+
+2023-03-27 09:00 +0200: arrived
+";
+        let mut tl = Timelog::new_from_string(log);
+        assert!(tl.offsets);
+        let entries: Vec<String> = tl.get_all().map(|e| e.to_string()).collect();
+        // in the offset of the start of the day, so that durations are right
+        assert_eq!(
+            entries,
+            vec![
+                "2023-03-26 01:00: arrived",
+                "2023-03-26 02:30: code",
+                "2023-03-27 09:00: arrived"
+            ]
+        );
+        assert_eq!(tl.contents_to_save().unwrap(), log);
+
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let east = |h| FixedOffset::east_opt(h * 3600).unwrap();
+        tl.insert_at_offset("email".to_string(), time("2023-03-27 10:00"), Some(east(2)));
+        // after flying to Tokyo
+        tl.insert_at_offset(
+            "meeting".to_string(),
+            time("2023-03-27 20:00"),
+            Some(east(9)),
+        );
+        assert_eq!(tl.last().unwrap().stop, time("2023-03-27 13:00"));
+        assert_eq!(
+            tl.contents_to_save().unwrap(),
+            format!("{log}2023-03-27 10:00 +0200: email\n2023-03-27 20:00 +0900: meeting\n")
+        );
+
+        // gtimelog's format has none
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        assert!(!tl.offsets);
+        assert_eq!(parse_offset("-0430"), FixedOffset::west_opt(16200));
+        assert_eq!(parse_offset("+2"), None);
+        assert_eq!(
+            format_offset(FixedOffset::west_opt(16200).unwrap()),
+            "-0430"
+        );
+        assert!(Timelog::parse_line("2023-03-26 01:00 +01: arrived").is_none());
+    }
+
+    #[test]
+    fn test_notes() {
+        let log = "2022-06-09 09:00: arrived
+2022-06-09 12:00: rtimelog: code
+  fixed the parser
+	and its tests
+
+2022-06-10 09:00: arrived
+  came late
+2022-06-10 10:00: email
+";
+        let storage = MemoryStorage::new(log);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let notes: Vec<_> = tl.get_all().map(|e| tl.note(e)).collect();
+        assert_eq!(
+            notes,
+            vec![
+                None,
+                Some("fixed the parser\nand its tests"),
+                Some("came late"),
+                None
+            ]
+        );
+        // notes get saved indented
+        assert_eq!(tl.format_store(), log.replace("\tand", "  and"));
+
+        assert!(tl.append_note("with a colleague: joe"));
+        assert!(tl.append_note("second line"));
+        tl.save().unwrap();
+        assert!(storage
+            .contents()
+            .ends_with("10:00: email\n  with a colleague: joe\n  second line\n"));
+        assert!(!Timelog::new_from_string("").append_note("nothing"));
+
+        // the notes of not loaded entries are not loaded either
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let since = time("2022-06-10 00:00");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        let recent: Vec<_> = tl.get_all().map(|e| tl.note(e)).collect();
+        assert_eq!(
+            recent,
+            vec![
+                Some("came late"),
+                Some("with a colleague: joe\nsecond line")
+            ]
+        );
+        // ... until loading them
+        tl.prepend_older(Timelog::load_older(&storage, since).unwrap(), since);
+        let all: Vec<_> = tl.get_all().filter_map(|e| tl.note(e)).collect();
+        assert_eq!(
+            all,
+            vec![
+                "fixed the parser\nand its tests",
+                "came late",
+                "with a colleague: joe\nsecond line"
+            ]
+        );
+
+        // indented lines which don't follow an entry are no notes
+        let tl = Timelog::new_from_string("  orphan\n\n  2022-06-10 09:00: arrived\n");
+        assert_eq!(tl.get_all().count(), 1);
+        assert_eq!(tl.note(tl.get_all().next().unwrap()), None);
+
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let count = tl
+            .recover(&[
+                "2022-06-09 06:30: lost".to_string(),
+                "  with its note".to_string(),
+                // already there, so is its note
+                "2022-06-09 06:02: arrived".to_string(),
+                "  not mine".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(count, 1);
+        let notes: Vec<_> = tl.get_all().filter_map(|e| tl.note(e)).collect();
+        assert_eq!(notes, vec!["with its note"]);
+    }
+
+    #[test]
+    fn test_unknown_lines() {
+        let log = "# gtimelog log of joe
+2022-06-09 09:00: arrived
+2022-06-09 12:00: rtimelog: code
+  fixed the parser
+# TODO: split this
+
+# vacation from here
+
+2022-06-10 09:00: arrived
+  came late
+ not indented enough? no, that's a note
+garbage
+2022-06-10 10:00: email
+# end
+";
+        let storage = MemoryStorage::new(log);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        assert_eq!(tl.len(), 4);
+        let notes: Vec<_> = tl.get_all().filter_map(|e| tl.note(e)).collect();
+        assert_eq!(
+            notes,
+            vec![
+                "fixed the parser",
+                "came late\nnot indented enough? no, that's a note"
+            ]
+        );
+        // only notes get their indentation fixed
+        let saved = log.replace(" not indented", "  not indented");
+        assert_eq!(tl.format_store(), saved);
+        assert_eq!(
+            Timelog::new_from_string(&log.replace('\n', "\r\n")).format_store(),
+            saved.replace('\n', "\r\n")
+        );
+
+        // new entries go after the lines at the end, like in the storage
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        tl.add_at("arrived".to_string(), time("2022-06-13 09:00"))
+            .unwrap();
+        tl.save().unwrap();
+        let appended = format!("{log}\n2022-06-13 09:00: arrived\n");
+        assert_eq!(storage.contents(), appended);
+        let saved = appended.replace(" not indented", "  not indented");
+        assert_eq!(tl.format_store(), saved);
+
+        // removing entries keeps the lines before them
+        let popped = tl.pop_last().unwrap();
+        assert_eq!(popped.to_string(), "2022-06-13 09:00: arrived");
+        assert!(tl.format_store().ends_with("10:00: email\n# end\n\n"));
+        tl.remove(2).unwrap();
+        assert!(tl
+            .format_store()
+            .contains("# vacation from here\n\ngarbage\n2022-06-10 10:00: email\n"));
+
+        // also when loading the older entries later
+        let since = time("2022-06-10 00:00");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage.clone()), since).unwrap();
+        tl.ensure_loaded(NaiveDateTime::MIN).unwrap();
+        assert_eq!(tl.format_store(), saved);
+    }
+
+    #[test]
+    fn test_trailing_lines() {
+        let log = "2022-06-09 09:00: arrived\n2022-06-09 12:00: rtimelog: code\n\n\n";
+        let storage = MemoryStorage::new(log);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        assert_eq!(tl.format_store(), log);
+        tl.save_atomic().unwrap();
+        assert_eq!(storage.contents(), log);
+
+        // a rewrite after appending has the same lines as the append
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        tl.add_at("arrived".to_string(), time("2022-06-10 09:00"))
+            .unwrap();
+        tl.save().unwrap();
+        let appended = storage.contents();
+        assert_eq!(appended, format!("{log}\n2022-06-10 09:00: arrived\n"));
+        tl.save_atomic().unwrap();
+        assert_eq!(storage.contents(), appended);
+        assert_eq!(
+            Timelog::new_from_string(&appended.replace('\n', "\r\n")).format_store(),
+            appended.replace('\n', "\r\n")
+        );
+    }
+
+    #[test]
+    fn test_crlf() {
+        let crlf = TWO_DAYS.trim_start().replace('\n', "\r\n");
+        let tl = Timelog::new_from_string(&crlf);
+        assert_eq!(tl.get_all().count(), 10);
+        assert_eq!(&*tl.get_all().last().unwrap().task, "customer joe: support");
+        // line endings are kept
+        assert_eq!(tl.format_store(), crlf);
+
+        // byte order mark
+        let tl = Timelog::new_from_string(&format!("\u{feff}{crlf}"));
+        assert_eq!(tl.get_all().count(), 10);
+        assert_eq!(tl.format_store(), crlf);
+
+        // a latin-1 byte only damages its own line
+        let dir = env::temp_dir().join(format!("rtimelog-encoding-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timelog.txt");
+        fs::write(
+            &path,
+            b"\xef\xbb\xbf2022-06-09 06:02: arrived\n2022-06-09 07:00: caf\xe9\n2022-06-09 08:00: code\n",
+        )
+        .unwrap();
+        let tl = Timelog::new_from_file(&path).unwrap();
+        let tasks: Vec<&str> = tl.get_all().map(|e| &*e.task).collect();
+        assert_eq!(tasks, vec!["arrived", "caf\u{fffd}", "code"]);
+        assert_eq!(
+            FileStorage::new(&path).load().unwrap(),
+            "2022-06-09 06:02: arrived\n2022-06-09 07:00: caf\u{fffd}\n2022-06-09 08:00: code\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_get_history() {
         let tl = Timelog::new_from_string("");
-        assert!(Timelog::get_history(
-            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(), 1)
-        )
+        assert!(Timelog::get_history(tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(),
+            1,
+            NaiveTime::MIN
+        ))
         .is_empty());
 
         let tl = Timelog::new_from_string(TWO_DAYS);
-        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let entries = tl.get_n_days(
+            &NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+            1,
+            NaiveTime::MIN,
+        );
         assert_eq!(
             Timelog::get_history(entries),
             // no duplicate "rtimelog: code"
@@ -480,11 +3140,200 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pop_last() {
+        let storage = MemoryStorage::new(TWO_DAYS);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        tl.add_at("typo".to_string(), time("2022-06-10 17:00"))
+            .unwrap();
+        assert_eq!(&*tl.pop_last().unwrap().task, "typo");
+        assert_eq!(&*tl.pop_last().unwrap().task, "customer joe: support");
+        tl.save().unwrap();
+        assert!(storage
+            .contents()
+            .ends_with("2022-06-10 15:00: bug triage\n"));
+
+        let mut tl = Timelog::new_from_string("");
+        assert_eq!(tl.pop_last(), None);
+        assert_eq!(tl.last_added(), None);
+    }
+
+    #[test]
+    fn test_pop_last_added() {
+        let storage = MemoryStorage::new(TWO_DAYS);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        // a forgotten entry before the last one
+        tl.add_at("typo".to_string(), time("2022-06-10 12:30"))
+            .unwrap();
+        // entries which go before it don't change which one got added last
+        tl.insert(0, time("2022-06-09 00:00"), "midnight").unwrap();
+        assert_eq!(&*tl.last_added().unwrap().task, "typo");
+        assert_eq!(&*tl.pop_last().unwrap().task, "typo");
+        assert!(!tl.entries.iter().any(|e| &*e.task == "typo"));
+        // after that, the last one again
+        assert_eq!(&*tl.last_added().unwrap().task, "customer joe: support");
+
+        tl.add_at("typo".to_string(), time("2022-06-10 12:30"))
+            .unwrap();
+        tl.remove(0).unwrap();
+        assert_eq!(&*tl.pop_last().unwrap().task, "typo");
+        tl.save().unwrap();
+        assert_eq!(storage.contents(), TWO_DAYS.trim_start());
+    }
+
     #[test]
     fn test_add() {
         let mut tl = Timelog::new_from_string("");
-        tl.add("think hard".to_string());
+        tl.add("think hard".to_string()).unwrap();
         assert_eq!(tl.entries.len(), 1);
-        assert_eq!(tl.entries[0].task, "think hard");
+        assert_eq!(&*tl.entries[0].task, "think hard");
+
+        // at a pinned time, in minutes
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let time = |t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").unwrap();
+        tl.set_clock(Box::new(FixedClock(time("2022-06-10 16:45:30"))));
+        assert_eq!(tl.now(), time("2022-06-10 16:45:30"));
+        tl.add("email".to_string()).unwrap();
+        assert_eq!(&format!("{}", tl.entries[10]), "2022-06-10 16:45: email");
+        let s = Sentinels::default();
+        assert_eq!(tl.today(&s).len(), 7);
+        tl.set_clock(Box::new(FixedClock(time("2022-06-09 12:00:00"))));
+        assert_eq!(tl.today(&s).len(), 4);
+
+        // a line break would write a second, forged entry line
+        for task in ["line one\n2022-06-10 17:00: injected", "a\rb", "  "] {
+            assert!(matches!(
+                tl.add(task.to_string()),
+                Err(TimelogError::InvalidTask(_))
+            ));
+            assert!(matches!(
+                tl.add_at(task.to_string(), time("2022-06-10 16:50:00")),
+                Err(TimelogError::InvalidTask(_))
+            ));
+        }
+        assert_eq!(tl.entries.len(), 11);
+        tl.add("  trimmed ".to_string()).unwrap();
+        assert!(tl.entries.iter().any(|e| &*e.task == "trimmed"));
+    }
+
+    #[test]
+    fn test_add_at() {
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let stop = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(16, 30, 0)
+            .unwrap();
+        tl.add_at("** away".to_string(), stop).unwrap();
+        assert_eq!(tl.entries.len(), 11);
+        assert_eq!(&format!("{}", tl.entries[10]), "2022-06-10 16:30: ** away");
+
+        // forgotten entries go between the others, and need a rewrite
+        let storage = MemoryStorage::new(TWO_DAYS);
+        let mut tl = Timelog::new_from_storage(Box::new(storage.clone())).unwrap();
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        tl.add_at("meeting".to_string(), time("2022-06-10 14:30"))
+            .unwrap();
+        tl.add_at("email".to_string(), time("2022-06-10 15:00"))
+            .unwrap();
+        tl.save().unwrap();
+        assert!(storage.contents().contains(
+            "2022-06-10 14:00: rtimelog: code\n2022-06-10 14:30: meeting\n\
+             2022-06-10 15:00: bug triage\n2022-06-10 15:00: email\n"
+        ));
+
+        // but not before the entries which are not loaded yet
+        let since = time("2022-06-10 00:00");
+        let mut tl = Timelog::new_from_storage_recent(Box::new(storage), since).unwrap();
+        assert!(matches!(
+            tl.add_at("work".to_string(), time("2022-06-09 23:00")),
+            Err(TimelogError::Misplaced { .. })
+        ));
+    }
+
+    #[test]
+    fn test_add_snapped() {
+        let time = |t| NaiveDateTime::parse_from_str(t, TIME_FMT).unwrap();
+        let snap = Snap {
+            every: Some(Duration::minutes(15)),
+            up: false,
+            keep_raw: true,
+            seconds: false,
+        };
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        tl.add_at("** away".to_string(), time("2022-06-10 16:50"))
+            .unwrap();
+        // not before the last entry
+        tl.add_snapped("review".to_string(), time("2022-06-10 16:52"), &snap)
+            .unwrap();
+        tl.add_snapped("email".to_string(), time("2022-06-10 16:55"), &snap)
+            .unwrap();
+        tl.add_snapped("call".to_string(), time("2022-06-10 17:30"), &snap)
+            .unwrap();
+        let added: Vec<_> = tl.entries[11..]
+            .iter()
+            .map(|e| (e.to_string(), tl.note(e)))
+            .collect();
+        assert_eq!(
+            added,
+            vec![
+                (
+                    "2022-06-10 16:50: review".to_string(),
+                    Some("logged at 16:52")
+                ),
+                (
+                    "2022-06-10 17:00: email".to_string(),
+                    Some("logged at 16:55")
+                ),
+                ("2022-06-10 17:30: call".to_string(), None),
+            ]
+        );
+
+        let seconds = |t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").unwrap();
+        tl.add_snapped(
+            "minutes".to_string(),
+            seconds("2022-06-10 17:40:29"),
+            &Snap::default(),
+        )
+        .unwrap();
+        let snap = Snap {
+            seconds: true,
+            ..Snap::default()
+        };
+        tl.add_snapped("seconds".to_string(), seconds("2022-06-10 17:41:29"), &snap)
+            .unwrap();
+        tl.add_snapped(
+            "full minute".to_string(),
+            seconds("2022-06-10 17:42:00"),
+            &snap,
+        )
+        .unwrap();
+        let added: Vec<_> = tl.entries[14..].iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            added,
+            vec![
+                "2022-06-10 17:40: minutes",
+                "2022-06-10 17:41:29: seconds",
+                "2022-06-10 17:42: full minute"
+            ]
+        );
+        // they load again like that
+        let contents = tl.contents_to_save().unwrap();
+        assert!(contents.ends_with(
+            "2022-06-10 17:40: minutes\n2022-06-10 17:41:29: seconds\n2022-06-10 17:42: full minute\n"
+        ));
+        assert_eq!(
+            Timelog::new_from_string(&contents)
+                .contents_to_save()
+                .unwrap(),
+            contents
+        );
+        assert_eq!(
+            parse_time("2022-06-10 17:41:29"),
+            Some(seconds("2022-06-10 17:41:29"))
+        );
+        assert_eq!(parse_time("2022-06-10 17:41:9"), None);
+        assert_eq!(parse_time("2022-06-10 17:41:60"), None);
     }
 }
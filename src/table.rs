@@ -0,0 +1,271 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write as _;
+
+use chrono::Duration;
+
+/**
+ * How reports get printed: as text for reading, or as a table for other programs
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "csv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::Json),
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * One value in a Table
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Text(String),
+    Number(i64),
+    Bool(bool),
+    // whole minutes in CSV and JSON, which spreadsheets and scripts can calculate with; hours and
+    // minutes in Markdown, whose column heading then says "time" instead of "minutes"
+    Time(Duration),
+    Money { cents: i64 },
+}
+
+impl Cell {
+    pub fn text(s: &str) -> Cell {
+        Cell::Text(s.to_string())
+    }
+
+    // for CSV and JSON
+    fn raw(&self) -> String {
+        match self {
+            Cell::Text(s) => s.clone(),
+            Cell::Number(n) => n.to_string(),
+            Cell::Bool(b) => b.to_string(),
+            Cell::Time(d) => d.num_minutes().to_string(),
+            Cell::Money { cents } => money(*cents),
+        }
+    }
+
+    // for Markdown, i.e. people
+    fn readable(&self) -> String {
+        match self {
+            Cell::Time(d) => {
+                let sign = if *d < Duration::zero() { "-" } else { "" };
+                let minutes = d.num_minutes().abs();
+                format!("{sign}{} h {} min", minutes / 60, minutes % 60)
+            }
+            Cell::Bool(true) => "yes".to_string(),
+            Cell::Bool(false) => String::new(),
+            _ => self.raw(),
+        }
+    }
+}
+
+// like "1234.50"
+fn money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{sign}{}.{:02}", cents.abs() / 100, cents.abs() % 100)
+}
+
+// a quoted JSON string
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// a CSV field, quoted if it needs to
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/**
+ * The data of a report as rows with named columns, for the formats besides text
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(columns: &[&'static str]) -> Table {
+        Table {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Cell>) {
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    // each row as a JSON object, with the columns as keys
+    pub(crate) fn json_rows(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(name, cell)| {
+                        let value = match cell {
+                            Cell::Text(s) => json_string(s),
+                            _ => cell.raw(),
+                        };
+                        format!("{}: {value}", json_string(name))
+                    })
+                    .collect();
+                format!("{{{}}}", fields.join(", "))
+            })
+            .collect()
+    }
+
+    // Text is tab separated, without a header; reports have their own text format for reading
+    pub fn format(&self, format: OutputFormat) -> String {
+        let mut out = String::new();
+        match format {
+            OutputFormat::Text => {
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(Cell::readable).collect();
+                    writeln!(out, "{}", cells.join("\t")).unwrap();
+                }
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "{}", self.columns.join(",")).unwrap();
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(|c| csv_field(&c.raw())).collect();
+                    writeln!(out, "{}", cells.join(",")).unwrap();
+                }
+            }
+            OutputFormat::Json => {
+                let rows: Vec<String> = self.json_rows().iter().map(|r| format!("  {r}")).collect();
+                match rows.is_empty() {
+                    true => writeln!(out, "[]").unwrap(),
+                    false => writeln!(out, "[\n{}\n]", rows.join(",\n")).unwrap(),
+                }
+            }
+            OutputFormat::Markdown => {
+                let first = |i: usize| self.rows.first().map(|r| &r[i]);
+                let headings: Vec<&str> = (0..self.columns.len())
+                    .map(|i| match (self.columns[i], first(i)) {
+                        ("minutes", Some(Cell::Time(_))) => "time",
+                        (name, _) => name,
+                    })
+                    .collect();
+                writeln!(out, "| {} |", headings.join(" | ")).unwrap();
+                let align: Vec<&str> = (0..self.columns.len())
+                    .map(|i| match first(i) {
+                        Some(Cell::Time(_) | Cell::Number(_) | Cell::Money { .. }) => "---:",
+                        _ => "---",
+                    })
+                    .collect();
+                writeln!(out, "|{}|", align.join("|")).unwrap();
+                for row in &self.rows {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .map(|c| c.readable().replace('|', "\\|"))
+                        .collect();
+                    writeln!(out, "| {} |", cells.join(" | ")).unwrap();
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_table() {
+        let mut t = Table::new(&["task", "minutes", "slack", "amount"]);
+        t.push(vec![
+            Cell::text("customer \"joe\", support"),
+            Cell::Time(Duration::minutes(90)),
+            Cell::Bool(false),
+            Cell::Money { cents: 15050 },
+        ]);
+        t.push(vec![
+            Cell::text("** tea | biscuits"),
+            Cell::Time(Duration::minutes(-95)),
+            Cell::Bool(true),
+            Cell::Money { cents: -5 },
+        ]);
+        assert_eq!(
+            t.format(OutputFormat::Csv),
+            "task,minutes,slack,amount
+\"customer \"\"joe\"\", support\",90,false,150.50
+** tea | biscuits,-95,true,-0.05
+"
+        );
+        assert_eq!(
+            t.format(OutputFormat::Json),
+            r#"[
+  {"task": "customer \"joe\", support", "minutes": 90, "slack": false, "amount": 150.50},
+  {"task": "** tea | biscuits", "minutes": -95, "slack": true, "amount": -0.05}
+]
+"#
+        );
+        assert_eq!(
+            t.format(OutputFormat::Markdown),
+            r#"| task | time | slack | amount |
+|---|---:|---|---:|
+| customer "joe", support | 1 h 30 min |  | 150.50 |
+| ** tea \| biscuits | -1 h 35 min | yes | -0.05 |
+"#
+        );
+        assert_eq!(
+            t.format(OutputFormat::Text),
+            "customer \"joe\", support\t1 h 30 min\t\t150.50\n** tea | biscuits\t-1 h 35 min\tyes\t-0.05\n"
+        );
+        assert_eq!(Table::new(&["day"]).format(OutputFormat::Json), "[]\n");
+        assert_eq!(json_string("a\u{1}\tb"), "\"a\\u0001\\tb\"");
+        assert_eq!(OutputFormat::parse("md"), Some(OutputFormat::Markdown));
+        assert_eq!(OutputFormat::parse("xml"), None);
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::report::PeriodReport;
+
+// like ~/.config/rtimelog/templates/weekly.tmpl for `name` "weekly"; next to gtimelog's
+// configuration directory, see Config::get_config_dir()
+pub fn path(name: &str) -> PathBuf {
+    Config::get_config_dir()
+        .with_file_name("rtimelog")
+        .join("templates")
+        .join(format!("{name}.tmpl"))
+}
+
+/**
+ * Row of a list in the template context: a task, project, or day with its work
+ */
+#[cfg(feature = "templates")]
+#[derive(serde::Serialize)]
+struct Row {
+    name: String,
+    // like "2h 30m", or in words for plain output
+    time: String,
+    minutes: i64,
+    // decimal, like "2.50"
+    hours: String,
+}
+
+/**
+ * What templates can use of a report; the times are like in the report's text, the dates
+ * in the `date_format`, and the `_iso` ones like 2024-02-05
+ */
+#[cfg(feature = "templates")]
+#[derive(serde::Serialize)]
+struct Context {
+    name: String,
+    email: String,
+    first: String,
+    last: String,
+    first_iso: String,
+    last_iso: String,
+    // ISO week number of the first day
+    week: String,
+    total_work: String,
+    total_work_hours: String,
+    total_slack: String,
+    target: String,
+    overtime: String,
+    work: Vec<Row>,
+    slack: Vec<Row>,
+    projects: Vec<Row>,
+    days: Vec<Row>,
+}
+
+#[cfg(feature = "templates")]
+impl Context {
+    fn new(report: &PeriodReport, config: &Config) -> Context {
+        use crate::activity::duration_in_words;
+        use crate::billing::decimal_hours;
+        use crate::report::{short, signed};
+        use chrono::{Duration, NaiveDate};
+
+        let plain = config.plain;
+        let time = |d: Duration| match plain {
+            true => duration_in_words(d),
+            false => short(d),
+        };
+        let row = |name: String, d: Duration| Row {
+            name,
+            time: time(d),
+            minutes: d.num_minutes(),
+            hours: decimal_hours(d),
+        };
+        let rows = |items: &[(String, Duration)]| {
+            items
+                .iter()
+                .map(|(name, d)| row(name.clone(), *d))
+                .collect()
+        };
+        let date = |d: &NaiveDate| config.date_format.format(d);
+        Context {
+            name: config.name.clone().unwrap_or_default(),
+            email: config.email.clone().unwrap_or_default(),
+            first: date(&report.first),
+            last: date(&report.last),
+            first_iso: report.first.format("%F").to_string(),
+            last_iso: report.last.format("%F").to_string(),
+            week: (report.first + Duration::days(3)).format("%V").to_string(),
+            total_work: time(report.total_work),
+            total_work_hours: decimal_hours(report.total_work),
+            total_slack: time(report.total_slack),
+            target: time(report.target(report.first, report.last, config)),
+            overtime: signed(report.overtime(config), plain),
+            work: rows(&report.work),
+            slack: rows(&report.slack),
+            projects: rows(&report.projects),
+            days: report
+                .days
+                .iter()
+                .map(|(day, d)| row(date(day), *d))
+                .collect(),
+        }
+    }
+}
+
+// `report` in the given template, with TinyTemplate syntax like "{total_work}" and
+// "{{ for task in work }}{task.time}: {task.name}\n{{ endfor }}"
+#[cfg(feature = "templates")]
+pub fn render(template: &str, report: &PeriodReport, config: &Config) -> Result<String, io::Error> {
+    let invalid = |e: tinytemplate::error::Error| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid template: {e}"))
+    };
+    let mut tt = tinytemplate::TinyTemplate::new();
+    // it's text, not HTML
+    tt.set_default_formatter(&tinytemplate::format_unescaped);
+    tt.add_template("report", template).map_err(invalid)?;
+    tt.render("report", &Context::new(report, config))
+        .map_err(invalid)
+}
+
+#[cfg(not(feature = "templates"))]
+pub fn render(
+    _template: &str,
+    _report: &PeriodReport,
+    _config: &Config,
+) -> Result<String, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Report templates need rtimelog with the templates feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_path() {
+        let path = path("weekly");
+        assert!(path.ends_with("rtimelog/templates/weekly.tmpl"));
+        assert_eq!(
+            path.parent().unwrap().parent().unwrap().parent(),
+            Config::get_config_dir().parent()
+        );
+    }
+
+    #[cfg(feature = "templates")]
+    #[test]
+    fn test_render() {
+        use crate::store::{Entry, Timelog};
+        use chrono::NaiveDate;
+
+        let tl = Timelog::new_from_string(
+            "2024-02-05 09:00: arrived
+2024-02-05 11:30: customer joe: support
+2024-02-05 12:00: ** lunch
+2024-02-06 09:00: arrived
+2024-02-06 10:00: rtimelog: code <review>
+",
+        );
+        let config = Config::new_from_string("[gtimelog]\nname = Joe\n");
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let all: Vec<Entry> = tl.get_all().cloned().collect();
+        let report = PeriodReport::new(&all, day(5), day(11), &config);
+        let template = "Timesheet of {name}, week {week} from {first_iso}
+{{ for task in work }}{task.hours} {task.name}
+{{ endfor }}{{ for day in days }}{{ if day.minutes }}{day.name}: {day.time}
+{{ endif }}{{ endfor }}Total: {total_work} of {target} ({overtime})
+";
+        assert_eq!(
+            render(template, &report, &config).unwrap(),
+            "Timesheet of Joe, week 06 from 2024-02-05
+2.50 customer joe: support
+1.00 rtimelog: code <review>
+Monday, 2024-02-05: 2h 30m
+Tuesday, 2024-02-06: 1h
+Total: 3h 30m of 40h (-36h 30m)
+"
+        );
+        let e = render("{unknown}", &report, &config).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(render("{{ for }}", &report, &config).is_err());
+    }
+}
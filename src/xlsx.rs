@@ -0,0 +1,258 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Excel workbooks, saved with rust_xlsxwriter
+
+use std::io;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate};
+use rust_xlsxwriter::{Format, XlsxError};
+
+use crate::config::Config;
+use crate::report::Timesheet;
+use crate::store::Entry;
+
+/**
+ * Cell of a worksheet
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    // bold, for the column and row titles
+    Heading(String),
+    // a number of hours with two decimals, which spreadsheets can add up further
+    Hours(Duration),
+}
+
+/**
+ * Worksheet with its rows of cells
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+/**
+ * Workbook with its sheets, see to_bytes()
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Workbook {
+    pub sheets: Vec<Sheet>,
+}
+
+fn to_io_error(e: XlsxError) -> io::Error {
+    match e {
+        XlsxError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidInput, e),
+    }
+}
+
+impl Workbook {
+    fn to_xlsx(&self) -> Result<rust_xlsxwriter::Workbook, XlsxError> {
+        let bold = Format::new().set_bold();
+        let hours = Format::new().set_num_format("0.00");
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        for sheet in &self.sheets {
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(&sheet.name)?;
+            worksheet.set_column_width(0, 40)?;
+            for (r, row) in (0..).zip(&sheet.rows) {
+                for (c, cell) in (0..).zip(row) {
+                    match cell {
+                        Cell::Empty => (),
+                        Cell::Text(text) => {
+                            worksheet.write_string(r, c, text)?;
+                        }
+                        Cell::Heading(text) => {
+                            worksheet.write_string_with_format(r, c, text, &bold)?;
+                        }
+                        Cell::Hours(d) => {
+                            let h = d.num_minutes() as f64 / 60.0;
+                            worksheet.write_number_with_format(r, c, h, &hours)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(workbook)
+    }
+
+    // the .xlsx file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        self.to_xlsx()
+            .and_then(|mut w| w.save_to_buffer())
+            .map_err(to_io_error)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+        self.to_xlsx()
+            .and_then(|mut w| w.save(path))
+            .map_err(to_io_error)
+    }
+}
+
+// the entries from `first` to `last` as a timesheet: one sheet per week (see Timesheet::weeks()),
+// with a column per day and a row per project, and their totals
+pub fn timesheet(
+    entries: &[Entry],
+    first: NaiveDate,
+    last: NaiveDate,
+    config: &Config,
+) -> Workbook {
+    let mut workbook = Workbook::default();
+    for week in Timesheet::weeks(entries, first, last, config) {
+        let mut heading = vec![Cell::Heading("Project".to_string())];
+        heading.extend(
+            week.days
+                .iter()
+                .map(|(day, _)| Cell::Heading(day.format("%a %Y-%m-%d").to_string())),
+        );
+        heading.push(Cell::Heading("Total".to_string()));
+        let mut rows = vec![heading];
+        for (project, work, total) in &week.projects {
+            let mut row = vec![Cell::Text(project.clone())];
+            row.extend(work.iter().map(|d| match d.is_zero() {
+                true => Cell::Empty,
+                false => Cell::Hours(*d),
+            }));
+            row.push(Cell::Hours(*total));
+            rows.push(row);
+        }
+        let mut total = vec![Cell::Heading("Total".to_string())];
+        total.extend(week.days.iter().map(|(_, d)| Cell::Hours(*d)));
+        total.push(Cell::Hours(week.total_work));
+        rows.push(total);
+
+        workbook.sheets.push(Sheet {
+            name: week.days[0].0.format("%Y-%m-%d").to_string(),
+            rows,
+        });
+    }
+    workbook
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Timelog;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_timesheet() {
+        let tl = Timelog::new_from_string(
+            "2024-02-02 09:00: arrived
+2024-02-02 10:30: rtimelog: code
+2024-02-02 11:00: ** tea
+
+2024-02-05 09:00: arrived
+2024-02-05 10:00: customer joe: support
+2024-02-05 12:00: rtimelog: docs
+
+2024-02-06 09:00: arrived
+2024-02-06 10:00: customer joe: support
+",
+        );
+        let entries: Vec<Entry> = tl.get_all().cloned().collect();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        let hours = |m| Cell::Hours(Duration::minutes(m));
+        let heading = |s: &str| Cell::Heading(s.to_string());
+        let workbook = timesheet(&entries, day(1), day(6), &Config::default());
+        assert_eq!(
+            workbook.sheets,
+            vec![
+                Sheet {
+                    name: "2024-02-01".to_string(),
+                    rows: vec![
+                        vec![
+                            heading("Project"),
+                            heading("Thu 2024-02-01"),
+                            heading("Fri 2024-02-02"),
+                            heading("Sat 2024-02-03"),
+                            heading("Sun 2024-02-04"),
+                            heading("Total"),
+                        ],
+                        vec![
+                            Cell::Text("rtimelog".to_string()),
+                            Cell::Empty,
+                            hours(90),
+                            Cell::Empty,
+                            Cell::Empty,
+                            hours(90),
+                        ],
+                        vec![
+                            heading("Total"),
+                            hours(0),
+                            hours(90),
+                            hours(0),
+                            hours(0),
+                            hours(90),
+                        ],
+                    ]
+                },
+                Sheet {
+                    name: "2024-02-05".to_string(),
+                    rows: vec![
+                        vec![
+                            heading("Project"),
+                            heading("Mon 2024-02-05"),
+                            heading("Tue 2024-02-06"),
+                            heading("Total"),
+                        ],
+                        vec![
+                            Cell::Text("customer joe".to_string()),
+                            hours(60),
+                            hours(60),
+                            hours(120),
+                        ],
+                        vec![
+                            Cell::Text("rtimelog".to_string()),
+                            hours(120),
+                            Cell::Empty,
+                            hours(120),
+                        ],
+                        vec![heading("Total"), hours(180), hours(60), hours(240)],
+                    ]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let workbook = Workbook {
+            sheets: vec![Sheet {
+                name: "R&D".to_string(),
+                rows: vec![vec![Cell::Text("<fix>".to_string())]],
+            }],
+        };
+        // an .xlsx file is a zip archive
+        assert!(workbook.to_bytes().unwrap().starts_with(b"PK\x03\x04"));
+
+        // Excel does not allow some characters in sheet names
+        let workbook = Workbook {
+            sheets: vec![Sheet {
+                name: "R/D".to_string(),
+                rows: vec![],
+            }],
+        };
+        assert_eq!(
+            workbook.to_bytes().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
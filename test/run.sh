@@ -3,6 +3,8 @@ set -eux
 
 # Run tests in debug mode
 cargo test
+cargo test --features async
+cargo test --features serde
 
 # Build release mode
 cargo rustc --release --lib -- -Dwarnings
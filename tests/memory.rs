@@ -0,0 +1,126 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Memory ceilings for a log with one million entries. This needs its own allocator, thus it is
+// not a unit test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write as _;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+
+use rtimelog::activity::Activities;
+use rtimelog::config::Sentinels;
+use rtimelog::storage::FileStorage;
+use rtimelog::store::Timelog;
+
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+// the measurements don't work with tests running in parallel
+static SERIAL: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(current, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+const MB: usize = 1024 * 1024;
+const DAYS: i64 = 50_000;
+
+fn first_day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()
+}
+
+// 50.000 days with 20 entries each
+fn generate() -> String {
+    let tasks = ["rtimelog: code", "email", "** tea", "customer joe: support"];
+    let mut log = String::new();
+    for d in 0..DAYS {
+        let day = first_day() + Duration::days(d);
+        for i in 0..20 {
+            writeln!(
+                log,
+                "{} {:02}:{:02}: {}",
+                day.format("%Y-%m-%d"),
+                8 + i / 3,
+                (i % 3) * 20,
+                tasks[i as usize % tasks.len()]
+            )
+            .unwrap();
+        }
+        log.push('\n');
+    }
+    log
+}
+
+#[test]
+fn test_full_load() {
+    let _serial = SERIAL.lock().unwrap();
+    let log = generate();
+    let before = CURRENT.load(Ordering::SeqCst);
+    let tl = Timelog::new_from_string(&log);
+    let retained = CURRENT.load(Ordering::SeqCst) - before;
+
+    let last_day = first_day() + Duration::days(DAYS - 1);
+    assert_eq!(
+        tl.get_n_days(&last_day, DAYS as u32 + 1, NaiveTime::MIN)
+            .len(),
+        1_000_000
+    );
+    // tasks are shared, an entry is just the time and a pointer
+    assert!(retained < 36 * MB, "{} MB", retained / MB);
+}
+
+#[test]
+fn test_range_load() {
+    let _serial = SERIAL.lock().unwrap();
+    let path = std::env::temp_dir().join(format!("rtimelog-memory-{}.txt", std::process::id()));
+    fs::write(&path, generate()).unwrap();
+    let storage = FileStorage::new(&path);
+
+    let begin = (first_day() + Duration::days(1000))
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let tl = Timelog::new_from_storage_range(&storage, begin, begin + Duration::weeks(1)).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    fs::remove_file(&path).unwrap();
+
+    let week = tl.get_n_days(&(begin.date() + Duration::days(6)), 7, NaiveTime::MIN);
+    assert_eq!(week.len(), 140);
+    assert_eq!(
+        Activities::new_from_entries(week, &Sentinels::default()).total_work(),
+        Duration::minutes(7 * 280)
+    );
+    // streaming through the file only holds the week
+    assert!(peak < MB, "{} kB", peak / 1024);
+}